@@ -7,10 +7,10 @@ use std::time::Duration;
 // use common_vector::editor::{InputValue, ObjectProperty};
 // use common_vector::{basic::string_to_f32, editor::Editor};
 use floem::keyboard::ModifiersState;
-use floem::reactive::{RwSignal, SignalUpdate};
+use floem::reactive::{RwSignal, SignalGet, SignalUpdate};
 use stunts_engine::animations::{
     AnimationData, AnimationProperty, BackgroundFill, EasingType, KeyType, KeyframeValue,
-    ObjectType, UIKeyframe,
+    ObjectType, Sequence, UIKeyframe,
 };
 use stunts_engine::editor::{
     color_to_wgpu, string_to_f32, wgpu_to_human, Editor, InputValue, ObjectProperty, PathType,
@@ -19,11 +19,28 @@ use stunts_engine::polygon::{SavedPoint, SavedPolygonConfig};
 use stunts_engine::st_image::SavedStImageConfig;
 use stunts_engine::st_video::SavedStVideoConfig;
 use stunts_engine::text_due::SavedTextRendererConfig;
+use stunts_engine::timelines::{SavedTimelineStateConfig, TimelineSequence, TrackType};
 use undo::Edit;
 use undo::Record;
 use uuid::Uuid;
 
-use crate::helpers::saved_state::SavedState;
+use crate::helpers::clips;
+use crate::helpers::clock::{project_frame_rate, PlaybackClock, DEFAULT_FRAME_RATE, FRAME_RATES};
+use crate::helpers::keyframes::{auto_key_object, KeyframeClipboard};
+use crate::helpers::looks::StagedLooks;
+use crate::helpers::markers::MARKER_COLORS;
+use crate::helpers::nested;
+use crate::helpers::onion::OnionSkinSettings;
+use crate::helpers::recording::MotionRecorder;
+use crate::helpers::retime::{self, Pivot, RetimeScope};
+use crate::helpers::saved_state::{
+    AnimationRepeat, ClipTrim, ColorKeyframe, ColorTarget, ColorTrack, Marker, NestedSequence,
+    PropertyModifier, RepeatMode, SavedState, SelectorKeyframe, SequenceTransition, ShapeKeyframe,
+    ShapeProperty, ShapeTrack, TextAnimator, TimelineTrack, TrackAssignment,
+};
+use crate::helpers::snapping::SnapSettings;
+use crate::helpers::timeline_playback::TimelinePlayback;
+use crate::helpers::tracks::{ensure_tracks, new_track, project_tracks, track_of};
 use crate::helpers::utilities::save_saved_state_raw;
 
 #[derive(Debug)]
@@ -954,9 +971,264 @@ impl Edit for ObjectEdit {
     }
 }
 
+/// Replaces a whole sequence, so keyframe operations touching many animations undo in one step.
+/// project carries what the operation changed beside the sequence, like retimed color keyframes.
+#[derive(Debug)]
+pub struct SequenceEdit {
+    pub sequence_id: String,
+    pub old_sequence: Sequence,
+    pub new_sequence: Sequence,
+    pub signal: Option<RwSignal<Sequence>>,
+    pub project: Option<ProjectEdit>,
+}
+
+impl SequenceEdit {
+    fn apply(&self, record_state: &mut RecordState, sequence: &Sequence) {
+        let mut editor = record_state.editor.lock().unwrap();
+        let saved_state = record_state
+            .saved_state
+            .as_mut()
+            .expect("Couldn't get saved state");
+
+        saved_state.sequences.iter_mut().for_each(|s| {
+            if s.id == self.sequence_id {
+                *s = sequence.clone();
+            }
+        });
+
+        save_saved_state_raw(saved_state.clone());
+
+        let is_current = editor
+            .current_sequence_data
+            .as_ref()
+            .map_or(false, |s| s.id == self.sequence_id);
+
+        if is_current {
+            editor.current_sequence_data = Some(sequence.clone());
+            editor.update_motion_paths(sequence);
+        }
+
+        drop(editor);
+
+        if let Some(signal) = self.signal {
+            signal.set(sequence.clone());
+        }
+    }
+}
+
+impl Edit for SequenceEdit {
+    type Target = RecordState;
+    type Output = ();
+
+    fn edit(&mut self, record_state: &mut RecordState) {
+        self.apply(record_state, &self.new_sequence);
+
+        if let Some(project) = self.project.as_mut() {
+            project.edit(record_state);
+        }
+    }
+
+    fn undo(&mut self, record_state: &mut RecordState) {
+        if let Some(project) = self.project.as_mut() {
+            project.undo(record_state);
+        }
+
+        self.apply(record_state, &self.old_sequence);
+    }
+}
+
+/// Signals of the sequence timeline, registered by the sequences panel so project edits
+/// and their undo keep it in line with the saved state
+#[derive(Clone, Copy, Debug)]
+pub struct TimelineSignals {
+    pub tracks: RwSignal<Vec<TimelineTrack>>,
+    pub track_assignments: RwSignal<Vec<TrackAssignment>>,
+    pub timeline_sequences: RwSignal<Vec<TimelineSequence>>,
+    pub clip_trims: RwSignal<Vec<ClipTrim>>,
+    pub transitions: RwSignal<Vec<SequenceTransition>>,
+    pub export_play_timeline_config: RwSignal<Option<SavedTimelineStateConfig>>,
+}
+
+impl TimelineSignals {
+    pub fn refresh(&self, saved_state: &SavedState) {
+        self.tracks.set(project_tracks(saved_state));
+        self.track_assignments
+            .set(saved_state.track_assignments.clone());
+        self.clip_trims.set(saved_state.clip_trims.clone());
+        self.transitions.set(saved_state.transitions.clone());
+        self.timeline_sequences
+            .set(saved_state.timeline_state.timeline_sequences.clone());
+
+        if saved_state.timeline_state.timeline_sequences.is_empty() {
+            self.export_play_timeline_config.set(None);
+        } else {
+            self.export_play_timeline_config
+                .set(Some(saved_state.timeline_state.clone()));
+        }
+    }
+}
+
+/// Signals mirroring the parts of the saved state beside the sequences
+#[derive(Clone, Copy, Debug)]
+pub struct ProjectSignals {
+    pub color_tracks: RwSignal<Vec<ColorTrack>>,
+    pub shape_tracks: RwSignal<Vec<ShapeTrack>>,
+    pub text_animators: RwSignal<Vec<TextAnimator>>,
+    pub hold_keyframes: RwSignal<Vec<String>>,
+    pub frame_rate: RwSignal<u32>,
+    pub markers: RwSignal<Vec<Marker>>,
+    pub nested_sequences: RwSignal<Vec<NestedSequence>>,
+    pub timeline: Option<TimelineSignals>,
+}
+
+impl ProjectSignals {
+    /// Set the signals whose part of the saved state differs between from and to
+    fn refresh(&self, from: &SavedState, to: &SavedState) {
+        if from.color_tracks != to.color_tracks {
+            self.color_tracks.set(to.color_tracks.clone());
+        }
+        if from.shape_tracks != to.shape_tracks {
+            self.shape_tracks.set(to.shape_tracks.clone());
+        }
+        if from.text_animators != to.text_animators {
+            self.text_animators.set(to.text_animators.clone());
+        }
+        if from.hold_keyframes != to.hold_keyframes {
+            self.hold_keyframes.set(to.hold_keyframes.clone());
+        }
+        if from.frame_rate != to.frame_rate {
+            self.frame_rate.set(project_frame_rate(to));
+        }
+        if from.markers != to.markers {
+            self.markers.set(to.markers.clone());
+        }
+        if from.nested_sequences != to.nested_sequences {
+            self.nested_sequences.set(to.nested_sequences.clone());
+        }
+
+        let timeline_changed = from.timeline_state != to.timeline_state
+            || from.tracks != to.tracks
+            || from.track_assignments != to.track_assignments
+            || from.transitions != to.transitions
+            || from.clip_trims != to.clip_trims;
+
+        if let (Some(timeline), true) = (self.timeline, timeline_changed) {
+            timeline.refresh(to);
+        }
+    }
+}
+
+/// Replaces the parts of the saved state kept beside the sequences, so edits to repeats,
+/// modifiers, color and shape tracks, text animators, holds, markers, nesting, tracks and clips
+/// undo in one step. Sequences have SequenceEdit and are left out of both states.
+#[derive(Debug)]
+pub struct ProjectEdit {
+    pub old_state: SavedState,
+    pub new_state: SavedState,
+    pub signals: ProjectSignals,
+}
+
+impl ProjectEdit {
+    /// Write the fields that differ between from and to, leaving the others as they are now
+    fn apply(&self, record_state: &mut RecordState, from: &SavedState, to: &SavedState) {
+        let saved_state = record_state
+            .saved_state
+            .as_mut()
+            .expect("Couldn't get saved state");
+
+        if from.timeline_state != to.timeline_state {
+            saved_state.timeline_state = to.timeline_state.clone();
+        }
+        if from.animation_repeats != to.animation_repeats {
+            saved_state.animation_repeats = to.animation_repeats.clone();
+        }
+        if from.property_modifiers != to.property_modifiers {
+            saved_state.property_modifiers = to.property_modifiers.clone();
+        }
+        if from.color_tracks != to.color_tracks {
+            saved_state.color_tracks = to.color_tracks.clone();
+        }
+        if from.shape_tracks != to.shape_tracks {
+            saved_state.shape_tracks = to.shape_tracks.clone();
+        }
+        if from.text_animators != to.text_animators {
+            saved_state.text_animators = to.text_animators.clone();
+        }
+        if from.hold_keyframes != to.hold_keyframes {
+            saved_state.hold_keyframes = to.hold_keyframes.clone();
+        }
+        if from.tracks != to.tracks {
+            saved_state.tracks = to.tracks.clone();
+        }
+        if from.track_assignments != to.track_assignments {
+            saved_state.track_assignments = to.track_assignments.clone();
+        }
+        if from.transitions != to.transitions {
+            saved_state.transitions = to.transitions.clone();
+        }
+        if from.clip_trims != to.clip_trims {
+            saved_state.clip_trims = to.clip_trims.clone();
+        }
+        if from.frame_rate != to.frame_rate {
+            saved_state.frame_rate = to.frame_rate;
+        }
+        if from.markers != to.markers {
+            saved_state.markers = to.markers.clone();
+        }
+        if from.nested_sequences != to.nested_sequences {
+            saved_state.nested_sequences = to.nested_sequences.clone();
+        }
+
+        save_saved_state_raw(saved_state.clone());
+
+        self.signals.refresh(from, to);
+    }
+}
+
+impl Edit for ProjectEdit {
+    type Target = RecordState;
+    type Output = ();
+
+    fn edit(&mut self, record_state: &mut RecordState) {
+        self.apply(record_state, &self.old_state, &self.new_state);
+    }
+
+    fn undo(&mut self, record_state: &mut RecordState) {
+        self.apply(record_state, &self.new_state, &self.old_state);
+    }
+}
+
+#[derive(Debug)]
+pub enum StateEdit {
+    Object(ObjectEdit),
+    Sequence(SequenceEdit),
+    Project(ProjectEdit),
+}
+
+impl Edit for StateEdit {
+    type Target = RecordState;
+    type Output = ();
+
+    fn edit(&mut self, record_state: &mut RecordState) {
+        match self {
+            StateEdit::Object(edit) => edit.edit(record_state),
+            StateEdit::Sequence(edit) => edit.edit(record_state),
+            StateEdit::Project(edit) => edit.edit(record_state),
+        }
+    }
+
+    fn undo(&mut self, record_state: &mut RecordState) {
+        match self {
+            StateEdit::Object(edit) => edit.undo(record_state),
+            StateEdit::Sequence(edit) => edit.undo(record_state),
+            StateEdit::Project(edit) => edit.undo(record_state),
+        }
+    }
+}
+
 pub struct EditorState {
     pub editor: Arc<Mutex<Editor>>,
-    pub record: Arc<Mutex<Record<ObjectEdit>>>,
+    pub record: Arc<Mutex<Record<StateEdit>>>,
     pub record_state: RecordState,
     pub polygon_selected: bool,
     pub selected_polygon_id: Uuid,
@@ -972,17 +1244,55 @@ pub struct EditorState {
     pub project_selected_signal: Option<RwSignal<Uuid>>,
     pub active_sequence_mode: RwSignal<String>,
     // pub sequence_timeline_state: TimelineState,
+    /// position in the open sequence, in sequence time. Keyframes count from their
+    /// animation's start, convert with scrub::animation_time
+    pub keyframe_playhead: RwSignal<Duration>,
+    /// position on the sequence timeline, shared by clip edits and timeline playback
+    pub timeline_playhead: RwSignal<Duration>,
+    pub timeline_playback: Option<TimelinePlayback>,
+    /// frame clock of the sequence being played on its own
+    pub sequence_clock: Option<PlaybackClock>,
+    /// stand-ins the preview shows for objects with keyed looks or text animators
+    pub staged_looks: StagedLooks,
+    /// the project's frame rate, mirrored from the saved state for the timeline headers
+    pub frame_rate: RwSignal<u32>,
+    /// when on, playback shows every frame instead of keeping up with real time
+    pub play_every_frame: RwSignal<bool>,
+    /// every marker and region of the project, mirrored from the saved state for the timelines
+    pub markers: RwSignal<Vec<Marker>>,
+    /// sequences placed inside other sequences, mirrored from the saved state for the sequence panel
+    pub nested_sequences: RwSignal<Vec<NestedSequence>>,
+    pub keyframe_clipboard: Option<KeyframeClipboard>,
+    pub snap_settings: SnapSettings,
+    /// when on, moving an object on the canvas keys its value at the playhead
+    pub auto_key: RwSignal<bool>,
+    /// when on, dragging an object during playback records its path
+    pub record_motion: RwSignal<bool>,
+    pub simplify_recording: RwSignal<bool>,
+    pub motion_recorder: Option<MotionRecorder>,
+    /// set by the project view so edits made outside the panels can refresh it
+    pub sequence_data_signal: Option<RwSignal<Sequence>>,
+    /// set by the sequences panel, see TimelineSignals
+    pub timeline_signals: Option<TimelineSignals>,
+    /// every color track of the project, mirrored from the saved state for the keyframe timeline
+    pub color_tracks: RwSignal<Vec<ColorTrack>>,
+    /// likewise for width, height, border radius and stroke thickness tracks
+    pub shape_tracks: RwSignal<Vec<ShapeTrack>>,
+    pub text_animators: RwSignal<Vec<TextAnimator>>,
+    /// ids of hold keyframes, drawn differently in the keyframe timeline
+    pub hold_keyframes: RwSignal<Vec<String>>,
+    pub onion_skin: OnionSkinSettings,
 }
 
 pub struct RecordState {
     pub editor: Arc<Mutex<Editor>>,
-    // pub record: Arc<Mutex<Record<ObjectEdit>>>,
+    // pub record: Arc<Mutex<Record<StateEdit>>>,
     // pub editor_state: EditorState,
     pub saved_state: Option<SavedState>,
 }
 
 impl EditorState {
-    pub fn new(editor: Arc<Mutex<Editor>>, record: Arc<Mutex<Record<ObjectEdit>>>) -> Self {
+    pub fn new(editor: Arc<Mutex<Editor>>, record: Arc<Mutex<Record<StateEdit>>>) -> Self {
         // let sequence_timeline_state = TimelineState::new();
 
         Self {
@@ -1007,6 +1317,28 @@ impl EditorState {
             project_selected_signal: None,
             // sequence_timeline_state,
             active_sequence_mode: RwSignal::new("layout".to_string()),
+            keyframe_playhead: RwSignal::new(Duration::from_secs(0)),
+            timeline_playhead: RwSignal::new(Duration::from_secs(0)),
+            timeline_playback: None,
+            sequence_clock: None,
+            staged_looks: StagedLooks::default(),
+            frame_rate: RwSignal::new(DEFAULT_FRAME_RATE),
+            play_every_frame: RwSignal::new(false),
+            markers: RwSignal::new(Vec::new()),
+            nested_sequences: RwSignal::new(Vec::new()),
+            keyframe_clipboard: None,
+            snap_settings: SnapSettings::new(),
+            auto_key: RwSignal::new(false),
+            record_motion: RwSignal::new(false),
+            simplify_recording: RwSignal::new(true),
+            motion_recorder: None,
+            sequence_data_signal: None,
+            timeline_signals: None,
+            color_tracks: RwSignal::new(Vec::new()),
+            shape_tracks: RwSignal::new(Vec::new()),
+            text_animators: RwSignal::new(Vec::new()),
+            hold_keyframes: RwSignal::new(Vec::new()),
+            onion_skin: OnionSkinSettings::new(),
         }
     }
 
@@ -1257,8 +1589,7 @@ impl EditorState {
         properties.push(position_prop);
         properties.push(rotation_prop);
         properties.push(scale_prop);
-        // properties.push(perspective_x_prop);
-        // properties.push(perspective_y_prop);
+        // perspective tilt is keyed on shape tracks, KeyframeValue has no variant for it
         properties.push(opacity_prop);
 
         if object_type == ObjectType::VideoItem {
@@ -1337,38 +1668,11 @@ impl EditorState {
         new_motion_path
     }
 
-    pub fn reverse_keyframes(&self, animation: AnimationData) -> AnimationData {
-        let mut new_animation = animation.clone();
-
-        // For each property in the animation
-        for property in &mut new_animation.properties {
-            if property.keyframes.len() <= 1 {
-                continue; // No need to reverse single keyframe or empty properties
-            }
-
-            // Sort keyframes by time first to ensure proper ordering
-            property
-                .keyframes
-                .sort_by(|a, b| a.time.partial_cmp(&b.time).unwrap());
-
-            // Create a vector of all times
-            let times: Vec<Duration> = property.keyframes.iter().map(|k| k.time).collect();
-
-            // Reverse the times vector
-            let reversed_times: Vec<Duration> = times.iter().rev().cloned().collect();
-
-            // Apply the reversed times to the keyframes
-            for (keyframe, new_time) in property.keyframes.iter_mut().zip(reversed_times.iter()) {
-                keyframe.time = *new_time;
-            }
-        }
-
-        new_animation
-    }
-
-    /// squish keyframes into target_duration, keeping proportional time between them
+    /// squish keyframes into target_duration, keeping proportional time between them.
+    /// Start times are scaled too so animations keep their place relative to each other,
+    /// the keyframes kept beside the sequence are scaled in the saved state.
     pub fn scale_keyframes(
-        &self,
+        &mut self,
         selected_sequence_id: String,
         target_duration_s: f32,
     ) -> Vec<AnimationData> {
@@ -1379,46 +1683,1024 @@ impl EditorState {
         let saved_state = self
             .record_state
             .saved_state
-            .as_ref()
+            .as_mut()
             .expect("Couldn't get saved state");
         if let Some(sequence) = saved_state
             .sequences
             .iter()
             .find(|a| a.id == selected_sequence_id)
         {
-            for animation in sequence.polygon_motion_paths.clone() {
-                let new_animation = self.scale_animation(animation, target_duration);
-                animations.push(new_animation);
+            let mut new_sequence = sequence.clone();
+            let scope = RetimeScope::Animations(Vec::new());
+
+            if let Some((_, end_ms)) = retime::scope_span(&new_sequence, saved_state, &scope) {
+                if end_ms > 0 {
+                    let factor = target_duration.as_millis() as f32 / end_ms as f32;
+                    retime::scale_keyframes(&mut new_sequence, saved_state, &scope, factor, 0);
+                }
             }
+
+            animations = new_sequence.polygon_motion_paths;
         }
 
         animations
     }
 
-    pub fn scale_animation(
+    /// Run an operation on a copy of the sequence and record the result as a single undo step
+    pub fn edit_sequence<F>(
+        &mut self,
+        sequence_id: String,
+        signal: Option<RwSignal<Sequence>>,
+        operation: F,
+    ) where
+        F: FnOnce(&mut Sequence),
+    {
+        self.edit_sequence_and_project(sequence_id, signal, |sequence, _| operation(sequence));
+    }
+
+    /// Like edit_sequence, for operations that also change what's kept beside the sequences.
+    /// The saved state the operation gets still holds the old copy of the sequence.
+    pub fn edit_sequence_and_project<F>(
+        &mut self,
+        sequence_id: String,
+        signal: Option<RwSignal<Sequence>>,
+        operation: F,
+    ) where
+        F: FnOnce(&mut Sequence, &mut SavedState),
+    {
+        let old_state = self
+            .record_state
+            .saved_state
+            .clone()
+            .expect("Couldn't get saved state");
+        let old_sequence = old_state
+            .sequences
+            .iter()
+            .find(|s| s.id == sequence_id)
+            .cloned()
+            .expect("Couldn't find sequence");
+
+        let mut new_state = old_state.clone();
+        let mut new_sequence = old_sequence.clone();
+        operation(&mut new_sequence, &mut new_state);
+
+        let edit = SequenceEdit {
+            sequence_id,
+            old_sequence,
+            new_sequence,
+            signal,
+            project: self.project_edit(old_state, new_state),
+        };
+
+        let mut record = self.record.lock().unwrap();
+        record.edit(&mut self.record_state, StateEdit::Sequence(edit));
+    }
+
+    pub fn reverse_scope(
+        &mut self,
+        sequence_id: String,
+        signal: Option<RwSignal<Sequence>>,
+        scope: RetimeScope,
+    ) {
+        self.edit_sequence_and_project(sequence_id, signal, |sequence, saved_state| {
+            retime::reverse_keyframes(sequence, saved_state, &scope);
+        });
+    }
+
+    /// playhead_ms is in sequence time, only used when pivoting on the playhead
+    pub fn scale_scope(
+        &mut self,
+        sequence_id: String,
+        signal: Option<RwSignal<Sequence>>,
+        scope: RetimeScope,
+        factor: f32,
+        pivot: Pivot,
+        playhead_ms: i64,
+    ) {
+        self.edit_sequence_and_project(sequence_id, signal, |sequence, saved_state| {
+            let pivot_ms = retime::pivot_time(sequence, saved_state, &scope, pivot, playhead_ms);
+            retime::scale_keyframes(sequence, saved_state, &scope, factor, pivot_ms);
+        });
+    }
+
+    pub fn offset_scope(
+        &mut self,
+        sequence_id: String,
+        signal: Option<RwSignal<Sequence>>,
+        scope: RetimeScope,
+        offset_ms: i64,
+    ) {
+        self.edit_sequence_and_project(sequence_id, signal, |sequence, saved_state| {
+            retime::offset_keyframes(sequence, saved_state, &scope, offset_ms);
+        });
+    }
+
+    /// Write a value of an object at the keyframe playhead, as auto-key does for canvas moves
+    pub fn auto_key(
+        &mut self,
+        sequence_id: String,
+        signal: Option<RwSignal<Sequence>>,
+        object_id: &str,
+        property_path: &str,
+        value: KeyframeValue,
+    ) {
+        let playhead = self.keyframe_playhead.get_untracked();
+        let frame_rate = self.frame_rate.get_untracked();
+
+        self.edit_sequence(sequence_id, signal, |sequence| {
+            auto_key_object(
+                sequence,
+                object_id,
+                property_path,
+                playhead,
+                value,
+                frame_rate,
+            );
+        });
+    }
+
+    pub fn selected_object_id(&self) -> Option<Uuid> {
+        if self.polygon_selected {
+            Some(self.selected_polygon_id)
+        } else if self.text_selected {
+            Some(self.selected_text_id)
+        } else if self.image_selected {
+            Some(self.selected_image_id)
+        } else if self.video_selected {
+            Some(self.selected_video_id)
+        } else {
+            None
+        }
+    }
+
+    /// Write the recorded pointer path into the object's position keyframes as one undo step
+    pub fn finish_motion_recording(&mut self, sequence_id: String) {
+        let recorder = match self.motion_recorder.take() {
+            Some(recorder) => recorder,
+            None => return,
+        };
+
+        // one pixel keeps the shape while dropping most of the per-frame samples
+        let tolerance = if self.simplify_recording.get() {
+            Some(1.0)
+        } else {
+            None
+        };
+        let signal = self.sequence_data_signal;
+
+        self.edit_sequence(sequence_id, signal, |sequence| {
+            if let Some(animation) = sequence
+                .polygon_motion_paths
+                .iter_mut()
+                .find(|pm| pm.polygon_id == recorder.object_id)
+            {
+                if recorder.apply(animation, tolerance) {
+                    println!("Recorded {} motion samples", recorder.samples.len());
+                }
+            }
+        });
+    }
+
+    /// Run an edit on a copy of the saved state and record what it changed beside the sequences
+    /// as a single undo step, see ProjectEdit. Changes to the sequences themselves are dropped,
+    /// those go through edit_sequence.
+    pub fn edit_project<R>(&mut self, edit: impl FnOnce(&mut SavedState) -> R) -> R {
+        let old_state = self
+            .record_state
+            .saved_state
+            .clone()
+            .expect("Couldn't get Saved State");
+        let mut new_state = old_state.clone();
+
+        let result = edit(&mut new_state);
+        self.record_project_edit(old_state, new_state);
+
+        result
+    }
+
+    /// Like edit_project, recording nothing when the edit fails
+    pub fn try_edit_project<R>(
+        &mut self,
+        edit: impl FnOnce(&mut SavedState) -> Result<R, String>,
+    ) -> Result<R, String> {
+        let old_state = self
+            .record_state
+            .saved_state
+            .clone()
+            .expect("Couldn't get Saved State");
+        let mut new_state = old_state.clone();
+
+        let result = edit(&mut new_state)?;
+        self.record_project_edit(old_state, new_state);
+
+        Ok(result)
+    }
+
+    fn record_project_edit(&mut self, old_state: SavedState, new_state: SavedState) {
+        if let Some(edit) = self.project_edit(old_state, new_state) {
+            let mut record = self.record.lock().unwrap();
+            record.edit(&mut self.record_state, StateEdit::Project(edit));
+        }
+    }
+
+    /// What changed beside the sequences between two saved states, none when nothing did
+    fn project_edit(
         &self,
-        animation: AnimationData,
-        target_duration: Duration,
-    ) -> AnimationData {
-        let original_duration = animation.duration;
-        let scale_factor = target_duration.as_secs_f32() / original_duration.as_secs_f32();
-
-        // Create a new animation with scaled keyframes
-        let mut new_animation = animation.clone();
-        new_animation.duration = target_duration;
-
-        // Scale each property's keyframes
-        for property in &mut new_animation.properties {
-            for keyframe in &mut property.keyframes {
-                let original_time_secs = keyframe.time.as_secs_f32();
-                let new_time_secs = original_time_secs * scale_factor;
-                keyframe.time = Duration::from_secs_f32(new_time_secs);
+        mut old_state: SavedState,
+        mut new_state: SavedState,
+    ) -> Option<ProjectEdit> {
+        old_state.sequences.clear();
+        new_state.sequences.clear();
+
+        if old_state == new_state {
+            return None;
+        }
+
+        Some(ProjectEdit {
+            old_state,
+            new_state,
+            signals: ProjectSignals {
+                color_tracks: self.color_tracks,
+                shape_tracks: self.shape_tracks,
+                text_animators: self.text_animators,
+                hold_keyframes: self.hold_keyframes,
+                frame_rate: self.frame_rate,
+                markers: self.markers,
+                nested_sequences: self.nested_sequences,
+                timeline: self.timeline_signals,
+            },
+        })
+    }
+
+    /// Drop everything kept beside the sequences for an animation whose object was deleted
+    pub fn remove_animation_tables(&mut self, animation: &AnimationData) {
+        let mut keyframe_ids = Vec::new();
+        let mut properties: Vec<&AnimationProperty> = animation.properties.iter().collect();
+
+        while let Some(property) = properties.pop() {
+            keyframe_ids.extend(property.keyframes.iter().map(|k| k.id.clone()));
+            properties.extend(property.children.iter());
+        }
+
+        self.edit_project(|saved_state| {
+            saved_state
+                .color_tracks
+                .iter()
+                .filter(|t| t.animation_id == animation.id)
+                .flat_map(|t| t.keyframes.iter().map(|k| k.id.clone()))
+                .chain(
+                    saved_state
+                        .shape_tracks
+                        .iter()
+                        .filter(|t| t.animation_id == animation.id)
+                        .flat_map(|t| t.keyframes.iter().map(|k| k.id.clone())),
+                )
+                .for_each(|id| keyframe_ids.push(id));
+
+            saved_state
+                .animation_repeats
+                .retain(|r| r.animation_id != animation.id);
+            saved_state
+                .property_modifiers
+                .retain(|m| m.animation_id != animation.id);
+            saved_state
+                .color_tracks
+                .retain(|t| t.animation_id != animation.id);
+            saved_state
+                .shape_tracks
+                .retain(|t| t.animation_id != animation.id);
+            saved_state
+                .text_animators
+                .retain(|a| a.animation_id != animation.id);
+            saved_state
+                .hold_keyframes
+                .retain(|id| !keyframe_ids.contains(id));
+        });
+    }
+
+    pub fn set_animation_repeat(&mut self, animation_id: String, mode: RepeatMode) {
+        self.edit_project(|saved_state| {
+            saved_state
+                .animation_repeats
+                .retain(|r| r.animation_id != animation_id);
+
+            if mode != RepeatMode::Once {
+                saved_state
+                    .animation_repeats
+                    .push(AnimationRepeat { animation_id, mode });
+            }
+        });
+    }
+
+    pub fn add_property_modifier(&mut self, modifier: PropertyModifier) {
+        self.edit_project(|saved_state| {
+            saved_state.property_modifiers.push(modifier);
+        });
+    }
+
+    pub fn remove_property_modifier(&mut self, modifier_id: &str) {
+        self.edit_project(|saved_state| {
+            saved_state
+                .property_modifiers
+                .retain(|m| m.id != modifier_id);
+        });
+    }
+
+    /// modifiers attached to one property of an animation
+    pub fn get_property_modifiers(
+        &self,
+        animation_id: &str,
+        property_path: &str,
+    ) -> Vec<PropertyModifier> {
+        self.record_state
+            .saved_state
+            .as_ref()
+            .map(|saved_state| {
+                saved_state
+                    .property_modifiers
+                    .iter()
+                    .filter(|m| m.animation_id == animation_id && m.property_path == property_path)
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Key a color at time_ms, replacing a keyframe on the same frame. Returns the keyframe id.
+    pub fn key_color(
+        &mut self,
+        animation_id: String,
+        target: ColorTarget,
+        time_ms: u64,
+        color: [i32; 4],
+    ) -> String {
+        self.edit_project(|saved_state| {
+            let track_index = match saved_state
+                .color_tracks
+                .iter()
+                .position(|t| t.animation_id == animation_id && t.target == target)
+            {
+                Some(index) => index,
+                None => {
+                    saved_state.color_tracks.push(ColorTrack {
+                        animation_id,
+                        target,
+                        keyframes: Vec::new(),
+                    });
+                    saved_state.color_tracks.len() - 1
+                }
+            };
+
+            let half_frame_ms = 500 / project_frame_rate(saved_state) as u64;
+            let track = &mut saved_state.color_tracks[track_index];
+
+            let id = match track
+                .keyframes
+                .iter_mut()
+                .find(|k| k.time_ms.abs_diff(time_ms) <= half_frame_ms)
+            {
+                Some(keyframe) => {
+                    keyframe.color = color;
+                    keyframe.id.clone()
+                }
+                None => {
+                    let id = Uuid::new_v4().to_string();
+                    track.keyframes.push(ColorKeyframe {
+                        id: id.clone(),
+                        time_ms,
+                        color,
+                        easing: EasingType::EaseInOut,
+                    });
+                    id
+                }
+            };
+
+            track.keyframes.sort_by_key(|k| k.time_ms);
+
+            id
+        })
+    }
+
+    /// replace a color keyframe by id, for time and color edits
+    pub fn update_color_keyframe(&mut self, keyframe: ColorKeyframe) {
+        self.edit_project(|saved_state| {
+            saved_state.color_tracks.iter_mut().for_each(|t| {
+                if let Some(existing) = t.keyframes.iter_mut().find(|k| k.id == keyframe.id) {
+                    *existing = keyframe.clone();
+                    t.keyframes.sort_by_key(|k| k.time_ms);
+                }
+            });
+        });
+    }
+
+    pub fn remove_color_keyframe(&mut self, keyframe_id: &str) {
+        self.edit_project(|saved_state| {
+            saved_state.color_tracks.iter_mut().for_each(|t| {
+                t.keyframes.retain(|k| k.id != keyframe_id);
+            });
+            saved_state.color_tracks.retain(|t| !t.keyframes.is_empty());
+            saved_state.hold_keyframes.retain(|id| id != keyframe_id);
+        });
+    }
+
+    /// Key a dimension at time_ms, replacing a keyframe on the same frame. Returns the keyframe id.
+    pub fn key_shape(
+        &mut self,
+        animation_id: String,
+        property: ShapeProperty,
+        time_ms: u64,
+        value: i32,
+    ) -> String {
+        self.edit_project(|saved_state| {
+            let track_index = match saved_state
+                .shape_tracks
+                .iter()
+                .position(|t| t.animation_id == animation_id && t.property == property)
+            {
+                Some(index) => index,
+                None => {
+                    saved_state.shape_tracks.push(ShapeTrack {
+                        animation_id,
+                        property,
+                        keyframes: Vec::new(),
+                    });
+                    saved_state.shape_tracks.len() - 1
+                }
+            };
+
+            let half_frame_ms = 500 / project_frame_rate(saved_state) as u64;
+            let track = &mut saved_state.shape_tracks[track_index];
+
+            let id = match track
+                .keyframes
+                .iter_mut()
+                .find(|k| k.time_ms.abs_diff(time_ms) <= half_frame_ms)
+            {
+                Some(keyframe) => {
+                    keyframe.value = value;
+                    keyframe.id.clone()
+                }
+                None => {
+                    let id = Uuid::new_v4().to_string();
+                    track.keyframes.push(ShapeKeyframe {
+                        id: id.clone(),
+                        time_ms,
+                        value,
+                        easing: EasingType::EaseInOut,
+                    });
+                    id
+                }
+            };
+
+            track.keyframes.sort_by_key(|k| k.time_ms);
+
+            id
+        })
+    }
+
+    /// replace a shape keyframe by id, for time and value edits
+    pub fn update_shape_keyframe(&mut self, keyframe: ShapeKeyframe) {
+        self.edit_project(|saved_state| {
+            saved_state.shape_tracks.iter_mut().for_each(|t| {
+                if let Some(existing) = t.keyframes.iter_mut().find(|k| k.id == keyframe.id) {
+                    *existing = keyframe.clone();
+                    t.keyframes.sort_by_key(|k| k.time_ms);
+                }
+            });
+        });
+    }
+
+    pub fn remove_shape_keyframe(&mut self, keyframe_id: &str) {
+        self.edit_project(|saved_state| {
+            saved_state.shape_tracks.iter_mut().for_each(|t| {
+                t.keyframes.retain(|k| k.id != keyframe_id);
+            });
+            saved_state.shape_tracks.retain(|t| !t.keyframes.is_empty());
+            saved_state.hold_keyframes.retain(|id| id != keyframe_id);
+        });
+    }
+
+    /// add or replace a text animator by id
+    pub fn save_text_animator(&mut self, animator: TextAnimator) {
+        self.edit_project(|saved_state| {
+            match saved_state
+                .text_animators
+                .iter_mut()
+                .find(|a| a.id == animator.id)
+            {
+                Some(existing) => *existing = animator,
+                None => saved_state.text_animators.push(animator),
+            }
+        });
+    }
+
+    pub fn remove_text_animator(&mut self, animator_id: &str) {
+        self.edit_project(|saved_state| {
+            saved_state.text_animators.retain(|a| a.id != animator_id);
+        });
+    }
+
+    /// Key the selector range of an animator at time_ms, replacing a keyframe on the same frame
+    pub fn key_text_selector(&mut self, animator_id: &str, time_ms: u64, start: i32, end: i32) {
+        self.edit_project(|saved_state| {
+            let half_frame_ms = 500 / project_frame_rate(saved_state) as u64;
+
+            let animator = match saved_state
+                .text_animators
+                .iter_mut()
+                .find(|a| a.id == animator_id)
+            {
+                Some(animator) => animator,
+                None => return,
+            };
+
+            match animator
+                .selector
+                .iter_mut()
+                .find(|k| k.time_ms.abs_diff(time_ms) <= half_frame_ms)
+            {
+                Some(keyframe) => {
+                    keyframe.start = start;
+                    keyframe.end = end;
+                }
+                None => animator.selector.push(SelectorKeyframe {
+                    id: Uuid::new_v4().to_string(),
+                    time_ms,
+                    start,
+                    end,
+                }),
+            }
+
+            animator.selector.sort_by_key(|k| k.time_ms);
+        });
+    }
+
+    /// Make a keyframe jump to the next one instead of interpolating, or back
+    pub fn set_keyframe_hold(&mut self, keyframe_id: &str, hold: bool) {
+        self.edit_project(|saved_state| {
+            let is_hold = saved_state
+                .hold_keyframes
+                .iter()
+                .any(|id| id == keyframe_id);
+
+            if is_hold == hold {
+                return;
+            }
+
+            if hold {
+                saved_state.hold_keyframes.push(keyframe_id.to_string());
+            } else {
+                saved_state.hold_keyframes.retain(|id| id != keyframe_id);
             }
+        });
+    }
+
+    pub fn set_frame_rate(&mut self, frame_rate: u32) {
+        if !FRAME_RATES.contains(&frame_rate) {
+            return;
         }
 
-        // animations.push(new_animation);
+        self.edit_project(|saved_state| {
+            saved_state.frame_rate = frame_rate;
+        });
+    }
+
+    /// Add a marker, or a region when given an end, to the sequence timeline or a sequence
+    pub fn add_marker(
+        &mut self,
+        sequence_id: Option<String>,
+        time_ms: i32,
+        end_time_ms: Option<i32>,
+    ) -> String {
+        self.edit_project(|saved_state| {
+            let count = saved_state
+                .markers
+                .iter()
+                .filter(|m| {
+                    m.sequence_id == sequence_id && m.end_time_ms.is_some() == end_time_ms.is_some()
+                })
+                .count();
+            let label = match end_time_ms {
+                Some(_) => format!("Region {}", count + 1),
+                None => format!("Marker {}", count + 1),
+            };
+
+            let marker = Marker {
+                id: Uuid::new_v4().to_string(),
+                sequence_id,
+                time_ms: time_ms.max(0),
+                end_time_ms: end_time_ms.map(|end| end.max(time_ms + 1)),
+                label,
+                color: MARKER_COLORS[0].1,
+                note: String::new(),
+            };
+            let marker_id = marker.id.clone();
+
+            saved_state.markers.push(marker);
+
+            marker_id
+        })
+    }
+
+    /// Replace a marker's label, color, note or times
+    pub fn update_marker(&mut self, marker: Marker) {
+        self.edit_project(|saved_state| {
+            if let Some(existing) = saved_state.markers.iter_mut().find(|m| m.id == marker.id) {
+                *existing = marker;
+            }
+        });
+    }
+
+    pub fn remove_marker(&mut self, marker_id: &str) {
+        self.edit_project(|saved_state| {
+            saved_state.markers.retain(|m| m.id != marker_id);
+        });
+    }
+
+    /// Place a sequence inside another, starting at a time in the parent.
+    /// Refused when the parent would end up inside itself.
+    pub fn add_nested_sequence(
+        &mut self,
+        parent_sequence_id: &str,
+        child_sequence_id: &str,
+        start_time_ms: i32,
+    ) -> Result<NestedSequence, String> {
+        self.try_edit_project(|saved_state| {
+            if !saved_state
+                .sequences
+                .iter()
+                .any(|s| s.id == parent_sequence_id)
+            {
+                return Err("Sequence not found".to_string());
+            }
+
+            let child = saved_state
+                .sequences
+                .iter()
+                .find(|s| s.id == child_sequence_id)
+                .ok_or_else(|| "Nested sequence not found".to_string())?;
+
+            if nested::creates_cycle(
+                &saved_state.nested_sequences,
+                parent_sequence_id,
+                child_sequence_id,
+            ) {
+                return Err("A sequence can't be nested inside itself".to_string());
+            }
+
+            let nested_sequence = NestedSequence {
+                id: Uuid::new_v4().to_string(),
+                parent_sequence_id: parent_sequence_id.to_string(),
+                child_sequence_id: child_sequence_id.to_string(),
+                name: child.name.clone(),
+                start_time_ms: start_time_ms.max(0),
+                time_offset_ms: 0,
+                speed_pct: 100,
+                properties: nested::default_placement(),
+            };
+
+            saved_state.nested_sequences.push(nested_sequence.clone());
+
+            Ok(nested_sequence)
+        })
+    }
+
+    /// Replace the timing or placement of a nested sequence, it stays in the same parent
+    pub fn update_nested_sequence(&mut self, nested_sequence: NestedSequence) {
+        self.edit_project(|saved_state| {
+            let existing = match saved_state
+                .nested_sequences
+                .iter_mut()
+                .find(|n| n.id == nested_sequence.id)
+            {
+                Some(existing) => existing,
+                None => return,
+            };
+
+            *existing = NestedSequence {
+                parent_sequence_id: existing.parent_sequence_id.clone(),
+                child_sequence_id: existing.child_sequence_id.clone(),
+                start_time_ms: nested_sequence.start_time_ms.max(0),
+                speed_pct: nested_sequence
+                    .speed_pct
+                    .clamp(nested::MIN_SPEED_PCT, nested::MAX_SPEED_PCT),
+                ..nested_sequence
+            };
+        });
+    }
+
+    pub fn remove_nested_sequence(&mut self, nested_sequence_id: &str) {
+        self.edit_project(|saved_state| {
+            saved_state
+                .nested_sequences
+                .retain(|n| n.id != nested_sequence_id);
+        });
+    }
+
+    /// New video tracks go above the others so they overlay them, audio tracks go last
+    pub fn add_track(&mut self, kind: TrackType) {
+        self.edit_project(|saved_state| {
+            ensure_tracks(saved_state);
+
+            let track = new_track(&saved_state.tracks, kind.clone());
+
+            match kind {
+                TrackType::Video => {
+                    let index = saved_state
+                        .tracks
+                        .iter()
+                        .position(|t| t.kind == TrackType::Video)
+                        .unwrap_or(0);
+
+                    saved_state.tracks.insert(index, track);
+                }
+                TrackType::Audio => saved_state.tracks.push(track),
+            }
+        });
+    }
+
+    /// Save a renamed, locked or muted track
+    pub fn update_track(&mut self, track: TimelineTrack) {
+        self.edit_project(|saved_state| {
+            ensure_tracks(saved_state);
+
+            if let Some(existing) = saved_state.tracks.iter_mut().find(|t| t.id == track.id) {
+                *existing = track;
+            }
+        });
+    }
+
+    /// Ids of the timeline sequences on a track
+    pub fn track_sequence_ids(&self, track_id: &str) -> Vec<String> {
+        let saved_state = match self.record_state.saved_state.as_ref() {
+            Some(saved_state) => saved_state,
+            None => return Vec::new(),
+        };
+
+        let tracks = project_tracks(saved_state);
+
+        saved_state
+            .timeline_state
+            .timeline_sequences
+            .iter()
+            .filter(|ts| {
+                track_of(&tracks, &saved_state.track_assignments, ts).map(|t| t.id.as_str())
+                    == Some(track_id)
+            })
+            .map(|ts| ts.id.clone())
+            .collect()
+    }
+
+    /// Remove a track. A track with sequences on it is only removed along with them when
+    /// remove_sequences is set, taking their trims and transitions too.
+    /// The last track of a type stays, new sequences need somewhere to go.
+    pub fn remove_track(&mut self, track_id: &str, remove_sequences: bool) -> Result<(), String> {
+        let removed = self.track_sequence_ids(track_id);
+
+        self.try_edit_project(|saved_state| {
+            ensure_tracks(saved_state);
+
+            let kind = match saved_state.tracks.iter().find(|t| t.id == track_id) {
+                Some(track) => track.kind.clone(),
+                None => return Err("Track not found".to_string()),
+            };
+
+            if saved_state.tracks.iter().filter(|t| t.kind == kind).count() <= 1 {
+                return Err("Can't remove the last track of its type".to_string());
+            }
+
+            if !removed.is_empty() && !remove_sequences {
+                return Err(format!("Track still has {} sequences on it", removed.len()));
+            }
+
+            saved_state
+                .timeline_state
+                .timeline_sequences
+                .retain(|ts| !removed.contains(&ts.id));
+            saved_state
+                .track_assignments
+                .retain(|a| a.track_id != track_id && !removed.contains(&a.timeline_sequence_id));
+            saved_state
+                .clip_trims
+                .retain(|t| !removed.contains(&t.timeline_sequence_id));
+            saved_state.transitions.retain(|t| {
+                !removed.contains(&t.from_timeline_sequence_id)
+                    && !removed.contains(&t.to_timeline_sequence_id)
+            });
+            saved_state.tracks.retain(|t| t.id != track_id);
+
+            Ok(())
+        })
+    }
+
+    /// Move a track up (negative) or down (positive) among the tracks of its type
+    pub fn move_track(&mut self, track_id: &str, offset: i32) {
+        self.edit_project(|saved_state| {
+            ensure_tracks(saved_state);
+
+            let kind = match saved_state.tracks.iter().find(|t| t.id == track_id) {
+                Some(track) => track.kind.clone(),
+                None => return,
+            };
+            // where the tracks of the same type are, top to bottom
+            let slots: Vec<usize> = saved_state
+                .tracks
+                .iter()
+                .enumerate()
+                .filter(|(_, t)| t.kind == kind)
+                .map(|(index, _)| index)
+                .collect();
+            let slot = match slots
+                .iter()
+                .position(|index| saved_state.tracks[*index].id == track_id)
+            {
+                Some(slot) => slot,
+                None => return,
+            };
+            let target = (slot as i32 + offset).clamp(0, slots.len() as i32 - 1) as usize;
+
+            let track = saved_state.tracks.remove(slots[slot]);
+            saved_state.tracks.insert(slots[target], track);
+        });
+    }
+
+    /// Put a timeline sequence on another track of the same type
+    pub fn assign_track(
+        &mut self,
+        timeline_sequence_id: &str,
+        track_id: &str,
+    ) -> Result<(), String> {
+        self.try_edit_project(|saved_state| {
+            assign_to_track(saved_state, timeline_sequence_id, track_id)
+        })
+    }
+
+    /// Move a timeline sequence to a new start, and onto another track when dropped over one,
+    /// as a single undo step
+    pub fn move_clip(
+        &mut self,
+        timeline_sequence_id: &str,
+        start_ms: i32,
+        track_id: Option<&str>,
+    ) -> Result<(), String> {
+        self.try_edit_project(|saved_state| {
+            let timeline_sequence = saved_state
+                .timeline_state
+                .timeline_sequences
+                .iter_mut()
+                .find(|ts| ts.id == timeline_sequence_id)
+                .ok_or_else(|| "Timeline sequence not found".to_string())?;
+
+            timeline_sequence.start_time_ms = start_ms;
+
+            match track_id {
+                Some(track_id) => assign_to_track(saved_state, timeline_sequence_id, track_id),
+                None => Ok(()),
+            }
+        })
+    }
+
+    /// Add or replace the transition between two timeline sequences
+    pub fn save_transition(&mut self, transition: SequenceTransition) {
+        self.edit_project(|saved_state| {
+            saved_state.transitions.retain(|t| {
+                t.from_timeline_sequence_id != transition.from_timeline_sequence_id
+                    || t.to_timeline_sequence_id != transition.to_timeline_sequence_id
+            });
+            saved_state.transitions.push(transition);
+        });
+    }
+
+    /// Back to a hard cut between two timeline sequences
+    pub fn remove_transition(
+        &mut self,
+        from_timeline_sequence_id: &str,
+        to_timeline_sequence_id: &str,
+    ) {
+        self.edit_project(|saved_state| {
+            saved_state.transitions.retain(|t| {
+                t.from_timeline_sequence_id != from_timeline_sequence_id
+                    || t.to_timeline_sequence_id != to_timeline_sequence_id
+            });
+        });
+    }
+
+    /// Run a clip edit against the saved timeline and trims, recording it only when it succeeds
+    fn edit_clips<R>(
+        &mut self,
+        edit: impl FnOnce(&mut SavedState, &HashMap<String, i32>) -> Result<R, String>,
+    ) -> Result<R, String> {
+        self.try_edit_project(|saved_state| {
+            let durations: HashMap<String, i32> = saved_state
+                .sequences
+                .iter()
+                .map(|s| (s.id.clone(), s.duration_ms))
+                .collect();
+
+            edit(saved_state, &durations)
+        })
+    }
+
+    pub fn trim_clip_in(
+        &mut self,
+        timeline_sequence_id: &str,
+        start_ms: i32,
+    ) -> Result<(), String> {
+        self.edit_clips(|saved_state, durations| {
+            clips::trim_in(
+                &mut saved_state.timeline_state,
+                &mut saved_state.clip_trims,
+                |id| durations.get(id).copied(),
+                timeline_sequence_id,
+                start_ms,
+            )
+        })
+    }
+
+    pub fn trim_clip_out(&mut self, timeline_sequence_id: &str, end_ms: i32) -> Result<(), String> {
+        self.edit_clips(|saved_state, durations| {
+            clips::trim_out(
+                &mut saved_state.timeline_state,
+                &mut saved_state.clip_trims,
+                |id| durations.get(id).copied(),
+                timeline_sequence_id,
+                end_ms,
+            )
+        })
+    }
+
+    /// Split a clip at a timeline time. The second half stays on the same track
+    /// and takes over the transition out of the clip.
+    pub fn split_clip(
+        &mut self,
+        timeline_sequence_id: &str,
+        time_ms: i32,
+    ) -> Result<String, String> {
+        self.edit_clips(|saved_state, durations| {
+            let second_id = clips::split_clip(
+                &mut saved_state.timeline_state,
+                &mut saved_state.clip_trims,
+                |id| durations.get(id).copied(),
+                timeline_sequence_id,
+                time_ms,
+            )?;
+
+            if let Some(assignment) = saved_state
+                .track_assignments
+                .iter()
+                .find(|a| a.timeline_sequence_id == timeline_sequence_id)
+                .cloned()
+            {
+                saved_state.track_assignments.push(TrackAssignment {
+                    timeline_sequence_id: second_id.clone(),
+                    track_id: assignment.track_id,
+                });
+            }
+
+            saved_state
+                .transitions
+                .iter_mut()
+                .filter(|t| t.from_timeline_sequence_id == timeline_sequence_id)
+                .for_each(|t| t.from_timeline_sequence_id = second_id.clone());
+
+            Ok(second_id)
+        })
+    }
+
+    /// Remove a clip and close the gap it leaves
+    pub fn ripple_delete_clip(&mut self, timeline_sequence_id: &str) -> Result<(), String> {
+        self.edit_clips(|saved_state, durations| {
+            clips::ripple_delete(
+                &mut saved_state.timeline_state,
+                &mut saved_state.clip_trims,
+                |id| durations.get(id).copied(),
+                timeline_sequence_id,
+            )?;
+
+            saved_state
+                .track_assignments
+                .retain(|a| a.timeline_sequence_id != timeline_sequence_id);
+            saved_state.transitions.retain(|t| {
+                t.from_timeline_sequence_id != timeline_sequence_id
+                    && t.to_timeline_sequence_id != timeline_sequence_id
+            });
 
-        new_animation
+            Ok(())
+        })
+    }
+
+    pub fn ripple_move_clip(
+        &mut self,
+        timeline_sequence_id: &str,
+        start_ms: i32,
+    ) -> Result<(), String> {
+        self.edit_clips(|saved_state, _| {
+            clips::ripple_move(
+                &mut saved_state.timeline_state,
+                timeline_sequence_id,
+                start_ms,
+            )
+        })
     }
 
     pub fn add_saved_polygon(
@@ -1628,7 +2910,7 @@ impl EditorState {
         };
 
         let mut record = self.record.lock().unwrap();
-        record.edit(&mut self.record_state, edit);
+        record.edit(&mut self.record_state, StateEdit::Object(edit));
 
         Ok(())
     }
@@ -1672,7 +2954,7 @@ impl EditorState {
         };
 
         let mut record = self.record.lock().unwrap();
-        record.edit(&mut self.record_state, edit);
+        record.edit(&mut self.record_state, StateEdit::Object(edit));
 
         Ok(())
     }
@@ -1709,7 +2991,7 @@ impl EditorState {
         };
 
         let mut record = self.record.lock().unwrap();
-        record.edit(&mut self.record_state, edit);
+        record.edit(&mut self.record_state, StateEdit::Object(edit));
 
         Ok(())
     }
@@ -1747,7 +3029,7 @@ impl EditorState {
         };
 
         let mut record = self.record.lock().unwrap();
-        record.edit(&mut self.record_state, edit);
+        record.edit(&mut self.record_state, StateEdit::Object(edit));
 
         Ok(())
     }
@@ -1784,7 +3066,7 @@ impl EditorState {
         };
 
         let mut record = self.record.lock().unwrap();
-        record.edit(&mut self.record_state, edit);
+        record.edit(&mut self.record_state, StateEdit::Object(edit));
 
         Ok(())
     }
@@ -1820,7 +3102,7 @@ impl EditorState {
         };
 
         let mut record = self.record.lock().unwrap();
-        record.edit(&mut self.record_state, edit);
+        record.edit(&mut self.record_state, StateEdit::Object(edit));
 
         Ok(())
     }
@@ -1857,7 +3139,7 @@ impl EditorState {
         };
 
         let mut record = self.record.lock().unwrap();
-        record.edit(&mut self.record_state, edit);
+        record.edit(&mut self.record_state, StateEdit::Object(edit));
 
         Ok(())
     }
@@ -1893,7 +3175,7 @@ impl EditorState {
         };
 
         let mut record = self.record.lock().unwrap();
-        record.edit(&mut self.record_state, edit);
+        record.edit(&mut self.record_state, StateEdit::Object(edit));
 
         Ok(())
     }
@@ -1925,7 +3207,7 @@ impl EditorState {
         };
 
         let mut record = self.record.lock().unwrap();
-        record.edit(&mut self.record_state, edit);
+        record.edit(&mut self.record_state, StateEdit::Object(edit));
 
         Ok(())
     }
@@ -1958,7 +3240,7 @@ impl EditorState {
         };
 
         let mut record = self.record.lock().unwrap();
-        record.edit(&mut self.record_state, edit);
+        record.edit(&mut self.record_state, StateEdit::Object(edit));
 
         Ok(())
     }
@@ -1990,7 +3272,7 @@ impl EditorState {
         };
 
         let mut record = self.record.lock().unwrap();
-        record.edit(&mut self.record_state, edit);
+        record.edit(&mut self.record_state, StateEdit::Object(edit));
 
         Ok(())
     }
@@ -2023,7 +3305,7 @@ impl EditorState {
         };
 
         let mut record = self.record.lock().unwrap();
-        record.edit(&mut self.record_state, edit);
+        record.edit(&mut self.record_state, StateEdit::Object(edit));
 
         Ok(())
     }
@@ -2059,7 +3341,7 @@ impl EditorState {
         };
 
         let mut record = self.record.lock().unwrap();
-        record.edit(&mut self.record_state, edit);
+        record.edit(&mut self.record_state, StateEdit::Object(edit));
 
         Ok(())
     }
@@ -2092,7 +3374,7 @@ impl EditorState {
         };
 
         let mut record = self.record.lock().unwrap();
-        record.edit(&mut self.record_state, edit);
+        record.edit(&mut self.record_state, StateEdit::Object(edit));
 
         Ok(())
     }
@@ -2125,7 +3407,7 @@ impl EditorState {
         };
 
         let mut record = self.record.lock().unwrap();
-        record.edit(&mut self.record_state, edit);
+        record.edit(&mut self.record_state, StateEdit::Object(edit));
 
         Ok(())
     }
@@ -2158,7 +3440,7 @@ impl EditorState {
         };
 
         let mut record = self.record.lock().unwrap();
-        record.edit(&mut self.record_state, edit);
+        record.edit(&mut self.record_state, StateEdit::Object(edit));
 
         Ok(())
     }
@@ -2180,3 +3462,42 @@ impl EditorState {
         }
     }
 }
+
+/// Assign a timeline sequence to a track of the same type
+fn assign_to_track(
+    saved_state: &mut SavedState,
+    timeline_sequence_id: &str,
+    track_id: &str,
+) -> Result<(), String> {
+    ensure_tracks(saved_state);
+
+    let track = saved_state
+        .tracks
+        .iter()
+        .find(|t| t.id == track_id)
+        .ok_or_else(|| "Track not found".to_string())?;
+    let timeline_sequence = saved_state
+        .timeline_state
+        .timeline_sequences
+        .iter()
+        .find(|ts| ts.id == timeline_sequence_id)
+        .ok_or_else(|| "Timeline sequence not found".to_string())?;
+
+    if track.kind != timeline_sequence.track_type {
+        return Err("Sequences can only move between tracks of the same type".to_string());
+    }
+
+    if track.locked {
+        return Err("Track is locked".to_string());
+    }
+
+    saved_state
+        .track_assignments
+        .retain(|a| a.timeline_sequence_id != timeline_sequence_id);
+    saved_state.track_assignments.push(TrackAssignment {
+        timeline_sequence_id: timeline_sequence_id.to_string(),
+        track_id: track_id.to_string(),
+    });
+
+    Ok(())
+}
@@ -0,0 +1,577 @@
+use std::time::Duration;
+
+use stunts_engine::animations::{AnimationData, AnimationProperty, KeyType, Sequence, UIKeyframe};
+use stunts_engine::timelines::{SavedTimelineStateConfig, TimelineSequence};
+use uuid::Uuid;
+
+use super::keyframes::value_from_components;
+use super::modifiers::sample_property;
+use super::saved_state::ClipTrim;
+
+/// shortest a clip can be trimmed to
+pub const MIN_CLIP_MS: i32 = 100;
+
+/// Source offset and length of a clip, the whole sequence when it isn't trimmed
+pub fn clip_range(
+    trims: &[ClipTrim],
+    timeline_sequence_id: &str,
+    sequence_duration_ms: i32,
+) -> (i32, i32) {
+    match trims
+        .iter()
+        .find(|t| t.timeline_sequence_id == timeline_sequence_id)
+    {
+        Some(trim) => (trim.source_offset_ms, trim.duration_ms),
+        None => (0, sequence_duration_ms),
+    }
+}
+
+/// Store a clip's range, untrimmed clips keep no entry
+fn set_trim(
+    trims: &mut Vec<ClipTrim>,
+    timeline_sequence_id: &str,
+    source_offset_ms: i32,
+    duration_ms: i32,
+    sequence_duration_ms: i32,
+) {
+    trims.retain(|t| t.timeline_sequence_id != timeline_sequence_id);
+
+    if source_offset_ms != 0 || duration_ms != sequence_duration_ms {
+        trims.push(ClipTrim {
+            timeline_sequence_id: timeline_sequence_id.to_string(),
+            source_offset_ms,
+            duration_ms,
+        });
+    }
+}
+
+/// The clip with its sequence's duration
+fn find_clip<F>(
+    config: &SavedTimelineStateConfig,
+    duration_of: &F,
+    timeline_sequence_id: &str,
+) -> Result<(TimelineSequence, i32), String>
+where
+    F: Fn(&str) -> Option<i32>,
+{
+    let timeline_sequence = config
+        .timeline_sequences
+        .iter()
+        .find(|ts| ts.id == timeline_sequence_id)
+        .ok_or_else(|| "Timeline sequence not found".to_string())?;
+    let duration_ms = duration_of(&timeline_sequence.sequence_id)
+        .ok_or_else(|| "Sequence duration not found".to_string())?;
+
+    Ok((timeline_sequence.clone(), duration_ms))
+}
+
+/// Move the in point of a clip to a timeline time, the rest of the clip stays put
+pub fn trim_in<F>(
+    config: &mut SavedTimelineStateConfig,
+    trims: &mut Vec<ClipTrim>,
+    duration_of: F,
+    timeline_sequence_id: &str,
+    new_start_ms: i32,
+) -> Result<(), String>
+where
+    F: Fn(&str) -> Option<i32>,
+{
+    let (clip, sequence_duration_ms) = find_clip(config, &duration_of, timeline_sequence_id)?;
+    let (offset_ms, duration_ms) = clip_range(trims, &clip.id, sequence_duration_ms);
+
+    // can't reach before the sequence starts or past the out point
+    let shift = (new_start_ms - clip.start_time_ms).clamp(
+        (-offset_ms).max(-clip.start_time_ms),
+        (duration_ms - MIN_CLIP_MS).max(0),
+    );
+
+    set_trim(
+        trims,
+        &clip.id,
+        offset_ms + shift,
+        duration_ms - shift,
+        sequence_duration_ms,
+    );
+
+    if let Some(ts) = config
+        .timeline_sequences
+        .iter_mut()
+        .find(|ts| ts.id == clip.id)
+    {
+        ts.start_time_ms += shift;
+    }
+
+    Ok(())
+}
+
+/// Move the out point of a clip to a timeline time
+pub fn trim_out<F>(
+    config: &mut SavedTimelineStateConfig,
+    trims: &mut Vec<ClipTrim>,
+    duration_of: F,
+    timeline_sequence_id: &str,
+    new_end_ms: i32,
+) -> Result<(), String>
+where
+    F: Fn(&str) -> Option<i32>,
+{
+    let (clip, sequence_duration_ms) = find_clip(config, &duration_of, timeline_sequence_id)?;
+    let (offset_ms, _) = clip_range(trims, &clip.id, sequence_duration_ms);
+
+    let available_ms = sequence_duration_ms - offset_ms;
+    let duration_ms =
+        (new_end_ms - clip.start_time_ms).clamp(MIN_CLIP_MS.min(available_ms), available_ms);
+
+    set_trim(
+        trims,
+        &clip.id,
+        offset_ms,
+        duration_ms,
+        sequence_duration_ms,
+    );
+
+    Ok(())
+}
+
+/// Split a clip in two at a timeline time. The first half keeps its id,
+/// the id of the new second half is returned.
+pub fn split_clip<F>(
+    config: &mut SavedTimelineStateConfig,
+    trims: &mut Vec<ClipTrim>,
+    duration_of: F,
+    timeline_sequence_id: &str,
+    time_ms: i32,
+) -> Result<String, String>
+where
+    F: Fn(&str) -> Option<i32>,
+{
+    let (clip, sequence_duration_ms) = find_clip(config, &duration_of, timeline_sequence_id)?;
+    let (offset_ms, duration_ms) = clip_range(trims, &clip.id, sequence_duration_ms);
+
+    let first_ms = time_ms - clip.start_time_ms;
+
+    if first_ms < MIN_CLIP_MS || duration_ms - first_ms < MIN_CLIP_MS {
+        return Err("Split point is too close to the clip's edges".to_string());
+    }
+
+    let second = TimelineSequence {
+        id: Uuid::new_v4().to_string(),
+        sequence_id: clip.sequence_id.clone(),
+        track_type: clip.track_type.clone(),
+        start_time_ms: time_ms,
+    };
+
+    set_trim(trims, &clip.id, offset_ms, first_ms, sequence_duration_ms);
+    set_trim(
+        trims,
+        &second.id,
+        offset_ms + first_ms,
+        duration_ms - first_ms,
+        sequence_duration_ms,
+    );
+
+    let index = config
+        .timeline_sequences
+        .iter()
+        .position(|ts| ts.id == clip.id)
+        .map(|index| index + 1)
+        .unwrap_or(config.timeline_sequences.len());
+    let second_id = second.id.clone();
+
+    config.timeline_sequences.insert(index, second);
+
+    Ok(second_id)
+}
+
+/// Shift every clip starting at or after a time, on every track so they stay in sync.
+/// The shift is limited so no clip moves before the start of the timeline.
+fn ripple<P>(config: &mut SavedTimelineStateConfig, shift_ms: i32, is_later: P)
+where
+    P: Fn(&TimelineSequence) -> bool,
+{
+    let earliest = config
+        .timeline_sequences
+        .iter()
+        .filter(|ts| is_later(ts))
+        .map(|ts| ts.start_time_ms)
+        .min();
+
+    let shift_ms = match earliest {
+        Some(earliest) => shift_ms.max(-earliest),
+        None => return,
+    };
+
+    config
+        .timeline_sequences
+        .iter_mut()
+        .filter(|ts| is_later(ts))
+        .for_each(|ts| ts.start_time_ms += shift_ms);
+}
+
+/// Remove a clip and pull the clips after it back to close the gap
+pub fn ripple_delete<F>(
+    config: &mut SavedTimelineStateConfig,
+    trims: &mut Vec<ClipTrim>,
+    duration_of: F,
+    timeline_sequence_id: &str,
+) -> Result<(), String>
+where
+    F: Fn(&str) -> Option<i32>,
+{
+    let (clip, sequence_duration_ms) = find_clip(config, &duration_of, timeline_sequence_id)?;
+    let (_, duration_ms) = clip_range(trims, &clip.id, sequence_duration_ms);
+    let end_ms = clip.start_time_ms + duration_ms;
+
+    config.timeline_sequences.retain(|ts| ts.id != clip.id);
+    trims.retain(|t| t.timeline_sequence_id != clip.id);
+
+    ripple(config, -duration_ms, |ts| ts.start_time_ms >= end_ms);
+
+    Ok(())
+}
+
+/// Move a clip and every clip after it by the same amount
+pub fn ripple_move(
+    config: &mut SavedTimelineStateConfig,
+    timeline_sequence_id: &str,
+    new_start_ms: i32,
+) -> Result<(), String> {
+    let clip = config
+        .timeline_sequences
+        .iter()
+        .find(|ts| ts.id == timeline_sequence_id)
+        .cloned()
+        .ok_or_else(|| "Timeline sequence not found".to_string())?;
+
+    ripple(config, new_start_ms - clip.start_time_ms, |ts| {
+        ts.id == clip.id || ts.start_time_ms >= clip.start_time_ms
+    });
+
+    Ok(())
+}
+
+/// Drop the part of a property before a time, holding the value it had there
+fn cut_property(property: &AnimationProperty, cut: Duration) -> AnimationProperty {
+    let mut new_property = property.clone();
+
+    let boundary = match (
+        sample_property(property, cut, &[]),
+        property.keyframes.first(),
+    ) {
+        (Some(values), Some(first)) => Some(UIKeyframe {
+            id: Uuid::new_v4().to_string(),
+            time: Duration::ZERO,
+            value: value_from_components(&first.value, &values),
+            easing: first.easing.clone(),
+            path_type: first.path_type.clone(),
+            key_type: KeyType::Frame,
+        }),
+        _ => None,
+    };
+
+    new_property.keyframes = boundary
+        .into_iter()
+        .chain(property.keyframes.iter().filter(|k| k.time > cut).map(|k| {
+            let mut keyframe = k.clone();
+            keyframe.time -= cut;
+
+            if let KeyType::Range(range_data) = &mut keyframe.key_type {
+                range_data.end_time = range_data.end_time.saturating_sub(cut);
+            }
+
+            keyframe
+        }))
+        .collect();
+
+    new_property.children = property
+        .children
+        .iter()
+        .map(|c| cut_property(c, cut))
+        .collect();
+
+    new_property
+}
+
+fn trim_animation(animation: &AnimationData, source_offset_ms: i32) -> AnimationData {
+    let mut new_animation = animation.clone();
+    let start_ms = animation.start_time_ms - source_offset_ms;
+
+    if start_ms >= 0 {
+        new_animation.start_time_ms = start_ms;
+        return new_animation;
+    }
+
+    // started before the in point, play on from where it had got to
+    let cut = Duration::from_millis(-start_ms as u64).min(animation.duration);
+
+    new_animation.start_time_ms = 0;
+    new_animation.duration = animation.duration - cut;
+    new_animation.properties = animation
+        .properties
+        .iter()
+        .map(|p| cut_property(p, cut))
+        .collect();
+
+    new_animation
+}
+
+/// Id of the copy of a sequence one timeline clip plays, the same when asked again for the copy
+pub fn placement_id(sequence_id: &str, timeline_sequence_id: &str) -> String {
+    let suffix = format!("-{}", timeline_sequence_id);
+
+    if sequence_id.ends_with(&suffix) {
+        return sequence_id.to_string();
+    }
+
+    format!("{}{}", sequence_id, suffix)
+}
+
+/// Copy of a sequence playing only a clip's range, under its own id. Video items are
+/// timed by the engine, playback seeks them to the offset kept in the timeline model.
+pub fn trimmed_sequence(
+    sequence: &Sequence,
+    timeline_sequence_id: &str,
+    source_offset_ms: i32,
+    duration_ms: i32,
+) -> Sequence {
+    let mut trimmed = sequence.clone();
+
+    trimmed.id = placement_id(&sequence.id, timeline_sequence_id);
+    trimmed.duration_ms = duration_ms;
+    trimmed.polygon_motion_paths = sequence
+        .polygon_motion_paths
+        .iter()
+        .map(|a| trim_animation(a, source_offset_ms))
+        .collect();
+
+    trimmed
+}
+
+/// Give every trimmed clip its own trimmed copy of its sequence
+pub fn apply_trims(
+    sequences: &mut Vec<Sequence>,
+    config: &mut SavedTimelineStateConfig,
+    trims: &[ClipTrim],
+) {
+    for ts in config.timeline_sequences.iter_mut() {
+        let trim = match trims.iter().find(|t| t.timeline_sequence_id == ts.id) {
+            Some(trim) => trim,
+            None => continue,
+        };
+        let sequence = match sequences.iter().find(|s| s.id == ts.sequence_id) {
+            Some(sequence) => sequence,
+            None => continue,
+        };
+
+        let trimmed = trimmed_sequence(sequence, &ts.id, trim.source_offset_ms, trim.duration_ms);
+
+        ts.sequence_id = trimmed.id.clone();
+        sequences.push(trimmed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use stunts_engine::timelines::TrackType;
+
+    use super::*;
+
+    const SEQUENCE_MS: i32 = 1000;
+
+    fn clip(id: &str, start_time_ms: i32) -> TimelineSequence {
+        TimelineSequence {
+            id: id.to_string(),
+            sequence_id: "sequence".to_string(),
+            track_type: TrackType::Video,
+            start_time_ms,
+        }
+    }
+
+    fn timeline(clips: &[(&str, i32)]) -> SavedTimelineStateConfig {
+        SavedTimelineStateConfig {
+            timeline_sequences: clips.iter().map(|(id, start)| clip(id, *start)).collect(),
+        }
+    }
+
+    fn duration_of(_: &str) -> Option<i32> {
+        Some(SEQUENCE_MS)
+    }
+
+    fn start_of(config: &SavedTimelineStateConfig, id: &str) -> i32 {
+        config
+            .timeline_sequences
+            .iter()
+            .find(|ts| ts.id == id)
+            .map(|ts| ts.start_time_ms)
+            .expect("Couldn't find clip")
+    }
+
+    #[test]
+    fn trim_in_moves_the_in_point_and_start() {
+        let mut config = timeline(&[("a", 0)]);
+        let mut trims = Vec::new();
+
+        trim_in(&mut config, &mut trims, duration_of, "a", 300).unwrap();
+
+        assert_eq!(clip_range(&trims, "a", SEQUENCE_MS), (300, 700));
+        assert_eq!(start_of(&config, "a"), 300);
+    }
+
+    #[test]
+    fn trim_in_keeps_the_shortest_clip() {
+        let mut config = timeline(&[("a", 0)]);
+        let mut trims = Vec::new();
+
+        trim_in(&mut config, &mut trims, duration_of, "a", 950).unwrap();
+
+        assert_eq!(
+            clip_range(&trims, "a", SEQUENCE_MS),
+            (SEQUENCE_MS - MIN_CLIP_MS, MIN_CLIP_MS)
+        );
+        assert_eq!(start_of(&config, "a"), SEQUENCE_MS - MIN_CLIP_MS);
+    }
+
+    #[test]
+    fn trim_in_stops_at_the_sequence_start() {
+        let mut config = timeline(&[("a", 500)]);
+        let mut trims = Vec::new();
+
+        trim_in(&mut config, &mut trims, duration_of, "a", 700).unwrap();
+        trim_in(&mut config, &mut trims, duration_of, "a", 0).unwrap();
+
+        // back to the whole sequence, which keeps no trim
+        assert!(trims.is_empty());
+        assert_eq!(start_of(&config, "a"), 500);
+    }
+
+    #[test]
+    fn trim_in_stops_at_the_timeline_start() {
+        let mut config = timeline(&[("a", 0)]);
+        let mut trims = Vec::new();
+
+        trim_in(&mut config, &mut trims, duration_of, "a", -200).unwrap();
+
+        assert!(trims.is_empty());
+        assert_eq!(start_of(&config, "a"), 0);
+    }
+
+    #[test]
+    fn trim_out_moves_the_out_point() {
+        let mut config = timeline(&[("a", 0)]);
+        let mut trims = Vec::new();
+
+        trim_out(&mut config, &mut trims, duration_of, "a", 400).unwrap();
+
+        assert_eq!(clip_range(&trims, "a", SEQUENCE_MS), (0, 400));
+        assert_eq!(start_of(&config, "a"), 0);
+    }
+
+    #[test]
+    fn trim_out_keeps_the_shortest_clip() {
+        let mut config = timeline(&[("a", 0)]);
+        let mut trims = Vec::new();
+
+        trim_out(&mut config, &mut trims, duration_of, "a", 20).unwrap();
+
+        assert_eq!(clip_range(&trims, "a", SEQUENCE_MS), (0, MIN_CLIP_MS));
+    }
+
+    #[test]
+    fn trim_out_stops_at_the_sequence_end() {
+        let mut config = timeline(&[("a", 0)]);
+        let mut trims = Vec::new();
+
+        trim_in(&mut config, &mut trims, duration_of, "a", 300).unwrap();
+        trim_out(&mut config, &mut trims, duration_of, "a", 5000).unwrap();
+
+        assert_eq!(clip_range(&trims, "a", SEQUENCE_MS), (300, 700));
+    }
+
+    #[test]
+    fn split_clip_makes_two_trimmed_halves() {
+        let mut config = timeline(&[("a", 1000), ("b", 3000)]);
+        let mut trims = Vec::new();
+
+        let second = split_clip(&mut config, &mut trims, duration_of, "a", 1400).unwrap();
+
+        assert_eq!(clip_range(&trims, "a", SEQUENCE_MS), (0, 400));
+        assert_eq!(clip_range(&trims, &second, SEQUENCE_MS), (400, 600));
+        assert_eq!(start_of(&config, &second), 1400);
+        assert_eq!(config.timeline_sequences[1].id, second);
+    }
+
+    #[test]
+    fn split_clip_refuses_slivers() {
+        let mut config = timeline(&[("a", 0)]);
+        let mut trims = Vec::new();
+
+        assert!(split_clip(&mut config, &mut trims, duration_of, "a", MIN_CLIP_MS / 2).is_err());
+        assert!(split_clip(
+            &mut config,
+            &mut trims,
+            duration_of,
+            "a",
+            SEQUENCE_MS - MIN_CLIP_MS / 2
+        )
+        .is_err());
+        assert_eq!(config.timeline_sequences.len(), 1);
+        assert!(trims.is_empty());
+    }
+
+    #[test]
+    fn ripple_delete_closes_the_gap() {
+        let mut config = timeline(&[("a", 0), ("b", 1000), ("c", 2500)]);
+        let mut trims = Vec::new();
+
+        trim_out(&mut config, &mut trims, duration_of, "b", 1400).unwrap();
+        ripple_delete(&mut config, &mut trims, duration_of, "b").unwrap();
+
+        assert!(trims.is_empty());
+        assert_eq!(config.timeline_sequences.len(), 2);
+        assert_eq!(start_of(&config, "a"), 0);
+        assert_eq!(start_of(&config, "c"), 2100);
+    }
+
+    #[test]
+    fn ripple_delete_leaves_overlapping_clips() {
+        let mut config = timeline(&[("a", 0), ("b", 300)]);
+        let mut trims = Vec::new();
+
+        ripple_delete(&mut config, &mut trims, duration_of, "a").unwrap();
+
+        // b started before a ended, there's no gap after a to close
+        assert_eq!(start_of(&config, "b"), 300);
+    }
+
+    #[test]
+    fn ripple_move_takes_later_clips_along() {
+        let mut config = timeline(&[("a", 0), ("b", 1000), ("c", 2500)]);
+
+        ripple_move(&mut config, "b", 1500).unwrap();
+
+        assert_eq!(start_of(&config, "a"), 0);
+        assert_eq!(start_of(&config, "b"), 1500);
+        assert_eq!(start_of(&config, "c"), 3000);
+    }
+
+    #[test]
+    fn ripple_move_stops_at_the_timeline_start() {
+        let mut config = timeline(&[("a", 0), ("b", 1000), ("c", 2500)]);
+
+        ripple_move(&mut config, "b", -800).unwrap();
+
+        assert_eq!(start_of(&config, "b"), 0);
+        assert_eq!(start_of(&config, "c"), 1500);
+    }
+
+    #[test]
+    fn missing_clips_are_errors() {
+        let mut config = timeline(&[("a", 0)]);
+        let mut trims = Vec::new();
+
+        assert!(trim_in(&mut config, &mut trims, duration_of, "x", 0).is_err());
+        assert!(trim_out(&mut config, &mut trims, duration_of, "x", 0).is_err());
+        assert!(ripple_delete(&mut config, &mut trims, duration_of, "x").is_err());
+        assert!(ripple_move(&mut config, "x", 0).is_err());
+    }
+}
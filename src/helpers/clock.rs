@@ -0,0 +1,133 @@
+use std::time::{Duration, Instant};
+
+use stunts_engine::editor::Editor;
+
+use super::saved_state::SavedState;
+
+/// frame rate of projects saved before the frame rate setting, see project_frame_rate
+pub const DEFAULT_FRAME_RATE: u32 = 30;
+
+/// frame rates a project can be set to
+pub const FRAME_RATES: [u32; 5] = [24, 25, 30, 50, 60];
+
+/// The project's frame rate, falling back to the default for anything unsupported
+pub fn project_frame_rate(saved_state: &SavedState) -> u32 {
+    if FRAME_RATES.contains(&saved_state.frame_rate) {
+        saved_state.frame_rate
+    } else {
+        DEFAULT_FRAME_RATE
+    }
+}
+
+/// Whole frames reached at a time
+pub fn frame_at(time: Duration, fps: u32) -> u64 {
+    (time.as_nanos() * fps.max(1) as u128 / 1_000_000_000) as u64
+}
+
+/// Time a frame starts at
+pub fn frame_time(frame: u64, fps: u32) -> Duration {
+    Duration::from_nanos((frame as u128 * 1_000_000_000 / fps.max(1) as u128) as u64)
+}
+
+/// HH:MM:SS:FF
+pub fn timecode(time: Duration, fps: u32) -> String {
+    let fps = fps.max(1);
+    let frame = frame_at(time, fps);
+    let seconds = frame / fps as u64;
+
+    format!(
+        "{:02}:{:02}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60,
+        frame % fps as u64
+    )
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ClockMode {
+    /// keeps up with the wall clock, skipping frames when rendering falls behind
+    RealTime,
+    /// shows every frame once, running slower than real time if it has to
+    EveryFrame,
+}
+
+impl ClockMode {
+    pub fn from_every_frame(every_frame: bool) -> Self {
+        if every_frame {
+            ClockMode::EveryFrame
+        } else {
+            ClockMode::RealTime
+        }
+    }
+}
+
+/// Playback position counted in whole frames of the project frame rate,
+/// so preview lands on the same frames export renders
+pub struct PlaybackClock {
+    fps: u32,
+    mode: ClockMode,
+    /// frame at `started`
+    from: u64,
+    started: Instant,
+    frame: u64,
+    /// the first tick shows the starting frame itself
+    ticked: bool,
+}
+
+impl PlaybackClock {
+    pub fn new(fps: u32, mode: ClockMode, from: Duration) -> Self {
+        let from = frame_at(from, fps);
+
+        Self {
+            fps,
+            mode,
+            from,
+            started: Instant::now(),
+            frame: from,
+            ticked: false,
+        }
+    }
+
+    pub fn time(&self) -> Duration {
+        frame_time(self.frame, self.fps)
+    }
+
+    pub fn seek(&mut self, time: Duration) {
+        *self = Self::new(self.fps, self.mode, time);
+    }
+
+    /// Switch modes carrying on from the current frame
+    pub fn set_mode(&mut self, mode: ClockMode) {
+        if mode != self.mode {
+            self.mode = mode;
+            self.from = self.frame;
+            self.started = Instant::now();
+        }
+    }
+
+    /// Move on to the frame to show now and return its time
+    pub fn tick(&mut self) -> Duration {
+        self.frame = match self.mode {
+            ClockMode::RealTime => self.from + frame_at(self.started.elapsed(), self.fps),
+            ClockMode::EveryFrame if self.ticked => self.frame + 1,
+            ClockMode::EveryFrame => self.frame,
+        };
+
+        // real time restarts counting from here if the mode changes
+        if self.mode == ClockMode::EveryFrame {
+            self.from = self.frame;
+            self.started = Instant::now();
+        }
+
+        self.ticked = true;
+
+        self.time()
+    }
+}
+
+/// Have the engine show a sequence at a time on its next step. The engine times
+/// playback from `start_playing_time`, so it's moved to put the time at now.
+pub fn sync_engine(editor: &mut Editor, time: Duration) {
+    editor.start_playing_time = Instant::now().checked_sub(time);
+}
@@ -0,0 +1,134 @@
+use std::time::Duration;
+
+use palette::{IntoColor, LinSrgb, Mix, Oklab, Srgb};
+use stunts_engine::animations::{ObjectType, Sequence};
+
+use super::modifiers::ease;
+use super::saved_state::{ColorTarget, ColorTrack};
+
+impl ColorTarget {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ColorTarget::Fill => "Fill Color",
+            ColorTarget::Stroke => "Stroke Color",
+            ColorTarget::Text => "Text Color",
+            ColorTarget::TextBackground => "Background Color",
+        }
+    }
+
+    /// row id in the keyframe timeline, kept apart from engine property paths
+    pub fn path(&self) -> &'static str {
+        match self {
+            ColorTarget::Fill => "color/fill",
+            ColorTarget::Stroke => "color/stroke",
+            ColorTarget::Text => "color/text",
+            ColorTarget::TextBackground => "color/background",
+        }
+    }
+
+    pub fn from_path(path: &str) -> Option<ColorTarget> {
+        match path {
+            "color/fill" => Some(ColorTarget::Fill),
+            "color/stroke" => Some(ColorTarget::Stroke),
+            "color/text" => Some(ColorTarget::Text),
+            "color/background" => Some(ColorTarget::TextBackground),
+            _ => None,
+        }
+    }
+
+    /// colors an object of this type can animate
+    pub fn for_object(object_type: &ObjectType) -> Vec<ColorTarget> {
+        match object_type {
+            ObjectType::Polygon => vec![ColorTarget::Fill, ColorTarget::Stroke],
+            ObjectType::TextItem => vec![ColorTarget::Text, ColorTarget::TextBackground],
+            _ => Vec::new(),
+        }
+    }
+}
+
+fn to_oklab(color: [i32; 4]) -> Oklab {
+    Srgb::new(
+        color[0] as f32 / 255.0,
+        color[1] as f32 / 255.0,
+        color[2] as f32 / 255.0,
+    )
+    .into_linear()
+    .into_color()
+}
+
+fn to_channel(value: f32) -> i32 {
+    (value * 255.0).round().clamp(0.0, 255.0) as i32
+}
+
+/// Blend two rgba colors through Oklab so fades don't dip through muddy midpoints.
+/// Alpha is blended linearly.
+pub fn mix_colors(from: [i32; 4], to: [i32; 4], progress: f32) -> [i32; 4] {
+    let progress = progress.clamp(0.0, 1.0);
+    let mixed = to_oklab(from).mix(to_oklab(to), progress);
+
+    let linear: LinSrgb = mixed.into_color();
+    let rgb = Srgb::from_linear(linear);
+    let alpha = from[3] as f32 + (to[3] - from[3]) as f32 * progress;
+
+    [
+        to_channel(rgb.red),
+        to_channel(rgb.green),
+        to_channel(rgb.blue),
+        alpha.round() as i32,
+    ]
+}
+
+/// Color of a track at a time relative to the animation start, held before the first and after the last keyframe.
+/// Eased by the earlier keyframe, held keyframes keep their color until the next one.
+pub fn sample_color(
+    track: &ColorTrack,
+    time: Duration,
+    hold_keyframes: &[String],
+) -> Option<[i32; 4]> {
+    let time_ms = time.as_millis() as u64;
+    // keyframes are kept sorted by time
+    let next = track.keyframes.partition_point(|k| k.time_ms <= time_ms);
+
+    let start = match next.checked_sub(1) {
+        Some(index) => &track.keyframes[index],
+        None => return track.keyframes.first().map(|k| k.color),
+    };
+    let end = match track.keyframes.get(next) {
+        Some(end) if !hold_keyframes.contains(&start.id) => end,
+        _ => return Some(start.color),
+    };
+
+    let progress = (time_ms - start.time_ms) as f64 / (end.time_ms - start.time_ms) as f64;
+
+    Some(mix_colors(
+        start.color,
+        end.color,
+        ease(&start.easing, progress) as f32,
+    ))
+}
+
+/// Saved color of an object in the sequence, used as the value of new color keyframes
+pub fn base_color(sequence: &Sequence, object_id: &str, target: ColorTarget) -> Option<[i32; 4]> {
+    match target {
+        ColorTarget::Fill => sequence
+            .active_polygons
+            .iter()
+            .find(|p| p.id == object_id)
+            .map(|p| p.fill),
+        ColorTarget::Stroke => sequence
+            .active_polygons
+            .iter()
+            .find(|p| p.id == object_id)
+            .map(|p| p.stroke.fill),
+        ColorTarget::Text => sequence
+            .active_text_items
+            .iter()
+            .find(|t| t.id == object_id)
+            .map(|t| t.color),
+        ColorTarget::TextBackground => sequence
+            .active_text_items
+            .iter()
+            .find(|t| t.id == object_id)
+            .map(|t| t.background_fill.unwrap_or([200, 200, 200, 255])),
+    }
+}
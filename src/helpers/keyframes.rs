@@ -0,0 +1,497 @@
+use std::time::Duration;
+
+use stunts_engine::animations::{
+    AnimationData, AnimationProperty, EasingType, KeyType, KeyframeValue, Sequence, UIKeyframe,
+};
+use stunts_engine::editor::PathType;
+use uuid::Uuid;
+
+use super::saved_state::{ColorTarget, ColorTrack, SavedState, ShapeProperty, ShapeTrack};
+use super::scrub::animation_time;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PasteMode {
+    /// position keyframes are moved by the difference between source and target object positions
+    Relative,
+    /// values are pasted exactly as copied
+    Absolute,
+}
+
+/// Keyframes copied from one property or a whole AnimationData, with its color and shape tracks.
+/// Times are stored relative to the earliest copied keyframe.
+#[derive(Clone, Debug)]
+pub struct KeyframeClipboard {
+    pub source_position: [i32; 2],
+    pub properties: Vec<AnimationProperty>,
+    pub color_tracks: Vec<ColorTrack>,
+    pub shape_tracks: Vec<ShapeTrack>,
+    /// ids of copied keyframes that were held, so pasted copies hold too
+    pub holds: Vec<String>,
+}
+
+/// Find the saved position of any object type in a sequence
+pub fn get_object_position(sequence: &Sequence, object_id: &str) -> Option<[i32; 2]> {
+    if let Some(polygon) = sequence.active_polygons.iter().find(|p| p.id == object_id) {
+        return Some([polygon.position.x, polygon.position.y]);
+    }
+    if let Some(text) = sequence
+        .active_text_items
+        .iter()
+        .find(|t| t.id == object_id)
+    {
+        return Some([text.position.x, text.position.y]);
+    }
+    if let Some(image) = sequence
+        .active_image_items
+        .iter()
+        .find(|i| i.id == object_id)
+    {
+        return Some([image.position.x, image.position.y]);
+    }
+    if let Some(video) = sequence
+        .active_video_items
+        .iter()
+        .find(|v| v.id == object_id)
+    {
+        return Some([video.position.x, video.position.y]);
+    }
+
+    None
+}
+
+/// Numeric components of a keyframe value, None for values that can't be interpolated
+pub fn value_components(value: &KeyframeValue) -> Option<Vec<f64>> {
+    match value {
+        KeyframeValue::Position(position) => Some(vec![position[0] as f64, position[1] as f64]),
+        KeyframeValue::Rotation(v)
+        | KeyframeValue::Scale(v)
+        | KeyframeValue::Opacity(v)
+        | KeyframeValue::Zoom(v) => Some(vec![*v as f64]),
+        _ => None,
+    }
+}
+
+/// Rebuild a value of the same kind as template from numeric components
+pub fn value_from_components(template: &KeyframeValue, values: &[f64]) -> KeyframeValue {
+    match template {
+        KeyframeValue::Position(_) => {
+            KeyframeValue::Position([values[0].round() as i32, values[1].round() as i32])
+        }
+        KeyframeValue::Rotation(_) => KeyframeValue::Rotation(values[0].round() as i32),
+        KeyframeValue::Scale(_) => KeyframeValue::Scale(values[0].round() as i32),
+        KeyframeValue::Opacity(_) => KeyframeValue::Opacity(values[0].round() as i32),
+        KeyframeValue::Zoom(_) => KeyframeValue::Zoom(values[0].round() as i32),
+        other => other.clone(),
+    }
+}
+
+fn shift_keyframe_time(keyframe: &mut UIKeyframe, from: Duration, to: Duration) {
+    keyframe.time = (keyframe.time + to).saturating_sub(from);
+
+    if let KeyType::Range(ref mut range_data) = keyframe.key_type {
+        range_data.end_time = (range_data.end_time + to).saturating_sub(from);
+    }
+}
+
+fn earliest_time(
+    properties: &[AnimationProperty],
+    color_tracks: &[ColorTrack],
+    shape_tracks: &[ShapeTrack],
+) -> Option<Duration> {
+    let side_times = color_tracks
+        .iter()
+        .flat_map(|t| t.keyframes.iter().map(|k| k.time_ms))
+        .chain(
+            shape_tracks
+                .iter()
+                .flat_map(|t| t.keyframes.iter().map(|k| k.time_ms)),
+        )
+        .map(Duration::from_millis);
+
+    properties
+        .iter()
+        .flat_map(|p| {
+            p.keyframes
+                .iter()
+                .chain(p.children.iter().flat_map(|c| c.keyframes.iter()))
+        })
+        .map(|k| k.time)
+        .chain(side_times)
+        .min()
+}
+
+/// Copy keyframes from a single property (by property_path) or from all properties when None.
+/// Color and shape tracks of the animation are copied along, matched by their row path.
+pub fn copy_keyframes(
+    animation: &AnimationData,
+    property_path: Option<&str>,
+    source_position: [i32; 2],
+    saved_state: &SavedState,
+) -> Option<KeyframeClipboard> {
+    let mut properties: Vec<AnimationProperty> = animation
+        .properties
+        .iter()
+        .filter(|p| property_path.map_or(true, |path| p.property_path == path))
+        .cloned()
+        .collect();
+    let mut color_tracks: Vec<ColorTrack> = saved_state
+        .color_tracks
+        .iter()
+        .filter(|t| t.animation_id == animation.id && !t.keyframes.is_empty())
+        .filter(|t| property_path.map_or(true, |path| t.target.path() == path))
+        .cloned()
+        .collect();
+    let mut shape_tracks: Vec<ShapeTrack> = saved_state
+        .shape_tracks
+        .iter()
+        .filter(|t| t.animation_id == animation.id && !t.keyframes.is_empty())
+        .filter(|t| property_path.map_or(true, |path| t.property.path() == path))
+        .cloned()
+        .collect();
+
+    let start = earliest_time(&properties, &color_tracks, &shape_tracks)?;
+    let start_ms = start.as_millis() as u64;
+
+    properties.iter_mut().for_each(|p| {
+        p.keyframes
+            .iter_mut()
+            .for_each(|k| shift_keyframe_time(k, start, Duration::ZERO));
+        p.children.iter_mut().for_each(|c| {
+            c.keyframes
+                .iter_mut()
+                .for_each(|k| shift_keyframe_time(k, start, Duration::ZERO));
+        });
+    });
+
+    color_tracks.iter_mut().for_each(|t| {
+        t.keyframes.iter_mut().for_each(|k| k.time_ms -= start_ms);
+    });
+    shape_tracks.iter_mut().for_each(|t| {
+        t.keyframes.iter_mut().for_each(|k| k.time_ms -= start_ms);
+    });
+
+    let holds = properties
+        .iter()
+        .flat_map(|p| {
+            p.keyframes
+                .iter()
+                .chain(p.children.iter().flat_map(|c| c.keyframes.iter()))
+                .map(|k| &k.id)
+        })
+        .chain(
+            color_tracks
+                .iter()
+                .flat_map(|t| t.keyframes.iter().map(|k| &k.id)),
+        )
+        .chain(
+            shape_tracks
+                .iter()
+                .flat_map(|t| t.keyframes.iter().map(|k| &k.id)),
+        )
+        .filter(|id| saved_state.hold_keyframes.contains(id))
+        .cloned()
+        .collect();
+
+    Some(KeyframeClipboard {
+        source_position,
+        properties,
+        color_tracks,
+        shape_tracks,
+        holds,
+    })
+}
+
+/// Id of a pasted keyframe, recorded as held when its copy was
+fn pasted_id(
+    clipboard: &KeyframeClipboard,
+    copied_id: &str,
+    hold_keyframes: &mut Vec<String>,
+) -> String {
+    let id = Uuid::new_v4().to_string();
+
+    if clipboard.holds.iter().any(|held| held == copied_id) {
+        hold_keyframes.push(id.clone());
+    }
+
+    id
+}
+
+fn paste_into_property(
+    clipboard: &KeyframeClipboard,
+    source: &AnimationProperty,
+    target: &mut AnimationProperty,
+    playhead: Duration,
+    mode: PasteMode,
+    target_position: [i32; 2],
+    hold_keyframes: &mut Vec<String>,
+) -> usize {
+    let offset = [
+        target_position[0] - clipboard.source_position[0],
+        target_position[1] - clipboard.source_position[1],
+    ];
+
+    let mut pasted = 0;
+
+    for keyframe in &source.keyframes {
+        let mut new_keyframe = keyframe.clone();
+        new_keyframe.id = pasted_id(clipboard, &keyframe.id, hold_keyframes);
+        shift_keyframe_time(&mut new_keyframe, Duration::ZERO, playhead);
+
+        if mode == PasteMode::Relative {
+            if let KeyframeValue::Position(position) = new_keyframe.value {
+                new_keyframe.value =
+                    KeyframeValue::Position([position[0] + offset[0], position[1] + offset[1]]);
+            }
+        }
+
+        // a pasted keyframe replaces whatever was already at that time
+        target.keyframes.retain(|k| k.time != new_keyframe.time);
+        target.keyframes.push(new_keyframe);
+        pasted += 1;
+    }
+
+    target.keyframes.sort_by_key(|k| k.time);
+
+    pasted
+}
+
+/// Paste clipboard keyframes into matching properties of the target, starting at the playhead.
+/// Color and shape keyframes go into the target's tracks in saved_state, and held keyframes
+/// in the clipboard add their pasted ids to its hold_keyframes. Properties the target
+/// doesn't have are left out. Returns the number of keyframes pasted.
+pub fn paste_keyframes(
+    clipboard: &KeyframeClipboard,
+    target: &mut AnimationData,
+    playhead: Duration,
+    mode: PasteMode,
+    target_position: [i32; 2],
+    saved_state: &mut SavedState,
+) -> usize {
+    let hold_keyframes = &mut saved_state.hold_keyframes;
+    let mut pasted = 0;
+
+    for source in &clipboard.properties {
+        let target_prop = match target
+            .properties
+            .iter_mut()
+            .find(|p| p.property_path == source.property_path)
+        {
+            Some(prop) => prop,
+            None => continue,
+        };
+
+        pasted += paste_into_property(
+            clipboard,
+            source,
+            target_prop,
+            playhead,
+            mode,
+            target_position,
+            hold_keyframes,
+        );
+
+        for source_child in &source.children {
+            if let Some(target_child) = target_prop
+                .children
+                .iter_mut()
+                .find(|c| c.property_path == source_child.property_path)
+            {
+                pasted += paste_into_property(
+                    clipboard,
+                    source_child,
+                    target_child,
+                    playhead,
+                    mode,
+                    target_position,
+                    hold_keyframes,
+                );
+            }
+        }
+    }
+
+    let playhead_ms = playhead.as_millis() as u64;
+
+    let color_targets = ColorTarget::for_object(&target.object_type);
+    let shape_properties = ShapeProperty::for_object(&target.object_type);
+
+    for source in &clipboard.color_tracks {
+        if !color_targets.contains(&source.target) {
+            continue;
+        }
+
+        let index = match saved_state
+            .color_tracks
+            .iter()
+            .position(|t| t.animation_id == target.id && t.target == source.target)
+        {
+            Some(index) => index,
+            None => {
+                saved_state.color_tracks.push(ColorTrack {
+                    animation_id: target.id.clone(),
+                    target: source.target,
+                    keyframes: Vec::new(),
+                });
+                saved_state.color_tracks.len() - 1
+            }
+        };
+
+        for keyframe in &source.keyframes {
+            let mut new_keyframe = keyframe.clone();
+            new_keyframe.id = pasted_id(clipboard, &keyframe.id, &mut saved_state.hold_keyframes);
+            new_keyframe.time_ms += playhead_ms;
+
+            let track = &mut saved_state.color_tracks[index];
+            track
+                .keyframes
+                .retain(|k| k.time_ms != new_keyframe.time_ms);
+            track.keyframes.push(new_keyframe);
+            pasted += 1;
+        }
+
+        saved_state.color_tracks[index]
+            .keyframes
+            .sort_by_key(|k| k.time_ms);
+    }
+
+    for source in &clipboard.shape_tracks {
+        if !shape_properties.contains(&source.property) {
+            continue;
+        }
+
+        let index = match saved_state
+            .shape_tracks
+            .iter()
+            .position(|t| t.animation_id == target.id && t.property == source.property)
+        {
+            Some(index) => index,
+            None => {
+                saved_state.shape_tracks.push(ShapeTrack {
+                    animation_id: target.id.clone(),
+                    property: source.property,
+                    keyframes: Vec::new(),
+                });
+                saved_state.shape_tracks.len() - 1
+            }
+        };
+
+        for keyframe in &source.keyframes {
+            let mut new_keyframe = keyframe.clone();
+            new_keyframe.id = pasted_id(clipboard, &keyframe.id, &mut saved_state.hold_keyframes);
+            new_keyframe.time_ms += playhead_ms;
+
+            let track = &mut saved_state.shape_tracks[index];
+            track
+                .keyframes
+                .retain(|k| k.time_ms != new_keyframe.time_ms);
+            track.keyframes.push(new_keyframe);
+            pasted += 1;
+        }
+
+        saved_state.shape_tracks[index]
+            .keyframes
+            .sort_by_key(|k| k.time_ms);
+    }
+
+    pasted
+}
+
+/// Keyframe with an id in the properties or any of their children
+pub fn find_keyframe_mut<'a>(
+    properties: &'a mut [AnimationProperty],
+    keyframe_id: &str,
+) -> Option<&'a mut UIKeyframe> {
+    for property in properties.iter_mut() {
+        if let Some(keyframe) = property.keyframes.iter_mut().find(|k| k.id == keyframe_id) {
+            return Some(keyframe);
+        }
+
+        if let Some(keyframe) = find_keyframe_mut(&mut property.children, keyframe_id) {
+            return Some(keyframe);
+        }
+    }
+
+    None
+}
+
+/// Put the keyframes of the properties and their children back in time order
+pub fn sort_keyframes(properties: &mut [AnimationProperty]) {
+    for property in properties.iter_mut() {
+        property.keyframes.sort_by_key(|k| k.time);
+        sort_keyframes(&mut property.children);
+    }
+}
+
+/// Write a value at a time, updating a keyframe already within half a frame of it or adding a new one.
+/// Returns the id of the keyframe that was written, or None if the animation has no such property.
+pub fn upsert_keyframe(
+    animation: &mut AnimationData,
+    property_path: &str,
+    time: Duration,
+    value: KeyframeValue,
+    fps: u32,
+) -> Option<String> {
+    let half_frame = Duration::from_secs_f64(0.5 / fps.max(1) as f64);
+
+    let property = animation
+        .properties
+        .iter_mut()
+        .find(|p| p.property_path == property_path)?;
+
+    let existing = property.keyframes.iter_mut().find(|k| {
+        let distance = if k.time > time {
+            k.time - time
+        } else {
+            time - k.time
+        };
+        distance <= half_frame
+    });
+
+    let keyframe_id = match existing {
+        Some(keyframe) => {
+            keyframe.value = value;
+            keyframe.id.clone()
+        }
+        None => {
+            let keyframe_id = Uuid::new_v4().to_string();
+
+            property.keyframes.push(UIKeyframe {
+                id: keyframe_id.clone(),
+                time,
+                value,
+                easing: EasingType::EaseInOut,
+                path_type: PathType::Linear,
+                key_type: KeyType::Frame,
+            });
+            property.keyframes.sort_by_key(|k| k.time);
+
+            keyframe_id
+        }
+    };
+
+    if time > animation.duration {
+        animation.duration = time;
+    }
+
+    Some(keyframe_id)
+}
+
+/// Auto-key a value on an object's animation at the keyframe playhead, which is in sequence time.
+/// Returns the keyed animation, or None if the object has none or it lacks the property.
+pub fn auto_key_object(
+    sequence: &mut Sequence,
+    object_id: &str,
+    property_path: &str,
+    playhead: Duration,
+    value: KeyframeValue,
+    fps: u32,
+) -> Option<AnimationData> {
+    let animation = sequence
+        .polygon_motion_paths
+        .iter_mut()
+        .find(|pm| pm.polygon_id == object_id)?;
+    let time = animation_time(animation, playhead);
+
+    upsert_keyframe(animation, property_path, time, value, fps)?;
+
+    Some(animation.clone())
+}
@@ -0,0 +1,1301 @@
+use std::collections::HashMap;
+use std::ops::Range;
+use std::str::FromStr;
+use std::time::Duration;
+
+use stunts_engine::animations::{
+    AnimationData, AnimationProperty, EasingType, KeyType, KeyframeValue, ObjectType, Sequence,
+    UIKeyframe,
+};
+use stunts_engine::editor::{Editor, PathType};
+use stunts_engine::polygon::SavedPolygonConfig;
+use stunts_engine::st_image::SavedStImageConfig;
+use stunts_engine::text_due::SavedTextRendererConfig;
+use uuid::Uuid;
+
+use super::clock::{frame_at, frame_time, project_frame_rate};
+use super::colors::sample_color;
+use super::keyframes::{get_object_position, value_components};
+use super::modifiers::sample_property;
+use super::saved_state::{
+    ColorTarget, ColorTrack, SavedState, ShapeProperty, ShapeTrack, TextAnimator,
+};
+use super::shapes::{base_shape_value, resolve_shape_values, sample_tilt, Tilt};
+use super::text_animators::{line_states, split_lines, LineState};
+
+/// Id of a stand-in drawn in place of an object. The same every time for the same
+/// object and tag, so restaging only rebuilds stand-ins whose config changed.
+pub fn stand_in_id(object_id: &str, tag: &str) -> String {
+    // FNV-1a, stable between runs unlike the std hasher
+    let hash = tag
+        .bytes()
+        .fold(0x6c62272e07bb014262b821756295c58d_u128, |hash, byte| {
+            (hash ^ byte as u128).wrapping_mul(0x0000000001000000000000000000013B)
+        });
+
+    match Uuid::from_str(object_id) {
+        Ok(id) => Uuid::from_u128(id.as_u128() ^ hash).to_string(),
+        Err(_) => format!("{}-{}", object_id, tag),
+    }
+}
+
+/// Where an object is drawn at a moment
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Pose {
+    position: [f64; 2],
+    rotation: f64,
+    /// 1 is the saved size
+    scale: f64,
+    /// 0 to 1
+    opacity: f64,
+}
+
+fn pose_at(animation: &AnimationData, base_position: [i32; 2], sequence_time: Duration) -> Pose {
+    let local =
+        sequence_time.saturating_sub(Duration::from_millis(animation.start_time_ms.max(0) as u64));
+    let sample = |property_path: &str| {
+        animation
+            .properties
+            .iter()
+            .find(|p| p.property_path == property_path)
+            .and_then(|p| sample_property(p, local, &[]))
+    };
+
+    Pose {
+        position: sample("position")
+            .map(|values| [values[0], values[1]])
+            .unwrap_or([base_position[0] as f64, base_position[1] as f64]),
+        rotation: sample("rotation").map(|values| values[0]).unwrap_or(0.0),
+        scale: sample("scale")
+            .map(|values| values[0] / 100.0)
+            .unwrap_or(1.0),
+        opacity: sample("opacity")
+            .map(|values| values[0] / 100.0)
+            .unwrap_or(1.0),
+    }
+}
+
+/// How a stand-in sits in the object it draws part of, in the object's own unscaled space
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Part {
+    offset: [f64; 2],
+    rotation: f64,
+    scale: f64,
+    opacity: f64,
+}
+
+const WHOLE: Part = Part {
+    offset: [0.0, 0.0],
+    rotation: 0.0,
+    scale: 1.0,
+    opacity: 1.0,
+};
+
+impl Part {
+    /// The part's pose once the object is moved, turned and scaled
+    fn place(&self, pose: &Pose) -> Pose {
+        let (sin, cos) = pose.rotation.to_radians().sin_cos();
+        let x = self.offset[0] * pose.scale;
+        let y = self.offset[1] * pose.scale;
+
+        Pose {
+            position: [
+                pose.position[0] + x * cos - y * sin,
+                pose.position[1] + x * sin + y * cos,
+            ],
+            rotation: pose.rotation + self.rotation,
+            scale: pose.scale * self.scale,
+            opacity: pose.opacity * self.opacity,
+        }
+    }
+}
+
+fn frame_keyframe(time: Duration, value: KeyframeValue) -> UIKeyframe {
+    UIKeyframe {
+        id: Uuid::new_v4().to_string(),
+        time,
+        value,
+        easing: EasingType::Linear,
+        path_type: PathType::Linear,
+        key_type: KeyType::Frame,
+    }
+}
+
+/// Leave out keyframes with the same value as both neighbours, linear playback doesn't need them
+fn drop_repeats(keyframes: Vec<UIKeyframe>) -> Vec<UIKeyframe> {
+    let values: Vec<Option<Vec<f64>>> = keyframes
+        .iter()
+        .map(|k| value_components(&k.value))
+        .collect();
+
+    keyframes
+        .into_iter()
+        .enumerate()
+        .filter(|(index, _)| {
+            *index == 0
+                || *index + 1 == values.len()
+                || values[*index - 1] != values[*index]
+                || values[*index + 1] != values[*index]
+        })
+        .map(|(_, keyframe)| keyframe)
+        .collect()
+}
+
+fn baked_property(
+    name: &str,
+    property_path: &str,
+    keyframes: Vec<UIKeyframe>,
+) -> AnimationProperty {
+    AnimationProperty {
+        name: name.to_string(),
+        property_path: property_path.to_string(),
+        children: Vec::new(),
+        keyframes: drop_repeats(keyframes),
+        depth: 0,
+    }
+}
+
+/// Animation of a stand-in shown over a run of frames of the sequence and hidden
+/// before and after, following the object it stands in for. `fade` turns the opacity
+/// of the placed part at a frame into the one drawn, see Crossfade::opacity.
+fn stand_in_animation(
+    source: &AnimationData,
+    base_position: [i32; 2],
+    stand_in_id: &str,
+    frames: &Range<u64>,
+    fps: u32,
+    sequence_duration: Duration,
+    part: &dyn Fn(u64) -> Part,
+    fade: &dyn Fn(u64, f64) -> f64,
+) -> AnimationData {
+    let last_frame = frame_at(sequence_duration, fps);
+    let step = Duration::from_millis(1);
+
+    let mut position = Vec::new();
+    let mut rotation = Vec::new();
+    let mut scale = Vec::new();
+    let mut opacity = Vec::new();
+
+    let start = frame_time(frames.start, fps);
+    if start >= step {
+        opacity.push(frame_keyframe(Duration::ZERO, KeyframeValue::Opacity(0)));
+        opacity.push(frame_keyframe(start - step, KeyframeValue::Opacity(0)));
+    }
+
+    for frame in frames.clone() {
+        let time = frame_time(frame, fps);
+        let pose = part(frame).place(&pose_at(source, base_position, time));
+
+        position.push(frame_keyframe(
+            time,
+            KeyframeValue::Position([
+                pose.position[0].round() as i32,
+                pose.position[1].round() as i32,
+            ]),
+        ));
+        rotation.push(frame_keyframe(
+            time,
+            KeyframeValue::Rotation(pose.rotation.round() as i32),
+        ));
+        scale.push(frame_keyframe(
+            time,
+            KeyframeValue::Scale((pose.scale * 100.0).round() as i32),
+        ));
+        opacity.push(frame_keyframe(
+            time,
+            KeyframeValue::Opacity((fade(frame, pose.opacity) * 100.0).round() as i32),
+        ));
+    }
+
+    if frames.end <= last_frame {
+        opacity.push(frame_keyframe(
+            frame_time(frames.end, fps),
+            KeyframeValue::Opacity(0),
+        ));
+    }
+
+    AnimationData {
+        id: stand_in_id.to_string(),
+        object_type: source.object_type.clone(),
+        polygon_id: stand_in_id.to_string(),
+        duration: sequence_duration,
+        start_time_ms: 0,
+        position: source.position,
+        properties: vec![
+            baked_property("Position", "position", position),
+            baked_property("Rotation", "rotation", rotation),
+            baked_property("Scale", "scale", scale),
+            baked_property("Opacity", "opacity", opacity),
+        ],
+    }
+}
+
+type Colors = Vec<(ColorTarget, [i32; 4])>;
+
+/// Keyed colors and dimensions of an object at a moment
+#[derive(Clone, Debug, PartialEq)]
+struct Look {
+    colors: Colors,
+    /// width, height, border radius and stroke thickness, the saved ones where nothing is keyed
+    size: [i32; 4],
+}
+
+/// Saved width, height, border radius and stroke thickness of an animation's object
+fn base_size(sequence: &Sequence, animation: &AnimationData) -> [i32; 4] {
+    let base = |property: ShapeProperty| {
+        base_shape_value(sequence, &animation.polygon_id, property).unwrap_or(0)
+    };
+
+    [
+        base(ShapeProperty::Width),
+        base(ShapeProperty::Height),
+        base(ShapeProperty::BorderRadius),
+        base(ShapeProperty::StrokeThickness),
+    ]
+}
+
+fn size_at(
+    base: [i32; 4],
+    animation: &AnimationData,
+    shape_tracks: &[ShapeTrack],
+    hold_keyframes: &[String],
+    local: Duration,
+) -> [i32; 4] {
+    let mut size = base;
+
+    for (property, value) in resolve_shape_values(animation, shape_tracks, local, hold_keyframes) {
+        match property {
+            ShapeProperty::Width => size[0] = value,
+            ShapeProperty::Height => size[1] = value,
+            ShapeProperty::BorderRadius => size[2] = value,
+            ShapeProperty::StrokeThickness => size[3] = value,
+            _ => {}
+        }
+    }
+
+    size
+}
+
+fn colors_at(color_tracks: &[&ColorTrack], hold_keyframes: &[String], local: Duration) -> Colors {
+    color_tracks
+        .iter()
+        .filter_map(|track| {
+            sample_color(track, local, hold_keyframes).map(|color| (track.target, color))
+        })
+        .collect()
+}
+
+fn look_at(
+    sequence: &Sequence,
+    animation: &AnimationData,
+    color_tracks: &[&ColorTrack],
+    shape_tracks: &[ShapeTrack],
+    hold_keyframes: &[String],
+    local: Duration,
+) -> Look {
+    Look {
+        colors: colors_at(color_tracks, hold_keyframes, local),
+        size: size_at(
+            base_size(sequence, animation),
+            animation,
+            shape_tracks,
+            hold_keyframes,
+            local,
+        ),
+    }
+}
+
+/// Even scale taking one size to the other, when they're in proportion
+fn scale_between(from: &[i32; 4], to: &[i32; 4]) -> Option<f64> {
+    if from[0] <= 0 {
+        return None;
+    }
+
+    let scale = to[0] as f64 / from[0] as f64;
+    let fits = from
+        .iter()
+        .zip(to.iter())
+        .all(|(from, to)| (*from as f64 * scale - *to as f64).abs() <= 0.5);
+
+    fits.then_some(scale)
+}
+
+/// Consecutive frames drawn at one size
+struct SizeRun {
+    frames: Range<u64>,
+    size: [i32; 4],
+    /// scale of each frame against the size
+    scales: Vec<f64>,
+}
+
+impl SizeRun {
+    fn scale(&self, frame: u64) -> f64 {
+        self.scales
+            .get(frame.saturating_sub(self.frames.start) as usize)
+            .copied()
+            .unwrap_or(1.0)
+    }
+}
+
+/// Split frames into runs with the same size. Shapes that scale evenly, unlike text boxes,
+/// stay in one run while only their size changes in proportion. Colors are crossfaded
+/// within a run instead, see Crossfade.
+fn size_runs(sizes: Vec<[i32; 4]>, scalable: bool) -> Vec<SizeRun> {
+    let mut runs: Vec<SizeRun> = Vec::new();
+
+    for (frame, size) in sizes.into_iter().enumerate() {
+        let frame = frame as u64;
+        let scale = runs.last().and_then(|run| {
+            if run.size == size {
+                Some(1.0)
+            } else if scalable {
+                scale_between(&run.size, &size)
+            } else {
+                None
+            }
+        });
+
+        match (runs.last_mut(), scale) {
+            (Some(run), Some(scale)) => {
+                run.frames.end = frame + 1;
+                run.scales.push(scale);
+            }
+            _ => runs.push(SizeRun {
+                frames: frame..frame + 1,
+                size,
+                scales: vec![1.0],
+            }),
+        }
+    }
+
+    runs
+}
+
+/// Frames of a run where its colors reach a keyframe, with the colors there. The run's
+/// first frame is always one, keyframes that change nothing aren't.
+fn color_stops(
+    color_tracks: &[&ColorTrack],
+    hold_keyframes: &[String],
+    start: Duration,
+    frames: &Range<u64>,
+    fps: u32,
+) -> Vec<(u64, Colors)> {
+    let mut stop_frames: Vec<u64> = color_tracks
+        .iter()
+        .flat_map(|track| track.keyframes.iter())
+        .map(|keyframe| frame_at(start + Duration::from_millis(keyframe.time_ms), fps))
+        .filter(|frame| frames.contains(frame))
+        .chain(std::iter::once(frames.start))
+        .collect();
+    stop_frames.sort();
+    stop_frames.dedup();
+
+    let mut stops: Vec<(u64, Colors)> = Vec::new();
+
+    for frame in stop_frames {
+        let local = frame_time(frame, fps).saturating_sub(start);
+        let colors = colors_at(color_tracks, hold_keyframes, local);
+
+        if stops.last().map_or(true, |(_, last)| *last != colors) {
+            stops.push((frame, colors));
+        }
+    }
+
+    stops
+}
+
+/// A run drawn as a stand-in per color stop, each fading into the next. Two stand-ins
+/// show at a time at most, so a fade costs one extra stand-in rather than one per frame.
+struct Crossfade {
+    stop_frames: Vec<u64>,
+    first_frame: u64,
+    /// per frame of the run, the stop last reached and the progress toward the next
+    progress: Vec<(usize, f64)>,
+}
+
+impl Crossfade {
+    /// `colors` samples the run's colors at a frame. Progress follows the channel that
+    /// changes the most, so easing and holds carry over to the fade.
+    fn new(stops: &[(u64, Colors)], frames: &Range<u64>, colors: &dyn Fn(u64) -> Colors) -> Self {
+        let progress = frames
+            .clone()
+            .map(|frame| {
+                let from = stops
+                    .partition_point(|(stop, _)| *stop <= frame)
+                    .saturating_sub(1);
+                let (from_colors, to_colors) = match (stops.get(from), stops.get(from + 1)) {
+                    (Some(from_stop), Some(to_stop)) => (&from_stop.1, &to_stop.1),
+                    _ => return (from, 0.0),
+                };
+
+                let (target, channel, span) = from_colors
+                    .iter()
+                    .zip(to_colors)
+                    .enumerate()
+                    .flat_map(|(target, ((_, from), (_, to)))| {
+                        (0..4).map(move |channel| (target, channel, to[channel] - from[channel]))
+                    })
+                    .max_by_key(|(_, _, span)| span.abs())
+                    .unwrap_or((0, 0, 0));
+
+                if span == 0 {
+                    return (from, 0.0);
+                }
+
+                let start = from_colors[target].1[channel];
+                let value = colors(frame)
+                    .get(target)
+                    .map(|(_, color)| color[channel])
+                    .unwrap_or(start);
+
+                (from, ((value - start) as f64 / span as f64).clamp(0.0, 1.0))
+            })
+            .collect();
+
+        Crossfade {
+            stop_frames: stops.iter().map(|(frame, _)| *frame).collect(),
+            first_frame: frames.start,
+            progress,
+        }
+    }
+
+    /// Layers below the object's own a stop's stand-in is drawn on. Consecutive stops
+    /// alternate so the two of a fade never share a layer, the last keeps the object's.
+    fn layers_below(&self, stop: usize) -> i32 {
+        ((self.stop_frames.len() - 1 - stop) % 2) as i32
+    }
+
+    /// Frames a stop's stand-in shows in, from the stop before it up to the one after
+    fn frames(&self, stop: usize, run: &Range<u64>) -> Range<u64> {
+        let start = match stop.checked_sub(1) {
+            Some(before) => self.stop_frames[before],
+            None => run.start,
+        };
+        let end = self.stop_frames.get(stop + 1).copied().unwrap_or(run.end);
+
+        start..end
+    }
+
+    /// Opacity a stop's stand-in is drawn at, given the one the object has at the frame.
+    /// The two stand-ins of a fade composite to the object at its opacity, with the
+    /// stops' colors mixed by the progress.
+    fn opacity(&self, stop: usize, frame: u64, opacity: f64) -> f64 {
+        let (from, progress) = match self
+            .progress
+            .get(frame.saturating_sub(self.first_frame) as usize)
+        {
+            Some(progress) => *progress,
+            None => return 0.0,
+        };
+
+        let (weight, other) = if stop == from {
+            (1.0 - progress, from + 1)
+        } else if stop == from + 1 {
+            (progress, from)
+        } else {
+            return 0.0;
+        };
+
+        if other >= self.stop_frames.len() || self.layers_below(stop) < self.layers_below(other) {
+            return opacity * weight;
+        }
+
+        // under the other stand-in, which covers its share of what's below
+        let uncovered = 1.0 - opacity * (1.0 - weight);
+
+        if uncovered > 0.0 {
+            (opacity * weight / uncovered).min(1.0)
+        } else {
+            0.0
+        }
+    }
+}
+
+/// A tilted polygon of `size` drawn flat: the rectangle closest to its projection, with the
+/// projected edges' average lengths, turned along them and moved to their center.
+/// Corner rounding and stroke are kept, the perspective taper isn't.
+fn tilted_shape(tilt: &Tilt, size: [f64; 2]) -> ([f64; 2], Part) {
+    let (half_width, half_height) = (size[0] / 2.0, size[1] / 2.0);
+    let [top_left, top_right, bottom_right, bottom_left] = [
+        [-half_width, -half_height],
+        [half_width, -half_height],
+        [half_width, half_height],
+        [-half_width, half_height],
+    ]
+    .map(|corner| tilt.project(corner));
+
+    let length = |a: [f64; 2], b: [f64; 2]| (b[0] - a[0]).hypot(b[1] - a[1]);
+    let width = (length(top_left, top_right) + length(bottom_left, bottom_right)) / 2.0;
+    let height = (length(top_left, bottom_left) + length(top_right, bottom_right)) / 2.0;
+    // both edges together, so a taper doesn't turn the shape
+    let along = [
+        top_right[0] - top_left[0] + bottom_right[0] - bottom_left[0],
+        top_right[1] - top_left[1] + bottom_right[1] - bottom_left[1],
+    ];
+
+    let part = Part {
+        offset: [
+            (top_left[0] + top_right[0] + bottom_right[0] + bottom_left[0]) / 4.0,
+            (top_left[1] + top_right[1] + bottom_right[1] + bottom_left[1]) / 4.0,
+        ],
+        rotation: along[1].atan2(along[0]).to_degrees(),
+        ..WHOLE
+    };
+
+    ([width, height], part)
+}
+
+/// Videos can't be stood in for, a keyed size is followed by scaling the video evenly instead
+fn scale_video(
+    sequence: &Sequence,
+    animation: &AnimationData,
+    shape_tracks: &[ShapeTrack],
+    hold_keyframes: &[String],
+    fps: u32,
+    last_frame: u64,
+) -> AnimationData {
+    let base = |property: ShapeProperty| {
+        base_shape_value(sequence, &animation.polygon_id, property).unwrap_or(1)
+    };
+    let (base_width, base_height) = (base(ShapeProperty::Width), base(ShapeProperty::Height));
+    let start = Duration::from_millis(animation.start_time_ms.max(0) as u64);
+    let end = frame_at(start + animation.duration, fps).min(last_frame);
+    let scale_property = animation
+        .properties
+        .iter()
+        .find(|p| p.property_path == "scale");
+
+    let keyframes = (frame_at(start, fps)..=end)
+        .map(|frame| {
+            let local = frame_time(frame, fps).saturating_sub(start);
+            let (mut width, mut height) = (base_width, base_height);
+
+            for (property, value) in
+                resolve_shape_values(animation, shape_tracks, local, hold_keyframes)
+            {
+                match property {
+                    ShapeProperty::Width => width = value,
+                    ShapeProperty::Height => height = value,
+                    _ => {}
+                }
+            }
+
+            let keyed = scale_property
+                .and_then(|p| sample_property(p, local, &[]))
+                .map(|values| values[0])
+                .unwrap_or(100.0);
+            let size = (width.max(1) as f64 * height.max(1) as f64
+                / (base_width.max(1) as f64 * base_height.max(1) as f64))
+                .sqrt();
+
+            frame_keyframe(local, KeyframeValue::Scale((keyed * size).round() as i32))
+        })
+        .collect();
+
+    let mut scaled = animation.clone();
+    scaled.properties.retain(|p| p.property_path != "scale");
+    scaled
+        .properties
+        .push(baked_property("Scale", "scale", keyframes));
+
+    scaled
+}
+
+fn dress_polygon(
+    polygon: &SavedPolygonConfig,
+    id: &str,
+    look: &Look,
+    dimensions: (i32, i32),
+) -> SavedPolygonConfig {
+    let mut polygon = polygon.clone();
+
+    polygon.id = id.to_string();
+    // sizes below a pixel break the shape builders
+    polygon.dimensions = (dimensions.0.max(1), dimensions.1.max(1));
+    polygon.border_radius = look.size[2].max(0);
+    polygon.stroke.thickness = look.size[3].max(0);
+
+    for (target, color) in &look.colors {
+        match target {
+            ColorTarget::Fill => polygon.fill = *color,
+            ColorTarget::Stroke => polygon.stroke.fill = *color,
+            _ => {}
+        }
+    }
+
+    polygon
+}
+
+fn dress_text(text: &SavedTextRendererConfig, id: &str, look: &Look) -> SavedTextRendererConfig {
+    let mut text = text.clone();
+
+    text.id = id.to_string();
+    text.dimensions = (look.size[0].max(1), look.size[1].max(1));
+
+    for (target, color) in &look.colors {
+        match target {
+            ColorTarget::Text => text.color = *color,
+            ColorTarget::TextBackground => text.background_fill = Some(*color),
+            _ => {}
+        }
+    }
+
+    text
+}
+
+fn dress_image(image: &SavedStImageConfig, id: &str, look: &Look) -> SavedStImageConfig {
+    let mut image = image.clone();
+
+    image.id = id.to_string();
+    image.dimensions = (look.size[0].max(1) as u32, look.size[1].max(1) as u32);
+
+    image
+}
+
+/// Draw a text with animators as a stand-in per line, each following its line state,
+/// over a stand-in of the empty box that keeps the background. `text` is the dressed text
+/// with the stand-in id of the box.
+///
+/// Each line's stand-in is the whole box with the other lines left empty, so the engine
+/// lays the line out where it is in the text. Lines are turned and scaled about the box
+/// center, and a line that wraps in the box leaves the lines below a row too high.
+fn bake_lines(
+    baked: &mut Sequence,
+    text: &SavedTextRendererConfig,
+    line_frames: &[Vec<LineState>],
+    animation: &AnimationData,
+    base_position: [i32; 2],
+    frames: &Range<u64>,
+    fade: &dyn Fn(u64, f64) -> f64,
+    tag: &str,
+    fps: u32,
+    sequence_duration: Duration,
+) {
+    let lines = split_lines(&text.text);
+
+    let mut text_box = text.clone();
+    text_box.text = String::new();
+    baked.active_text_items.push(text_box);
+
+    for (index, range) in lines.iter().enumerate() {
+        if text.text[range.clone()].trim().is_empty() {
+            continue;
+        }
+
+        let id = stand_in_id(&animation.polygon_id, &format!("{}-line-{}", tag, index));
+        let mut line = text.clone();
+        line.id = id.clone();
+        line.text = lines
+            .iter()
+            .map(|other| {
+                if other == range {
+                    &text.text[other.clone()]
+                } else if text.text[other.clone()].ends_with('\n') {
+                    "\n"
+                } else {
+                    ""
+                }
+            })
+            .collect();
+        line.background_fill = Some([0, 0, 0, 0]);
+
+        baked.active_text_items.push(line);
+        baked.polygon_motion_paths.push(stand_in_animation(
+            animation,
+            base_position,
+            &id,
+            frames,
+            fps,
+            sequence_duration,
+            &|frame| match line_frames
+                .get(frame as usize)
+                .and_then(|states| states.get(index))
+            {
+                Some(state) => Part {
+                    offset: [state.offset[0] as f64, state.offset[1] as f64],
+                    rotation: state.rotation as f64,
+                    scale: state.scale as f64,
+                    opacity: state.opacity as f64,
+                },
+                None => WHOLE,
+            },
+            fade,
+        ));
+    }
+}
+
+/// Draw objects with keyed colors, dimensions or tilts through stand-ins, so preview and
+/// export both show them without the engine knowing about those keyframes. Each run of
+/// frames with the same size gets a stand-in per color stop, crossfaded into each other.
+/// The objects they stand in for are left out.
+///
+/// Stand-ins of a fade sit on the object's layer and the one below, and blend in the
+/// engine's color space rather than through Oklab.
+///
+/// Only polygons tilt, drawn as the flat rectangle closest to their projection, see
+/// tilted_shape. Tilts keyed on other objects are left out.
+pub fn bake_looks(saved_state: &SavedState, sequence: &Sequence) -> Sequence {
+    let fps = project_frame_rate(saved_state);
+    let duration = Duration::from_millis(sequence.duration_ms.max(0) as u64);
+    let last_frame = frame_at(duration, fps);
+    let shape_tracks = &saved_state.shape_tracks;
+    let hold_keyframes = &saved_state.hold_keyframes;
+
+    let mut baked = sequence.clone();
+
+    for animation in &sequence.polygon_motion_paths {
+        let color_tracks: Vec<&ColorTrack> = saved_state
+            .color_tracks
+            .iter()
+            .filter(|t| t.animation_id == animation.id)
+            .collect();
+        // tilts keyed before only polygons could tilt are left out
+        let shape_properties = ShapeProperty::for_object(&animation.object_type);
+        let shaped = shape_tracks
+            .iter()
+            .any(|t| t.animation_id == animation.id && shape_properties.contains(&t.property));
+        let text_animators: Vec<&TextAnimator> = saved_state
+            .text_animators
+            .iter()
+            .filter(|a| a.animation_id == animation.id)
+            .collect();
+        let animated_text =
+            animation.object_type == ObjectType::TextItem && !text_animators.is_empty();
+
+        if color_tracks.is_empty() && !shaped && !animated_text {
+            continue;
+        }
+
+        let object_id = &animation.polygon_id;
+
+        if animation.object_type == ObjectType::VideoItem {
+            let scaled = scale_video(
+                sequence,
+                animation,
+                shape_tracks,
+                hold_keyframes,
+                fps,
+                last_frame,
+            );
+
+            baked
+                .polygon_motion_paths
+                .iter_mut()
+                .filter(|a| a.id == animation.id)
+                .for_each(|a| *a = scaled.clone());
+
+            continue;
+        }
+
+        let base_position = match get_object_position(sequence, object_id) {
+            Some(position) => position,
+            None => continue,
+        };
+        let start = Duration::from_millis(animation.start_time_ms.max(0) as u64);
+        let local = |frame: u64| frame_time(frame, fps).saturating_sub(start);
+
+        let tilted = animation.object_type == ObjectType::Polygon
+            && shape_tracks.iter().any(|t| {
+                t.animation_id == animation.id
+                    && matches!(
+                        t.property,
+                        ShapeProperty::TiltX | ShapeProperty::TiltY | ShapeProperty::Perspective
+                    )
+            });
+
+        // only sizes split runs, so colors are sampled at stops and through fades alone.
+        // Tilted polygons take the size of their projection, moved and turned by tilt_parts.
+        let base = base_size(sequence, animation);
+        let mut sizes = Vec::new();
+        let mut tilt_parts = Vec::new();
+
+        for frame in 0..=last_frame {
+            let mut size = if shaped {
+                size_at(base, animation, shape_tracks, hold_keyframes, local(frame))
+            } else {
+                base
+            };
+
+            if tilted {
+                let tilt = sample_tilt(animation, shape_tracks, local(frame), hold_keyframes)
+                    .unwrap_or(Tilt::FLAT);
+                let (projected, part) = tilted_shape(&tilt, [size[0] as f64, size[1] as f64]);
+
+                size[0] = projected[0].round() as i32;
+                size[1] = projected[1].round() as i32;
+                tilt_parts.push(part);
+            }
+
+            sizes.push(size);
+        }
+
+        let scalable = animation.object_type != ObjectType::TextItem;
+
+        // what the text animators do to each line at every frame
+        let line_frames: Vec<Vec<LineState>> = match (
+            animated_text,
+            sequence
+                .active_text_items
+                .iter()
+                .find(|t| t.id == *object_id),
+        ) {
+            (true, Some(text)) => (0..=last_frame)
+                .map(|frame| line_states(&text.text, &text_animators, local(frame)))
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        for (index, run) in size_runs(sizes, scalable).into_iter().enumerate() {
+            let stops = color_stops(&color_tracks, hold_keyframes, start, &run.frames, fps);
+            let crossfade = Crossfade::new(&stops, &run.frames, &|frame| {
+                colors_at(&color_tracks, hold_keyframes, local(frame))
+            });
+            let [width, height, _, _] = run.size;
+            let whole = |frame: u64| Part {
+                scale: run.scale(frame),
+                ..tilt_parts.get(frame as usize).copied().unwrap_or(WHOLE)
+            };
+
+            for (stop, (_, colors)) in stops.iter().enumerate() {
+                let tag = format!("look-{}-{}", index, stop);
+                let id = stand_in_id(object_id, &tag);
+                let look = Look {
+                    colors: colors.clone(),
+                    size: run.size,
+                };
+                let frames = crossfade.frames(stop, &run.frames);
+                let layers_below = crossfade.layers_below(stop);
+                let fade = |frame: u64, opacity: f64| crossfade.opacity(stop, frame, opacity);
+
+                match animation.object_type {
+                    ObjectType::Polygon => {
+                        let polygon =
+                            match sequence.active_polygons.iter().find(|p| p.id == *object_id) {
+                                Some(polygon) => polygon,
+                                None => continue,
+                            };
+
+                        let mut dressed = dress_polygon(polygon, &id, &look, (width, height));
+                        dressed.layer -= layers_below;
+                        baked.active_polygons.push(dressed);
+                    }
+                    ObjectType::TextItem => {
+                        let mut text = match sequence
+                            .active_text_items
+                            .iter()
+                            .find(|t| t.id == *object_id)
+                        {
+                            Some(text) => dress_text(text, &id, &look),
+                            None => continue,
+                        };
+                        text.layer -= layers_below;
+
+                        if !animated_text {
+                            baked.active_text_items.push(text);
+                        } else {
+                            bake_lines(
+                                &mut baked,
+                                &text,
+                                &line_frames,
+                                animation,
+                                base_position,
+                                &frames,
+                                &fade,
+                                &tag,
+                                fps,
+                                duration,
+                            );
+                        }
+                    }
+                    _ => {
+                        let mut image = match sequence
+                            .active_image_items
+                            .iter()
+                            .find(|i| i.id == *object_id)
+                        {
+                            Some(image) => dress_image(image, &id, &look),
+                            None => continue,
+                        };
+                        image.layer -= layers_below;
+                        baked.active_image_items.push(image);
+                    }
+                }
+
+                baked.polygon_motion_paths.push(stand_in_animation(
+                    animation,
+                    base_position,
+                    &id,
+                    &frames,
+                    fps,
+                    duration,
+                    &whole,
+                    &fade,
+                ));
+            }
+        }
+
+        baked.active_polygons.retain(|p| p.id != *object_id);
+        baked.active_text_items.retain(|t| t.id != *object_id);
+        baked.active_image_items.retain(|i| i.id != *object_id);
+        baked
+            .polygon_motion_paths
+            .retain(|a| a.polygon_id != *object_id);
+    }
+
+    baked
+}
+
+/// Animation holding a stand-in still at one pose for the whole sequence
+fn still_animation(
+    source: &AnimationData,
+    stand_in_id: &str,
+    pose: &Pose,
+    sequence_duration: Duration,
+) -> AnimationData {
+    let still = |name: &str, property_path: &str, value: KeyframeValue| {
+        baked_property(
+            name,
+            property_path,
+            vec![
+                frame_keyframe(Duration::ZERO, value.clone()),
+                frame_keyframe(sequence_duration, value),
+            ],
+        )
+    };
+
+    AnimationData {
+        id: stand_in_id.to_string(),
+        object_type: source.object_type.clone(),
+        polygon_id: stand_in_id.to_string(),
+        duration: sequence_duration,
+        start_time_ms: 0,
+        position: source.position,
+        properties: vec![
+            still(
+                "Position",
+                "position",
+                KeyframeValue::Position([
+                    pose.position[0].round() as i32,
+                    pose.position[1].round() as i32,
+                ]),
+            ),
+            still(
+                "Rotation",
+                "rotation",
+                KeyframeValue::Rotation(pose.rotation.round() as i32),
+            ),
+            still(
+                "Scale",
+                "scale",
+                KeyframeValue::Scale((pose.scale * 100.0).round() as i32),
+            ),
+            still(
+                "Opacity",
+                "opacity",
+                KeyframeValue::Opacity((pose.opacity * 100.0).round() as i32),
+            ),
+        ],
+    }
+}
+
+/// Add translucent copies of an object posed as it is at other moments, for onion skinning.
+/// `placed` is the sequence before its looks were baked into `baked`, `ghosts` are
+/// sequence times with the opacity each copy is drawn at. Copies sit a layer behind
+/// the object. Videos get none.
+pub fn add_ghosts(
+    saved_state: &SavedState,
+    placed: &Sequence,
+    baked: &mut Sequence,
+    object_id: &str,
+    ghosts: &[(Duration, f64)],
+) {
+    let animation = match placed
+        .polygon_motion_paths
+        .iter()
+        .find(|a| a.polygon_id == object_id)
+    {
+        Some(animation) => animation,
+        None => return,
+    };
+    let base_position = match get_object_position(placed, object_id) {
+        Some(position) => position,
+        None => return,
+    };
+
+    let color_tracks: Vec<&ColorTrack> = saved_state
+        .color_tracks
+        .iter()
+        .filter(|t| t.animation_id == animation.id)
+        .collect();
+    let shape_tracks = &saved_state.shape_tracks;
+    let hold_keyframes = &saved_state.hold_keyframes;
+    let start = Duration::from_millis(animation.start_time_ms.max(0) as u64);
+    let duration = Duration::from_millis(placed.duration_ms.max(0) as u64);
+
+    for (index, (time, opacity)) in ghosts.iter().enumerate() {
+        let local = time.saturating_sub(start);
+        let look = look_at(
+            placed,
+            animation,
+            &color_tracks,
+            shape_tracks,
+            hold_keyframes,
+            local,
+        );
+        let (size, part) = match animation.object_type {
+            ObjectType::Polygon => {
+                let tilt = sample_tilt(animation, shape_tracks, local, hold_keyframes)
+                    .unwrap_or(Tilt::FLAT);
+
+                tilted_shape(&tilt, [look.size[0] as f64, look.size[1] as f64])
+            }
+            _ => ([look.size[0] as f64, look.size[1] as f64], WHOLE),
+        };
+        let part = Part {
+            opacity: *opacity,
+            ..part
+        };
+        let pose = part.place(&pose_at(animation, base_position, *time));
+        let id = stand_in_id(object_id, &format!("ghost-{}", index));
+
+        match animation.object_type {
+            ObjectType::Polygon => {
+                match placed.active_polygons.iter().find(|p| p.id == object_id) {
+                    Some(polygon) => {
+                        let dimensions = (size[0].round() as i32, size[1].round() as i32);
+                        let mut ghost = dress_polygon(polygon, &id, &look, dimensions);
+                        ghost.layer -= 1;
+                        baked.active_polygons.push(ghost);
+                    }
+                    None => return,
+                }
+            }
+            ObjectType::TextItem => {
+                match placed.active_text_items.iter().find(|t| t.id == object_id) {
+                    Some(text) => {
+                        let mut ghost = dress_text(text, &id, &look);
+                        ghost.layer -= 1;
+                        baked.active_text_items.push(ghost);
+                    }
+                    None => return,
+                }
+            }
+            ObjectType::ImageItem => {
+                match placed.active_image_items.iter().find(|i| i.id == object_id) {
+                    Some(image) => {
+                        let mut ghost = dress_image(image, &id, &look);
+                        ghost.layer -= 1;
+                        baked.active_image_items.push(ghost);
+                    }
+                    None => return,
+                }
+            }
+            ObjectType::VideoItem => return,
+        }
+
+        baked
+            .polygon_motion_paths
+            .push(still_animation(animation, &id, &pose, duration));
+    }
+}
+
+fn object_ids(sequence: &Sequence) -> Vec<String> {
+    sequence
+        .active_polygons
+        .iter()
+        .map(|p| p.id.clone())
+        .chain(sequence.active_text_items.iter().map(|t| t.id.clone()))
+        .chain(sequence.active_image_items.iter().map(|i| i.id.clone()))
+        .chain(sequence.active_video_items.iter().map(|v| v.id.clone()))
+        .collect()
+}
+
+fn set_hidden(editor: &mut Editor, id: &str, hidden: bool) {
+    editor
+        .polygons
+        .iter_mut()
+        .filter(|p| p.id.to_string() == id)
+        .for_each(|p| p.hidden = hidden);
+    editor
+        .text_items
+        .iter_mut()
+        .filter(|t| t.id.to_string() == id)
+        .for_each(|t| t.hidden = hidden);
+    editor
+        .image_items
+        .iter_mut()
+        .filter(|i| i.id == id)
+        .for_each(|i| i.hidden = hidden);
+    editor
+        .video_items
+        .iter_mut()
+        .filter(|v| v.id == id)
+        .for_each(|v| v.hidden = hidden);
+}
+
+fn is_visible(editor: &Editor, id: &str) -> bool {
+    editor
+        .polygons
+        .iter()
+        .any(|p| p.id.to_string() == id && !p.hidden)
+        || editor
+            .text_items
+            .iter()
+            .any(|t| t.id.to_string() == id && !t.hidden)
+        || editor.image_items.iter().any(|i| i.id == id && !i.hidden)
+        || editor.video_items.iter().any(|v| v.id == id && !v.hidden)
+}
+
+fn remove_object(editor: &mut Editor, id: &str) {
+    editor.polygons.retain(|p| p.id.to_string() != id);
+    editor.text_items.retain(|t| t.id.to_string() != id);
+    editor.image_items.retain(|i| i.id != id);
+}
+
+/// Stand-ins restored into the editor for the preview, and the objects hidden for them
+#[derive(Debug, Default)]
+pub struct StagedLooks {
+    /// stand-in ids and the config each was restored with, unchanged ones aren't rebuilt
+    stand_ins: HashMap<String, String>,
+    /// objects this hid, shown again by unstage
+    hidden: Vec<String>,
+    /// counts stagings, unstagings and releases, to notice others have staged since
+    changes: u64,
+}
+
+impl StagedLooks {
+    /// Restore the stand-ins of prepared sequences into the editor and hide the objects
+    /// they stand in for. `sources` are the same sequences before preparing, as restored on load.
+    /// Stand-ins are shown, callers showing one sequence at a time hide the rest themselves.
+    pub fn stage(&mut self, editor: &mut Editor, sources: &[Sequence], prepared: &[Sequence]) {
+        self.changes += 1;
+
+        let source_ids: Vec<String> = sources.iter().flat_map(object_ids).collect();
+        let prepared_ids: Vec<String> = prepared.iter().flat_map(object_ids).collect();
+
+        let mut missing = match prepared.first() {
+            Some(sequence) => sequence.clone(),
+            None => return,
+        };
+        missing.active_polygons = Vec::new();
+        missing.active_text_items = Vec::new();
+        missing.active_image_items = Vec::new();
+        missing.active_video_items = Vec::new();
+        missing.polygon_motion_paths = Vec::new();
+
+        let mut staged = HashMap::new();
+        let is_stand_in = |id: &String| !source_ids.contains(id);
+
+        for sequence in prepared {
+            for polygon in sequence
+                .active_polygons
+                .iter()
+                .filter(|p| is_stand_in(&p.id))
+            {
+                let key = serde_json::to_string(polygon).unwrap_or_default();
+
+                if self.stand_ins.get(&polygon.id) != Some(&key) {
+                    remove_object(editor, &polygon.id);
+                    missing.active_polygons.push(polygon.clone());
+                }
+
+                staged.insert(polygon.id.clone(), key);
+            }
+
+            for text in sequence
+                .active_text_items
+                .iter()
+                .filter(|t| is_stand_in(&t.id))
+            {
+                let key = serde_json::to_string(text).unwrap_or_default();
+
+                if self.stand_ins.get(&text.id) != Some(&key) {
+                    remove_object(editor, &text.id);
+                    missing.active_text_items.push(text.clone());
+                }
+
+                staged.insert(text.id.clone(), key);
+            }
+
+            for image in sequence
+                .active_image_items
+                .iter()
+                .filter(|i| is_stand_in(&i.id))
+            {
+                let key = serde_json::to_string(image).unwrap_or_default();
+
+                if self.stand_ins.get(&image.id) != Some(&key) {
+                    remove_object(editor, &image.id);
+                    missing.active_image_items.push(image.clone());
+                }
+
+                staged.insert(image.id.clone(), key);
+            }
+        }
+
+        // stand-ins of looks that changed or went away
+        for id in self.stand_ins.keys() {
+            if !staged.contains_key(id) {
+                remove_object(editor, id);
+            }
+        }
+
+        let any_missing = !missing.active_polygons.is_empty()
+            || !missing.active_text_items.is_empty()
+            || !missing.active_image_items.is_empty();
+
+        if let (true, Some(camera)) = (any_missing, editor.camera) {
+            editor.restore_sequence_objects(&missing, camera.window_size.clone(), &camera, false);
+        }
+
+        for id in staged.keys() {
+            set_hidden(editor, id, false);
+        }
+
+        self.stand_ins = staged;
+
+        // objects that no longer need a stand-in come back
+        let replaced: Vec<&String> = source_ids
+            .iter()
+            .filter(|id| !prepared_ids.contains(id))
+            .collect();
+
+        for id in std::mem::take(&mut self.hidden) {
+            if replaced.contains(&&id) {
+                self.hidden.push(id);
+            } else {
+                set_hidden(editor, &id, false);
+            }
+        }
+
+        for id in replaced {
+            if is_visible(editor, id) {
+                set_hidden(editor, id, true);
+                self.hidden.push(id.clone());
+            }
+        }
+    }
+
+    /// Hide the stand-ins and show the objects they stood in for
+    pub fn unstage(&mut self, editor: &mut Editor) {
+        self.changes += 1;
+
+        for id in self.stand_ins.keys() {
+            set_hidden(editor, id, true);
+        }
+
+        for id in std::mem::take(&mut self.hidden) {
+            set_hidden(editor, &id, false);
+        }
+    }
+
+    /// Forget the hidden objects without showing them, for when visibility is being reset anyway
+    pub fn release(&mut self) {
+        self.changes += 1;
+        self.hidden.clear();
+    }
+
+    pub fn changes(&self) -> u64 {
+        self.changes
+    }
+}
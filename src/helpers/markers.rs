@@ -0,0 +1,196 @@
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use super::saved_state::Marker;
+
+/// length a new region starts with
+pub const DEFAULT_REGION_MS: i32 = 2000;
+
+pub const MARKER_COLORS: [(&str, [u8; 3]); 6] = [
+    ("Orange", [255, 150, 0]),
+    ("Blue", [0, 120, 255]),
+    ("Green", [40, 180, 80]),
+    ("Purple", [150, 80, 220]),
+    ("Pink", [240, 80, 160]),
+    ("Gray", [120, 120, 120]),
+];
+
+pub fn color_label(color: [u8; 3]) -> String {
+    MARKER_COLORS
+        .iter()
+        .find(|(_, c)| *c == color)
+        .map(|(label, _)| label.to_string())
+        .unwrap_or_else(|| "Custom".to_string())
+}
+
+pub fn color_from_label(label: &str) -> Option<[u8; 3]> {
+    MARKER_COLORS
+        .iter()
+        .find(|(l, _)| *l == label)
+        .map(|(_, color)| *color)
+}
+
+/// Markers of the sequence timeline with no sequence id, or of one sequence, in time order
+pub fn markers_on(markers: &[Marker], sequence_id: Option<&str>) -> Vec<Marker> {
+    let mut on: Vec<Marker> = markers
+        .iter()
+        .filter(|m| m.sequence_id.as_deref() == sequence_id)
+        .cloned()
+        .collect();
+
+    on.sort_by_key(|m| m.time_ms);
+
+    on
+}
+
+/// Times to snap to, both ends of regions included
+pub fn marker_times(markers: &[Marker], sequence_id: Option<&str>) -> Vec<Duration> {
+    markers_on(markers, sequence_id)
+        .iter()
+        .flat_map(|m| std::iter::once(m.time_ms).chain(m.end_time_ms))
+        .map(|time_ms| Duration::from_millis(time_ms.max(0) as u64))
+        .collect()
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Chapter {
+    pub title: String,
+    pub start_ms: i32,
+    pub end_ms: i32,
+}
+
+/// A chapter per timeline marker. Regions cover their range,
+/// plain markers run until the next marker or the end of the timeline.
+pub fn timeline_chapters(markers: &[Marker], end_time_ms: i32) -> Vec<Chapter> {
+    let on = markers_on(markers, None);
+
+    on.iter()
+        .enumerate()
+        .filter(|(_, m)| m.time_ms < end_time_ms)
+        .map(|(index, marker)| {
+            let end_ms = marker.end_time_ms.unwrap_or_else(|| {
+                on.iter()
+                    .skip(index + 1)
+                    .map(|m| m.time_ms)
+                    .find(|time_ms| *time_ms > marker.time_ms)
+                    .unwrap_or(end_time_ms)
+            });
+
+            Chapter {
+                title: marker.label.clone(),
+                start_ms: marker.time_ms.max(0),
+                end_ms: end_ms.min(end_time_ms),
+            }
+        })
+        .collect()
+}
+
+/// Chapters in ffmpeg's metadata format, ready to mux into the exported video
+pub fn chapters_ffmetadata(chapters: &[Chapter]) -> String {
+    let mut metadata = String::from(";FFMETADATA1\n");
+
+    for chapter in chapters {
+        // = ; # \ and newlines have to be escaped in values
+        let title: String = chapter
+            .title
+            .chars()
+            .flat_map(|c| match c {
+                '=' | ';' | '#' | '\\' | '\n' => vec!['\\', c],
+                c => vec![c],
+            })
+            .collect();
+
+        metadata.push_str(&format!(
+            "\n[CHAPTER]\nTIMEBASE=1/1000\nSTART={}\nEND={}\ntitle={}\n",
+            chapter.start_ms, chapter.end_ms, title
+        ));
+    }
+
+    metadata
+}
+
+pub fn write_chapters(path: &Path, chapters: &[Chapter]) -> std::io::Result<()> {
+    fs::write(path, chapters_ffmetadata(chapters))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn marker(label: &str, time_ms: i32, end_time_ms: Option<i32>) -> Marker {
+        Marker {
+            id: label.to_string(),
+            sequence_id: None,
+            time_ms,
+            end_time_ms,
+            label: label.to_string(),
+            color: MARKER_COLORS[0].1,
+            note: String::new(),
+        }
+    }
+
+    fn chapter(title: &str, start_ms: i32, end_ms: i32) -> Chapter {
+        Chapter {
+            title: title.to_string(),
+            start_ms,
+            end_ms,
+        }
+    }
+
+    #[test]
+    fn markers_run_until_the_next_one_or_the_end() {
+        let markers = [marker("b", 1000, None), marker("a", 0, None)];
+
+        assert_eq!(
+            timeline_chapters(&markers, 3000),
+            vec![chapter("a", 0, 1000), chapter("b", 1000, 3000)]
+        );
+    }
+
+    #[test]
+    fn regions_cover_their_range_within_the_timeline() {
+        let markers = [
+            marker("intro", 0, Some(500)),
+            marker("cta", 2000, Some(4000)),
+        ];
+
+        assert_eq!(
+            timeline_chapters(&markers, 3000),
+            vec![chapter("intro", 0, 500), chapter("cta", 2000, 3000)]
+        );
+    }
+
+    #[test]
+    fn chapters_leave_out_sequence_markers_and_ones_past_the_end() {
+        let mut on_sequence = marker("object", 500, None);
+        on_sequence.sequence_id = Some("sequence".to_string());
+        let markers = [
+            marker("a", 0, None),
+            on_sequence,
+            marker("late", 3000, None),
+        ];
+
+        assert_eq!(
+            timeline_chapters(&markers, 3000),
+            vec![chapter("a", 0, 3000)]
+        );
+    }
+
+    #[test]
+    fn ffmetadata_lists_chapters_in_milliseconds() {
+        let metadata = chapters_ffmetadata(&[chapter("Intro", 0, 1500)]);
+
+        assert_eq!(
+            metadata,
+            ";FFMETADATA1\n\n[CHAPTER]\nTIMEBASE=1/1000\nSTART=0\nEND=1500\ntitle=Intro\n"
+        );
+    }
+
+    #[test]
+    fn ffmetadata_escapes_special_characters_in_titles() {
+        let metadata = chapters_ffmetadata(&[chapter("a=b;c#d\\e\nf", 0, 1000)]);
+
+        assert!(metadata.ends_with("title=a\\=b\\;c\\#d\\\\e\\\nf\n"));
+    }
+}
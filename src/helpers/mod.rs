@@ -1,3 +1,27 @@
+pub mod clips;
+pub mod clock;
+pub mod colors;
+pub mod keyframes;
+pub mod looks;
+pub mod markers;
+pub mod modifiers;
+pub mod nested;
+pub mod onion;
+pub mod playback;
 pub mod projects;
+pub mod recording;
+pub mod retime;
 pub mod saved_state;
+pub mod scrub;
+pub mod shapes;
+pub mod simplify;
+pub mod snapping;
+pub mod stagger;
+pub mod text_animators;
+pub mod timeline;
+pub mod timeline_playback;
+pub mod tracks;
+pub mod transitions;
 pub mod utilities;
+pub mod video;
+pub mod zoom;
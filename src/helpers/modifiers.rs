@@ -0,0 +1,334 @@
+use std::f64::consts::PI;
+use std::time::Duration;
+
+use stunts_engine::animations::{
+    AnimationData, AnimationProperty, EasingType, KeyType, UIKeyframe,
+};
+use stunts_engine::editor::PathType;
+use uuid::Uuid;
+
+use super::keyframes::{value_components, value_from_components};
+use super::saved_state::{ModifierAxis, ModifierKind, PropertyModifier};
+
+/// deterministic hash of (seed, index) to [-1, 1], so preview and export match
+fn hash_noise(seed: u32, index: i64) -> f64 {
+    let mut x = (index as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+        ^ (seed as u64).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    x ^= x >> 30;
+    x = x.wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    x ^= x >> 27;
+    x = x.wrapping_mul(0x94D0_49BB_1331_11EB);
+    x ^= x >> 31;
+
+    (x >> 40) as f64 / (1u64 << 24) as f64 * 2.0 - 1.0
+}
+
+/// smooth 1d value noise, t is measured in cycles
+fn value_noise(seed: u32, t: f64) -> f64 {
+    let index = t.floor();
+    let fraction = t - index;
+    let smoothed = fraction * fraction * (3.0 - 2.0 * fraction);
+
+    let a = hash_noise(seed, index as i64);
+    let b = hash_noise(seed, index as i64 + 1);
+
+    a + (b - a) * smoothed
+}
+
+/// Offset a modifier adds at the given time, for one component of the value
+pub fn modifier_offset(kind: &ModifierKind, time: Duration, component: u32) -> f64 {
+    let seconds = time.as_secs_f64();
+
+    match kind {
+        // layered octaves for a jittery, handheld feel
+        ModifierKind::Wiggle {
+            frequency_mhz,
+            amplitude,
+            seed,
+        } => {
+            let t = seconds * *frequency_mhz as f64 / 1000.0;
+            let seed = seed.wrapping_add(component.wrapping_mul(7919));
+            let wiggle = value_noise(seed, t) + 0.5 * value_noise(seed.wrapping_add(1), t * 2.0);
+
+            wiggle / 1.5 * *amplitude as f64
+        }
+        // a single smooth octave for slow floating drift
+        ModifierKind::Noise {
+            frequency_mhz,
+            amplitude,
+            seed,
+        } => {
+            let t = seconds * *frequency_mhz as f64 / 1000.0;
+            let seed = seed.wrapping_add(component.wrapping_mul(7919));
+
+            value_noise(seed, t) * *amplitude as f64
+        }
+        ModifierKind::Oscillator {
+            period_ms,
+            phase_deg,
+            amplitude,
+        } => {
+            if *period_ms == 0 {
+                return 0.0;
+            }
+
+            let angle = 2.0 * PI * time.as_millis() as f64 / *period_ms as f64
+                + (*phase_deg as f64).to_radians();
+
+            angle.sin() * *amplitude as f64
+        }
+    }
+}
+
+/// Same easing curves the engine uses during playback, shared by everything eased outside it
+pub fn ease(easing: &EasingType, t: f64) -> f64 {
+    match easing {
+        EasingType::Linear => t,
+        EasingType::EaseIn => t * t,
+        EasingType::EaseOut => t * (2.0 - t),
+        EasingType::EaseInOut => {
+            if t < 0.5 {
+                2.0 * t * t
+            } else {
+                -1.0 + (4.0 - 2.0 * t) * t
+            }
+        }
+    }
+}
+
+pub fn easing_label(easing: &EasingType) -> &'static str {
+    match easing {
+        EasingType::Linear => "Linear",
+        EasingType::EaseIn => "Ease In",
+        EasingType::EaseOut => "Ease Out",
+        EasingType::EaseInOut => "Ease In Out",
+    }
+}
+
+pub fn easing_types() -> Vec<EasingType> {
+    vec![
+        EasingType::Linear,
+        EasingType::EaseIn,
+        EasingType::EaseOut,
+        EasingType::EaseInOut,
+    ]
+}
+
+pub fn easing_from_label(label: &str) -> Option<EasingType> {
+    easing_types()
+        .into_iter()
+        .find(|easing| easing_label(easing) == label)
+}
+
+/// Point on a position path. Bezier control points are absolute positions,
+/// a missing one falls back to the segment end it belongs to.
+fn path_point(path_type: &PathType, start: &[f64], end: &[f64], progress: f64) -> Vec<f64> {
+    let curve = match path_type {
+        PathType::Bezier(curve) if start.len() == 2 && end.len() == 2 => curve,
+        _ => {
+            return start
+                .iter()
+                .zip(end)
+                .map(|(a, b)| a + (b - a) * progress)
+                .collect()
+        }
+    };
+
+    let p1 = curve
+        .control_point1
+        .as_ref()
+        .map(|cp| [cp.x as f64, cp.y as f64])
+        .unwrap_or([start[0], start[1]]);
+    let p2 = curve
+        .control_point2
+        .as_ref()
+        .map(|cp| [cp.x as f64, cp.y as f64])
+        .unwrap_or([end[0], end[1]]);
+
+    let u = 1.0 - progress;
+    let weights = [
+        u * u * u,
+        3.0 * u * u * progress,
+        3.0 * u * progress * progress,
+        progress * progress * progress,
+    ];
+
+    (0..2)
+        .map(|i| {
+            weights[0] * start[i] + weights[1] * p1[i] + weights[2] * p2[i] + weights[3] * end[i]
+        })
+        .collect()
+}
+
+/// Keyframed value of a property at a time, before any modifiers.
+/// Interpolates like the engine: each segment uses its start keyframe's easing and path type.
+/// Keyframes in `hold_keyframes` keep their value until the next one. Prepared animations
+/// have their holds baked in already and pass none.
+pub fn sample_property(
+    property: &AnimationProperty,
+    time: Duration,
+    hold_keyframes: &[String],
+) -> Option<Vec<f64>> {
+    // range keyframes hold their value until end_time
+    let mut points: Vec<(Duration, Vec<f64>, EasingType, PathType, bool)> = Vec::new();
+
+    for keyframe in &property.keyframes {
+        let values = value_components(&keyframe.value)?;
+        let held = hold_keyframes.contains(&keyframe.id);
+
+        match &keyframe.key_type {
+            KeyType::Range(range_data) => {
+                points.push((
+                    keyframe.time,
+                    values.clone(),
+                    EasingType::Linear,
+                    PathType::Linear,
+                    false,
+                ));
+                points.push((
+                    range_data.end_time,
+                    values,
+                    keyframe.easing.clone(),
+                    keyframe.path_type.clone(),
+                    held,
+                ));
+            }
+            KeyType::Frame => points.push((
+                keyframe.time,
+                values,
+                keyframe.easing.clone(),
+                keyframe.path_type.clone(),
+                held,
+            )),
+        }
+    }
+
+    points.sort_by_key(|(t, _, _, _, _)| *t);
+
+    let first = points.first()?;
+    if time <= first.0 {
+        return Some(first.1.clone());
+    }
+
+    for pair in points.windows(2) {
+        let (start_time, start_values, easing, path_type, held) = &pair[0];
+        let (end_time, end_values, _, _, _) = &pair[1];
+
+        if time > *end_time {
+            continue;
+        }
+
+        if *held && time < *end_time {
+            return Some(start_values.clone());
+        }
+
+        let span = (*end_time - *start_time).as_secs_f64();
+        let progress = if span > 0.0 {
+            (time - *start_time).as_secs_f64() / span
+        } else {
+            1.0
+        };
+        let progress = ease(easing, progress);
+
+        return Some(path_point(path_type, start_values, end_values, progress));
+    }
+
+    points.last().map(|(_, values, _, _, _)| values.clone())
+}
+
+fn applies_to(axis: ModifierAxis, component: usize, component_count: usize) -> bool {
+    if component_count < 2 {
+        return true;
+    }
+
+    match axis {
+        ModifierAxis::Both => true,
+        ModifierAxis::X => component == 0,
+        ModifierAxis::Y => component == 1,
+    }
+}
+
+fn modify_property(
+    property: &mut AnimationProperty,
+    modifiers: &[&PropertyModifier],
+    duration: Duration,
+    fps: u32,
+) {
+    let matching: Vec<&PropertyModifier> = modifiers
+        .iter()
+        .filter(|m| m.property_path == property.property_path)
+        .cloned()
+        .collect();
+
+    if !matching.is_empty() && fps > 0 {
+        if let Some(template) = property.keyframes.first().map(|k| k.value.clone()) {
+            // one keyframe per frame, plus the originals so keyed values are hit exactly
+            let frame_count = (duration.as_secs_f64() * fps as f64).ceil() as u64;
+            let mut times: Vec<Duration> = (0..=frame_count)
+                .map(|frame| Duration::from_secs_f64(frame as f64 / fps as f64))
+                .chain(property.keyframes.iter().map(|k| k.time))
+                .collect();
+            times.sort();
+            times.dedup();
+
+            let mut baked = Vec::new();
+
+            for time in times {
+                let mut values = match sample_property(property, time, &[]) {
+                    Some(values) => values,
+                    None => return,
+                };
+                let count = values.len();
+
+                for modifier in &matching {
+                    for (component, value) in values.iter_mut().enumerate() {
+                        if applies_to(modifier.axis, component, count) {
+                            *value += modifier_offset(&modifier.kind, time, component as u32);
+                        }
+                    }
+                }
+
+                baked.push(UIKeyframe {
+                    id: Uuid::new_v4().to_string(),
+                    time,
+                    value: value_from_components(&template, &values),
+                    easing: EasingType::Linear,
+                    path_type: PathType::Linear,
+                    key_type: KeyType::Frame,
+                });
+            }
+
+            property.keyframes = baked;
+        }
+    }
+
+    for child in &mut property.children {
+        modify_property(child, modifiers, duration, fps);
+    }
+}
+
+/// Bake an animation's property modifiers into per-frame keyframes for the engine
+pub fn apply_modifiers(
+    animation: &AnimationData,
+    modifiers: &[PropertyModifier],
+    fps: u32,
+) -> AnimationData {
+    let relevant: Vec<&PropertyModifier> = modifiers
+        .iter()
+        .filter(|m| m.animation_id == animation.id)
+        .collect();
+
+    if relevant.is_empty() {
+        return animation.clone();
+    }
+
+    let mut new_animation = animation.clone();
+    let duration = animation.duration;
+
+    new_animation
+        .properties
+        .iter_mut()
+        .for_each(|p| modify_property(p, &relevant, duration, fps));
+
+    new_animation
+}
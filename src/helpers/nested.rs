@@ -0,0 +1,752 @@
+use std::str::FromStr;
+use std::time::Duration;
+
+use stunts_engine::animations::{
+    AnimationData, AnimationProperty, EasingType, KeyType, KeyframeValue, ObjectType, Sequence,
+    UIKeyframe,
+};
+use stunts_engine::editor::PathType;
+use uuid::Uuid;
+
+use super::clock::{frame_at, frame_time, project_frame_rate};
+use super::keyframes::{value_components, value_from_components};
+use super::modifiers::sample_property;
+use super::saved_state::{ColorTrack, NestedSequence, SavedState, ShapeTrack, TextAnimator};
+
+/// size of a sequence's canvas, nested sequences turn and scale about its middle
+pub const CANVAS_SIZE: [f64; 2] = [800.0, 450.0];
+
+pub const MIN_SPEED_PCT: u32 = 10;
+pub const MAX_SPEED_PCT: u32 = 1000;
+
+/// Whether putting one sequence inside another would end up with a sequence inside itself
+pub fn creates_cycle(nested: &[NestedSequence], parent_id: &str, child_id: &str) -> bool {
+    // the parent would contain itself if it can be reached from the child
+    let mut to_visit = vec![child_id.to_string()];
+    let mut visited: Vec<String> = Vec::new();
+
+    while let Some(id) = to_visit.pop() {
+        if id == parent_id {
+            return true;
+        }
+
+        if visited.contains(&id) {
+            continue;
+        }
+
+        to_visit.extend(
+            nested
+                .iter()
+                .filter(|n| n.parent_sequence_id == id)
+                .map(|n| n.child_sequence_id.clone()),
+        );
+        visited.push(id);
+    }
+
+    false
+}
+
+/// Sequences that can go inside a sequence without a cycle
+pub fn nestable_sequences<'a>(saved_state: &'a SavedState, parent_id: &str) -> Vec<&'a Sequence> {
+    saved_state
+        .sequences
+        .iter()
+        .filter(|s| !creates_cycle(&saved_state.nested_sequences, parent_id, &s.id))
+        .collect()
+}
+
+/// Sequences placed inside one sequence
+pub fn nested_in(nested: &[NestedSequence], parent_id: &str) -> Vec<NestedSequence> {
+    nested
+        .iter()
+        .filter(|n| n.parent_sequence_id == parent_id)
+        .cloned()
+        .collect()
+}
+
+/// Id of the copy of a nested object or animation. The same every time, so copies
+/// restored into the editor are found again by the prepared sequences.
+pub fn copied_id(nested_id: &str, id: &str) -> String {
+    match (Uuid::from_str(nested_id), Uuid::from_str(id)) {
+        (Ok(nested_id), Ok(id)) => {
+            Uuid::from_u128(nested_id.as_u128() ^ id.as_u128().rotate_left(1)).to_string()
+        }
+        _ => format!("{}-{}", nested_id, id),
+    }
+}
+
+fn speed(nested: &NestedSequence) -> f64 {
+    nested.speed_pct.clamp(MIN_SPEED_PCT, MAX_SPEED_PCT) as f64 / 100.0
+}
+
+/// How long a nested sequence plays in its parent, from its offset to its end
+pub fn nested_duration_ms(nested: &NestedSequence, child_duration_ms: i32) -> i32 {
+    let remaining_ms = (child_duration_ms - nested.time_offset_ms).max(0) as f64;
+
+    (remaining_ms / speed(nested)) as i32
+}
+
+/// Time in the nested sequence shown a while after it started
+fn child_time(nested: &NestedSequence, local: Duration) -> Duration {
+    let time_ms = nested.time_offset_ms as f64 + local.as_secs_f64() * 1000.0 * speed(nested);
+
+    Duration::from_secs_f64(time_ms.max(0.0) / 1000.0)
+}
+
+/// Time in the parent a time in the nested sequence is shown at
+fn parent_time(nested: &NestedSequence, child: Duration) -> Duration {
+    let child_ms = child.as_secs_f64() * 1000.0;
+    let local_ms = ((child_ms - nested.time_offset_ms as f64) / speed(nested)).max(0.0);
+
+    Duration::from_millis(nested.start_time_ms.max(0) as u64)
+        + Duration::from_secs_f64(local_ms / 1000.0)
+}
+
+fn still_property(name: &str, property_path: &str, value: KeyframeValue) -> AnimationProperty {
+    AnimationProperty {
+        name: name.to_string(),
+        property_path: property_path.to_string(),
+        children: Vec::new(),
+        keyframes: vec![UIKeyframe {
+            id: Uuid::new_v4().to_string(),
+            time: Duration::ZERO,
+            value,
+            easing: EasingType::EaseInOut,
+            path_type: PathType::Linear,
+            key_type: KeyType::Frame,
+        }],
+        depth: 0,
+    }
+}
+
+/// Properties that aren't keyed yet, holding the value they'd have unkeyed
+fn missing_properties(
+    properties: &[AnimationProperty],
+    position: [i32; 2],
+) -> Vec<AnimationProperty> {
+    [
+        ("Position", "position", KeyframeValue::Position(position)),
+        ("Rotation", "rotation", KeyframeValue::Rotation(0)),
+        ("Scale", "scale", KeyframeValue::Scale(100)),
+        ("Opacity", "opacity", KeyframeValue::Opacity(100)),
+    ]
+    .into_iter()
+    .filter(|(_, path, _)| !properties.iter().any(|p| p.property_path == *path))
+    .map(|(name, path, value)| still_property(name, path, value))
+    .collect()
+}
+
+/// Placement of a newly nested sequence, filling the parent's canvas as it fills its own
+pub fn default_placement() -> Vec<AnimationProperty> {
+    let middle = [(CANVAS_SIZE[0] / 2.0) as i32, (CANVAS_SIZE[1] / 2.0) as i32];
+
+    missing_properties(&[], middle)
+}
+
+/// Key one placement property at a time since the nested sequence started,
+/// updating a keyframe already within half a frame of it
+pub fn key_placement(
+    nested: &mut NestedSequence,
+    property_path: &str,
+    time: Duration,
+    value: KeyframeValue,
+    fps: u32,
+) {
+    let half_frame = Duration::from_secs_f64(0.5 / fps.max(1) as f64);
+
+    let property = match nested
+        .properties
+        .iter_mut()
+        .find(|p| p.property_path == property_path)
+    {
+        Some(property) => property,
+        None => return,
+    };
+
+    match property.keyframes.iter_mut().find(|k| {
+        let distance = if k.time > time {
+            k.time - time
+        } else {
+            time - k.time
+        };
+        distance <= half_frame
+    }) {
+        Some(keyframe) => keyframe.value = value,
+        None => {
+            property.keyframes.push(UIKeyframe {
+                id: Uuid::new_v4().to_string(),
+                time,
+                value,
+                easing: EasingType::EaseInOut,
+                path_type: PathType::Linear,
+                key_type: KeyType::Frame,
+            });
+            property.keyframes.sort_by_key(|k| k.time);
+        }
+    }
+}
+
+/// Position, rotation, scale and opacity of a whole nested sequence at a moment
+struct Placement {
+    position: [f64; 2],
+    rotation: f64,
+    scale: f64,
+    opacity: f64,
+}
+
+impl Placement {
+    fn at(nested: &NestedSequence, local: Duration) -> Self {
+        let sample = |property_path: &str| sample_placement(nested, property_path, local);
+
+        Self {
+            position: sample("position")
+                .map(|values| [values[0], values[1]])
+                .unwrap_or([CANVAS_SIZE[0] / 2.0, CANVAS_SIZE[1] / 2.0]),
+            rotation: sample("rotation").map(|values| values[0]).unwrap_or(0.0),
+            scale: sample("scale")
+                .map(|values| values[0] / 100.0)
+                .unwrap_or(1.0),
+            opacity: sample("opacity")
+                .map(|values| values[0] / 100.0)
+                .unwrap_or(1.0),
+        }
+    }
+
+    /// A nested object's value as seen in the parent
+    fn apply(&self, property_path: &str, values: &mut [f64]) {
+        match property_path {
+            "position" => {
+                // from the middle of the nested canvas, turned and scaled with it
+                let (sin, cos) = self.rotation.to_radians().sin_cos();
+                let x = (values[0] - CANVAS_SIZE[0] / 2.0) * self.scale;
+                let y = (values[1] - CANVAS_SIZE[1] / 2.0) * self.scale;
+
+                values[0] = self.position[0] + x * cos - y * sin;
+                values[1] = self.position[1] + x * sin + y * cos;
+            }
+            "rotation" => values[0] += self.rotation,
+            "scale" => values[0] *= self.scale,
+            "opacity" => values[0] *= self.opacity,
+            _ => {}
+        }
+    }
+}
+
+fn frame_keyframe(time: Duration, value: KeyframeValue) -> UIKeyframe {
+    UIKeyframe {
+        id: Uuid::new_v4().to_string(),
+        time,
+        value,
+        easing: EasingType::Linear,
+        path_type: PathType::Linear,
+        key_type: KeyType::Frame,
+    }
+}
+
+/// A nested object's animation in the parent's time, sampled every frame
+/// the nested sequence plays and hidden before and after
+fn place_animation(
+    animation: &AnimationData,
+    position: [i32; 2],
+    nested: &NestedSequence,
+    nested_ms: i32,
+    parent_duration_ms: i32,
+    fps: u32,
+) -> AnimationData {
+    let start = Duration::from_millis(nested.start_time_ms.max(0) as u64);
+    let length = Duration::from_millis(nested_ms.max(0) as u64);
+    let end = start + length;
+    let animation_start = Duration::from_millis(animation.start_time_ms.max(0) as u64);
+
+    let mut properties = animation.properties.clone();
+    properties.extend(missing_properties(&animation.properties, position));
+
+    let times: Vec<Duration> = (0..=frame_at(length, fps))
+        .map(|frame| frame_time(frame, fps).min(length))
+        .collect();
+    let placements: Vec<Placement> = times
+        .iter()
+        .map(|local| Placement::at(nested, *local))
+        .collect();
+
+    for property in properties.iter_mut() {
+        let template = match property.keyframes.first() {
+            Some(keyframe) => keyframe.value.clone(),
+            None => continue,
+        };
+
+        // values that can't be sampled, like paths, keep their keyframes moved into place
+        if value_components(&template).is_none() {
+            property
+                .keyframes
+                .iter_mut()
+                .for_each(|k| k.time = parent_time(nested, animation_start + k.time));
+            continue;
+        }
+
+        let original = property.clone();
+        let mut keyframes = Vec::new();
+
+        for (local, placement) in times.iter().zip(&placements) {
+            // keyframes count from the animation's start in the nested sequence
+            let time = child_time(nested, *local).saturating_sub(animation_start);
+            let mut values = match sample_property(&original, time, &[]) {
+                Some(values) => values,
+                None => continue,
+            };
+
+            placement.apply(&property.property_path, &mut values);
+
+            keyframes.push(frame_keyframe(
+                start + *local,
+                value_from_components(&template, &values),
+            ));
+        }
+
+        if property.property_path == "opacity" {
+            let hidden = value_from_components(&template, &[0.0]);
+            let step = Duration::from_millis(1);
+
+            if start >= step {
+                keyframes.insert(0, frame_keyframe(start - step, hidden.clone()));
+                keyframes.insert(0, frame_keyframe(Duration::ZERO, hidden.clone()));
+            }
+
+            if (end.as_millis() as i32) < parent_duration_ms {
+                keyframes.push(frame_keyframe(end + step, hidden));
+            }
+        }
+
+        property.keyframes = keyframes;
+    }
+
+    let mut placed = animation.clone();
+
+    placed.start_time_ms = 0;
+    placed.duration = Duration::from_millis(parent_duration_ms.max(0) as u64).max(end);
+    placed.properties = properties;
+
+    placed
+}
+
+/// Every object of a sequence with its type, position and animation, objects
+/// without an animation get one that holds them where they are
+fn object_animations(sequence: &Sequence) -> Vec<(AnimationData, [i32; 2])> {
+    let objects = sequence
+        .active_polygons
+        .iter()
+        .map(|p| {
+            (
+                p.id.clone(),
+                ObjectType::Polygon,
+                [p.position.x, p.position.y],
+            )
+        })
+        .chain(sequence.active_text_items.iter().map(|t| {
+            (
+                t.id.clone(),
+                ObjectType::TextItem,
+                [t.position.x, t.position.y],
+            )
+        }))
+        .chain(sequence.active_image_items.iter().map(|i| {
+            (
+                i.id.clone(),
+                ObjectType::ImageItem,
+                [i.position.x, i.position.y],
+            )
+        }))
+        .chain(sequence.active_video_items.iter().map(|v| {
+            (
+                v.id.clone(),
+                ObjectType::VideoItem,
+                [v.position.x, v.position.y],
+            )
+        }));
+
+    objects
+        .map(|(object_id, object_type, position)| {
+            let animation = sequence
+                .polygon_motion_paths
+                .iter()
+                .find(|a| a.polygon_id == object_id)
+                .cloned()
+                .unwrap_or_else(|| AnimationData {
+                    id: object_id.clone(),
+                    object_type,
+                    polygon_id: object_id.clone(),
+                    duration: Duration::from_millis(sequence.duration_ms.max(0) as u64),
+                    start_time_ms: 0,
+                    position: [0, 0],
+                    properties: Vec::new(),
+                });
+
+            (animation, position)
+        })
+        .collect()
+}
+
+/// The objects a nested sequence adds to its parent, copied under ids of their own
+/// with animations placed and timed by it. Sequences nested in it come along too.
+fn placed_objects(
+    saved_state: &SavedState,
+    nested: &NestedSequence,
+    prepare: &dyn Fn(&Sequence) -> Sequence,
+    parent_duration_ms: i32,
+    fps: u32,
+    visited: &mut Vec<String>,
+) -> Option<Sequence> {
+    // cycles are refused when nesting, but saved states can be edited by hand
+    if visited.contains(&nested.child_sequence_id) {
+        return None;
+    }
+
+    let child = saved_state
+        .sequences
+        .iter()
+        .find(|s| s.id == nested.child_sequence_id)?;
+
+    visited.push(child.id.clone());
+    let child = flatten(saved_state, &prepare(child), prepare, fps, visited);
+    visited.pop();
+
+    let nested_ms = nested_duration_ms(nested, child.duration_ms);
+    let copy = |id: &str| copied_id(&nested.id, id);
+
+    let mut placed = child.clone();
+
+    placed.id = copy(&child.id);
+    placed.polygon_motion_paths = object_animations(&child)
+        .iter()
+        .map(|(animation, position)| {
+            let mut animation = place_animation(
+                animation,
+                *position,
+                nested,
+                nested_ms,
+                parent_duration_ms,
+                fps,
+            );
+
+            animation.id = copy(&animation.id);
+            animation.polygon_id = copy(&animation.polygon_id);
+
+            animation
+        })
+        .collect();
+    placed
+        .active_polygons
+        .iter_mut()
+        .for_each(|p| p.id = copy(&p.id));
+    placed
+        .active_text_items
+        .iter_mut()
+        .for_each(|t| t.id = copy(&t.id));
+    placed
+        .active_image_items
+        .iter_mut()
+        .for_each(|i| i.id = copy(&i.id));
+    placed
+        .active_video_items
+        .iter_mut()
+        .for_each(|v| v.id = copy(&v.id));
+
+    Some(placed)
+}
+
+fn flatten(
+    saved_state: &SavedState,
+    sequence: &Sequence,
+    prepare: &dyn Fn(&Sequence) -> Sequence,
+    fps: u32,
+    visited: &mut Vec<String>,
+) -> Sequence {
+    let mut flat = sequence.clone();
+
+    for nested in nested_in(&saved_state.nested_sequences, &sequence.id) {
+        let placed = match placed_objects(
+            saved_state,
+            &nested,
+            prepare,
+            sequence.duration_ms,
+            fps,
+            visited,
+        ) {
+            Some(placed) => placed,
+            None => continue,
+        };
+
+        flat.active_polygons.extend(placed.active_polygons);
+        flat.active_text_items.extend(placed.active_text_items);
+        flat.active_image_items.extend(placed.active_image_items);
+        flat.active_video_items.extend(placed.active_video_items);
+        flat.polygon_motion_paths
+            .extend(placed.polygon_motion_paths);
+    }
+
+    flat
+}
+
+/// The sequence with the objects of every sequence nested in it copied in, placed
+/// and timed by their nesting. `prepare` applies each nested sequence's own settings first.
+/// The looks and videos of the copies are kept apart, see `nest_looks` and `nested_videos`.
+pub fn nest_sequence(
+    saved_state: &SavedState,
+    sequence: &Sequence,
+    prepare: &dyn Fn(&Sequence) -> Sequence,
+) -> Sequence {
+    let mut visited = vec![sequence.id.clone()];
+
+    flatten(
+        saved_state,
+        sequence,
+        prepare,
+        project_frame_rate(saved_state),
+        &mut visited,
+    )
+}
+
+/// Just the copies one nested sequence adds to its parent, to restore into the editor
+pub fn nested_objects(saved_state: &SavedState, nested: &NestedSequence) -> Option<Sequence> {
+    let parent = saved_state
+        .sequences
+        .iter()
+        .find(|s| s.id == nested.parent_sequence_id)?;
+    let mut visited = vec![parent.id.clone()];
+
+    placed_objects(
+        saved_state,
+        nested,
+        &|s: &Sequence| s.clone(),
+        parent.duration_ms,
+        project_frame_rate(saved_state),
+        &mut visited,
+    )
+}
+
+/// Every sequence with its nested objects copied in, for restoring into the editor
+pub fn nest_sequences(saved_state: &SavedState) -> Vec<Sequence> {
+    saved_state
+        .sequences
+        .iter()
+        .map(|s| nest_sequence(saved_state, s, &|s: &Sequence| s.clone()))
+        .collect()
+}
+
+/// Color and shape tracks and text animators, kept beside the sequences by animation id
+#[derive(Default)]
+struct SideTables {
+    color_tracks: Vec<ColorTrack>,
+    shape_tracks: Vec<ShapeTrack>,
+    text_animators: Vec<TextAnimator>,
+}
+
+impl SideTables {
+    fn extend(&mut self, other: SideTables) {
+        self.color_tracks.extend(other.color_tracks);
+        self.shape_tracks.extend(other.shape_tracks);
+        self.text_animators.extend(other.text_animators);
+    }
+}
+
+/// The side tables of a nested sequence's animations, moved onto the copies of the
+/// animations and into the parent's time. Sequences nested in it come along too.
+fn nested_tables(
+    saved_state: &SavedState,
+    nested: &NestedSequence,
+    visited: &mut Vec<String>,
+) -> SideTables {
+    let mut tables = SideTables::default();
+
+    if visited.contains(&nested.child_sequence_id) {
+        return tables;
+    }
+
+    let child = match saved_state
+        .sequences
+        .iter()
+        .find(|s| s.id == nested.child_sequence_id)
+    {
+        Some(child) => child,
+        None => return tables,
+    };
+    let owned = |animation_id: &str| {
+        child
+            .polygon_motion_paths
+            .iter()
+            .any(|a| a.id == animation_id)
+    };
+
+    tables.color_tracks = saved_state
+        .color_tracks
+        .iter()
+        .filter(|t| owned(&t.animation_id))
+        .cloned()
+        .collect();
+    tables.shape_tracks = saved_state
+        .shape_tracks
+        .iter()
+        .filter(|t| owned(&t.animation_id))
+        .cloned()
+        .collect();
+    tables.text_animators = saved_state
+        .text_animators
+        .iter()
+        .filter(|a| owned(&a.animation_id))
+        .cloned()
+        .collect();
+
+    visited.push(child.id.clone());
+    for inner in nested_in(&saved_state.nested_sequences, &child.id) {
+        let inner_tables = nested_tables(saved_state, &inner, visited);
+        tables.extend(inner_tables);
+    }
+    visited.pop();
+
+    // keyframes count from their animation's start, the copies start with the parent
+    let start_ms = |animation_id: &str| {
+        child
+            .polygon_motion_paths
+            .iter()
+            .find(|a| a.id == animation_id)
+            .map(|a| a.start_time_ms.max(0) as u64)
+            .unwrap_or(0)
+    };
+    let retime = |animation_id: &str, time_ms: u64| {
+        parent_time(
+            nested,
+            Duration::from_millis(start_ms(animation_id) + time_ms),
+        )
+        .as_millis() as u64
+    };
+    let copy = |id: &str| copied_id(&nested.id, id);
+
+    for track in &mut tables.color_tracks {
+        for keyframe in &mut track.keyframes {
+            keyframe.id = copy(&keyframe.id);
+            keyframe.time_ms = retime(&track.animation_id, keyframe.time_ms);
+        }
+        track.animation_id = copy(&track.animation_id);
+    }
+
+    for track in &mut tables.shape_tracks {
+        for keyframe in &mut track.keyframes {
+            keyframe.id = copy(&keyframe.id);
+            keyframe.time_ms = retime(&track.animation_id, keyframe.time_ms);
+        }
+        track.animation_id = copy(&track.animation_id);
+    }
+
+    for animator in &mut tables.text_animators {
+        for keyframe in &mut animator.selector {
+            keyframe.id = copy(&keyframe.id);
+            keyframe.time_ms = retime(&animator.animation_id, keyframe.time_ms);
+        }
+        animator.id = copy(&animator.id);
+        animator.animation_id = copy(&animator.animation_id);
+    }
+
+    tables
+}
+
+/// The saved state with the color and shape tracks and text animators of every sequence
+/// nested in a sequence copied onto the nested copies, for baking the sequence's looks
+pub fn nest_looks(saved_state: &SavedState, sequence_id: &str) -> SavedState {
+    let mut tables = SideTables::default();
+    let mut visited = vec![sequence_id.to_string()];
+
+    for nested in nested_in(&saved_state.nested_sequences, sequence_id) {
+        tables.extend(nested_tables(saved_state, &nested, &mut visited));
+    }
+
+    let mut nested_state = saved_state.clone();
+
+    nested_state.color_tracks.extend(tables.color_tracks);
+    nested_state.shape_tracks.extend(tables.shape_tracks);
+    nested_state.text_animators.extend(tables.text_animators);
+
+    nested_state
+}
+
+/// A nested copy of a video item with the nestings it's in, outermost first.
+/// The engine plays videos from their start, so nested ones are seeked to their time instead.
+#[derive(Clone, Debug)]
+pub struct NestedVideo {
+    pub video_id: String,
+    nestings: Vec<NestedSequence>,
+}
+
+impl NestedVideo {
+    /// Time in the video's own sequence shown at a time in the sequence it's nested in
+    pub fn source_time(&self, time: Duration) -> Duration {
+        self.nestings.iter().fold(time, |time, nested| {
+            let start = Duration::from_millis(nested.start_time_ms.max(0) as u64);
+
+            child_time(nested, time.saturating_sub(start))
+        })
+    }
+}
+
+fn videos_nested_in(
+    saved_state: &SavedState,
+    sequence_id: &str,
+    visited: &mut Vec<String>,
+) -> Vec<NestedVideo> {
+    let mut videos = Vec::new();
+
+    for nested in nested_in(&saved_state.nested_sequences, sequence_id) {
+        if visited.contains(&nested.child_sequence_id) {
+            continue;
+        }
+
+        let child = match saved_state
+            .sequences
+            .iter()
+            .find(|s| s.id == nested.child_sequence_id)
+        {
+            Some(child) => child,
+            None => continue,
+        };
+
+        let mut child_videos: Vec<NestedVideo> = child
+            .active_video_items
+            .iter()
+            .map(|v| NestedVideo {
+                video_id: v.id.clone(),
+                nestings: Vec::new(),
+            })
+            .collect();
+
+        visited.push(child.id.clone());
+        child_videos.extend(videos_nested_in(saved_state, &child.id, visited));
+        visited.pop();
+
+        videos.extend(child_videos.into_iter().map(|mut video| {
+            video.video_id = copied_id(&nested.id, &video.video_id);
+            video.nestings.insert(0, nested.clone());
+            video
+        }));
+    }
+
+    videos
+}
+
+/// The nested copies of video items in a sequence, from sequences nested inside those too
+pub fn nested_videos(saved_state: &SavedState, sequence_id: &str) -> Vec<NestedVideo> {
+    let mut visited = vec![sequence_id.to_string()];
+
+    videos_nested_in(saved_state, sequence_id, &mut visited)
+}
+
+/// A placement property's value a while after the nested sequence started
+pub fn sample_placement(
+    nested: &NestedSequence,
+    property_path: &str,
+    local: Duration,
+) -> Option<Vec<f64>> {
+    nested
+        .properties
+        .iter()
+        .find(|p| p.property_path == property_path)
+        .and_then(|p| sample_property(p, local, &[]))
+}
@@ -0,0 +1,278 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+use std::time::Duration;
+
+use floem::common::rgb_to_wgpu;
+use floem::reactive::{RwSignal, SignalGet};
+use stunts_engine::animations::{AnimationData, Sequence};
+use stunts_engine::camera::Camera;
+use stunts_engine::dot::RingDot;
+use stunts_engine::editor::Point;
+
+use super::clock::project_frame_rate;
+use super::looks::add_ghosts;
+use super::modifiers::sample_property;
+use super::saved_state::SavedState;
+
+/// most ticks drawn along one path, long animations get sparse instead of slow
+const MAX_FRAME_TICKS: usize = 600;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum OnionStep {
+    /// ghosts at the neighbouring keyframes
+    Keyframes,
+    /// ghosts every n frames from the playhead
+    Frames(u32),
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct OnionSkinSettings {
+    pub enabled: RwSignal<bool>,
+    pub before: RwSignal<u32>,
+    pub after: RwSignal<u32>,
+    pub step: RwSignal<OnionStep>,
+    pub frame_ticks: RwSignal<bool>,
+}
+
+impl OnionSkinSettings {
+    pub fn new() -> Self {
+        Self {
+            enabled: RwSignal::new(false),
+            before: RwSignal::new(2),
+            after: RwSignal::new(2),
+            step: RwSignal::new(OnionStep::Keyframes),
+            frame_ticks: RwSignal::new(true),
+        }
+    }
+}
+
+/// Ghost times around the playhead, all relative to the animation start.
+/// The offset is negative for ghosts before the playhead.
+pub fn ghost_times(
+    animation: &AnimationData,
+    playhead: Duration,
+    step: OnionStep,
+    before: u32,
+    after: u32,
+    fps: u32,
+) -> Vec<(Duration, i32)> {
+    let mut times = Vec::new();
+
+    match step {
+        OnionStep::Keyframes => {
+            let mut keyframe_times: Vec<Duration> = animation
+                .properties
+                .iter()
+                .flat_map(|p| p.keyframes.iter().map(|k| k.time))
+                .collect();
+            keyframe_times.sort();
+            keyframe_times.dedup();
+
+            let earlier = keyframe_times.iter().filter(|t| **t < playhead).rev();
+            for (index, time) in earlier.take(before as usize).enumerate() {
+                times.push((*time, -(index as i32 + 1)));
+            }
+
+            let later = keyframe_times.iter().filter(|t| **t > playhead);
+            for (index, time) in later.take(after as usize).enumerate() {
+                times.push((*time, index as i32 + 1));
+            }
+        }
+        OnionStep::Frames(frames) => {
+            let spacing = Duration::from_secs_f64(frames.max(1) as f64 / fps.max(1) as f64);
+
+            for index in 1..=before {
+                match playhead.checked_sub(spacing * index) {
+                    Some(time) => times.push((time, -(index as i32))),
+                    None => break,
+                }
+            }
+
+            for index in 1..=after {
+                let time = playhead + spacing * index;
+
+                if time > animation.duration {
+                    break;
+                }
+
+                times.push((time, index as i32));
+            }
+        }
+    }
+
+    times
+}
+
+/// Position of the object at every frame its position keyframes cover
+pub fn frame_tick_positions(
+    animation: &AnimationData,
+    hold_keyframes: &[String],
+    fps: u32,
+) -> Vec<[i32; 2]> {
+    let property = match animation
+        .properties
+        .iter()
+        .find(|p| p.property_path == "position")
+    {
+        Some(property) => property,
+        None => return Vec::new(),
+    };
+
+    let (first, last) = match (
+        property.keyframes.iter().map(|k| k.time).min(),
+        property.keyframes.iter().map(|k| k.time).max(),
+    ) {
+        (Some(first), Some(last)) => (first, last),
+        _ => return Vec::new(),
+    };
+
+    let frame = Duration::from_secs_f64(1.0 / fps.max(1) as f64);
+    let frame_count = ((last - first).as_secs_f64() * fps.max(1) as f64).ceil() as usize + 1;
+    let every = (frame_count / MAX_FRAME_TICKS).max(1) as u32;
+
+    let mut positions: Vec<[i32; 2]> = Vec::new();
+    let mut time = first;
+
+    while time <= last {
+        if let Some(values) = sample_property(property, time, hold_keyframes) {
+            let position = [values[0].round() as i32, values[1].round() as i32];
+
+            // holds would stack dots on one spot
+            if positions.last() != Some(&position) {
+                positions.push(position);
+            }
+        }
+
+        time += frame * every;
+    }
+
+    positions
+}
+
+/// Sequence times to pose ghosts of an animation's object at around a playhead in
+/// sequence time, with the opacity of each. Closer ghosts are stronger.
+pub fn onion_ghosts(
+    animation: &AnimationData,
+    playhead: Duration,
+    step: OnionStep,
+    before: u32,
+    after: u32,
+    fps: u32,
+) -> Vec<(Duration, f64)> {
+    let start = Duration::from_millis(animation.start_time_ms.max(0) as u64);
+
+    ghost_times(
+        animation,
+        playhead.saturating_sub(start),
+        step,
+        before,
+        after,
+        fps,
+    )
+    .into_iter()
+    .map(|(time, offset)| {
+        let reach = if offset < 0 { before } else { after }.max(1) as f64;
+        let opacity = 0.35 * (1.0 - (offset.abs() as f64 - 1.0) / reach);
+
+        (start + time, opacity)
+    })
+    .collect()
+}
+
+/// Add ghosts of the selected object to the sequence the paused preview is posed with.
+/// `placed` is the sequence before its looks were baked into `prepared`.
+pub fn add_onion_ghosts(
+    saved_state: &SavedState,
+    placed: &Sequence,
+    prepared: &mut Sequence,
+    object_id: &str,
+    settings: &OnionSkinSettings,
+    playhead: Duration,
+) {
+    let ghosts = match placed
+        .polygon_motion_paths
+        .iter()
+        .find(|a| a.polygon_id == object_id)
+    {
+        Some(animation) => onion_ghosts(
+            animation,
+            playhead,
+            settings.step.get_untracked(),
+            settings.before.get_untracked(),
+            settings.after.get_untracked(),
+            project_frame_rate(saved_state),
+        ),
+        None => return,
+    };
+
+    add_ghosts(saved_state, placed, prepared, object_id, &ghosts);
+}
+
+/// Dots at the selected object's frame spacing, built once per change rather than every frame.
+/// Its ghosts are objects of the posed sequence, see add_onion_ghosts.
+pub struct OnionSkin {
+    pub key: u64,
+    pub ticks: Vec<RingDot>,
+}
+
+/// Identifies everything an onion skin is built from, to know when to rebuild it
+pub fn onion_skin_key(
+    animation: &AnimationData,
+    hold_keyframes: &[String],
+    step: OnionStep,
+    before: u32,
+    after: u32,
+    frame_ticks: bool,
+) -> u64 {
+    let mut hasher = DefaultHasher::new();
+
+    animation.hash(&mut hasher);
+    hold_keyframes.hash(&mut hasher);
+    step.hash(&mut hasher);
+    before.hash(&mut hasher);
+    after.hash(&mut hasher);
+    frame_ticks.hash(&mut hasher);
+
+    hasher.finish()
+}
+
+pub fn build_onion_skin(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    model_bind_group_layout: &Arc<wgpu::BindGroupLayout>,
+    group_bind_group_layout: &Arc<wgpu::BindGroupLayout>,
+    camera: &Camera,
+    animation: &AnimationData,
+    hold_keyframes: &[String],
+    key: u64,
+    frame_ticks: bool,
+    fps: u32,
+) -> OnionSkin {
+    let mut ticks = Vec::new();
+
+    if frame_ticks {
+        for position in frame_tick_positions(animation, hold_keyframes, fps) {
+            let tick = RingDot::new(
+                device,
+                queue,
+                model_bind_group_layout,
+                group_bind_group_layout,
+                &camera.window_size,
+                Point {
+                    x: position[0] as f32,
+                    y: position[1] as f32,
+                },
+                rgb_to_wgpu(40, 40, 40, 255.0 / 2.0),
+                camera,
+            );
+
+            tick.transform
+                .update_uniform_buffer(queue, &camera.window_size);
+
+            ticks.push(tick);
+        }
+    }
+
+    OnionSkin { key, ticks }
+}
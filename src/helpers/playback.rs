@@ -0,0 +1,284 @@
+use std::time::Duration;
+
+use stunts_engine::animations::{AnimationData, AnimationProperty, KeyType, Sequence, UIKeyframe};
+use stunts_engine::timelines::SavedTimelineStateConfig;
+use uuid::Uuid;
+
+use super::clips::apply_trims;
+use super::clock::project_frame_rate;
+use super::looks::bake_looks;
+use super::modifiers::apply_modifiers;
+use super::nested::{nest_looks, nest_sequence};
+use super::saved_state::{RepeatMode, SavedState};
+use super::tracks::compose_timeline;
+use super::transitions::{apply_transitions, schedule_transitions, WipeClip};
+
+fn property_end_time(property: &AnimationProperty) -> Duration {
+    property
+        .keyframes
+        .iter()
+        .map(|k| match &k.key_type {
+            KeyType::Range(range_data) => range_data.end_time.max(k.time),
+            KeyType::Frame => k.time,
+        })
+        .chain(property.children.iter().map(property_end_time))
+        .max()
+        .unwrap_or(Duration::ZERO)
+}
+
+/// Length of one pass of an animation, taken from its last keyframe
+pub fn cycle_length(animation: &AnimationData) -> Duration {
+    animation
+        .properties
+        .iter()
+        .map(property_end_time)
+        .max()
+        .unwrap_or(Duration::ZERO)
+}
+
+fn repeat_keyframe(
+    keyframe: &UIKeyframe,
+    cycle: Duration,
+    pass_start: Duration,
+    reversed: bool,
+) -> UIKeyframe {
+    let mut new_keyframe = keyframe.clone();
+    new_keyframe.id = Uuid::new_v4().to_string();
+
+    if reversed {
+        match &mut new_keyframe.key_type {
+            KeyType::Range(range_data) => {
+                let end_time = range_data.end_time;
+                range_data.end_time = pass_start + cycle.saturating_sub(keyframe.time);
+                new_keyframe.time = pass_start + cycle.saturating_sub(end_time);
+            }
+            KeyType::Frame => {
+                new_keyframe.time = pass_start + cycle.saturating_sub(keyframe.time);
+            }
+        }
+    } else {
+        new_keyframe.time = pass_start + keyframe.time;
+
+        if let KeyType::Range(range_data) = &mut new_keyframe.key_type {
+            range_data.end_time = pass_start + range_data.end_time;
+        }
+    }
+
+    new_keyframe
+}
+
+fn repeat_property(
+    property: &AnimationProperty,
+    cycle: Duration,
+    passes: u32,
+    ping_pong: bool,
+) -> AnimationProperty {
+    let mut new_property = property.clone();
+
+    for pass in 1..passes {
+        let pass_start = cycle * pass;
+        let reversed = ping_pong && pass % 2 == 1;
+
+        for keyframe in &property.keyframes {
+            let mut new_keyframe = repeat_keyframe(keyframe, cycle, pass_start, reversed);
+
+            if new_keyframe.time == pass_start {
+                if ping_pong {
+                    // already at this value from the end of the previous pass
+                    continue;
+                }
+
+                // jump back to the first value just after the previous pass ends
+                new_keyframe.time += Duration::from_millis(1);
+            }
+
+            new_property.keyframes.push(new_keyframe);
+        }
+    }
+
+    new_property.keyframes.sort_by_key(|k| k.time);
+
+    new_property.children = property
+        .children
+        .iter()
+        .map(|c| repeat_property(c, cycle, passes, ping_pong))
+        .collect();
+
+    new_property
+}
+
+/// Expand an animation's repeat mode into plain keyframes so the engine can play it as-is
+pub fn bake_repeat(
+    animation: &AnimationData,
+    mode: &RepeatMode,
+    sequence_duration_ms: i32,
+) -> AnimationData {
+    let cycle = cycle_length(animation);
+
+    if cycle.is_zero() {
+        return animation.clone();
+    }
+
+    let available_ms = (sequence_duration_ms - animation.start_time_ms).max(0) as u128;
+    let passes_to_fill = ((available_ms + cycle.as_millis() - 1) / cycle.as_millis()).max(1) as u32;
+
+    let (passes, ping_pong) = match mode {
+        RepeatMode::Once => return animation.clone(),
+        RepeatMode::Loop(count) => ((*count).max(1), false),
+        RepeatMode::LoopForever => (passes_to_fill, false),
+        RepeatMode::PingPong => (passes_to_fill, true),
+    };
+
+    let mut new_animation = animation.clone();
+
+    new_animation.properties = animation
+        .properties
+        .iter()
+        .map(|p| repeat_property(p, cycle, passes, ping_pong))
+        .collect();
+    new_animation.duration = animation.duration.max(cycle * passes);
+
+    new_animation
+}
+
+fn hold_property(property: &AnimationProperty, hold_keyframes: &[String]) -> AnimationProperty {
+    let mut new_property = property.clone();
+    let mut keyframes = property.keyframes.clone();
+    keyframes.sort_by_key(|k| k.time);
+
+    for pair in keyframes.windows(2) {
+        let (keyframe, next) = (&pair[0], &pair[1]);
+
+        if !hold_keyframes.contains(&keyframe.id) {
+            continue;
+        }
+
+        let held_from = match &keyframe.key_type {
+            KeyType::Range(range_data) => range_data.end_time.max(keyframe.time),
+            KeyType::Frame => keyframe.time,
+        };
+        let jump_time = next.time.saturating_sub(Duration::from_millis(1));
+
+        // adjacent keyframes already jump
+        if jump_time <= held_from {
+            continue;
+        }
+
+        // keep the value until just before the next keyframe
+        let mut held = keyframe.clone();
+        held.id = Uuid::new_v4().to_string();
+        held.time = jump_time;
+        held.key_type = KeyType::Frame;
+
+        new_property.keyframes.push(held);
+    }
+
+    new_property.keyframes.sort_by_key(|k| k.time);
+
+    new_property.children = property
+        .children
+        .iter()
+        .map(|c| hold_property(c, hold_keyframes))
+        .collect();
+
+    new_property
+}
+
+/// Expand hold keyframes into plain keyframes, the engine only knows how to interpolate
+pub fn bake_holds(animation: &AnimationData, hold_keyframes: &[String]) -> AnimationData {
+    let mut new_animation = animation.clone();
+
+    new_animation.properties = animation
+        .properties
+        .iter()
+        .map(|p| hold_property(p, hold_keyframes))
+        .collect();
+
+    new_animation
+}
+
+/// Repeats, holds and modifiers of a sequence's own animations baked in
+fn prepare_animations(saved_state: &SavedState, sequence: &Sequence) -> Sequence {
+    let mut prepared = sequence.clone();
+
+    prepared
+        .polygon_motion_paths
+        .iter_mut()
+        .for_each(|animation| {
+            // holds first so repeated passes hold too
+            *animation = bake_holds(animation, &saved_state.hold_keyframes);
+
+            if let Some(repeat) = saved_state
+                .animation_repeats
+                .iter()
+                .find(|r| r.animation_id == animation.id)
+            {
+                *animation = bake_repeat(animation, &repeat.mode, sequence.duration_ms);
+            }
+
+            // modifiers layer on top of the repeated keyframes
+            *animation = apply_modifiers(
+                animation,
+                &saved_state.property_modifiers,
+                project_frame_rate(saved_state),
+            );
+        });
+
+    prepared
+}
+
+/// Sequence with all app-side animation settings applied, for preview and export
+pub fn prepare_sequence(saved_state: &SavedState, sequence: &Sequence) -> Sequence {
+    // keyed looks are drawn by stand-ins following the placed animations,
+    // nested copies keep the looks of what they copy
+    bake_looks(
+        &nest_looks(saved_state, &sequence.id),
+        &place_sequence(saved_state, sequence),
+    )
+}
+
+/// Sequence with its animation settings applied and nested sequences placed in it,
+/// before keyed looks are baked
+pub fn place_sequence(saved_state: &SavedState, sequence: &Sequence) -> Sequence {
+    // nested sequences play with their own settings, then get placed in this one
+    nest_sequence(
+        saved_state,
+        &prepare_animations(saved_state, sequence),
+        &|child: &Sequence| prepare_animations(saved_state, child),
+    )
+}
+
+pub fn prepare_sequences(saved_state: &SavedState) -> Vec<Sequence> {
+    saved_state
+        .sequences
+        .iter()
+        .map(|s| prepare_sequence(saved_state, s))
+        .collect()
+}
+
+/// Sequences and timeline for playing or exporting the whole timeline,
+/// with track order, mutes, trims and transitions applied. Playing clips wipes
+/// at their edge, the wipes to clip come back with them.
+pub fn prepare_timeline(
+    saved_state: &SavedState,
+    config: &SavedTimelineStateConfig,
+    clip_wipes: bool,
+) -> (Vec<Sequence>, SavedTimelineStateConfig, Vec<WipeClip>) {
+    let mut timeline = compose_timeline(saved_state, config);
+    let mut sequences = prepare_sequences(saved_state);
+
+    // trimmed clips play their own copies of their sequences
+    apply_trims(&mut sequences, &mut timeline, &saved_state.clip_trims);
+
+    // transitions go on last, over the repeats and modifiers, each clip in its own copy
+    let passes = schedule_transitions(saved_state, &sequences, &timeline);
+    let wipes = apply_transitions(
+        &mut sequences,
+        &mut timeline,
+        &passes,
+        project_frame_rate(saved_state),
+        clip_wipes,
+    );
+
+    (sequences, timeline, wipes)
+}
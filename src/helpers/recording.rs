@@ -0,0 +1,131 @@
+use std::time::Duration;
+
+use stunts_engine::animations::{AnimationData, EasingType, KeyType, KeyframeValue, UIKeyframe};
+use stunts_engine::editor::{Editor, PathType, Point};
+use uuid::Uuid;
+
+use super::simplify::simplify_keyframes;
+
+/// Pointer samples for one object, captured while the sequence plays
+#[derive(Clone, Debug)]
+pub struct MotionRecorder {
+    pub object_id: String,
+    /// pointer minus object position at mouse-down, in canvas coordinates
+    pub grab_offset: Point,
+    /// sequence time and canvas position of the object
+    pub samples: Vec<(Duration, [i32; 2])>,
+}
+
+/// Position the engine currently draws an object at, in canvas coordinates
+pub fn rendered_position(editor: &Editor, object_id: &str) -> Option<Point> {
+    let position = editor
+        .polygons
+        .iter()
+        .find(|p| p.id.to_string() == object_id)
+        .map(|p| p.transform.position)
+        .or_else(|| {
+            editor
+                .text_items
+                .iter()
+                .find(|t| t.id.to_string() == object_id)
+                .map(|t| t.transform.position)
+        })
+        .or_else(|| {
+            editor
+                .image_items
+                .iter()
+                .find(|i| i.id == object_id)
+                .map(|i| i.transform.position)
+        })
+        .or_else(|| {
+            editor
+                .video_items
+                .iter()
+                .find(|v| v.id == object_id)
+                .map(|v| v.transform.position)
+        })?;
+
+    Some(Point {
+        x: position.x,
+        y: position.y,
+    })
+}
+
+impl MotionRecorder {
+    pub fn new(object_id: String, grab_offset: Point) -> Self {
+        Self {
+            object_id,
+            grab_offset,
+            samples: Vec::new(),
+        }
+    }
+
+    /// Keep at most one sample per frame. pointer is in canvas coordinates.
+    pub fn add_sample(&mut self, time: Duration, pointer: Point, fps: u32) {
+        let frame = Duration::from_secs_f64(1.0 / fps.max(1) as f64);
+        let position = [
+            (pointer.x - self.grab_offset.x).round() as i32,
+            (pointer.y - self.grab_offset.y).round() as i32,
+        ];
+
+        if let Some((last_time, _)) = self.samples.last() {
+            if time < *last_time + frame {
+                return;
+            }
+        }
+
+        self.samples.push((time, position));
+    }
+
+    /// Replace the object's position keyframes over the recorded span with the samples.
+    /// Keyframes outside the span are kept. Returns false if nothing was recorded.
+    pub fn apply(&self, animation: &mut AnimationData, simplify_tolerance: Option<f32>) -> bool {
+        let start_ms = animation.start_time_ms.max(0) as u64;
+
+        // samples are in sequence time, keyframes are relative to the animation start
+        let recorded: Vec<UIKeyframe> = self
+            .samples
+            .iter()
+            .filter(|(time, _)| time.as_millis() as u64 >= start_ms)
+            .map(|(time, position)| UIKeyframe {
+                id: Uuid::new_v4().to_string(),
+                time: *time - Duration::from_millis(start_ms),
+                value: KeyframeValue::Position(*position),
+                easing: EasingType::Linear,
+                path_type: PathType::Linear,
+                key_type: KeyType::Frame,
+            })
+            .collect();
+
+        let (first, last) = match (recorded.first(), recorded.last()) {
+            (Some(first), Some(last)) => (first.time, last.time),
+            _ => return false,
+        };
+
+        let recorded = match simplify_tolerance {
+            Some(tolerance) => simplify_keyframes(&recorded, tolerance, &[]),
+            None => recorded,
+        };
+
+        let property = match animation
+            .properties
+            .iter_mut()
+            .find(|p| p.property_path == "position")
+        {
+            Some(property) => property,
+            None => return false,
+        };
+
+        property
+            .keyframes
+            .retain(|k| k.time < first || k.time > last);
+        property.keyframes.extend(recorded);
+        property.keyframes.sort_by_key(|k| k.time);
+
+        if last > animation.duration {
+            animation.duration = last;
+        }
+
+        true
+    }
+}
@@ -0,0 +1,507 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use stunts_engine::animations::{AnimationData, AnimationProperty, KeyType, Sequence};
+
+use super::saved_state::SavedState;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Pivot {
+    Start,
+    Playhead,
+    End,
+}
+
+/// Which keyframes a retime operation applies to
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RetimeScope {
+    /// individual keyframes by id, in any animation of the sequence
+    Keyframes(Vec<String>),
+    /// every keyframe of one property, including its children
+    Property {
+        animation_id: String,
+        property_path: String,
+    },
+    /// whole animations by id, moving their start times as well. All animations when empty.
+    Animations(Vec<String>),
+}
+
+fn includes_animation(ids: &[String], animation: &AnimationData) -> bool {
+    ids.is_empty() || ids.contains(&animation.id)
+}
+
+fn to_ms(time: Duration) -> i64 {
+    time.as_millis() as i64
+}
+
+fn from_ms(ms: i64) -> Duration {
+    Duration::from_millis(ms as u64)
+}
+
+/// Keyframes kept beside the sequence for an animation, one (path, ids and times) per track.
+/// Times are in ms relative to the animation start. Text selectors have no path.
+fn table_tracks(
+    saved_state: &SavedState,
+    animation_id: &str,
+) -> Vec<(Option<&'static str>, Vec<(String, i64)>)> {
+    let colors = saved_state
+        .color_tracks
+        .iter()
+        .filter(|t| t.animation_id == animation_id)
+        .map(|t| {
+            let keys = t.keyframes.iter().map(|k| (k.id.clone(), k.time_ms as i64));
+
+            (Some(t.target.path()), keys.collect())
+        });
+    let shapes = saved_state
+        .shape_tracks
+        .iter()
+        .filter(|t| t.animation_id == animation_id)
+        .map(|t| {
+            let keys = t.keyframes.iter().map(|k| (k.id.clone(), k.time_ms as i64));
+
+            (Some(t.property.path()), keys.collect())
+        });
+    let selectors = saved_state
+        .text_animators
+        .iter()
+        .filter(|a| a.animation_id == animation_id)
+        .map(|a| {
+            let keys = a.selector.iter().map(|k| (k.id.clone(), k.time_ms as i64));
+
+            (None, keys.collect())
+        });
+
+    colors.chain(shapes).chain(selectors).collect()
+}
+
+fn table_key_selected(
+    scope: &RetimeScope,
+    animation_id: &str,
+    path: Option<&str>,
+    keyframe_id: &str,
+) -> bool {
+    match scope {
+        RetimeScope::Keyframes(ids) => ids.iter().any(|id| id == keyframe_id),
+        RetimeScope::Property {
+            animation_id: id,
+            property_path,
+        } => id == animation_id && path == Some(property_path.as_str()),
+        RetimeScope::Animations(ids) => ids.is_empty() || ids.iter().any(|id| id == animation_id),
+    }
+}
+
+/// Sequence time (ms) an animation ends, counting the keyframes kept beside it
+fn animation_end_ms(animation: &AnimationData, saved_state: &SavedState) -> i64 {
+    let last_table_key = table_tracks(saved_state, &animation.id)
+        .into_iter()
+        .flat_map(|(_, keys)| keys.into_iter().map(|(_, time)| time))
+        .max()
+        .unwrap_or(0);
+
+    animation.start_time_ms as i64 + to_ms(animation.duration).max(last_table_key)
+}
+
+fn collect_property_times(
+    property: &AnimationProperty,
+    scope: &RetimeScope,
+    animation_id: &str,
+    in_property: bool,
+    start_ms: i64,
+    times: &mut Vec<i64>,
+) {
+    let in_property = in_property
+        || matches!(scope, RetimeScope::Property { animation_id: id, property_path }
+            if id == animation_id && *property_path == property.property_path);
+
+    for keyframe in &property.keyframes {
+        let selected = match scope {
+            RetimeScope::Keyframes(ids) => ids.contains(&keyframe.id),
+            _ => in_property,
+        };
+
+        if !selected {
+            continue;
+        }
+
+        times.push(start_ms + to_ms(keyframe.time));
+
+        if let KeyType::Range(range_data) = &keyframe.key_type {
+            times.push(start_ms + to_ms(range_data.end_time));
+        }
+    }
+
+    for child in &property.children {
+        collect_property_times(child, scope, animation_id, in_property, start_ms, times);
+    }
+}
+
+/// Sequence times (ms) covered by the scope, each with the earliest time it may be moved to:
+/// zero for whole animations, the animation start for keyframes
+fn scope_times(
+    sequence: &Sequence,
+    saved_state: &SavedState,
+    scope: &RetimeScope,
+) -> Vec<(i64, i64)> {
+    let mut times = Vec::new();
+
+    for animation in &sequence.polygon_motion_paths {
+        let start_ms = animation.start_time_ms as i64;
+
+        match scope {
+            RetimeScope::Animations(ids) => {
+                if includes_animation(ids, animation) {
+                    times.push((0, start_ms));
+                    times.push((0, animation_end_ms(animation, saved_state)));
+                }
+            }
+            _ => {
+                let mut keyframe_times = Vec::new();
+
+                animation.properties.iter().for_each(|p| {
+                    collect_property_times(
+                        p,
+                        scope,
+                        &animation.id,
+                        false,
+                        start_ms,
+                        &mut keyframe_times,
+                    )
+                });
+
+                for (path, keys) in table_tracks(saved_state, &animation.id) {
+                    keyframe_times.extend(
+                        keys.into_iter()
+                            .filter(|(id, _)| table_key_selected(scope, &animation.id, path, id))
+                            .map(|(_, time)| start_ms + time),
+                    );
+                }
+
+                times.extend(keyframe_times.into_iter().map(|time| (start_ms, time)));
+            }
+        }
+    }
+
+    times
+}
+
+/// Earliest and latest sequence time (ms) covered by the scope
+pub fn scope_span(
+    sequence: &Sequence,
+    saved_state: &SavedState,
+    scope: &RetimeScope,
+) -> Option<(i64, i64)> {
+    let times = scope_times(sequence, saved_state, scope);
+
+    Some((
+        times.iter().map(|(_, time)| *time).min()?,
+        times.iter().map(|(_, time)| *time).max()?,
+    ))
+}
+
+/// Resolve a pivot to a sequence time (ms). playhead_ms is in sequence time.
+pub fn pivot_time(
+    sequence: &Sequence,
+    saved_state: &SavedState,
+    scope: &RetimeScope,
+    pivot: Pivot,
+    playhead_ms: i64,
+) -> i64 {
+    let (start, end) = scope_span(sequence, saved_state, scope).unwrap_or((0, 0));
+
+    match pivot {
+        Pivot::Start => start,
+        Pivot::Playhead => playhead_ms,
+        Pivot::End => end,
+    }
+}
+
+fn retime_property<F>(
+    property: &mut AnimationProperty,
+    scope: &RetimeScope,
+    animation_id: &str,
+    in_property: bool,
+    start_ms: i64,
+    map: &F,
+) where
+    F: Fn(i64) -> i64,
+{
+    let in_property = in_property
+        || matches!(scope, RetimeScope::Property { animation_id: id, property_path }
+            if id == animation_id && *property_path == property.property_path);
+
+    for keyframe in &mut property.keyframes {
+        let selected = match scope {
+            RetimeScope::Keyframes(ids) => ids.contains(&keyframe.id),
+            RetimeScope::Property { .. } => in_property,
+            RetimeScope::Animations(_) => true,
+        };
+
+        if !selected {
+            continue;
+        }
+
+        let time = map(start_ms + to_ms(keyframe.time)) - start_ms;
+
+        match &mut keyframe.key_type {
+            KeyType::Range(range_data) => {
+                let end_time = map(start_ms + to_ms(range_data.end_time)) - start_ms;
+                // reversing swaps which end of the range comes first
+                keyframe.time = from_ms(time.min(end_time));
+                range_data.end_time = from_ms(time.max(end_time));
+            }
+            KeyType::Frame => {
+                keyframe.time = from_ms(time);
+            }
+        }
+    }
+
+    property.keyframes.sort_by_key(|k| k.time);
+
+    for child in &mut property.children {
+        retime_property(child, scope, animation_id, in_property, start_ms, map);
+    }
+}
+
+fn last_keyframe_end(property: &AnimationProperty) -> Duration {
+    property
+        .keyframes
+        .iter()
+        .map(|k| match &k.key_type {
+            KeyType::Range(range_data) => range_data.end_time.max(k.time),
+            KeyType::Frame => k.time,
+        })
+        .chain(property.children.iter().map(last_keyframe_end))
+        .max()
+        .unwrap_or(Duration::ZERO)
+}
+
+fn retime_tables<F>(
+    saved_state: &mut SavedState,
+    scope: &RetimeScope,
+    animation_id: &str,
+    start_ms: i64,
+    map: &F,
+) where
+    F: Fn(i64) -> i64,
+{
+    let selected = |path: Option<&str>, id: &str| table_key_selected(scope, animation_id, path, id);
+    let retime = |time_ms: &mut u64| *time_ms = (map(start_ms + *time_ms as i64) - start_ms) as u64;
+
+    for track in &mut saved_state.color_tracks {
+        if track.animation_id == animation_id {
+            let path = Some(track.target.path());
+
+            track
+                .keyframes
+                .iter_mut()
+                .filter(|k| selected(path, &k.id))
+                .for_each(|k| retime(&mut k.time_ms));
+            track.keyframes.sort_by_key(|k| k.time_ms);
+        }
+    }
+
+    for track in &mut saved_state.shape_tracks {
+        if track.animation_id == animation_id {
+            let path = Some(track.property.path());
+
+            track
+                .keyframes
+                .iter_mut()
+                .filter(|k| selected(path, &k.id))
+                .for_each(|k| retime(&mut k.time_ms));
+            track.keyframes.sort_by_key(|k| k.time_ms);
+        }
+    }
+
+    for animator in &mut saved_state.text_animators {
+        if animator.animation_id == animation_id {
+            animator
+                .selector
+                .iter_mut()
+                .filter(|k| selected(None, &k.id))
+                .for_each(|k| retime(&mut k.time_ms));
+            animator.selector.sort_by_key(|k| k.time_ms);
+        }
+    }
+}
+
+fn collect_property_orders(property: &AnimationProperty, orders: &mut Vec<Vec<(String, i64)>>) {
+    orders.push(
+        property
+            .keyframes
+            .iter()
+            .map(|k| (k.id.clone(), to_ms(k.time)))
+            .collect(),
+    );
+
+    for child in &property.children {
+        collect_property_orders(child, orders);
+    }
+}
+
+/// Ids and times of the keyframes on every track of the sequence, in time order
+fn track_orders(sequence: &Sequence, saved_state: &SavedState) -> Vec<Vec<(String, i64)>> {
+    let mut orders = Vec::new();
+
+    for animation in &sequence.polygon_motion_paths {
+        animation
+            .properties
+            .iter()
+            .for_each(|p| collect_property_orders(p, &mut orders));
+        orders.extend(
+            table_tracks(saved_state, &animation.id)
+                .into_iter()
+                .map(|(_, keys)| keys),
+        );
+    }
+
+    for keys in &mut orders {
+        keys.sort_by_key(|(_, time)| *time);
+    }
+
+    orders
+}
+
+/// A hold covers the span from its keyframe to the next one. Where retiming swapped
+/// the two, as reversing does, the hold moves to the keyframe that now starts the span.
+fn move_holds(
+    before: &[Vec<(String, i64)>],
+    after: &[Vec<(String, i64)>],
+    holds: &mut Vec<String>,
+) {
+    let times: HashMap<&str, i64> = after
+        .iter()
+        .flatten()
+        .map(|(id, time)| (id.as_str(), *time))
+        .collect();
+    let mut moved = Vec::new();
+
+    for keys in before {
+        for pair in keys.windows(2) {
+            let (from, to) = (&pair[0].0, &pair[1].0);
+
+            if holds.contains(from) && times.get(to.as_str()) < times.get(from.as_str()) {
+                moved.push((from.clone(), to.clone()));
+            }
+        }
+    }
+
+    holds.retain(|id| !moved.iter().any(|(from, _)| from == id));
+
+    for (_, to) in moved {
+        if !holds.contains(&to) {
+            holds.push(to);
+        }
+    }
+}
+
+/// Apply a mapping of sequence times (ms) to every keyframe in scope, including the color,
+/// shape and text selector keyframes kept beside the sequence in saved_state.
+/// Whole animations also get their start time and duration mapped, other scopes keep the start time.
+/// Anything mapped before its animation start, or before zero for whole animations, shifts
+/// everything in scope along by the same amount so the spacing is kept.
+pub fn retime_sequence<F>(
+    sequence: &mut Sequence,
+    saved_state: &mut SavedState,
+    scope: &RetimeScope,
+    map: F,
+) where
+    F: Fn(i64) -> i64,
+{
+    let shift = scope_times(sequence, saved_state, scope)
+        .into_iter()
+        .map(|(earliest, time)| earliest - map(time))
+        .max()
+        .unwrap_or(0)
+        .max(0);
+    let map = |time: i64| map(time) + shift;
+    let orders_before = track_orders(sequence, saved_state);
+
+    for animation in &mut sequence.polygon_motion_paths {
+        if let RetimeScope::Animations(ids) = scope {
+            if !includes_animation(ids, animation) {
+                continue;
+            }
+        }
+
+        let old_start = animation.start_time_ms as i64;
+        let mut new_start = old_start;
+
+        if let RetimeScope::Animations(_) = scope {
+            let mapped_start = map(old_start);
+            let mapped_end = map(animation_end_ms(animation, saved_state));
+
+            new_start = mapped_start.min(mapped_end);
+            animation.start_time_ms = new_start as i32;
+            animation.duration = from_ms((mapped_end - mapped_start).abs());
+        }
+
+        // keyframe times are relative to the animation start
+        let local_map = |time: i64| map(time) - new_start + old_start;
+        let animation_id = animation.id.clone();
+
+        animation
+            .properties
+            .iter_mut()
+            .for_each(|p| retime_property(p, scope, &animation_id, false, old_start, &local_map));
+        retime_tables(saved_state, scope, &animation_id, old_start, &local_map);
+
+        let last_end = animation
+            .properties
+            .iter()
+            .map(last_keyframe_end)
+            .max()
+            .unwrap_or(Duration::ZERO);
+
+        animation.duration = animation.duration.max(last_end);
+    }
+
+    let orders_after = track_orders(sequence, saved_state);
+    move_holds(
+        &orders_before,
+        &orders_after,
+        &mut saved_state.hold_keyframes,
+    );
+}
+
+/// Mirror keyframes in scope so they play backwards over the same span
+pub fn reverse_keyframes(
+    sequence: &mut Sequence,
+    saved_state: &mut SavedState,
+    scope: &RetimeScope,
+) {
+    let (start, end) = match scope_span(sequence, saved_state, scope) {
+        Some(span) => span,
+        None => return,
+    };
+
+    retime_sequence(sequence, saved_state, scope, |time| start + end - time);
+}
+
+/// Scale keyframe times in scope by factor around a pivot (sequence time in ms)
+pub fn scale_keyframes(
+    sequence: &mut Sequence,
+    saved_state: &mut SavedState,
+    scope: &RetimeScope,
+    factor: f32,
+    pivot_ms: i64,
+) {
+    if factor <= 0.0 {
+        return;
+    }
+
+    retime_sequence(sequence, saved_state, scope, |time| {
+        pivot_ms + ((time - pivot_ms) as f32 * factor).round() as i64
+    });
+}
+
+/// Shift keyframes in scope by offset_ms, stopping where the earliest of them reaches its limit
+pub fn offset_keyframes(
+    sequence: &mut Sequence,
+    saved_state: &mut SavedState,
+    scope: &RetimeScope,
+    offset_ms: i64,
+) {
+    retime_sequence(sequence, saved_state, scope, |time| time + offset_ms);
+}
@@ -1,14 +1,306 @@
 use serde::{Deserialize, Serialize};
 use stunts_engine::{
-    animations::Sequence, polygon::SavedPolygonConfig, timelines::SavedTimelineStateConfig,
+    animations::{AnimationProperty, EasingType, Sequence},
+    polygon::SavedPolygonConfig,
+    timelines::{SavedTimelineStateConfig, TrackType},
 };
 
+use super::clock::DEFAULT_FRAME_RATE;
+
 #[derive(Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Debug)]
 pub struct SavedState {
     pub id: String,
     // pub name: String,
     pub sequences: Vec<Sequence>,
     pub timeline_state: SavedTimelineStateConfig,
+    #[serde(default)]
+    pub animation_repeats: Vec<AnimationRepeat>,
+    #[serde(default)]
+    pub property_modifiers: Vec<PropertyModifier>,
+    #[serde(default)]
+    pub color_tracks: Vec<ColorTrack>,
+    #[serde(default)]
+    pub shape_tracks: Vec<ShapeTrack>,
+    #[serde(default)]
+    pub text_animators: Vec<TextAnimator>,
+    /// ids of keyframes that hold their value until the next keyframe instead of interpolating
+    #[serde(default)]
+    pub hold_keyframes: Vec<String>,
+    /// rows of the sequence timeline, top to bottom. Empty in older projects, see project_tracks
+    #[serde(default)]
+    pub tracks: Vec<TimelineTrack>,
+    #[serde(default)]
+    pub track_assignments: Vec<TrackAssignment>,
+    #[serde(default)]
+    pub transitions: Vec<SequenceTransition>,
+    /// in and out points of timeline clips that don't play their whole sequence
+    #[serde(default)]
+    pub clip_trims: Vec<ClipTrim>,
+    /// frames per second everything is previewed, snapped and exported at
+    #[serde(default = "default_frame_rate")]
+    pub frame_rate: u32,
+    /// markers and regions of the sequence timeline and of each sequence, kept here
+    /// because the engine's timeline and sequence types have nowhere to put them
+    #[serde(default)]
+    pub markers: Vec<Marker>,
+    /// sequences placed inside other sequences, the engine's sequences only hold plain objects
+    #[serde(default)]
+    pub nested_sequences: Vec<NestedSequence>,
+}
+
+fn default_frame_rate() -> u32 {
+    DEFAULT_FRAME_RATE
+}
+
+fn default_key_easing() -> EasingType {
+    EasingType::Linear
+}
+
+#[derive(Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Debug)]
+pub enum RepeatMode {
+    Once,
+    Loop(u32),
+    LoopForever,
+    PingPong,
+}
+
+/// repeat behavior for an AnimationData, keyed by its id
+#[derive(Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Debug)]
+pub struct AnimationRepeat {
+    pub animation_id: String,
+    pub mode: RepeatMode,
+}
+
+/// Procedural motion layered on top of keyframed values.
+/// Frequencies are stored in millihertz so the saved state stays Eq + Hash.
+#[derive(Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Debug)]
+pub enum ModifierKind {
+    Wiggle {
+        frequency_mhz: u32,
+        amplitude: i32,
+        seed: u32,
+    },
+    Noise {
+        frequency_mhz: u32,
+        amplitude: i32,
+        seed: u32,
+    },
+    Oscillator {
+        period_ms: u32,
+        phase_deg: i32,
+        amplitude: i32,
+    },
+}
+
+/// which components of a position a modifier moves, other properties only have one
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Debug)]
+pub enum ModifierAxis {
+    Both,
+    X,
+    Y,
+}
+
+/// modifier attached to an AnimationProperty, keyed by animation id and property_path
+#[derive(Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Debug)]
+pub struct PropertyModifier {
+    pub id: String,
+    pub animation_id: String,
+    pub property_path: String,
+    pub kind: ModifierKind,
+    pub axis: ModifierAxis,
+}
+
+/// which color of an object a ColorTrack animates
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Debug)]
+pub enum ColorTarget {
+    Fill,
+    Stroke,
+    Text,
+    TextBackground,
+}
+
+/// rgba 0-255 at a time relative to the animation start
+#[derive(Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Debug)]
+pub struct ColorKeyframe {
+    pub id: String,
+    pub time_ms: u64,
+    pub color: [i32; 4],
+    /// easing toward the next keyframe. Linear in older projects
+    #[serde(default = "default_key_easing")]
+    pub easing: EasingType,
+}
+
+/// Color keyframes for an AnimationData, keyed by its id.
+/// Kept beside the sequence since KeyframeValue has no color variant.
+#[derive(Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Debug)]
+pub struct ColorTrack {
+    pub animation_id: String,
+    pub target: ColorTarget,
+    pub keyframes: Vec<ColorKeyframe>,
+}
+
+/// which dimension of an object a ShapeTrack animates
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Debug)]
+pub enum ShapeProperty {
+    Width,
+    Height,
+    BorderRadius,
+    StrokeThickness,
+    /// tilt around the horizontal axis in degrees
+    TiltX,
+    /// tilt around the vertical axis in degrees
+    TiltY,
+    /// distance of the viewer from the canvas in pixels, smaller is stronger
+    Perspective,
+}
+
+/// value in pixels, or degrees for tilts, at a time relative to the animation start
+#[derive(Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Debug)]
+pub struct ShapeKeyframe {
+    pub id: String,
+    pub time_ms: u64,
+    pub value: i32,
+    /// easing toward the next keyframe. Linear in older projects
+    #[serde(default = "default_key_easing")]
+    pub easing: EasingType,
+}
+
+/// Size and tilt keyframes for an AnimationData, keyed by its id.
+/// Kept beside the sequence like ColorTrack, KeyframeValue has no size variants either.
+#[derive(Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Debug)]
+pub struct ShapeTrack {
+    pub animation_id: String,
+    pub property: ShapeProperty,
+    pub keyframes: Vec<ShapeKeyframe>,
+}
+
+/// what a text animator steps through. Only lines are placed, by the text's own newlines:
+/// the engine doesn't expose where it lays out glyphs. Characters and words from older
+/// projects are read as lines.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Debug)]
+pub enum TextUnit {
+    Character,
+    Word,
+    Line,
+}
+
+/// selector range in percent of the units, at a time relative to the animation start.
+/// Either end may go past 0 or 100 so a window can slide in and out.
+#[derive(Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Debug)]
+pub struct SelectorKeyframe {
+    pub id: String,
+    pub time_ms: u64,
+    pub start: i32,
+    pub end: i32,
+}
+
+/// Per-unit transform of a text item, keyed by its AnimationData id.
+/// Units inside the selector range get the full values, units outside it are left alone.
+#[derive(Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Debug)]
+pub struct TextAnimator {
+    pub id: String,
+    pub animation_id: String,
+    pub name: String,
+    pub unit: TextUnit,
+    pub offset: [i32; 2],
+    /// percent, 100 leaves the units as they are
+    pub opacity: i32,
+    /// percent, 100 leaves the units as they are
+    pub scale: i32,
+    /// degrees
+    pub rotation: i32,
+    /// width in units of the ramp at each edge of the range
+    pub softness: u32,
+    pub selector: Vec<SelectorKeyframe>,
+}
+
+/// A row of the sequence timeline. Video tracks above another video track overlay it.
+#[derive(Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Debug)]
+pub struct TimelineTrack {
+    pub id: String,
+    pub name: String,
+    pub kind: TrackType,
+    /// locked tracks keep their sequences in place
+    pub locked: bool,
+    /// muted tracks are left out of playback and export
+    pub muted: bool,
+}
+
+/// Which track a timeline sequence sits on. Sequences without one go on the first track of their type.
+#[derive(Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Debug)]
+pub struct TrackAssignment {
+    pub timeline_sequence_id: String,
+    pub track_id: String,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Debug)]
+pub enum TransitionKind {
+    Crossfade,
+    /// out through the background color of the sequences, then back in
+    Dip,
+    /// the incoming sequence slides in over the cut
+    Slide,
+    /// the outgoing sequence is pushed out as the incoming one slides in
+    Push,
+    Wipe,
+    Zoom,
+}
+
+/// A transition from one timeline sequence into the next one on its track
+#[derive(Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Debug)]
+pub struct SequenceTransition {
+    pub id: String,
+    pub from_timeline_sequence_id: String,
+    pub to_timeline_sequence_id: String,
+    pub kind: TransitionKind,
+    pub duration_ms: u32,
+    /// saved under the same names the earlier transition-only easings used
+    pub easing: EasingType,
+    /// what a dip goes out through, black in older projects
+    #[serde(default)]
+    pub dip_color: [u8; 3],
+}
+
+/// Part of its sequence a timeline clip plays
+#[derive(Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Debug)]
+pub struct ClipTrim {
+    pub timeline_sequence_id: String,
+    /// where in the sequence the clip starts playing
+    pub source_offset_ms: i32,
+    pub duration_ms: i32,
+}
+
+/// A labelled point, or a named region when it has an end, on a timeline
+#[derive(Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Debug)]
+pub struct Marker {
+    pub id: String,
+    /// the sequence whose object and keyframe timelines it's on, none for the sequence timeline
+    pub sequence_id: Option<String>,
+    pub time_ms: i32,
+    pub end_time_ms: Option<i32>,
+    pub label: String,
+    pub color: [u8; 3],
+    #[serde(default)]
+    pub note: String,
+}
+
+/// A sequence placed inside another one like an object, playing at its own local time.
+/// Its objects are copied into the parent for preview and export, see nested::nest_sequence.
+#[derive(Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Debug)]
+pub struct NestedSequence {
+    pub id: String,
+    pub parent_sequence_id: String,
+    pub child_sequence_id: String,
+    pub name: String,
+    /// when the nested sequence starts playing, in the parent's time
+    pub start_time_ms: i32,
+    /// time in the nested sequence shown when it starts
+    pub time_offset_ms: i32,
+    /// percent, 100 plays at normal speed
+    pub speed_pct: u32,
+    /// position, rotation, scale and opacity of the whole nested sequence, keyed in time
+    /// since it started. Position is where the middle of its canvas goes.
+    pub properties: Vec<AnimationProperty>,
 }
 
 #[derive(Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Debug)]
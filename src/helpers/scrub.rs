@@ -0,0 +1,146 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::time::Duration;
+
+use stunts_engine::animations::{AnimationData, AnimationProperty, Sequence};
+use stunts_engine::editor::Editor;
+
+use super::clock::sync_engine;
+use super::looks::bake_looks;
+use super::nested::{nest_looks, nest_sequence, nested_videos, NestedVideo};
+use super::playback::place_sequence;
+use super::saved_state::SavedState;
+use super::snapping::round_to_frame;
+use super::video::seek_sequence_videos;
+
+/// Move a time by whole frames, landing on the frame grid and staying inside the sequence
+pub fn step_frames(time: Duration, frames: i64, fps: u32, duration: Duration) -> Duration {
+    let fps = fps.max(1);
+    let frame = (round_to_frame(time, fps).as_secs_f64() * fps as f64).round() as i64 + frames;
+    let stepped = Duration::from_secs_f64(frame.max(0) as f64 / fps as f64);
+
+    stepped.min(duration)
+}
+
+/// Where an animation's keyframes start in its sequence
+fn animation_start(animation: &AnimationData) -> Duration {
+    Duration::from_millis(animation.start_time_ms.max(0) as u64)
+}
+
+/// A time in the sequence as a time in an animation, whose keyframes count from its start.
+/// The keyframe playhead is kept in sequence time, so it goes through here on its way to keyframes.
+pub fn animation_time(animation: &AnimationData, time: Duration) -> Duration {
+    time.saturating_sub(animation_start(animation))
+}
+
+/// A time in an animation as a time in its sequence
+pub fn sequence_time(animation: &AnimationData, time: Duration) -> Duration {
+    animation_start(animation) + time
+}
+
+fn property_keyframe_times(
+    property: &AnimationProperty,
+    offset: Duration,
+    times: &mut Vec<Duration>,
+) {
+    times.extend(property.keyframes.iter().map(|k| offset + k.time));
+
+    for child in &property.children {
+        property_keyframe_times(child, offset, times);
+    }
+}
+
+/// Every keyframe of a sequence in sequence time, in order
+pub fn sequence_keyframe_times(sequence: &Sequence) -> Vec<Duration> {
+    let mut times = Vec::new();
+
+    for animation in &sequence.polygon_motion_paths {
+        let offset = animation_start(animation);
+
+        for property in &animation.properties {
+            property_keyframe_times(property, offset, &mut times);
+        }
+    }
+
+    times.sort();
+    times.dedup();
+
+    times
+}
+
+/// Closest keyframe before a time, on any object
+pub fn previous_keyframe(sequence: &Sequence, time: Duration) -> Option<Duration> {
+    sequence_keyframe_times(sequence)
+        .into_iter()
+        .rev()
+        .find(|t| *t < time)
+}
+
+/// Closest keyframe after a time, on any object
+pub fn next_keyframe(sequence: &Sequence, time: Duration) -> Option<Duration> {
+    sequence_keyframe_times(sequence)
+        .into_iter()
+        .find(|t| *t > time)
+}
+
+/// Have the engine pose a sequence as it is at a time on its next step, without playing on,
+/// with its videos seeked to the time. Call `end_scrub` once the frame has been stepped.
+pub fn begin_scrub(
+    editor: &mut Editor,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    sequence: Sequence,
+    time: Duration,
+    nested_videos: &[NestedVideo],
+) {
+    seek_sequence_videos(editor, device, queue, &sequence, time, 0, nested_videos);
+
+    editor.current_sequence_data = Some(sequence);
+    sync_engine(editor, time);
+    editor.is_playing = true;
+}
+
+pub fn end_scrub(editor: &mut Editor) {
+    editor.is_playing = false;
+    editor.start_playing_time = None;
+}
+
+/// The sequence the paused preview is posed with, prepared once for each change to the
+/// project rather than on every move of the playhead
+pub struct ScrubCache {
+    pub key: u64,
+    /// as restored on load, what the stand-ins are staged against
+    pub source: Sequence,
+    /// before its looks were baked, what onion ghosts are taken from
+    pub placed: Sequence,
+    pub prepared: Sequence,
+    pub nested: Vec<NestedVideo>,
+    /// the staged looks' change count once this was staged, none before
+    pub staged: Option<u64>,
+}
+
+/// Identifies everything a scrub sequence is prepared from, to know when to prepare it again
+pub fn scrub_key(saved_state: &SavedState, sequence: &Sequence) -> u64 {
+    let mut hasher = DefaultHasher::new();
+
+    saved_state.hash(&mut hasher);
+    sequence.hash(&mut hasher);
+
+    hasher.finish()
+}
+
+impl ScrubCache {
+    pub fn new(saved_state: &SavedState, sequence: &Sequence, key: u64) -> Self {
+        let placed = place_sequence(saved_state, sequence);
+        let prepared = bake_looks(&nest_looks(saved_state, &sequence.id), &placed);
+
+        Self {
+            key,
+            source: nest_sequence(saved_state, sequence, &|s: &Sequence| s.clone()),
+            placed,
+            prepared,
+            nested: nested_videos(saved_state, &sequence.id),
+            staged: None,
+        }
+    }
+}
@@ -0,0 +1,232 @@
+use std::time::Duration;
+
+use stunts_engine::animations::{AnimationData, ObjectType, Sequence};
+
+use super::modifiers::ease;
+use super::saved_state::{ShapeProperty, ShapeTrack};
+
+/// viewer distance used until a perspective track is keyed
+pub const DEFAULT_PERSPECTIVE_PX: i32 = 1000;
+
+impl ShapeProperty {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ShapeProperty::Width => "Width",
+            ShapeProperty::Height => "Height",
+            ShapeProperty::BorderRadius => "Border Radius",
+            ShapeProperty::StrokeThickness => "Stroke Thickness",
+            ShapeProperty::TiltX => "Tilt X",
+            ShapeProperty::TiltY => "Tilt Y",
+            ShapeProperty::Perspective => "Perspective",
+        }
+    }
+
+    /// row id in the keyframe timeline, kept apart from engine property paths
+    pub fn path(&self) -> &'static str {
+        match self {
+            ShapeProperty::Width => "shape/width",
+            ShapeProperty::Height => "shape/height",
+            ShapeProperty::BorderRadius => "shape/border_radius",
+            ShapeProperty::StrokeThickness => "shape/stroke_thickness",
+            ShapeProperty::TiltX => "shape/tilt_x",
+            ShapeProperty::TiltY => "shape/tilt_y",
+            ShapeProperty::Perspective => "shape/perspective",
+        }
+    }
+
+    pub fn from_path(path: &str) -> Option<ShapeProperty> {
+        match path {
+            "shape/width" => Some(ShapeProperty::Width),
+            "shape/height" => Some(ShapeProperty::Height),
+            "shape/border_radius" => Some(ShapeProperty::BorderRadius),
+            "shape/stroke_thickness" => Some(ShapeProperty::StrokeThickness),
+            "shape/tilt_x" => Some(ShapeProperty::TiltX),
+            "shape/tilt_y" => Some(ShapeProperty::TiltY),
+            "shape/perspective" => Some(ShapeProperty::Perspective),
+            _ => None,
+        }
+    }
+
+    pub fn unit(&self) -> &'static str {
+        match self {
+            ShapeProperty::TiltX | ShapeProperty::TiltY => "deg",
+            _ => "px",
+        }
+    }
+
+    /// dimensions an object of this type can animate. Only polygons tilt, see bake_looks
+    pub fn for_object(object_type: &ObjectType) -> Vec<ShapeProperty> {
+        let mut properties = vec![ShapeProperty::Width, ShapeProperty::Height];
+
+        if *object_type == ObjectType::Polygon {
+            properties.push(ShapeProperty::BorderRadius);
+            properties.push(ShapeProperty::StrokeThickness);
+            properties.push(ShapeProperty::TiltX);
+            properties.push(ShapeProperty::TiltY);
+            properties.push(ShapeProperty::Perspective);
+        }
+
+        properties
+    }
+}
+
+/// Value of a track at a time relative to the animation start, held before the first and after the last keyframe.
+/// Eased by the earlier keyframe, held keyframes keep their value until the next one.
+pub fn sample_shape(track: &ShapeTrack, time: Duration, hold_keyframes: &[String]) -> Option<i32> {
+    let time_ms = time.as_millis() as u64;
+    // keyframes are kept sorted by time
+    let next = track.keyframes.partition_point(|k| k.time_ms <= time_ms);
+
+    let start = match next.checked_sub(1) {
+        Some(index) => &track.keyframes[index],
+        None => return track.keyframes.first().map(|k| k.value),
+    };
+    let end = match track.keyframes.get(next) {
+        Some(end) if !hold_keyframes.contains(&start.id) => end,
+        _ => return Some(start.value),
+    };
+
+    let progress = (time_ms - start.time_ms) as f64 / (end.time_ms - start.time_ms) as f64;
+    let value =
+        start.value as f64 + (end.value - start.value) as f64 * ease(&start.easing, progress);
+
+    Some(value.round() as i32)
+}
+
+/// Saved value of an object in the sequence, used as the value of new shape keyframes
+pub fn base_shape_value(
+    sequence: &Sequence,
+    object_id: &str,
+    property: ShapeProperty,
+) -> Option<i32> {
+    match property {
+        ShapeProperty::TiltX | ShapeProperty::TiltY => return Some(0),
+        ShapeProperty::Perspective => return Some(DEFAULT_PERSPECTIVE_PX),
+        _ => {}
+    }
+
+    let dimensions =
+        if let Some(polygon) = sequence.active_polygons.iter().find(|p| p.id == object_id) {
+            match property {
+                ShapeProperty::BorderRadius => return Some(polygon.border_radius),
+                ShapeProperty::StrokeThickness => return Some(polygon.stroke.thickness),
+                _ => (polygon.dimensions.0, polygon.dimensions.1),
+            }
+        } else if let Some(text) = sequence
+            .active_text_items
+            .iter()
+            .find(|t| t.id == object_id)
+        {
+            (text.dimensions.0, text.dimensions.1)
+        } else if let Some(image) = sequence
+            .active_image_items
+            .iter()
+            .find(|i| i.id == object_id)
+        {
+            (image.dimensions.0 as i32, image.dimensions.1 as i32)
+        } else if let Some(video) = sequence
+            .active_video_items
+            .iter()
+            .find(|v| v.id == object_id)
+        {
+            (video.dimensions.0 as i32, video.dimensions.1 as i32)
+        } else {
+            return None;
+        };
+
+    match property {
+        ShapeProperty::Width => Some(dimensions.0),
+        ShapeProperty::Height => Some(dimensions.1),
+        _ => None,
+    }
+}
+
+/// How an object is turned out of the canvas around its center, and how far it's seen from
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Tilt {
+    /// degrees around the horizontal axis
+    pub x: f64,
+    /// degrees around the vertical axis
+    pub y: f64,
+    /// viewer distance in pixels
+    pub distance: f64,
+}
+
+impl Tilt {
+    pub const FLAT: Tilt = Tilt {
+        x: 0.0,
+        y: 0.0,
+        distance: DEFAULT_PERSPECTIVE_PX as f64,
+    };
+
+    /// Where a point of the flat object lands once tilted and seen in perspective,
+    /// both relative to the object's center
+    pub fn project(&self, point: [f64; 2]) -> [f64; 2] {
+        let (sin_x, cos_x) = self.x.to_radians().sin_cos();
+        let (sin_y, cos_y) = self.y.to_radians().sin_cos();
+
+        // around the vertical axis, then the horizontal one
+        let x = point[0] * cos_y;
+        let z = -point[0] * sin_y;
+        let y = point[1] * cos_x - z * sin_x;
+        let z = point[1] * sin_x + z * cos_x;
+
+        // points are kept in front of the viewer
+        let distance = self.distance.max(1.0);
+        let factor = distance / (distance + z).max(distance * 0.05);
+
+        [x * factor, y * factor]
+    }
+}
+
+/// Tilt of an animation's object at a time relative to its start, None when no tilt is keyed
+pub fn sample_tilt(
+    animation: &AnimationData,
+    tracks: &[ShapeTrack],
+    time: Duration,
+    hold_keyframes: &[String],
+) -> Option<Tilt> {
+    let sample = |property: ShapeProperty| {
+        tracks
+            .iter()
+            .find(|t| t.animation_id == animation.id && t.property == property)
+            .and_then(|t| sample_shape(t, time, hold_keyframes))
+    };
+
+    let (x, y) = (sample(ShapeProperty::TiltX), sample(ShapeProperty::TiltY));
+
+    if x.is_none() && y.is_none() {
+        return None;
+    }
+
+    Some(Tilt {
+        x: x.unwrap_or(0) as f64,
+        y: y.unwrap_or(0) as f64,
+        distance: sample(ShapeProperty::Perspective).unwrap_or(DEFAULT_PERSPECTIVE_PX) as f64,
+    })
+}
+
+/// Keyed dimensions of an animation's object at a time relative to its start.
+/// Tilts don't change them, see sample_tilt.
+pub fn resolve_shape_values(
+    animation: &AnimationData,
+    tracks: &[ShapeTrack],
+    time: Duration,
+    hold_keyframes: &[String],
+) -> Vec<(ShapeProperty, i32)> {
+    [
+        ShapeProperty::Width,
+        ShapeProperty::Height,
+        ShapeProperty::BorderRadius,
+        ShapeProperty::StrokeThickness,
+    ]
+    .into_iter()
+    .filter_map(|property| {
+        tracks
+            .iter()
+            .find(|t| t.animation_id == animation.id && t.property == property)
+            .and_then(|t| sample_shape(t, time, hold_keyframes))
+            .map(|value| (property, value))
+    })
+    .collect()
+}
@@ -0,0 +1,174 @@
+use stunts_engine::animations::{
+    AnimationData, AnimationProperty, KeyType, KeyframeValue, UIKeyframe,
+};
+
+use super::keyframes::{value_components, value_from_components};
+
+/// Distance between a keyframe's value and the straight line between two others at the same time.
+/// Measuring against time keeps the timing envelope instead of only the spatial path.
+fn deviation(start: &UIKeyframe, end: &UIKeyframe, keyframe: &UIKeyframe) -> f32 {
+    let (a, b, v) = match (
+        value_components(&start.value),
+        value_components(&end.value),
+        value_components(&keyframe.value),
+    ) {
+        (Some(a), Some(b), Some(v)) => (a, b, v),
+        _ => return f32::MAX,
+    };
+
+    let span = end.time.saturating_sub(start.time).as_secs_f64();
+    let progress = if span > 0.0 {
+        keyframe.time.saturating_sub(start.time).as_secs_f64() / span
+    } else {
+        0.0
+    };
+
+    a.iter()
+        .zip(&b)
+        .zip(&v)
+        .map(|((a, b), v)| {
+            let expected = a + (b - a) * progress;
+            (v - expected) * (v - expected)
+        })
+        .sum::<f64>()
+        .sqrt() as f32
+}
+
+fn rdp(keyframes: &[UIKeyframe], first: usize, last: usize, tolerance: f32, keep: &mut [bool]) {
+    if last <= first + 1 {
+        return;
+    }
+
+    let mut max_distance = 0.0;
+    let mut max_index = first;
+
+    for i in (first + 1)..last {
+        let distance = deviation(&keyframes[first], &keyframes[last], &keyframes[i]);
+
+        if distance > max_distance {
+            max_distance = distance;
+            max_index = i;
+        }
+    }
+
+    if max_distance > tolerance {
+        keep[max_index] = true;
+        rdp(keyframes, first, max_index, tolerance, keep);
+        rdp(keyframes, max_index, last, tolerance, keep);
+    }
+}
+
+/// Ramer–Douglas–Peucker over keyframe values. The first, last and any range keyframes are always kept,
+/// as are held keyframes and the keyframe each hold steps to.
+pub fn simplify_keyframes(
+    keyframes: &[UIKeyframe],
+    tolerance: f32,
+    hold_keyframes: &[String],
+) -> Vec<UIKeyframe> {
+    let mut sorted = keyframes.to_vec();
+    sorted.sort_by_key(|k| k.time);
+
+    if sorted.len() < 3 {
+        return sorted;
+    }
+
+    let mut keep: Vec<bool> = sorted
+        .iter()
+        .map(|k| !matches!(k.key_type, KeyType::Frame))
+        .collect();
+    let last = sorted.len() - 1;
+    keep[0] = true;
+    keep[last] = true;
+    for i in 0..last {
+        if hold_keyframes.contains(&sorted[i].id) {
+            keep[i] = true;
+            keep[i + 1] = true;
+        }
+    }
+
+    // simplify between each pair of anchors separately
+    let anchors: Vec<usize> = (0..sorted.len()).filter(|i| keep[*i]).collect();
+    for pair in anchors.windows(2) {
+        rdp(&sorted, pair[0], pair[1], tolerance, &mut keep);
+    }
+
+    sorted
+        .into_iter()
+        .zip(keep)
+        .filter(|(_, keep)| *keep)
+        .map(|(k, _)| k)
+        .collect()
+}
+
+/// Centered moving average over position keyframes, radius keyframes either side.
+/// Times and the first and last positions stay where they are.
+pub fn smooth_keyframes(keyframes: &[UIKeyframe], radius: usize) -> Vec<UIKeyframe> {
+    let mut sorted = keyframes.to_vec();
+    sorted.sort_by_key(|k| k.time);
+
+    if sorted.len() < 3 || radius == 0 {
+        return sorted;
+    }
+
+    let positions: Vec<Option<Vec<f64>>> = sorted
+        .iter()
+        .map(|k| match k.value {
+            KeyframeValue::Position(_) => value_components(&k.value),
+            _ => None,
+        })
+        .collect();
+
+    let last = sorted.len() - 1;
+
+    for i in 1..last {
+        if positions[i].is_none() {
+            continue;
+        }
+
+        let from = i.saturating_sub(radius);
+        let to = (i + radius).min(last);
+        let window: Vec<&Vec<f64>> = positions[from..=to].iter().flatten().collect();
+
+        let averaged: Vec<f64> = (0..2)
+            .map(|c| window.iter().map(|p| p[c]).sum::<f64>() / window.len() as f64)
+            .collect();
+
+        sorted[i].value = value_from_components(&sorted[i].value, &averaged);
+    }
+
+    sorted
+}
+
+fn simplify_property(property: &mut AnimationProperty, tolerance: f32, hold_keyframes: &[String]) {
+    property.keyframes = simplify_keyframes(&property.keyframes, tolerance, hold_keyframes);
+    property
+        .children
+        .iter_mut()
+        .for_each(|c| simplify_property(c, tolerance, hold_keyframes));
+}
+
+fn smooth_property(property: &mut AnimationProperty, radius: usize) {
+    property.keyframes = smooth_keyframes(&property.keyframes, radius);
+    property
+        .children
+        .iter_mut()
+        .for_each(|c| smooth_property(c, radius));
+}
+
+pub fn simplify_animation(
+    animation: &mut AnimationData,
+    tolerance: f32,
+    hold_keyframes: &[String],
+) {
+    animation
+        .properties
+        .iter_mut()
+        .for_each(|p| simplify_property(p, tolerance, hold_keyframes));
+}
+
+pub fn smooth_animation(animation: &mut AnimationData, radius: usize) {
+    animation
+        .properties
+        .iter_mut()
+        .for_each(|p| smooth_property(p, radius));
+}
@@ -0,0 +1,240 @@
+use std::time::Duration;
+
+use floem::reactive::{RwSignal, SignalGet};
+use stunts_engine::animations::{AnimationData, AnimationProperty, KeyType, Sequence};
+
+/// distance in pixels within which a drag will snap to a target
+pub const SNAP_THRESHOLD_PX: f64 = 8.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SnapTarget {
+    Frame,
+    Playhead,
+    Keyframe,
+    Marker,
+    SequenceBoundary,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct SnapSettings {
+    pub enabled: RwSignal<bool>,
+    pub frames: RwSignal<bool>,
+    pub playhead: RwSignal<bool>,
+    pub keyframes: RwSignal<bool>,
+    pub markers: RwSignal<bool>,
+    pub boundaries: RwSignal<bool>,
+}
+
+impl SnapSettings {
+    pub fn new() -> Self {
+        Self {
+            enabled: RwSignal::new(true),
+            frames: RwSignal::new(true),
+            playhead: RwSignal::new(true),
+            keyframes: RwSignal::new(true),
+            markers: RwSignal::new(true),
+            boundaries: RwSignal::new(true),
+        }
+    }
+
+    pub fn allows(&self, target: SnapTarget) -> bool {
+        match target {
+            SnapTarget::Frame => self.frames.get(),
+            SnapTarget::Playhead => self.playhead.get(),
+            SnapTarget::Keyframe => self.keyframes.get(),
+            SnapTarget::Marker => self.markers.get(),
+            SnapTarget::SequenceBoundary => self.boundaries.get(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SnapResult {
+    pub time: Duration,
+    pub target: Option<SnapTarget>,
+}
+
+fn time_distance(a: Duration, b: Duration) -> Duration {
+    if a > b {
+        a - b
+    } else {
+        b - a
+    }
+}
+
+pub fn round_to_frame(time: Duration, fps: u32) -> Duration {
+    let frame = (time.as_secs_f64() * fps as f64).round();
+    Duration::from_secs_f64(frame / fps as f64)
+}
+
+/// Snap a time to the nearest enabled candidate within the threshold,
+/// otherwise to the nearest whole frame if frame snapping is enabled
+pub fn snap_time(
+    time: Duration,
+    candidates: &[(Duration, SnapTarget)],
+    settings: &SnapSettings,
+    fps: u32,
+    threshold: Duration,
+) -> SnapResult {
+    if !settings.enabled.get() {
+        return SnapResult { time, target: None };
+    }
+
+    let nearest = candidates
+        .iter()
+        .filter(|(_, target)| settings.allows(*target))
+        .map(|(candidate, target)| (time_distance(*candidate, time), *candidate, *target))
+        .filter(|(distance, _, _)| *distance <= threshold)
+        .min_by_key(|(distance, _, _)| *distance);
+
+    if let Some((_, candidate, target)) = nearest {
+        return SnapResult {
+            time: candidate,
+            target: Some(target),
+        };
+    }
+
+    if settings.frames.get() && fps > 0 {
+        return SnapResult {
+            time: round_to_frame(time, fps),
+            target: Some(SnapTarget::Frame),
+        };
+    }
+
+    SnapResult { time, target: None }
+}
+
+fn collect_property_keyframes(
+    property: &AnimationProperty,
+    exclude_id: Option<&str>,
+    candidates: &mut Vec<(Duration, SnapTarget)>,
+) {
+    for keyframe in &property.keyframes {
+        if Some(keyframe.id.as_str()) == exclude_id {
+            continue;
+        }
+
+        candidates.push((keyframe.time, SnapTarget::Keyframe));
+
+        if let KeyType::Range(range_data) = &keyframe.key_type {
+            candidates.push((range_data.end_time, SnapTarget::Keyframe));
+        }
+    }
+
+    for child in &property.children {
+        collect_property_keyframes(child, exclude_id, candidates);
+    }
+}
+
+/// Snap candidates for the keyframe timeline, skipping the keyframe being dragged.
+/// Markers and the playhead are in sequence time and are moved into the animation's time here.
+pub fn keyframe_snap_candidates(
+    animation: &AnimationData,
+    exclude_id: Option<&str>,
+    playhead: Duration,
+    markers: &[Duration],
+) -> Vec<(Duration, SnapTarget)> {
+    let mut candidates = Vec::new();
+
+    animation
+        .properties
+        .iter()
+        .for_each(|p| collect_property_keyframes(p, exclude_id, &mut candidates));
+
+    let start = Duration::from_millis(animation.start_time_ms.max(0) as u64);
+    candidates.extend(
+        markers
+            .iter()
+            .filter(|time| **time >= start)
+            .map(|time| (*time - start, SnapTarget::Marker)),
+    );
+
+    if playhead >= start {
+        candidates.push((playhead - start, SnapTarget::Playhead));
+    }
+    candidates.push((Duration::ZERO, SnapTarget::SequenceBoundary));
+    candidates.push((animation.duration, SnapTarget::SequenceBoundary));
+
+    candidates
+}
+
+/// Snap candidates for the object timeline bars, skipping the animation being dragged
+pub fn animation_snap_candidates(
+    sequence: &Sequence,
+    exclude_id: &str,
+    playhead: Duration,
+    markers: &[Duration],
+) -> Vec<(Duration, SnapTarget)> {
+    let mut candidates = Vec::new();
+
+    candidates.extend(markers.iter().map(|time| (*time, SnapTarget::Marker)));
+
+    sequence
+        .polygon_motion_paths
+        .iter()
+        .filter(|pm| pm.id != exclude_id)
+        .for_each(|pm| {
+            let start = Duration::from_millis(pm.start_time_ms.max(0) as u64);
+            candidates.push((start, SnapTarget::Keyframe));
+            candidates.push((start + pm.duration, SnapTarget::Keyframe));
+        });
+
+    candidates.push((playhead, SnapTarget::Playhead));
+    candidates.push((Duration::ZERO, SnapTarget::SequenceBoundary));
+    candidates.push((
+        Duration::from_millis(sequence.duration_ms.max(0) as u64),
+        SnapTarget::SequenceBoundary,
+    ));
+
+    candidates
+}
+
+/// Snap candidates for clips on the sequence timeline, markers include both ends of regions
+pub fn clip_snap_candidates(
+    playhead: Duration,
+    markers: &[Duration],
+) -> Vec<(Duration, SnapTarget)> {
+    let mut candidates: Vec<(Duration, SnapTarget)> = markers
+        .iter()
+        .map(|time| (*time, SnapTarget::Marker))
+        .collect();
+
+    candidates.push((playhead, SnapTarget::Playhead));
+    candidates.push((Duration::ZERO, SnapTarget::SequenceBoundary));
+
+    candidates
+}
+
+/// Snap an animation bar by either its start or end edge, whichever lands closer to a target.
+/// Returns the new start time and the time the snap happened at, for the indicator.
+pub fn snap_animation_start(
+    start: Duration,
+    length: Duration,
+    candidates: &[(Duration, SnapTarget)],
+    settings: &SnapSettings,
+    fps: u32,
+    threshold: Duration,
+) -> (Duration, Option<Duration>) {
+    let start_snap = snap_time(start, candidates, settings, fps, threshold);
+    let end_snap = snap_time(start + length, candidates, settings, fps, threshold);
+
+    let start_hit = start_snap.target.is_some() && start_snap.target != Some(SnapTarget::Frame);
+    let end_hit = end_snap.target.is_some() && end_snap.target != Some(SnapTarget::Frame);
+
+    if end_hit
+        && (!start_hit
+            || time_distance(end_snap.time, start + length) < time_distance(start_snap.time, start))
+    {
+        return (end_snap.time.saturating_sub(length), Some(end_snap.time));
+    }
+
+    if start_hit {
+        return (start_snap.time, Some(start_snap.time));
+    }
+
+    if start_snap.target == Some(SnapTarget::Frame) {
+        return (start_snap.time, None);
+    }
+
+    (start, None)
+}
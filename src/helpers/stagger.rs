@@ -0,0 +1,182 @@
+use rand::seq::SliceRandom;
+use stunts_engine::animations::{AnimationData, EasingType, Sequence};
+
+use super::keyframes::get_object_position;
+use super::modifiers::ease;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StaggerOrder {
+    Layer,
+    Spatial,
+    Random,
+}
+
+#[derive(Clone, Debug)]
+pub struct StaggerConfig {
+    pub interval_ms: i32,
+    pub easing: EasingType,
+    pub order: StaggerOrder,
+    /// objects in the middle start first and the delay grows outwards
+    pub mirror: bool,
+}
+
+/// Offsets in ms for `count` items already in stagger order.
+/// With linear easing this is a fixed interval, otherwise the same total spread is eased.
+/// Mirrored, the middle item starts at 0, or both middle items for an even count.
+pub fn stagger_offsets(count: usize, config: &StaggerConfig) -> Vec<i32> {
+    if count < 2 {
+        return vec![0; count];
+    }
+
+    let center = (count - 1) as f64 / 2.0;
+    // distance of the middle items from the center
+    let nearest = center.fract();
+    let steps = if config.mirror {
+        center - nearest
+    } else {
+        (count - 1) as f64
+    };
+
+    (0..count)
+        .map(|i| {
+            let step = if config.mirror {
+                (i as f64 - center).abs() - nearest
+            } else {
+                i as f64
+            };
+            let t = if steps > 0.0 { step / steps } else { 0.0 };
+
+            (ease(&config.easing, t) * steps * config.interval_ms as f64).round() as i32
+        })
+        .collect()
+}
+
+pub fn get_object_layer(sequence: &Sequence, object_id: &str) -> i32 {
+    if let Some(polygon) = sequence.active_polygons.iter().find(|p| p.id == object_id) {
+        return polygon.layer;
+    }
+    if let Some(text) = sequence
+        .active_text_items
+        .iter()
+        .find(|t| t.id == object_id)
+    {
+        return text.layer;
+    }
+    if let Some(image) = sequence
+        .active_image_items
+        .iter()
+        .find(|i| i.id == object_id)
+    {
+        return image.layer;
+    }
+    if let Some(video) = sequence
+        .active_video_items
+        .iter()
+        .find(|v| v.id == object_id)
+    {
+        return video.layer;
+    }
+
+    0
+}
+
+/// Offset start_time_ms of the given animations (all when empty) in the chosen order.
+/// The earliest current start time is kept as the start of the cascade.
+pub fn stagger_animations(
+    sequence: &Sequence,
+    animations: &mut Vec<AnimationData>,
+    animation_ids: &[String],
+    config: &StaggerConfig,
+) {
+    let mut targets: Vec<usize> = animations
+        .iter()
+        .enumerate()
+        .filter(|(_, a)| animation_ids.is_empty() || animation_ids.contains(&a.id))
+        .map(|(i, _)| i)
+        .collect();
+
+    if targets.is_empty() {
+        return;
+    }
+
+    match config.order {
+        StaggerOrder::Layer => {
+            targets.sort_by_key(|i| get_object_layer(sequence, &animations[*i].polygon_id));
+        }
+        StaggerOrder::Spatial => {
+            // reading order, top to bottom then left to right
+            targets.sort_by_key(|i| {
+                let position =
+                    get_object_position(sequence, &animations[*i].polygon_id).unwrap_or([0, 0]);
+                (position[1], position[0])
+            });
+        }
+        StaggerOrder::Random => {
+            targets.shuffle(&mut rand::thread_rng());
+        }
+    }
+
+    let base_start = targets
+        .iter()
+        .map(|i| animations[*i].start_time_ms)
+        .min()
+        .unwrap_or(0);
+
+    let offsets = stagger_offsets(targets.len(), config);
+
+    for (target, offset) in targets.iter().zip(offsets) {
+        animations[*target].start_time_ms = base_start + offset;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(easing: EasingType, mirror: bool) -> StaggerConfig {
+        StaggerConfig {
+            interval_ms: 100,
+            easing,
+            order: StaggerOrder::Layer,
+            mirror,
+        }
+    }
+
+    #[test]
+    fn linear_offsets_step_by_the_interval() {
+        let offsets = stagger_offsets(4, &config(EasingType::Linear, false));
+
+        assert_eq!(offsets, vec![0, 100, 200, 300]);
+    }
+
+    #[test]
+    fn mirror_starts_the_middle_item_first() {
+        let offsets = stagger_offsets(5, &config(EasingType::Linear, true));
+
+        assert_eq!(offsets, vec![200, 100, 0, 100, 200]);
+    }
+
+    #[test]
+    fn mirror_with_an_even_count_starts_both_middle_items_first() {
+        let offsets = stagger_offsets(4, &config(EasingType::Linear, true));
+
+        assert_eq!(offsets, vec![100, 0, 0, 100]);
+    }
+
+    #[test]
+    fn easing_keeps_the_total_spread() {
+        let offsets = stagger_offsets(5, &config(EasingType::EaseIn, false));
+
+        assert_eq!(offsets.first(), Some(&0));
+        assert_eq!(offsets.last(), Some(&400));
+        assert!(offsets[2] < 200);
+    }
+
+    #[test]
+    fn a_single_item_has_no_offset() {
+        assert_eq!(
+            stagger_offsets(1, &config(EasingType::Linear, true)),
+            vec![0]
+        );
+    }
+}
@@ -0,0 +1,206 @@
+use std::ops::Range;
+use std::time::Duration;
+
+use uuid::Uuid;
+
+use super::saved_state::{SelectorKeyframe, TextAnimator, TextUnit};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextPreset {
+    Reveal,
+    CascadeIn,
+    Wave,
+}
+
+impl TextPreset {
+    pub fn label(&self) -> &'static str {
+        match self {
+            TextPreset::Reveal => "Line Reveal",
+            TextPreset::CascadeIn => "Cascade In",
+            TextPreset::Wave => "Wave",
+        }
+    }
+
+    pub fn all() -> Vec<TextPreset> {
+        vec![TextPreset::Reveal, TextPreset::CascadeIn, TextPreset::Wave]
+    }
+
+    /// Animator running the preset across `duration`
+    pub fn build(&self, animation_id: String, duration: Duration) -> TextAnimator {
+        let end_ms = duration.as_millis() as u64;
+        let range = |time_ms: u64, start: i32, end: i32| SelectorKeyframe {
+            id: Uuid::new_v4().to_string(),
+            time_ms,
+            start,
+            end,
+        };
+
+        let base = TextAnimator {
+            id: Uuid::new_v4().to_string(),
+            animation_id,
+            name: self.label().to_string(),
+            unit: TextUnit::Line,
+            offset: [0, 0],
+            opacity: 100,
+            scale: 100,
+            rotation: 0,
+            softness: 0,
+            selector: Vec::new(),
+        };
+
+        match self {
+            // selected lines are hidden, the range shrinks from the front
+            TextPreset::Reveal => TextAnimator {
+                opacity: 0,
+                selector: vec![range(0, 0, 100), range(end_ms, 100, 100)],
+                ..base
+            },
+            TextPreset::CascadeIn => TextAnimator {
+                offset: [0, 40],
+                opacity: 0,
+                softness: 4,
+                selector: vec![range(0, 0, 100), range(end_ms, 100, 100)],
+                ..base
+            },
+            // a soft window lifting lines as it slides through
+            TextPreset::Wave => TextAnimator {
+                offset: [0, -20],
+                softness: 3,
+                selector: vec![range(0, -30, 0), range(end_ms, 100, 130)],
+                ..base
+            },
+        }
+    }
+}
+
+/// Byte ranges of the lines of a text. Lines keep their newline so they join back
+/// into the original text.
+pub fn split_lines(text: &str) -> Vec<Range<usize>> {
+    let mut lines = Vec::new();
+    let mut start = 0;
+
+    for (index, c) in text.char_indices() {
+        if c == '\n' {
+            lines.push(start..index + 1);
+            start = index + 1;
+        }
+    }
+
+    if start < text.len() {
+        lines.push(start..text.len());
+    }
+
+    lines
+}
+
+/// Selector range at a time, held before the first and after the last keyframe.
+/// Without keyframes the whole text is selected.
+pub fn sample_selector(selector: &[SelectorKeyframe], time: Duration) -> (f32, f32) {
+    let mut keyframes = selector.to_vec();
+    keyframes.sort_by_key(|k| k.time_ms);
+
+    let time_ms = time.as_millis() as u64;
+    let first = match keyframes.first() {
+        Some(first) => first,
+        None => return (0.0, 100.0),
+    };
+
+    if time_ms <= first.time_ms {
+        return (first.start as f32, first.end as f32);
+    }
+
+    for pair in keyframes.windows(2) {
+        let (from, to) = (&pair[0], &pair[1]);
+
+        if time_ms > to.time_ms {
+            continue;
+        }
+
+        let span = (to.time_ms - from.time_ms) as f32;
+        let progress = if span > 0.0 {
+            (time_ms - from.time_ms) as f32 / span
+        } else {
+            1.0
+        };
+
+        return (
+            from.start as f32 + (to.start - from.start) as f32 * progress,
+            from.end as f32 + (to.end - from.end) as f32 * progress,
+        );
+    }
+
+    let last = keyframes
+        .last()
+        .expect("Couldn't get last selector keyframe");
+
+    (last.start as f32, last.end as f32)
+}
+
+/// How strongly each unit is selected, 0 to 1
+pub fn unit_weights(animator: &TextAnimator, unit_count: usize, time: Duration) -> Vec<f32> {
+    if unit_count == 0 {
+        return Vec::new();
+    }
+
+    let (start, end) = sample_selector(&animator.selector, time);
+    let unit_width = 100.0 / unit_count as f32;
+    let softness = animator.softness as f32 * unit_width;
+
+    (0..unit_count)
+        .map(|index| {
+            let center = (index as f32 + 0.5) * unit_width;
+
+            if softness <= 0.0 {
+                return if center >= start && center < end {
+                    1.0
+                } else {
+                    0.0
+                };
+            }
+
+            // ramps are centered on the range edges
+            let inside = (center - start).min(end - center);
+            (inside / softness + 0.5).clamp(0.0, 1.0)
+        })
+        .collect()
+}
+
+/// Combined transform of one line after every animator on the text
+#[derive(Clone, Debug, PartialEq)]
+pub struct LineState {
+    pub range: Range<usize>,
+    pub offset: [f32; 2],
+    pub opacity: f32,
+    pub scale: f32,
+    pub rotation: f32,
+}
+
+/// Evaluate the animators of a text per line. Offsets and rotations add up,
+/// opacities and scales multiply.
+pub fn line_states(text: &str, animators: &[&TextAnimator], time: Duration) -> Vec<LineState> {
+    let lines = split_lines(text);
+    let mut states: Vec<LineState> = lines
+        .iter()
+        .map(|range| LineState {
+            range: range.clone(),
+            offset: [0.0, 0.0],
+            opacity: 1.0,
+            scale: 1.0,
+            rotation: 0.0,
+        })
+        .collect();
+
+    for animator in animators {
+        let weights = unit_weights(animator, lines.len(), time);
+
+        for (state, weight) in states.iter_mut().zip(weights) {
+            state.offset[0] += animator.offset[0] as f32 * weight;
+            state.offset[1] += animator.offset[1] as f32 * weight;
+            state.opacity *= 1.0 + (animator.opacity as f32 / 100.0 - 1.0) * weight;
+            state.scale *= 1.0 + (animator.scale as f32 / 100.0 - 1.0) * weight;
+            state.rotation += animator.rotation as f32 * weight;
+        }
+    }
+
+    states
+}
@@ -0,0 +1,323 @@
+use std::collections::HashMap;
+
+use stunts_engine::animations::Sequence;
+use stunts_engine::timelines::SavedTimelineStateConfig;
+
+use super::saved_state::{ClipTrim, SavedState};
+use super::tracks::compose_timeline;
+
+/// Where one timeline sequence sits, in milliseconds from the start of the timeline
+#[derive(Clone, Debug, PartialEq)]
+pub struct TimelineSpan {
+    pub timeline_sequence_id: String,
+    pub sequence_id: String,
+    pub start_ms: i32,
+    pub end_ms: i32,
+    /// where in the sequence the span starts playing, when trimmed
+    pub source_offset_ms: i32,
+}
+
+/// Layout of the sequence timeline. Durations are looked up by sequence id,
+/// so the order of the sequences and the timeline don't need to match.
+#[derive(Clone, Debug, Default)]
+pub struct TimelineModel {
+    /// in compositing order, later spans draw over earlier ones
+    pub spans: Vec<TimelineSpan>,
+}
+
+impl TimelineModel {
+    /// Timeline sequences without a known duration are left out
+    pub fn from_durations<F>(duration_of: F, config: &SavedTimelineStateConfig) -> Self
+    where
+        F: Fn(&str) -> Option<i32>,
+    {
+        let spans = config
+            .timeline_sequences
+            .iter()
+            .filter_map(|ts| {
+                let duration_ms = duration_of(&ts.sequence_id)?;
+
+                Some(TimelineSpan {
+                    timeline_sequence_id: ts.id.clone(),
+                    sequence_id: ts.sequence_id.clone(),
+                    start_ms: ts.start_time_ms,
+                    end_ms: ts.start_time_ms + duration_ms.max(0),
+                    source_offset_ms: 0,
+                })
+            })
+            .collect();
+
+        Self { spans }
+    }
+
+    pub fn new(sequences: &[Sequence], config: &SavedTimelineStateConfig) -> Self {
+        let durations: HashMap<String, i32> = sequences
+            .iter()
+            .map(|s| (s.id.clone(), s.duration_ms))
+            .collect();
+
+        Self::from_durations(|id| durations.get(id).copied(), config)
+    }
+
+    /// Shorten the spans of trimmed clips to their in and out points
+    pub fn with_trims(mut self, trims: &[ClipTrim]) -> Self {
+        for span in self.spans.iter_mut() {
+            if let Some(trim) = trims
+                .iter()
+                .find(|t| t.timeline_sequence_id == span.timeline_sequence_id)
+            {
+                span.end_ms = span.start_ms + trim.duration_ms.max(0);
+                span.source_offset_ms = trim.source_offset_ms;
+            }
+        }
+
+        self
+    }
+
+    /// Where the clips of a prepared timeline start in their sequences, for the video items
+    /// the engine times itself. Trims start a clip later in its sequence, transitions held
+    /// over its start earlier. `original` is the timeline before it was prepared.
+    pub fn with_source_offsets(
+        mut self,
+        trims: &[ClipTrim],
+        original: &SavedTimelineStateConfig,
+    ) -> Self {
+        for span in self.spans.iter_mut() {
+            let trim_ms = trims
+                .iter()
+                .find(|t| t.timeline_sequence_id == span.timeline_sequence_id)
+                .map(|t| t.source_offset_ms)
+                .unwrap_or(0);
+            let lead_ms = original
+                .timeline_sequences
+                .iter()
+                .find(|ts| ts.id == span.timeline_sequence_id)
+                .map(|ts| ts.start_time_ms - span.start_ms)
+                .unwrap_or(0);
+
+            span.source_offset_ms = trim_ms - lead_ms;
+        }
+
+        self
+    }
+
+    /// The timeline as it plays and exports, with track order, mutes and trims applied
+    pub fn for_project(saved_state: &SavedState, config: &SavedTimelineStateConfig) -> Self {
+        Self::new(
+            &saved_state.sequences,
+            &compose_timeline(saved_state, config),
+        )
+        .with_trims(&saved_state.clip_trims)
+    }
+
+    pub fn end_time_ms(&self) -> i32 {
+        self.spans
+            .iter()
+            .map(|s| s.end_ms)
+            .max()
+            .unwrap_or(0)
+            .max(0)
+    }
+
+    pub fn duration_s(&self) -> f64 {
+        self.end_time_ms() as f64 / 1000.0
+    }
+
+    /// Span boundaries with how many spans cover the time up to the next one
+    fn coverage(&self) -> Vec<(i32, usize)> {
+        let mut edges: Vec<i32> = self
+            .spans
+            .iter()
+            .flat_map(|s| [s.start_ms, s.end_ms])
+            .chain([0])
+            .collect();
+        edges.sort();
+        edges.dedup();
+
+        edges
+            .iter()
+            .map(|&time| {
+                let count = self
+                    .spans
+                    .iter()
+                    .filter(|s| s.start_ms <= time && time < s.end_ms)
+                    .count();
+
+                (time, count)
+            })
+            .collect()
+    }
+
+    /// Merge the stretches between boundaries whose coverage passes a test
+    fn ranges_where(&self, test: impl Fn(usize) -> bool) -> Vec<(i32, i32)> {
+        let end_time = self.end_time_ms();
+        let coverage = self.coverage();
+        let mut ranges: Vec<(i32, i32)> = Vec::new();
+
+        for pair in coverage.windows(2) {
+            let ((start, count), (end, _)) = (pair[0], pair[1]);
+
+            if start >= end_time || !test(count) {
+                continue;
+            }
+
+            match ranges.last_mut() {
+                Some(last) if last.1 == start => last.1 = end,
+                _ => ranges.push((start, end)),
+            }
+        }
+
+        ranges
+    }
+
+    /// Stretches before the end where nothing plays
+    pub fn gaps(&self) -> Vec<(i32, i32)> {
+        self.ranges_where(|count| count == 0)
+    }
+
+    /// Stretches where more than one sequence plays
+    pub fn overlaps(&self) -> Vec<(i32, i32)> {
+        self.ranges_where(|count| count > 1)
+    }
+
+    /// Every span playing at a time, in compositing order
+    pub fn active_at(&self, time_ms: i32) -> Vec<&TimelineSpan> {
+        self.spans
+            .iter()
+            .filter(|s| s.start_ms <= time_ms && time_ms < s.end_ms)
+            .collect()
+    }
+
+    /// The sequence on top at a time
+    pub fn active_sequence_at(&self, time_ms: i32) -> Option<&TimelineSpan> {
+        self.active_at(time_ms).last().copied()
+    }
+
+    /// First sequence to play, skipping a gap at the start
+    pub fn first_span(&self) -> Option<&TimelineSpan> {
+        let start_ms = self.spans.iter().map(|s| s.start_ms).min()?;
+
+        self.active_sequence_at(start_ms)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use stunts_engine::timelines::{TimelineSequence, TrackType};
+
+    use super::*;
+
+    fn clip(id: &str, sequence_id: &str, start_time_ms: i32) -> TimelineSequence {
+        TimelineSequence {
+            id: id.to_string(),
+            sequence_id: sequence_id.to_string(),
+            track_type: TrackType::Video,
+            start_time_ms,
+        }
+    }
+
+    fn timeline(clips: &[(&str, &str, i32)]) -> SavedTimelineStateConfig {
+        SavedTimelineStateConfig {
+            timeline_sequences: clips
+                .iter()
+                .map(|(id, sequence_id, start)| clip(id, sequence_id, *start))
+                .collect(),
+        }
+    }
+
+    /// "short" runs a second, "long" two, anything else is unknown
+    fn duration_of(sequence_id: &str) -> Option<i32> {
+        match sequence_id {
+            "short" => Some(1000),
+            "long" => Some(2000),
+            _ => None,
+        }
+    }
+
+    fn model(clips: &[(&str, &str, i32)]) -> TimelineModel {
+        TimelineModel::from_durations(duration_of, &timeline(clips))
+    }
+
+    fn ids(spans: Vec<&TimelineSpan>) -> Vec<&str> {
+        spans
+            .iter()
+            .map(|s| s.timeline_sequence_id.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn end_time_is_the_latest_end() {
+        let model = model(&[("a", "long", 0), ("b", "short", 500)]);
+
+        assert_eq!(model.end_time_ms(), 2000);
+        assert_eq!(model.duration_s(), 2.0);
+    }
+
+    #[test]
+    fn end_time_counts_a_gap_at_the_start() {
+        let model = model(&[("a", "short", 1500)]);
+
+        assert_eq!(model.end_time_ms(), 2500);
+        assert_eq!(model.gaps(), vec![(0, 1500)]);
+    }
+
+    #[test]
+    fn end_time_of_an_empty_timeline_is_zero() {
+        assert_eq!(model(&[]).end_time_ms(), 0);
+    }
+
+    #[test]
+    fn end_time_follows_trims() {
+        let model = model(&[("a", "long", 0)]).with_trims(&[ClipTrim {
+            timeline_sequence_id: "a".to_string(),
+            source_offset_ms: 300,
+            duration_ms: 700,
+        }]);
+
+        assert_eq!(model.end_time_ms(), 700);
+        assert_eq!(model.spans[0].source_offset_ms, 300);
+    }
+
+    #[test]
+    fn clips_without_a_duration_are_left_out() {
+        let model = model(&[("a", "short", 0), ("b", "missing", 0)]);
+
+        assert_eq!(model.spans.len(), 1);
+        assert_eq!(model.spans[0].timeline_sequence_id, "a");
+    }
+
+    #[test]
+    fn spans_keep_compositing_order() {
+        let model = model(&[("under", "short", 500), ("over", "long", 0)]);
+
+        assert_eq!(ids(model.active_at(700)), vec!["under", "over"]);
+        assert_eq!(
+            model
+                .active_sequence_at(700)
+                .map(|s| s.timeline_sequence_id.as_str()),
+            Some("over")
+        );
+        assert_eq!(model.overlaps(), vec![(500, 1500)]);
+    }
+
+    #[test]
+    fn clip_lookup_includes_the_start_and_not_the_end() {
+        let model = model(&[("a", "short", 0), ("b", "short", 1000)]);
+
+        assert_eq!(ids(model.active_at(0)), vec!["a"]);
+        assert_eq!(ids(model.active_at(999)), vec!["a"]);
+        assert_eq!(ids(model.active_at(1000)), vec!["b"]);
+        assert!(model.active_at(2000).is_empty());
+    }
+
+    #[test]
+    fn first_span_skips_a_gap_at_the_start() {
+        let model = model(&[("later", "short", 3000), ("first", "short", 1000)]);
+
+        assert_eq!(
+            model.first_span().map(|s| s.timeline_sequence_id.as_str()),
+            Some("first")
+        );
+        assert!(model.active_sequence_at(500).is_none());
+    }
+}
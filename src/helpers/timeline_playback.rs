@@ -0,0 +1,347 @@
+use std::time::Duration;
+
+use stunts_engine::animations::Sequence;
+use stunts_engine::editor::Editor;
+
+use super::clock::{sync_engine, ClockMode, PlaybackClock};
+use super::nested::NestedVideo;
+use super::timeline::{TimelineModel, TimelineSpan};
+use super::transitions::WipeClip;
+use super::video::seek_sequence_videos;
+
+/// layers between one playing span and the next, so each track's objects keep their own
+/// order while drawing over the tracks below
+const LAYER_STRIDE: i32 = 100;
+
+pub fn set_sequence_hidden(editor: &mut Editor, sequence: &Sequence, hidden: bool) {
+    for saved in &sequence.active_polygons {
+        if let Some(polygon) = editor
+            .polygons
+            .iter_mut()
+            .find(|p| p.id.to_string() == saved.id)
+        {
+            polygon.hidden = hidden;
+        }
+    }
+
+    for saved in &sequence.active_image_items {
+        if let Some(image) = editor
+            .image_items
+            .iter_mut()
+            .find(|i| i.id.to_string() == saved.id)
+        {
+            image.hidden = hidden;
+        }
+    }
+
+    for saved in &sequence.active_text_items {
+        if let Some(text) = editor
+            .text_items
+            .iter_mut()
+            .find(|t| t.id.to_string() == saved.id)
+        {
+            text.hidden = hidden;
+        }
+    }
+
+    for saved in &sequence.active_video_items {
+        if let Some(video) = editor
+            .video_items
+            .iter_mut()
+            .find(|v| v.id.to_string() == saved.id)
+        {
+            video.hidden = hidden;
+        }
+    }
+}
+
+/// Hide the objects of every sequence but the visible ones. Trimmed copies share their
+/// objects with the original, so everything is hidden before the visible ones are shown.
+pub fn show_only_sequences(editor: &mut Editor, sequences: &[Sequence], visible_ids: &[&str]) {
+    for sequence in sequences {
+        set_sequence_hidden(editor, sequence, true);
+    }
+
+    for sequence in sequences
+        .iter()
+        .filter(|s| visible_ids.contains(&s.id.as_str()))
+    {
+        set_sequence_hidden(editor, sequence, false);
+    }
+}
+
+/// Draw a sequence's objects at their saved layers moved up by an offset
+pub fn set_sequence_layers(editor: &mut Editor, sequence: &Sequence, offset: i32) {
+    for saved in &sequence.active_polygons {
+        if let Some(polygon) = editor
+            .polygons
+            .iter_mut()
+            .find(|p| p.id.to_string() == saved.id)
+        {
+            polygon.update_layer(saved.layer + offset);
+        }
+    }
+
+    for saved in &sequence.active_image_items {
+        if let Some(image) = editor
+            .image_items
+            .iter_mut()
+            .find(|i| i.id.to_string() == saved.id)
+        {
+            image.update_layer(saved.layer + offset);
+        }
+    }
+
+    for saved in &sequence.active_text_items {
+        if let Some(text) = editor
+            .text_items
+            .iter_mut()
+            .find(|t| t.id.to_string() == saved.id)
+        {
+            text.update_layer(saved.layer + offset);
+        }
+    }
+
+    for saved in &sequence.active_video_items {
+        if let Some(video) = editor
+            .video_items
+            .iter_mut()
+            .find(|v| v.id.to_string() == saved.id)
+        {
+            video.update_layer(saved.layer + offset);
+        }
+    }
+}
+
+/// One sequence with the objects and animations of every span playing at once, timed
+/// from the earliest start among them. The first span's sequence gives the background.
+fn composite(playing: &[(&TimelineSpan, &Sequence)]) -> Option<Sequence> {
+    let (_, first) = playing.first()?;
+    let base_ms = playing.iter().map(|(span, _)| span.start_ms).min()?;
+
+    if playing.len() == 1 {
+        return Some((*first).clone());
+    }
+
+    let mut composite = (*first).clone();
+    composite.active_polygons = Vec::new();
+    composite.active_text_items = Vec::new();
+    composite.active_image_items = Vec::new();
+    composite.active_video_items = Vec::new();
+    composite.polygon_motion_paths = Vec::new();
+
+    for (span, sequence) in playing {
+        let delay_ms = span.start_ms - base_ms;
+
+        composite.duration_ms = composite.duration_ms.max(delay_ms + sequence.duration_ms);
+        composite
+            .active_polygons
+            .extend(sequence.active_polygons.iter().cloned());
+        composite
+            .active_text_items
+            .extend(sequence.active_text_items.iter().cloned());
+        composite
+            .active_image_items
+            .extend(sequence.active_image_items.iter().cloned());
+        composite
+            .active_video_items
+            .extend(sequence.active_video_items.iter().cloned());
+        composite
+            .polygon_motion_paths
+            .extend(sequence.polygon_motion_paths.iter().map(|animation| {
+                let mut animation = animation.clone();
+                animation.start_time_ms += delay_ms;
+                animation
+            }));
+    }
+
+    Some(composite)
+}
+
+/// Playing the assembled timeline. The engine plays one sequence at a time, this hands it
+/// one made up of the spans playing at each point and switches when they change, so
+/// overlapping clips and both sides of a transition show together. Spans later in
+/// compositing order draw over earlier ones, as the tracks above overlay those below.
+/// Video items are stepped here too, the engine only advances them in its own video playback.
+pub struct TimelinePlayback {
+    pub sequences: Vec<Sequence>,
+    pub model: TimelineModel,
+    clock: PlaybackClock,
+    /// timeline sequences being shown, none in a gap, unknown after a seek
+    current: Option<Vec<String>>,
+    /// last position handed out, to notice the playhead being moved while playing
+    pub reported: Duration,
+    /// nested copies of videos, which show their nested sequence's time
+    nested_videos: Vec<NestedVideo>,
+    /// wipes the preview clips at their edge
+    wipes: Vec<WipeClip>,
+}
+
+impl TimelinePlayback {
+    /// Starts over when asked to play from the end
+    pub fn new(
+        sequences: Vec<Sequence>,
+        model: TimelineModel,
+        from: Duration,
+        fps: u32,
+        mode: ClockMode,
+    ) -> Self {
+        let from = if from.as_millis() as i32 >= model.end_time_ms() {
+            Duration::ZERO
+        } else {
+            from
+        };
+
+        Self {
+            sequences,
+            model,
+            clock: PlaybackClock::new(fps, mode, from),
+            current: None,
+            reported: from,
+            nested_videos: Vec::new(),
+            wipes: Vec::new(),
+        }
+    }
+
+    pub fn with_nested_videos(mut self, nested_videos: Vec<NestedVideo>) -> Self {
+        self.nested_videos = nested_videos;
+        self
+    }
+
+    pub fn with_wipes(mut self, wipes: Vec<WipeClip>) -> Self {
+        self.wipes = wipes;
+        self
+    }
+
+    /// Objects partway through a wipe at the position last stepped to,
+    /// with the part of the canvas width each still shows
+    pub fn wipe_clips(&self) -> Vec<(String, [f64; 2])> {
+        let time_ms = self.reported.as_millis() as i32;
+        let mut clips = Vec::new();
+
+        for wipe in &self.wipes {
+            let (visible, sequence) = match (
+                wipe.visible_x(time_ms),
+                self.sequences.iter().find(|s| s.id == wipe.sequence_id),
+            ) {
+                (Some(visible), Some(sequence)) => (visible, sequence),
+                _ => continue,
+            };
+
+            let ids = sequence
+                .active_polygons
+                .iter()
+                .map(|p| &p.id)
+                .chain(sequence.active_text_items.iter().map(|t| &t.id))
+                .chain(sequence.active_image_items.iter().map(|i| &i.id))
+                .chain(sequence.active_video_items.iter().map(|v| &v.id));
+
+            clips.extend(ids.map(|id| (id.clone(), visible)));
+        }
+
+        clips
+    }
+
+    pub fn position(&self) -> Duration {
+        self.clock.time()
+    }
+
+    pub fn seek(&mut self, position: Duration) {
+        self.clock.seek(position);
+        self.current = None;
+        self.reported = position;
+    }
+
+    pub fn set_mode(&mut self, mode: ClockMode) {
+        self.clock.set_mode(mode);
+    }
+
+    pub fn finished(&self) -> bool {
+        self.position().as_millis() as i32 >= self.model.end_time_ms()
+    }
+
+    /// Show whatever plays at the current position, returning the position for the playhead
+    pub fn step(&mut self, editor: &mut Editor) -> Duration {
+        let position = self.clock.tick();
+        let time_ms = position.as_millis() as i32;
+
+        let playing: Vec<(&TimelineSpan, &Sequence)> = self
+            .model
+            .active_at(time_ms)
+            .into_iter()
+            .filter_map(|span| {
+                self.sequences
+                    .iter()
+                    .find(|s| s.id == span.sequence_id)
+                    .map(|sequence| (span, sequence))
+            })
+            .collect();
+        let playing_ids: Vec<String> = playing
+            .iter()
+            .map(|(span, _)| span.timeline_sequence_id.clone())
+            .collect();
+
+        if self.current.as_ref() != Some(&playing_ids) {
+            let visible: Vec<&str> = playing.iter().map(|(_, s)| s.id.as_str()).collect();
+            show_only_sequences(editor, &self.sequences, &visible);
+
+            for (index, (_, sequence)) in playing.iter().enumerate() {
+                set_sequence_layers(editor, sequence, index as i32 * LAYER_STRIDE);
+            }
+
+            match composite(&playing) {
+                Some(sequence) => {
+                    editor.current_sequence_data = Some(sequence);
+                    editor.is_playing = true;
+                }
+                None => {
+                    // nothing plays in a gap
+                    editor.is_playing = false;
+                    editor.start_playing_time = None;
+                }
+            }
+
+            self.current = Some(playing_ids);
+        }
+
+        // the composite is shown at the clock's frame inside it
+        if let Some(start_ms) = playing.iter().map(|(span, _)| span.start_ms).min() {
+            sync_engine(
+                editor,
+                position.saturating_sub(Duration::from_millis(start_ms.max(0) as u64)),
+            );
+        }
+
+        self.reported = position;
+
+        position
+    }
+
+    /// Bring the videos of the shown clips to the frame of their sources at the position
+    /// last stepped to. Needs the gpu, so it's done from the render loop after stepping.
+    pub fn sync_videos(&self, editor: &mut Editor, device: &wgpu::Device, queue: &wgpu::Queue) {
+        let time_ms = self.reported.as_millis() as i32;
+
+        for span in self.model.active_at(time_ms) {
+            if let Some(sequence) = self.sequences.iter().find(|s| s.id == span.sequence_id) {
+                seek_sequence_videos(
+                    editor,
+                    device,
+                    queue,
+                    sequence,
+                    self.reported
+                        .saturating_sub(Duration::from_millis(span.start_ms.max(0) as u64)),
+                    span.source_offset_ms,
+                    &self.nested_videos,
+                );
+            }
+        }
+    }
+
+    /// Put every object back on its own layer, for when playback stops
+    pub fn restore_layers(&self, editor: &mut Editor) {
+        for sequence in &self.sequences {
+            set_sequence_layers(editor, sequence, 0);
+        }
+    }
+}
@@ -0,0 +1,98 @@
+use stunts_engine::timelines::{SavedTimelineStateConfig, TimelineSequence, TrackType};
+use uuid::Uuid;
+
+use super::saved_state::{SavedState, TimelineTrack, TrackAssignment};
+
+fn kind_label(kind: &TrackType) -> &'static str {
+    match kind {
+        TrackType::Video => "Video",
+        TrackType::Audio => "Audio",
+    }
+}
+
+/// A new track named after how many of its kind there are
+pub fn new_track(tracks: &[TimelineTrack], kind: TrackType) -> TimelineTrack {
+    let count = tracks.iter().filter(|t| t.kind == kind).count();
+
+    TimelineTrack {
+        id: Uuid::new_v4().to_string(),
+        name: format!("{} {}", kind_label(&kind), count + 1),
+        kind,
+        locked: false,
+        muted: false,
+    }
+}
+
+/// The video and audio pair projects had before tracks were saved
+pub fn default_tracks() -> Vec<TimelineTrack> {
+    let video = new_track(&[], TrackType::Video);
+    let audio = new_track(&[], TrackType::Audio);
+
+    vec![video, audio]
+}
+
+/// Tracks of a project, top to bottom
+pub fn project_tracks(saved_state: &SavedState) -> Vec<TimelineTrack> {
+    if saved_state.tracks.is_empty() {
+        default_tracks()
+    } else {
+        saved_state.tracks.clone()
+    }
+}
+
+/// Track a timeline sequence sits on, falling back to the first track of its type
+pub fn track_of<'a>(
+    tracks: &'a [TimelineTrack],
+    assignments: &[TrackAssignment],
+    timeline_sequence: &TimelineSequence,
+) -> Option<&'a TimelineTrack> {
+    let assigned = assignments
+        .iter()
+        .find(|a| a.timeline_sequence_id == timeline_sequence.id)
+        .and_then(|a| tracks.iter().find(|t| t.id == a.track_id))
+        .filter(|t| t.kind == timeline_sequence.track_type);
+
+    assigned.or_else(|| {
+        tracks
+            .iter()
+            .find(|t| t.kind == timeline_sequence.track_type)
+    })
+}
+
+/// Timeline for playback and export. Muted tracks are left out and sequences are listed
+/// from the bottom track up, so the upper tracks composite over the lower ones.
+pub fn compose_timeline(
+    saved_state: &SavedState,
+    config: &SavedTimelineStateConfig,
+) -> SavedTimelineStateConfig {
+    let tracks = project_tracks(saved_state);
+    let mut timeline_sequences = Vec::new();
+
+    for track in tracks.iter().rev() {
+        if track.muted {
+            continue;
+        }
+
+        let mut on_track: Vec<TimelineSequence> = config
+            .timeline_sequences
+            .iter()
+            .filter(|ts| {
+                track_of(&tracks, &saved_state.track_assignments, ts).map(|t| &t.id)
+                    == Some(&track.id)
+            })
+            .cloned()
+            .collect();
+        on_track.sort_by_key(|ts| ts.start_time_ms);
+
+        timeline_sequences.extend(on_track);
+    }
+
+    SavedTimelineStateConfig { timeline_sequences }
+}
+
+/// Older projects have no saved tracks, store the defaults before editing them
+pub fn ensure_tracks(saved_state: &mut SavedState) {
+    if saved_state.tracks.is_empty() {
+        saved_state.tracks = default_tracks();
+    }
+}
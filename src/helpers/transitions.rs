@@ -0,0 +1,660 @@
+use std::time::Duration;
+
+use stunts_engine::animations::{
+    AnimationData, AnimationProperty, BackgroundFill, EasingType, KeyType, KeyframeValue,
+    ObjectType, Sequence, UIKeyframe,
+};
+use stunts_engine::editor::PathType;
+use stunts_engine::polygon::{SavedPoint, SavedPolygonConfig, SavedStroke};
+use stunts_engine::timelines::{SavedTimelineStateConfig, TimelineSequence};
+use uuid::Uuid;
+
+use super::clips::placement_id;
+use super::keyframes::value_from_components;
+use super::looks::stand_in_id;
+use super::modifiers::{ease, sample_property};
+use super::nested::{default_placement, CANVAS_SIZE};
+use super::saved_state::{SavedState, TimelineTrack, TrackAssignment, TransitionKind};
+use super::timeline::{TimelineModel, TimelineSpan};
+use super::tracks::{project_tracks, track_of};
+
+/// clips this close on a track still count as back to back, about a pixel on the timeline
+pub const ADJACENT_GAP_MS: i32 = 100;
+
+pub const DEFAULT_TRANSITION_MS: u32 = 500;
+
+pub const DIP_COLORS: [(&str, [u8; 3]); 4] = [
+    ("Black", [0, 0, 0]),
+    ("White", [255, 255, 255]),
+    ("Gray", [120, 120, 120]),
+    ("Red", [200, 30, 30]),
+];
+
+pub fn dip_color_label(color: [u8; 3]) -> String {
+    DIP_COLORS
+        .iter()
+        .find(|(_, c)| *c == color)
+        .map(|(label, _)| label.to_string())
+        .unwrap_or_else(|| "Custom".to_string())
+}
+
+pub fn dip_color_from_label(label: &str) -> Option<[u8; 3]> {
+    DIP_COLORS
+        .iter()
+        .find(|(l, _)| *l == label)
+        .map(|(_, color)| *color)
+}
+
+impl TransitionKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            TransitionKind::Crossfade => "Crossfade",
+            TransitionKind::Dip => "Dip to Color",
+            TransitionKind::Slide => "Slide",
+            TransitionKind::Push => "Push",
+            TransitionKind::Wipe => "Wipe",
+            TransitionKind::Zoom => "Zoom",
+        }
+    }
+
+    pub fn all() -> Vec<TransitionKind> {
+        vec![
+            TransitionKind::Crossfade,
+            TransitionKind::Dip,
+            TransitionKind::Slide,
+            TransitionKind::Push,
+            TransitionKind::Wipe,
+            TransitionKind::Zoom,
+        ]
+    }
+
+    pub fn from_label(label: &str) -> Option<TransitionKind> {
+        TransitionKind::all()
+            .into_iter()
+            .find(|kind| kind.label() == label)
+    }
+
+    /// Whether both sides play across the whole transition, rather than one after the other
+    pub fn together(&self) -> bool {
+        matches!(
+            self,
+            TransitionKind::Crossfade
+                | TransitionKind::Slide
+                | TransitionKind::Push
+                | TransitionKind::Wipe
+        )
+    }
+}
+
+/// Consecutive timeline sequences on the same track, the places a transition can go
+pub fn adjacent_pairs(
+    tracks: &[TimelineTrack],
+    assignments: &[TrackAssignment],
+    model: &TimelineModel,
+    config: &SavedTimelineStateConfig,
+) -> Vec<(TimelineSequence, TimelineSequence)> {
+    let mut pairs = Vec::new();
+
+    for track in tracks {
+        let mut on_track: Vec<(&TimelineSequence, &TimelineSpan)> = config
+            .timeline_sequences
+            .iter()
+            .filter(|ts| track_of(tracks, assignments, ts).map(|t| &t.id) == Some(&track.id))
+            .filter_map(|ts| {
+                model
+                    .spans
+                    .iter()
+                    .find(|span| span.timeline_sequence_id == ts.id)
+                    .map(|span| (ts, span))
+            })
+            .collect();
+        on_track.sort_by_key(|(_, span)| span.start_ms);
+
+        for pair in on_track.windows(2) {
+            let ((from, from_span), (to, to_span)) = (pair[0], pair[1]);
+
+            if to_span.start_ms - from_span.end_ms <= ADJACENT_GAP_MS {
+                pairs.push((from.clone(), to.clone()));
+            }
+        }
+    }
+
+    pairs
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TransitionPhase {
+    Out,
+    In,
+}
+
+/// One side of a scheduled transition, in the sequence's own time. Sides running over
+/// the cut go past the end of the outgoing sequence or before the start of the incoming one.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TransitionPass {
+    pub timeline_sequence_id: String,
+    pub phase: TransitionPhase,
+    pub kind: TransitionKind,
+    pub easing: EasingType,
+    pub dip_color: [u8; 3],
+    pub start_ms: i32,
+    pub end_ms: i32,
+}
+
+impl TransitionPass {
+    /// Eased progress at a fraction of the way through the pass
+    pub fn progress(&self, t: f64) -> f64 {
+        ease(&self.easing, t.clamp(0.0, 1.0))
+    }
+}
+
+/// One side of a wipe as played on the timeline, clipped at the edge rather than baked.
+/// The edge sweeps left to right, the incoming side shows left of it, the outgoing right.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WipeClip {
+    /// the clip's own copy of its sequence
+    pub sequence_id: String,
+    pub phase: TransitionPhase,
+    pub easing: EasingType,
+    /// timeline time
+    pub start_ms: i32,
+    pub end_ms: i32,
+}
+
+impl WipeClip {
+    /// Part of the canvas width the side shows at a timeline time, none outside the wipe
+    pub fn visible_x(&self, time_ms: i32) -> Option<[f64; 2]> {
+        if self.end_ms <= self.start_ms || time_ms < self.start_ms || time_ms > self.end_ms {
+            return None;
+        }
+
+        let t = (time_ms - self.start_ms) as f64 / (self.end_ms - self.start_ms) as f64;
+        let edge = ease(&self.easing, t) * CANVAS_SIZE[0];
+
+        Some(match self.phase {
+            TransitionPhase::Out => [edge, CANVAS_SIZE[0]],
+            TransitionPhase::In => [0.0, edge],
+        })
+    }
+}
+
+/// Place the saved transitions on the timeline. Overlapping clips transition across the
+/// whole overlap, back to back clips around the cut. Crossfades, slides, pushes and wipes
+/// run both sides at once, dips and zooms take the outgoing sequence out before bringing
+/// the incoming one in.
+pub fn schedule_transitions(
+    saved_state: &SavedState,
+    sequences: &[Sequence],
+    config: &SavedTimelineStateConfig,
+) -> Vec<TransitionPass> {
+    let model = TimelineModel::new(sequences, config);
+    let pairs = adjacent_pairs(
+        &project_tracks(saved_state),
+        &saved_state.track_assignments,
+        &model,
+        config,
+    );
+
+    let span_of = |timeline_sequence_id: &str| {
+        model
+            .spans
+            .iter()
+            .find(|s| s.timeline_sequence_id == timeline_sequence_id)
+    };
+
+    let mut passes = Vec::new();
+
+    for transition in &saved_state.transitions {
+        // clips may have moved apart since the transition was added
+        let still_adjacent = pairs.iter().any(|(from, to)| {
+            from.id == transition.from_timeline_sequence_id
+                && to.id == transition.to_timeline_sequence_id
+        });
+
+        let (from, to) = match (
+            span_of(&transition.from_timeline_sequence_id),
+            span_of(&transition.to_timeline_sequence_id),
+        ) {
+            (Some(from), Some(to)) if still_adjacent => (from, to),
+            _ => continue,
+        };
+
+        let (window_start, window_end) = if to.start_ms < from.end_ms {
+            (to.start_ms, from.end_ms)
+        } else {
+            let cut = (from.end_ms + to.start_ms) / 2;
+            let half = transition.duration_ms as i32 / 2;
+
+            (cut - half, cut + half)
+        };
+        let middle = (window_start + window_end) / 2;
+
+        let (out_window, in_window) = if transition.kind.together() {
+            ((window_start, window_end), (window_start, window_end))
+        } else {
+            ((window_start, middle), (middle, window_end))
+        };
+
+        // only the side of each sequence facing the cut runs over it
+        passes.push(TransitionPass {
+            timeline_sequence_id: from.timeline_sequence_id.clone(),
+            phase: TransitionPhase::Out,
+            kind: transition.kind,
+            easing: transition.easing.clone(),
+            dip_color: transition.dip_color,
+            start_ms: (out_window.0 - from.start_ms).max(0),
+            end_ms: out_window.1 - from.start_ms,
+        });
+        passes.push(TransitionPass {
+            timeline_sequence_id: to.timeline_sequence_id.clone(),
+            phase: TransitionPhase::In,
+            kind: transition.kind,
+            easing: transition.easing.clone(),
+            dip_color: transition.dip_color,
+            start_ms: in_window.0 - to.start_ms,
+            end_ms: (in_window.1 - to.start_ms).min(to.end_ms - to.start_ms),
+        });
+    }
+
+    passes
+}
+
+/// Opacity factor, scale factor and horizontal offset of an object partway through a pass
+fn envelope(pass: &TransitionPass, progress: f64, x: f64) -> (f64, f64, f64) {
+    // 0 leaves the object as keyed, 1 is fully transitioned away
+    let amount = match pass.phase {
+        TransitionPhase::Out => progress,
+        TransitionPhase::In => 1.0 - progress,
+    };
+
+    match (pass.kind, pass.phase) {
+        (TransitionKind::Crossfade, _) => (1.0 - amount, 1.0, 0.0),
+        // the dip card covers the objects, they stay as they are
+        (TransitionKind::Dip, _) => (1.0, 1.0, 0.0),
+        (TransitionKind::Slide, TransitionPhase::Out) => (1.0, 1.0, 0.0),
+        (TransitionKind::Slide, TransitionPhase::In) => (1.0, 1.0, amount * CANVAS_SIZE[0]),
+        (TransitionKind::Push, TransitionPhase::Out) => (1.0, 1.0, -amount * CANVAS_SIZE[0]),
+        (TransitionKind::Push, TransitionPhase::In) => (1.0, 1.0, amount * CANVAS_SIZE[0]),
+        // where the sides can't be clipped at the edge, as in export, a soft edge
+        // sweeps left to right and objects switch over as it passes them
+        (TransitionKind::Wipe, phase) => {
+            let soft = CANVAS_SIZE[0] * 0.1;
+            let edge = progress * (CANVAS_SIZE[0] + soft) - soft / 2.0;
+            let passed = ((edge - x) / soft + 0.5).clamp(0.0, 1.0);
+
+            match phase {
+                TransitionPhase::Out => (1.0 - passed, 1.0, 0.0),
+                TransitionPhase::In => (passed, 1.0, 0.0),
+            }
+        }
+        (TransitionKind::Zoom, TransitionPhase::Out) => (1.0 - amount, 1.0 + amount * 0.5, 0.0),
+        (TransitionKind::Zoom, TransitionPhase::In) => (1.0 - amount, 1.0 - amount * 0.5, 0.0),
+    }
+}
+
+fn frame_keyframe(time: Duration, value: KeyframeValue) -> UIKeyframe {
+    UIKeyframe {
+        id: Uuid::new_v4().to_string(),
+        time,
+        value,
+        easing: EasingType::Linear,
+        path_type: PathType::Linear,
+        key_type: KeyType::Frame,
+    }
+}
+
+/// Times a pass is baked at, a frame apart from start to end
+fn pass_times(start: Duration, end: Duration, fps: u32) -> Vec<Duration> {
+    let frame_count = ((end - start).as_secs_f64() * fps as f64).ceil() as u64;
+
+    (0..frame_count)
+        .map(|frame| start + Duration::from_secs_f64(frame as f64 / fps as f64))
+        .chain([end])
+        .collect()
+}
+
+fn bake_pass(
+    animation: &mut AnimationData,
+    pass: &TransitionPass,
+    window: (Duration, Duration),
+    fps: u32,
+) {
+    let offset = Duration::from_millis(animation.start_time_ms.max(0) as u64);
+    let (start, end) = (
+        window.0.saturating_sub(offset),
+        window.1.saturating_sub(offset),
+    );
+
+    if end <= start || fps == 0 {
+        return;
+    }
+
+    let span = (end - start).as_secs_f64();
+    let times = pass_times(start, end, fps);
+
+    let position = animation
+        .properties
+        .iter()
+        .find(|p| p.property_path == "position")
+        .cloned();
+
+    for property in animation.properties.iter_mut() {
+        if !["opacity", "scale", "position"].contains(&property.property_path.as_str()) {
+            continue;
+        }
+
+        let template = match property.keyframes.first() {
+            Some(keyframe) => keyframe.value.clone(),
+            None => continue,
+        };
+
+        let original = property.clone();
+        let mut keyframes: Vec<UIKeyframe> = original
+            .keyframes
+            .iter()
+            .filter(|k| k.time < start || k.time > end)
+            .cloned()
+            .collect();
+
+        for time in &times {
+            let mut values = match sample_property(&original, *time, &[]) {
+                Some(values) => values,
+                None => continue,
+            };
+
+            let progress = pass.progress((*time - start).as_secs_f64() / span);
+            let x = position
+                .as_ref()
+                .and_then(|p| sample_property(p, *time, &[]))
+                .map(|values| values[0])
+                .unwrap_or(CANVAS_SIZE[0] / 2.0);
+            let (opacity, scale, offset_x) = envelope(pass, progress, x);
+
+            match property.property_path.as_str() {
+                "opacity" => values[0] *= opacity,
+                "scale" => values[0] *= scale,
+                _ => values[0] += offset_x,
+            }
+
+            keyframes.push(frame_keyframe(
+                *time,
+                value_from_components(&template, &values),
+            ));
+        }
+
+        keyframes.sort_by_key(|k| k.time);
+        property.keyframes = keyframes;
+    }
+}
+
+/// Move a property's keyframes later, holding the first value from the start
+fn delay_property(property: &mut AnimationProperty, delay: Duration) {
+    let first = property.keyframes.iter().min_by_key(|k| k.time).cloned();
+
+    property.keyframes.iter_mut().for_each(|k| {
+        k.time += delay;
+
+        if let KeyType::Range(range_data) = &mut k.key_type {
+            range_data.end_time += delay;
+        }
+    });
+
+    if let Some(first) = first {
+        property.keyframes.insert(
+            0,
+            UIKeyframe {
+                id: Uuid::new_v4().to_string(),
+                time: Duration::ZERO,
+                key_type: KeyType::Frame,
+                ..first
+            },
+        );
+    }
+
+    property
+        .children
+        .iter_mut()
+        .for_each(|c| delay_property(c, delay));
+}
+
+/// Copy of a sequence held on its first frame for lead_ms before it starts
+/// and on its last frame for tail_ms after it ends
+fn padded_sequence(sequence: &Sequence, lead_ms: i32, tail_ms: i32) -> Sequence {
+    let mut padded = sequence.clone();
+    let lead = Duration::from_millis(lead_ms.max(0) as u64);
+    let tail = Duration::from_millis(tail_ms.max(0) as u64);
+
+    padded.duration_ms += lead_ms.max(0) + tail_ms.max(0);
+
+    for animation in padded.polygon_motion_paths.iter_mut() {
+        animation.duration += lead + tail;
+
+        if lead.is_zero() {
+            continue;
+        }
+
+        // animations that start later just start later still
+        if animation.start_time_ms > 0 {
+            animation.start_time_ms += lead_ms;
+        } else {
+            animation
+                .properties
+                .iter_mut()
+                .for_each(|p| delay_property(p, lead));
+        }
+    }
+
+    padded
+}
+
+fn object_layers(sequence: &Sequence) -> Vec<i32> {
+    sequence
+        .active_polygons
+        .iter()
+        .map(|p| p.layer)
+        .chain(sequence.active_text_items.iter().map(|t| t.layer))
+        .chain(sequence.active_image_items.iter().map(|i| i.layer))
+        .chain(sequence.active_video_items.iter().map(|v| v.layer))
+        .collect()
+}
+
+/// A polygon filling the whole canvas
+fn canvas_card(id: &str, name: &str, fill: [i32; 4], layer: i32) -> SavedPolygonConfig {
+    SavedPolygonConfig {
+        id: id.to_string(),
+        name: name.to_string(),
+        fill,
+        dimensions: (CANVAS_SIZE[0] as i32, CANVAS_SIZE[1] as i32),
+        position: SavedPoint {
+            x: (CANVAS_SIZE[0] / 2.0) as i32,
+            y: (CANVAS_SIZE[1] / 2.0) as i32,
+        },
+        border_radius: 0,
+        stroke: SavedStroke {
+            thickness: 0,
+            fill: [0, 0, 0, 0],
+        },
+        layer,
+    }
+}
+
+/// A card of the sequence's background color under all its objects. The background behind
+/// the canvas stays put, slides and pushes move this card with the objects instead.
+fn add_background_card(sequence: &mut Sequence, timeline_sequence_id: &str) {
+    let fill = match &sequence.background_fill {
+        Some(BackgroundFill::Color(fill)) => *fill,
+        _ => return,
+    };
+
+    let id = stand_in_id(timeline_sequence_id, "background");
+    let bottom = object_layers(sequence).into_iter().min().unwrap_or(0);
+
+    sequence
+        .active_polygons
+        .push(canvas_card(&id, "Background", fill, bottom - 1));
+    sequence.polygon_motion_paths.push(AnimationData {
+        id: id.clone(),
+        object_type: ObjectType::Polygon,
+        polygon_id: id,
+        duration: Duration::from_millis(sequence.duration_ms.max(0) as u64),
+        start_time_ms: 0,
+        position: [0, 0],
+        properties: default_placement(),
+    });
+}
+
+/// A card of the dip color over the whole canvas, fading in as the outgoing sequence
+/// goes out and out as the incoming one comes in
+fn add_dip_card(
+    sequence: &mut Sequence,
+    timeline_sequence_id: &str,
+    pass: &TransitionPass,
+    window: (Duration, Duration),
+    fps: u32,
+) {
+    let (start, end) = window;
+
+    if end <= start || fps == 0 {
+        return;
+    }
+
+    let tag = match pass.phase {
+        TransitionPhase::Out => "dip-out",
+        TransitionPhase::In => "dip-in",
+    };
+    let id = stand_in_id(timeline_sequence_id, tag);
+    let duration = Duration::from_millis(sequence.duration_ms.max(0) as u64);
+    let span = (end - start).as_secs_f64();
+
+    let top = object_layers(sequence).into_iter().max().unwrap_or(0);
+    let [r, g, b] = pass.dip_color;
+
+    sequence.active_polygons.push(canvas_card(
+        &id,
+        "Dip",
+        [r as i32, g as i32, b as i32, 255],
+        top + 1,
+    ));
+
+    // 0 is clear, 1 covers everything
+    let cover = |progress: f64| match pass.phase {
+        TransitionPhase::Out => progress,
+        TransitionPhase::In => 1.0 - progress,
+    };
+    let opacity = |time: Duration, amount: f64| {
+        frame_keyframe(
+            time,
+            KeyframeValue::Opacity((amount * 100.0).round() as i32),
+        )
+    };
+
+    let mut keyframes = vec![opacity(Duration::ZERO, cover(0.0))];
+    keyframes.extend(pass_times(start, end, fps).into_iter().map(|time| {
+        let progress = pass.progress((time - start).as_secs_f64() / span);
+
+        opacity(time, cover(progress))
+    }));
+    keyframes.push(opacity(duration.max(end), cover(1.0)));
+
+    let mut properties = default_placement();
+    if let Some(property) = properties.iter_mut().find(|p| p.property_path == "opacity") {
+        property.keyframes = keyframes;
+    }
+
+    sequence.polygon_motion_paths.push(AnimationData {
+        id: id.clone(),
+        object_type: ObjectType::Polygon,
+        polygon_id: id,
+        duration,
+        start_time_ms: 0,
+        position: [0, 0],
+        properties,
+    });
+}
+
+/// Bake scheduled transitions into copies of the sequences involved, one for each clip
+/// so a sequence placed more than once only transitions where its clips do. Sides running
+/// over the cut hold the first or last frame of their sequence, so both stay on screen.
+/// Objects get their opacity, scale and position keyframes baked, dips get a card of
+/// their color, slides and pushes one of the background, and the engine plays and exports
+/// the copies as-is. With clip_wipes, wipes are left for the preview to clip at their edge
+/// and returned in timeline time, otherwise they're baked with a soft edge.
+pub fn apply_transitions(
+    sequences: &mut Vec<Sequence>,
+    config: &mut SavedTimelineStateConfig,
+    passes: &[TransitionPass],
+    fps: u32,
+    clip_wipes: bool,
+) -> Vec<WipeClip> {
+    let mut wipes = Vec::new();
+
+    for ts in config.timeline_sequences.iter_mut() {
+        let passes: Vec<&TransitionPass> = passes
+            .iter()
+            .filter(|p| p.timeline_sequence_id == ts.id)
+            .collect();
+
+        if passes.is_empty() {
+            continue;
+        }
+
+        let sequence = match sequences.iter().find(|s| s.id == ts.sequence_id) {
+            Some(sequence) => sequence,
+            None => continue,
+        };
+
+        let lead_ms = passes.iter().map(|p| -p.start_ms).max().unwrap_or(0).max(0);
+        let tail_ms = passes
+            .iter()
+            .map(|p| p.end_ms - sequence.duration_ms)
+            .max()
+            .unwrap_or(0)
+            .max(0);
+
+        let mut copy = padded_sequence(sequence, lead_ms, tail_ms);
+        copy.id = placement_id(&sequence.id, &ts.id);
+
+        if passes
+            .iter()
+            .any(|p| matches!(p.kind, TransitionKind::Slide | TransitionKind::Push))
+        {
+            add_background_card(&mut copy, &ts.id);
+        }
+
+        for pass in passes {
+            let at = |ms: i32| Duration::from_millis((ms + lead_ms).max(0) as u64);
+            let window = (at(pass.start_ms), at(pass.end_ms));
+
+            if pass.kind == TransitionKind::Dip {
+                add_dip_card(&mut copy, &ts.id, pass, window, fps);
+                continue;
+            }
+
+            if pass.kind == TransitionKind::Wipe && clip_wipes {
+                wipes.push(WipeClip {
+                    sequence_id: copy.id.clone(),
+                    phase: pass.phase,
+                    easing: pass.easing.clone(),
+                    start_ms: ts.start_time_ms + pass.start_ms,
+                    end_ms: ts.start_time_ms + pass.end_ms,
+                });
+                continue;
+            }
+
+            for animation in copy.polygon_motion_paths.iter_mut() {
+                bake_pass(animation, pass, window, fps);
+            }
+        }
+
+        ts.start_time_ms -= lead_ms;
+        ts.sequence_id = copy.id.clone();
+
+        // trimmed clips already have their own copy, it's replaced
+        match sequences.iter_mut().find(|s| s.id == copy.id) {
+            Some(existing) => *existing = copy,
+            None => sequences.push(copy),
+        }
+    }
+
+    wipes
+}
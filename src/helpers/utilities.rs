@@ -24,6 +24,7 @@ use stunts_engine::polygon::SavedStroke;
 use stunts_engine::timelines::SavedTimelineStateConfig;
 use uuid::Uuid;
 
+use super::clock::DEFAULT_FRAME_RATE;
 use super::saved_state::ProjectData;
 use super::saved_state::ProjectsDataFile;
 use super::saved_state::SavedState;
@@ -174,6 +175,19 @@ pub fn load_project_state(project_id: String) -> Result<SavedState, Box<dyn std:
             timeline_state: SavedTimelineStateConfig {
                 timeline_sequences: Vec::new(),
             },
+            animation_repeats: Vec::new(),
+            property_modifiers: Vec::new(),
+            color_tracks: Vec::new(),
+            shape_tracks: Vec::new(),
+            text_animators: Vec::new(),
+            hold_keyframes: Vec::new(),
+            tracks: Vec::new(),
+            track_assignments: Vec::new(),
+            transitions: Vec::new(),
+            clip_trims: Vec::new(),
+            frame_rate: DEFAULT_FRAME_RATE,
+            markers: Vec::new(),
+            nested_sequences: Vec::new(),
         };
 
         let json = serde_json::to_string_pretty(&json).expect("Couldn't serialize saved state");
@@ -205,6 +219,19 @@ pub fn create_project_state(name: String) -> Result<SavedState, Box<dyn std::err
         timeline_state: SavedTimelineStateConfig {
             timeline_sequences: Vec::new(),
         },
+        animation_repeats: Vec::new(),
+        property_modifiers: Vec::new(),
+        color_tracks: Vec::new(),
+        shape_tracks: Vec::new(),
+        text_animators: Vec::new(),
+        hold_keyframes: Vec::new(),
+        tracks: Vec::new(),
+        track_assignments: Vec::new(),
+        transitions: Vec::new(),
+        clip_trims: Vec::new(),
+        frame_rate: DEFAULT_FRAME_RATE,
+        markers: Vec::new(),
+        nested_sequences: Vec::new(),
     };
 
     let json = serde_json::to_string_pretty(&initial_state)?;
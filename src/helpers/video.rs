@@ -0,0 +1,79 @@
+use std::time::Duration;
+
+use stunts_engine::animations::Sequence;
+use stunts_engine::editor::Editor;
+use stunts_engine::st_video::StVideo;
+
+use super::nested::NestedVideo;
+
+/// Frame of a video's source shown at a time, kept inside the source
+fn source_frame(video: &StVideo, time: Duration) -> u32 {
+    let frame_rate = video.source_frame_rate.max(1.0);
+    let last_frame = (video.source_duration_ms.max(0) as f64 / 1000.0 * frame_rate) as u32;
+
+    ((time.as_secs_f64() * frame_rate) as u32).min(last_frame.saturating_sub(1))
+}
+
+/// Show the frame of a video's source at a time. The engine's videos only decode forwards,
+/// so going back restarts the source and draws up to the frame.
+pub fn seek_video(video: &mut StVideo, device: &wgpu::Device, queue: &wgpu::Queue, time: Duration) {
+    let frame = source_frame(video, time);
+
+    if frame < video.num_frames_drawn && video.reset_playback().is_err() {
+        return;
+    }
+
+    while video.num_frames_drawn <= frame {
+        if video.draw_video_frame(device, queue).is_err() {
+            break;
+        }
+    }
+}
+
+/// Seek the video items of a sequence to where they are at a time in it.
+/// source_offset_ms is where the sequence's own time starts in its source, for trimmed clips.
+/// Nested copies of videos go to their nested sequence's time.
+pub fn seek_sequence_videos(
+    editor: &mut Editor,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    sequence: &Sequence,
+    time: Duration,
+    source_offset_ms: i32,
+    nested_videos: &[NestedVideo],
+) {
+    let source_ms = time.as_millis() as i64 + source_offset_ms as i64;
+    let source_time = Duration::from_millis(source_ms.max(0) as u64);
+
+    for saved in &sequence.active_video_items {
+        let time = nested_videos
+            .iter()
+            .find(|n| n.video_id == saved.id)
+            .map(|n| n.source_time(source_time))
+            .unwrap_or(source_time);
+
+        if let Some(video) = editor.video_items.iter_mut().find(|v| v.id == saved.id) {
+            seek_video(video, device, queue, time);
+        }
+    }
+}
+
+/// Seek just the nested copies of videos to their nested sequences' time, for a sequence
+/// the engine plays itself
+pub fn seek_nested_videos(
+    editor: &mut Editor,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    nested_videos: &[NestedVideo],
+    time: Duration,
+) {
+    for nested in nested_videos {
+        if let Some(video) = editor
+            .video_items
+            .iter_mut()
+            .find(|v| v.id == nested.video_id)
+        {
+            seek_video(video, device, queue, nested.source_time(time));
+        }
+    }
+}
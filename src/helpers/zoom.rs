@@ -0,0 +1,240 @@
+use std::time::Duration;
+
+use floem::reactive::{RwSignal, SignalGet, SignalUpdate};
+
+use super::clock::{frame_at, frame_time};
+
+pub const MIN_PIXELS_PER_S: f64 = 2.0;
+pub const MAX_PIXELS_PER_S: f64 = 2000.0;
+/// zoom factor of one wheel notch or zoom button press
+pub const ZOOM_STEP: f64 = 1.25;
+/// tick labels are spaced at least this far apart
+const MIN_TICK_SPACING_PX: f64 = 60.0;
+
+/// tick steps in frames, used while a second is wider than the spacing
+const FRAME_STEPS: [u64; 5] = [1, 2, 5, 10, 15];
+/// tick steps in seconds
+const SECOND_STEPS: [u64; 10] = [1, 2, 5, 10, 15, 30, 60, 120, 300, 600];
+
+pub fn clamp_pixels_per_s(pixels_per_s: f64) -> f64 {
+    pixels_per_s.clamp(MIN_PIXELS_PER_S, MAX_PIXELS_PER_S)
+}
+
+/// Zoom by a factor keeping the time at `x` in view coordinates where it is.
+/// Returns the new pixels per second and scroll.
+pub fn zoom_about(x: f64, scroll_x: f64, pixels_per_s: f64, factor: f64) -> (f64, f64) {
+    let new_pixels_per_s = clamp_pixels_per_s(pixels_per_s * factor);
+    let time_s = (x + scroll_x) / pixels_per_s;
+
+    (new_pixels_per_s, (time_s * new_pixels_per_s - x).max(0.0))
+}
+
+/// Zooms in for a wheel turned away from the user, out for towards
+pub fn wheel_zoom_factor(delta_y: f64) -> f64 {
+    if delta_y < 0.0 {
+        ZOOM_STEP
+    } else {
+        1.0 / ZOOM_STEP
+    }
+}
+
+/// Pixels per second that fit a duration into a width
+pub fn fit_pixels_per_s(duration: Duration, width: f64) -> f64 {
+    let duration_s = duration.as_secs_f64();
+
+    if duration_s <= 0.0 {
+        return clamp_pixels_per_s(width);
+    }
+
+    clamp_pixels_per_s(width / duration_s)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Tick {
+    pub time: Duration,
+    pub label: String,
+}
+
+fn tick_label(time: Duration, fps: u32, by_frames: bool) -> String {
+    let frame = frame_at(time, fps);
+    let seconds = frame / fps.max(1) as u64;
+    let frame_in_second = frame % fps.max(1) as u64;
+
+    if by_frames && frame_in_second != 0 {
+        format!("{}f", frame_in_second)
+    } else if seconds >= 60 {
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    } else {
+        format!("{}s", seconds)
+    }
+}
+
+/// Ticks between two times, as dense as the zoom allows. Zoomed in far enough
+/// they fall on frames and are labelled by frame within each second.
+pub fn ticks(pixels_per_s: f64, from: Duration, to: Duration, fps: u32) -> Vec<Tick> {
+    let fps = fps.max(1);
+    let frame_px = pixels_per_s / fps as f64;
+
+    let step_frames = FRAME_STEPS
+        .iter()
+        .copied()
+        .filter(|frames| *frames < fps as u64)
+        .find(|frames| *frames as f64 * frame_px >= MIN_TICK_SPACING_PX)
+        .or_else(|| {
+            SECOND_STEPS
+                .iter()
+                .find(|seconds| **seconds as f64 * pixels_per_s >= MIN_TICK_SPACING_PX)
+                .map(|seconds| seconds * fps as u64)
+        })
+        .unwrap_or(*SECOND_STEPS.last().unwrap() * fps as u64);
+    let by_frames = step_frames < fps as u64;
+
+    let first = frame_at(from, fps).div_ceil(step_frames);
+    let last = frame_at(to, fps) / step_frames;
+
+    (first..=last)
+        .map(|index| {
+            let time = frame_time(index * step_frames, fps);
+
+            Tick {
+                time,
+                label: tick_label(time, fps, by_frames),
+            }
+        })
+        .collect()
+}
+
+/// Zoom and horizontal scroll of a timeline made of views. Positions inside
+/// the timeline are in time times pixels per second, scrolling moves them all.
+#[derive(Clone, Copy, Debug)]
+pub struct TimelineZoom {
+    pub pixels_per_s: RwSignal<f64>,
+    /// pixels the timeline is scrolled left by
+    pub scroll_x: RwSignal<f64>,
+}
+
+impl TimelineZoom {
+    pub fn new(pixels_per_s: RwSignal<f64>) -> Self {
+        Self {
+            pixels_per_s,
+            scroll_x: RwSignal::new(0.0),
+        }
+    }
+
+    /// Zoom keeping the time at `x` in view coordinates under it
+    pub fn zoom_at(&self, x: f64, factor: f64) {
+        let (pixels_per_s, scroll_x) = zoom_about(
+            x,
+            self.scroll_x.get_untracked(),
+            self.pixels_per_s.get_untracked(),
+            factor,
+        );
+
+        self.pixels_per_s.set(pixels_per_s);
+        self.scroll_x.set(scroll_x);
+    }
+
+    pub fn scroll_by(&self, dx: f64, max_scroll_x: f64) {
+        let scroll_x = (self.scroll_x.get_untracked() + dx).clamp(0.0, max_scroll_x.max(0.0));
+
+        self.scroll_x.set(scroll_x);
+    }
+
+    pub fn fit(&self, duration: Duration, width: f64) {
+        self.pixels_per_s.set(fit_pixels_per_s(duration, width));
+        self.scroll_x.set(0.0);
+    }
+
+    /// Ctrl and the wheel zooms to the pointer, the wheel alone scrolls
+    pub fn wheel(&self, x: f64, delta_x: f64, delta_y: f64, zoom: bool, max_scroll_x: f64) {
+        if zoom {
+            self.zoom_at(x, wheel_zoom_factor(delta_y));
+        } else {
+            // vertical wheels scroll sideways too
+            let dx = if delta_x != 0.0 { delta_x } else { delta_y };
+
+            self.scroll_by(dx, max_scroll_x);
+        }
+    }
+
+    /// Times visible in a view of a width
+    pub fn visible_range(&self, width: f64) -> (Duration, Duration) {
+        let pixels_per_s = self.pixels_per_s.get();
+        let scroll_x = self.scroll_x.get();
+
+        (
+            Duration::from_secs_f64(scroll_x / pixels_per_s),
+            Duration::from_secs_f64((scroll_x + width) / pixels_per_s),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(ticks: &[Tick]) -> Vec<&str> {
+        ticks.iter().map(|t| t.label.as_str()).collect()
+    }
+
+    #[test]
+    fn ticks_fall_on_seconds_when_zoomed_out() {
+        let ticks = ticks(100.0, Duration::ZERO, Duration::from_secs(3), 30);
+
+        assert_eq!(labels(&ticks), vec!["0s", "1s", "2s", "3s"]);
+        assert_eq!(ticks[1].time, Duration::from_secs(1));
+    }
+
+    #[test]
+    fn ticks_fall_on_frames_when_zoomed_in() {
+        let ticks = ticks(600.0, Duration::ZERO, Duration::from_secs(1), 30);
+
+        assert_eq!(
+            labels(&ticks),
+            vec!["0s", "5f", "10f", "15f", "20f", "25f", "1s"]
+        );
+        assert_eq!(ticks[1].time, frame_time(5, 30));
+    }
+
+    #[test]
+    fn ticks_label_minutes() {
+        let ticks = ticks(2.0, Duration::ZERO, Duration::from_secs(90), 30);
+
+        assert_eq!(labels(&ticks), vec!["0s", "30s", "1:00", "1:30"]);
+    }
+
+    #[test]
+    fn ticks_start_at_the_first_step_in_range() {
+        let ticks = ticks(
+            100.0,
+            Duration::from_millis(1500),
+            Duration::from_secs(3),
+            30,
+        );
+
+        assert_eq!(labels(&ticks), vec!["2s", "3s"]);
+    }
+
+    #[test]
+    fn zoom_about_keeps_the_time_under_the_pointer() {
+        let (pixels_per_s, scroll_x) = zoom_about(100.0, 50.0, 100.0, 2.0);
+
+        assert_eq!(pixels_per_s, 200.0);
+        assert_eq!((100.0 + scroll_x) / pixels_per_s, 1.5);
+    }
+
+    #[test]
+    fn zoom_about_clamps_the_zoom() {
+        let (pixels_per_s, _) = zoom_about(0.0, 0.0, MAX_PIXELS_PER_S, ZOOM_STEP);
+
+        assert_eq!(pixels_per_s, MAX_PIXELS_PER_S);
+    }
+
+    #[test]
+    fn zoom_about_never_scrolls_before_the_start() {
+        let (pixels_per_s, scroll_x) = zoom_about(100.0, 0.0, 100.0, 0.5);
+
+        assert_eq!(pixels_per_s, 50.0);
+        assert_eq!(scroll_x, 0.0);
+    }
+}
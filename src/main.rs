@@ -1,17 +1,26 @@
 use std::borrow::{Borrow, BorrowMut};
+use std::cell::RefCell;
 use std::rc::{Rc, Weak};
 use std::str::FromStr;
 use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
 
 use bytemuck::Contiguous;
 use cgmath::Vector4;
-use editor_state::{EditorState, ObjectEdit, RecordState};
+use editor_state::{EditorState, RecordState, StateEdit};
 use floem::common::{nav_button, option_button, rgb_to_wgpu, small_button};
 use floem::kurbo::Size;
+use floem::reactive::{SignalGet, SignalUpdate, SignalWith};
 use floem::window::WindowConfig;
 use floem_renderer::gpu_resources::{self, GpuResources};
 use floem_winit::dpi::{LogicalSize, PhysicalSize};
 use floem_winit::event::{ElementState, KeyEvent, Modifiers, MouseButton, MouseScrollDelta};
+use helpers::clock::{sync_engine, ClockMode};
+use helpers::nested::nested_videos;
+use helpers::onion::{add_onion_ghosts, build_onion_skin, onion_skin_key, OnionSkin};
+use helpers::recording::{rendered_position, MotionRecorder};
+use helpers::scrub::{begin_scrub, end_scrub, scrub_key, ScrubCache};
+use helpers::video::seek_nested_videos;
 // use helpers::utilities::load_ground_truth_state;
 use stunts_engine::camera::{Camera, CameraBinding};
 use stunts_engine::dot::{draw_dot, RingDot};
@@ -70,6 +79,33 @@ pub fn render_ray_intersection(
     // }
 }
 
+/// Limit drawing to part of the canvas width, placed on the surface by the camera's pan
+/// and zoom, or to the whole surface without one. False when none of it is on the surface.
+fn set_canvas_scissor(
+    render_pass: &mut wgpu::RenderPass,
+    camera: &Camera,
+    visible_x: Option<[f64; 2]>,
+) -> bool {
+    let (width, height) = (camera.window_size.width, camera.window_size.height);
+
+    let [left, right] = match visible_x {
+        Some(range) => range.map(|x| {
+            let surface_x = (x as f32 - camera.position.x) * camera.zoom;
+
+            (surface_x.round().max(0.0) as u32).min(width)
+        }),
+        None => [0, width],
+    };
+
+    if right <= left {
+        return false;
+    }
+
+    render_pass.set_scissor_rect(left, 0, right - left, height);
+
+    true
+}
+
 pub fn get_sensor_editor(handle: &EngineHandle) -> Option<Arc<Mutex<Editor>>> {
     handle.user_editor.as_ref().and_then(|e| {
         // let guard = e.lock().ok()?;
@@ -94,7 +130,15 @@ type RenderCallback<'a> = dyn for<'b> Fn(
         Option<Arc<wgpu::TextureView>>,
     ) + 'a;
 
-fn create_render_callback<'a>() -> Box<RenderCallback<'a>> {
+fn create_render_callback<'a>(editor_state: Arc<Mutex<EditorState>>) -> Box<RenderCallback<'a>> {
+    // ghosts and frame ticks of the selected object, kept until their inputs change
+    let onion_skin: RefCell<Option<OnionSkin>> = RefCell::new(None);
+    // keyframe playhead the paused preview was last posed at
+    // and the onion skin ghosts it was posed with
+    let last_scrub: RefCell<Option<(Duration, Option<u64>)>> = RefCell::new(None);
+    // what the paused preview is posed with, kept until the project changes
+    let scrub_cache: RefCell<Option<ScrubCache>> = RefCell::new(None);
+
     Box::new(
         move |mut encoder: wgpu::CommandEncoder,
               frame: wgpu::SurfaceTexture,
@@ -172,9 +216,251 @@ fn create_render_callback<'a>() -> Box<RenderCallback<'a>> {
 
                 let camera = editor.camera.expect("Couldn't get camera");
 
+                // playback advances by frames of the project frame rate,
+                // whole-timeline playback hands the engine the clips playing at each point as one sequence
+                let mut wipe_clips = Vec::new();
+                if let Ok(mut state) = editor_state.try_lock() {
+                    let timeline_playhead = state.timeline_playhead;
+                    let mode = ClockMode::from_every_frame(state.play_every_frame.get_untracked());
+
+                    if let Some(playback) = state.timeline_playback.as_mut() {
+                        // the playhead was moved while playing
+                        let playhead = timeline_playhead.get_untracked();
+                        if playhead != playback.reported {
+                            playback.seek(playhead);
+                        }
+
+                        playback.set_mode(mode);
+
+                        let position = playback.step(&mut editor);
+                        playback.sync_videos(
+                            &mut editor,
+                            &gpu_resources.device,
+                            &gpu_resources.queue,
+                        );
+                        let finished = playback.finished();
+                        wipe_clips = playback.wipe_clips();
+
+                        if finished {
+                            if let Some(playback) = state.timeline_playback.take() {
+                                playback.restore_layers(&mut editor);
+                            }
+                            editor.is_playing = false;
+                            editor.start_playing_time = None;
+                        }
+
+                        drop(state);
+
+                        timeline_playhead.set(position);
+                    } else if editor.is_playing {
+                        // a single sequence plays on its frame clock too
+                        if let Some(clock) = state.sequence_clock.as_mut() {
+                            clock.set_mode(mode);
+                            let time = clock.tick();
+
+                            sync_engine(&mut editor, time);
+
+                            // the engine plays nested videos from their start, they're seeked instead
+                            let nested = match (
+                                state.record_state.saved_state.as_ref(),
+                                editor.current_sequence_data.as_ref(),
+                            ) {
+                                (Some(saved_state), Some(sequence)) => {
+                                    nested_videos(saved_state, &sequence.id)
+                                }
+                                _ => Vec::new(),
+                            };
+                            seek_nested_videos(
+                                &mut editor,
+                                &gpu_resources.device,
+                                &gpu_resources.queue,
+                                &nested,
+                                time,
+                            );
+                        }
+                    }
+                }
+
+                // moving the playhead while paused poses the preview at its time
+                let mut scrubbing = false;
+                if !editor.is_playing {
+                    if let Ok(mut state) = editor_state.try_lock() {
+                        let playhead = state.keyframe_playhead.get_untracked();
+                        let settings = state.onion_skin;
+                        let signal = state.sequence_data_signal;
+                        let selected = state.selected_object_id().map(|id| id.to_string());
+
+                        // ghosts are posed with the objects, changing them poses the preview again
+                        let onion = match (settings.enabled.get_untracked(), signal, &selected) {
+                            (true, Some(signal), Some(object_id)) => {
+                                signal.with_untracked(|sequence| {
+                                    sequence
+                                        .polygon_motion_paths
+                                        .iter()
+                                        .find(|a| a.polygon_id == *object_id)
+                                        .map(|animation| {
+                                            onion_skin_key(
+                                                animation,
+                                                &state.hold_keyframes.get_untracked(),
+                                                settings.step.get_untracked(),
+                                                settings.before.get_untracked(),
+                                                settings.after.get_untracked(),
+                                                false,
+                                            )
+                                        })
+                                })
+                            }
+                            _ => None,
+                        };
+                        let scrub = Some((playhead, onion));
+
+                        if state.timeline_playback.is_none() && *last_scrub.borrow() != scrub {
+                            let ghosted = onion.is_some()
+                                || matches!(*last_scrub.borrow(), Some((_, Some(_))));
+
+                            let sequences = match (signal, state.record_state.saved_state.as_ref())
+                            {
+                                (Some(signal), Some(saved_state)) => {
+                                    let sequence = signal.get_untracked();
+                                    let key = scrub_key(saved_state, &sequence);
+
+                                    let mut cache = scrub_cache.borrow_mut();
+                                    if cache.as_ref().map(|c| c.key != key).unwrap_or(true) {
+                                        *cache = Some(ScrubCache::new(saved_state, &sequence, key));
+                                    }
+                                    let cache = cache.as_ref().expect("Couldn't get scrub cache");
+
+                                    let mut prepared = cache.prepared.clone();
+
+                                    if let (Some(_), Some(object_id)) = (onion, &selected) {
+                                        add_onion_ghosts(
+                                            saved_state,
+                                            &cache.placed,
+                                            &mut prepared,
+                                            object_id,
+                                            &settings,
+                                            playhead,
+                                        );
+                                    }
+
+                                    // stand-ins are only staged again when the project, the ghosts
+                                    // or someone else's staging changed them, scrubbing just poses
+                                    let restage = ghosted
+                                        || cache.staged != Some(state.staged_looks.changes());
+
+                                    Some((
+                                        restage.then(|| cache.source.clone()),
+                                        prepared,
+                                        cache.nested.clone(),
+                                    ))
+                                }
+                                _ => None,
+                            };
+
+                            if let Some((Some(source), sequence, _)) = &sequences {
+                                state.staged_looks.stage(
+                                    &mut editor,
+                                    &[source.clone()],
+                                    &[sequence.clone()],
+                                );
+
+                                if let Some(cache) = scrub_cache.borrow_mut().as_mut() {
+                                    cache.staged = Some(state.staged_looks.changes());
+                                }
+                            }
+
+                            drop(state);
+
+                            if let Some((_, sequence, nested)) = sequences {
+                                begin_scrub(
+                                    &mut editor,
+                                    &gpu_resources.device,
+                                    &gpu_resources.queue,
+                                    sequence,
+                                    playhead,
+                                    &nested,
+                                );
+                                scrubbing = true;
+                            }
+
+                            *last_scrub.borrow_mut() = scrub;
+                        }
+                    }
+                }
+
                 editor.step_video_animations(&camera, None);
                 editor.step_motion_path_animations(&camera, None);
 
+                if scrubbing {
+                    end_scrub(&mut editor);
+                }
+
+                if editor.is_playing {
+                    onion_skin.borrow_mut().take();
+                } else if let Ok(state) = editor_state.try_lock() {
+                    let settings = state.onion_skin;
+                    let selected = match (
+                        settings.enabled.get(),
+                        state.selected_object_id(),
+                        state.sequence_data_signal,
+                    ) {
+                        (true, Some(object_id), Some(sequence_signal)) => sequence_signal
+                            .get()
+                            .polygon_motion_paths
+                            .iter()
+                            .find(|a| a.polygon_id == object_id.to_string())
+                            .cloned(),
+                        _ => None,
+                    };
+
+                    match selected {
+                        Some(animation) => {
+                            let step = settings.step.get();
+                            let before = settings.before.get();
+                            let after = settings.after.get();
+                            let frame_ticks = settings.frame_ticks.get();
+                            let frame_rate = state.frame_rate.get();
+
+                            let hold_keyframes = state.hold_keyframes.get();
+                            let key = onion_skin_key(
+                                &animation,
+                                &hold_keyframes,
+                                step,
+                                before,
+                                after,
+                                frame_ticks,
+                            );
+                            let stale = onion_skin
+                                .borrow()
+                                .as_ref()
+                                .map(|skin| skin.key != key)
+                                .unwrap_or(true);
+
+                            if let (true, Some(model_layout), Some(group_layout)) = (
+                                stale,
+                                editor.model_bind_group_layout.as_ref(),
+                                editor.group_bind_group_layout.as_ref(),
+                            ) {
+                                *onion_skin.borrow_mut() = Some(build_onion_skin(
+                                    &gpu_resources.device,
+                                    &gpu_resources.queue,
+                                    model_layout,
+                                    group_layout,
+                                    &camera,
+                                    &animation,
+                                    &hold_keyframes,
+                                    key,
+                                    frame_ticks,
+                                    frame_rate,
+                                ));
+                            }
+                        }
+                        None => {
+                            onion_skin.borrow_mut().take();
+                        }
+                    }
+                }
+
                 let camera_binding = editor
                     .camera_binding
                     .as_ref()
@@ -244,9 +530,37 @@ fn create_render_callback<'a>() -> Box<RenderCallback<'a>> {
                     }
                 }
 
+                // frame ticks sit under the objects, ghosts are objects a layer behind theirs
+                if let Some(skin) = onion_skin.borrow().as_ref() {
+                    for tick in &skin.ticks {
+                        render_pass.set_bind_group(1, &tick.bind_group, &[]);
+                        render_pass.set_bind_group(3, &tick.group_bind_group, &[]);
+                        render_pass.set_vertex_buffer(0, tick.vertex_buffer.slice(..));
+                        render_pass.set_index_buffer(
+                            tick.index_buffer.slice(..),
+                            wgpu::IndexFormat::Uint32,
+                        );
+                        render_pass.draw_indexed(0..tick.indices.len() as u32, 0, 0..1);
+                    }
+                }
+
+                // objects partway through a wipe only draw their side of its edge
+                let clip_of = |id: &str| {
+                    wipe_clips
+                        .iter()
+                        .find(|(clip_id, _)| clip_id == id)
+                        .map(|(_, visible_x)| *visible_x)
+                };
+
                 // draw polygons
                 for (poly_index, polygon) in editor.polygons.iter().enumerate() {
-                    if !polygon.hidden {
+                    if !polygon.hidden
+                        && set_canvas_scissor(
+                            &mut render_pass,
+                            &camera,
+                            clip_of(&polygon.id.to_string()),
+                        )
+                    {
                         // uniform buffers are pricier, no reason to over-update when idle
                         // also need to remember to update uniform buffers after changes like scale, rotation, position
                         if let Some(dragging_id) = editor.dragging_polygon {
@@ -276,7 +590,13 @@ fn create_render_callback<'a>() -> Box<RenderCallback<'a>> {
 
                 // draw text items
                 for (text_index, text_item) in editor.text_items.iter().enumerate() {
-                    if !text_item.hidden {
+                    if !text_item.hidden
+                        && set_canvas_scissor(
+                            &mut render_pass,
+                            &camera,
+                            clip_of(&text_item.id.to_string()),
+                        )
+                    {
                         if !text_item.background_polygon.hidden {
                             // uniform buffers are pricier, no reason to over-update when idle
                             // also need to remember to update uniform buffers after changes like scale, rotation, position
@@ -354,7 +674,9 @@ fn create_render_callback<'a>() -> Box<RenderCallback<'a>> {
 
                 // draw image items
                 for (image_index, st_image) in editor.image_items.iter().enumerate() {
-                    if !st_image.hidden {
+                    if !st_image.hidden
+                        && set_canvas_scissor(&mut render_pass, &camera, clip_of(&st_image.id))
+                    {
                         // uniform buffers are pricier, no reason to over-update when idle
                         if let Some(dragging_id) = editor.dragging_image {
                             if dragging_id.to_string() == st_image.id {
@@ -383,7 +705,9 @@ fn create_render_callback<'a>() -> Box<RenderCallback<'a>> {
 
                 // draw video items
                 for (video_index, st_video) in editor.video_items.iter().enumerate() {
-                    if !st_video.hidden {
+                    if !st_video.hidden
+                        && set_canvas_scissor(&mut render_pass, &camera, clip_of(&st_video.id))
+                    {
                         // uniform buffers are pricier, no reason to over-update when idle
                         if let Some(dragging_id) = editor.dragging_video {
                             if dragging_id.to_string() == st_video.id {
@@ -410,6 +734,8 @@ fn create_render_callback<'a>() -> Box<RenderCallback<'a>> {
                     }
                 }
 
+                set_canvas_scissor(&mut render_pass, &camera, None);
+
                 if let Some(dot) = &editor.cursor_dot {
                     dot.transform
                         .update_uniform_buffer(&gpu_resources.queue, &camera.window_size);
@@ -433,6 +759,7 @@ fn create_render_callback<'a>() -> Box<RenderCallback<'a>> {
 }
 
 fn handle_cursor_moved(
+    editor_state: Arc<Mutex<EditorState>>,
     editor: std::sync::Arc<Mutex<Editor>>,
     gpu_resources: std::sync::Arc<GpuResources>,
     // window_size: WindowSize,
@@ -459,6 +786,31 @@ fn handle_cursor_moved(
                 positionY as f32,
             );
             // TODO: need callback for when cursor is done moving, then add translation to undo stack
+
+            // sequence time of this pointer position, for motion recording
+            let playing_time = if editor.is_playing {
+                editor.start_playing_time.map(|start| start.elapsed())
+            } else {
+                None
+            };
+            // canvas position after the engine applies camera pan and zoom
+            let pointer = Point {
+                x: editor.last_top_left.x,
+                y: editor.last_top_left.y,
+            };
+
+            drop(viewport);
+            drop(editor);
+
+            if let Some(playing_time) = playing_time {
+                let mut editor_state = editor_state.lock().unwrap();
+
+                let frame_rate = editor_state.frame_rate.get_untracked();
+
+                if let Some(recorder) = editor_state.motion_recorder.as_mut() {
+                    recorder.add_sample(playing_time, pointer, frame_rate);
+                }
+            }
         },
     ))
 }
@@ -469,7 +821,7 @@ fn handle_mouse_input(
     gpu_resources: std::sync::Arc<GpuResources>,
     // window_size: WindowSize,
     viewport: std::sync::Arc<Mutex<Viewport>>,
-    record: Arc<Mutex<Record<ObjectEdit>>>,
+    record: Arc<Mutex<Record<StateEdit>>>,
 ) -> Option<Box<dyn Fn(MouseButton, ElementState)>> {
     Some(Box::new(move |button, state| {
         let mut editor_orig = Arc::clone(&editor);
@@ -490,8 +842,50 @@ fn handle_mouse_input(
                 ElementState::Released => editor.handle_mouse_up(),
             };
 
+            let is_playing = editor.is_playing;
+            let current_sequence_id = editor.current_sequence_data.as_ref().map(|s| s.id.clone());
+
             drop(editor);
 
+            // holding the mouse on an object during playback records its motion
+            let mut state_guard = editor_state.lock().unwrap();
+
+            match state {
+                ElementState::Pressed => {
+                    if is_playing && state_guard.record_motion.get() {
+                        if let Some(object_id) = state_guard.selected_object_id() {
+                            let editor = editor_orig.lock().unwrap();
+                            let pointer = Point {
+                                x: editor.last_top_left.x,
+                                y: editor.last_top_left.y,
+                            };
+                            let object_position =
+                                rendered_position(&editor, &object_id.to_string());
+
+                            drop(editor);
+
+                            // keep the object where it was grabbed rather than snapping it to the pointer
+                            if let Some(object_position) = object_position {
+                                let grab_offset = Point {
+                                    x: pointer.x - object_position.x,
+                                    y: pointer.y - object_position.y,
+                                };
+
+                                state_guard.motion_recorder =
+                                    Some(MotionRecorder::new(object_id.to_string(), grab_offset));
+                            }
+                        }
+                    }
+                }
+                ElementState::Released => {
+                    if let Some(sequence_id) = current_sequence_id {
+                        state_guard.finish_motion_recording(sequence_id);
+                    }
+                }
+            }
+
+            drop(state_guard);
+
             // if (edit_config.is_some()) {
             //     let edit_config = edit_config.expect("Couldn't get polygon edit config");
 
@@ -737,6 +1131,8 @@ async fn main() {
     let state_3 = Arc::clone(&editor_state);
     let state_4 = Arc::clone(&editor_state);
     let state_5 = Arc::clone(&editor_state);
+    let state_6 = Arc::clone(&editor_state);
+    let state_7 = Arc::clone(&editor_state);
 
     // // load saved state (no projects as Ground Truth)
     // println!("Loading saved state...");
@@ -774,7 +1170,7 @@ async fn main() {
             .expect("Couldn't get window handle");
 
         // Create and set the render callback
-        let render_callback = create_render_callback();
+        let render_callback = create_render_callback(state_7);
 
         // window_handle.set_render_callback(render_callback);
         window_handle.set_encode_callback(render_callback);
@@ -1109,6 +1505,7 @@ async fn main() {
                 editor.cursor_dot = Some(cursor_ring_dot);
 
                 window_handle.handle_cursor_moved = handle_cursor_moved(
+                    state_6.clone(),
                     cloned2.clone(),
                     gpu_resources.clone(),
                     cloned_viewport.clone(),
@@ -54,8 +54,11 @@ use floem::{Application, CustomRenderCallback};
 use floem::{GpuHelper, View, WindowHandle};
 
 use crate::editor_state::EditorState;
+use crate::helpers::keyframes::auto_key_object;
+use crate::helpers::nested::nest_sequences;
 use crate::helpers::saved_state::SavedState;
 use crate::helpers::utilities::save_saved_state_raw;
+use crate::helpers::zoom::{fit_pixels_per_s, TimelineZoom};
 use crate::views::keyframe_panel::update_keyframe;
 use stunts_engine::animations::{
     AnimationData, AnimationProperty, BackgroundFill, EasingType, KeyframeValue, ObjectType,
@@ -64,8 +67,11 @@ use stunts_engine::animations::{
 
 use super::aside::tab_interface;
 use super::editor_settings::editor_settings;
-use super::inputs::{play_sequence_button, styled_input};
-use super::keyframe_panel::keyframe_properties_view;
+use super::inputs::{
+    keying_toggles, onion_skin_toggles, play_sequence_button, scrub_controls, snapping_toggles,
+    styled_input,
+};
+use super::keyframe_panel::{color_keyframe_view, keyframe_properties_view, shape_keyframe_view};
 use super::keyframe_timeline::{create_timeline, TimelineConfig, TimelineState};
 use super::object_timeline::build_object_timeline;
 use super::project_browser::project_browser;
@@ -540,11 +546,23 @@ pub fn project_view(
 
     let animation_data: RwSignal<Option<AnimationData>> = create_rw_signal(None);
     let selected_keyframes: RwSignal<Vec<UIKeyframe>> = create_rw_signal(Vec::new());
+    let selected_track_keyframe: RwSignal<Option<String>> = create_rw_signal(None);
 
     let pixels_per_s = create_rw_signal(38.0);
     let timeline_width = create_rw_signal(1200.0);
     let viewport_width = create_rw_signal(1600.0);
 
+    editor_state.lock().unwrap().sequence_data_signal = Some(selected_sequence_data);
+    let keyframe_playhead = editor_state.lock().unwrap().keyframe_playhead;
+    let snap_settings = editor_state.lock().unwrap().snap_settings;
+    let auto_key = editor_state.lock().unwrap().auto_key;
+    let record_motion = editor_state.lock().unwrap().record_motion;
+    let color_tracks = editor_state.lock().unwrap().color_tracks;
+    let shape_tracks = editor_state.lock().unwrap().shape_tracks;
+    let hold_keyframes = editor_state.lock().unwrap().hold_keyframes;
+    let simplify_recording = editor_state.lock().unwrap().simplify_recording;
+    let onion_skin = editor_state.lock().unwrap().onion_skin;
+
     let video_selected_ref = Arc::new(Mutex::new(video_selected));
     let selected_video_id_ref = Arc::new(Mutex::new(selected_video_id));
     let selected_video_data_ref = Arc::new(Mutex::new(selected_video_data));
@@ -942,7 +960,47 @@ pub fn project_view(
 
                 let mut editor_state = editor_state.lock().unwrap();
 
-                if let Some(current_keyframe) = current_keyframe.get_mut(0) {
+                // recorded paths are written when the recording finishes
+                if editor_state.motion_recorder.is_some() {
+                    return (selected_sequence_data.get(), selected_keyframes.get());
+                }
+
+                if editor_state.auto_key.get() {
+                    let playhead = editor_state.keyframe_playhead.get();
+                    let frame_rate = editor_state.frame_rate.get_untracked();
+                    let mut current_sequence = selected_sequence_data.get();
+
+                    let keyed_animation = auto_key_object(
+                        &mut current_sequence,
+                        &object_id.to_string(),
+                        "position",
+                        playhead,
+                        KeyframeValue::Position([point.x as i32, point.y as i32]),
+                        frame_rate,
+                    );
+
+                    if let Some(keyed_animation) = keyed_animation {
+                        if current_animation_data.id == keyed_animation.id {
+                            animation_data.set(Some(keyed_animation));
+                        }
+
+                        selected_sequence_data.set(current_sequence.clone());
+
+                        let last_saved_state = editor_state
+                            .record_state
+                            .saved_state
+                            .as_mut()
+                            .expect("Couldn't get Saved State");
+
+                        last_saved_state.sequences.iter_mut().for_each(|s| {
+                            if s.id == selected_sequence_id.get() {
+                                *s = current_sequence.clone();
+                            }
+                        });
+
+                        save_saved_state_raw(last_saved_state.clone());
+                    }
+                } else if let Some(current_keyframe) = current_keyframe.get_mut(0) {
                     // let mut current_keyframe = current_keyframe.get_mut(0).expect("Couldn't get Selected Keyframe");
                     let mut current_sequence = selected_sequence_data.get();
                     // let current_polygon = selected_polygon_data.read();
@@ -1433,7 +1491,8 @@ pub fn project_view(
                 .saved_state
                 .as_ref()
                 .expect("Couldn't get saved state");
-            let cloned_sequences = saved_state.sequences.clone();
+            // with copies of the objects of nested sequences, which play as the parent's own
+            let cloned_sequences = nest_sequences(saved_state);
 
             drop(editor_state);
 
@@ -1483,16 +1542,41 @@ pub fn project_view(
         }
     });
 
-    create_effect(move |_| {
+    create_effect(move |last_fit: Option<(String, i32, f64)>| {
         // println!("Viewport effect...");
         let window_width = viewport_width.get();
-        let total_s = selected_sequence_data.get().duration_ms / 1000; // track separate from main effect
+        let sequence = selected_sequence_data.get(); // track separate from main effect
 
         let new_timeline_width = (window_width as f64 - CANVAS_HORIZ_OFFSET as f64 - 200.0);
-        let p_per_s = new_timeline_width / total_s as f64;
+        let fit = (sequence.id, sequence.duration_ms, new_timeline_width);
+
+        // other edits to the sequence keep the user's zoom
+        if last_fit.as_ref() != Some(&fit) {
+            pixels_per_s.set(fit_pixels_per_s(
+                Duration::from_millis(fit.1.max(0) as u64),
+                new_timeline_width,
+            ));
+            timeline_width.set(new_timeline_width);
+        }
 
-        pixels_per_s.set(p_per_s);
-        timeline_width.set(new_timeline_width);
+        fit
+    });
+
+    create_effect(move |_| {
+        // keep the keyframe timeline in sync when tools rewrite the sequence
+        let sequence = selected_sequence_data.get();
+
+        if let Some(current_animation) = animation_data.get_untracked() {
+            if let Some(updated_animation) = sequence
+                .polygon_motion_paths
+                .iter()
+                .find(|pm| pm.id == current_animation.id)
+            {
+                if *updated_animation != current_animation {
+                    animation_data.set(Some(updated_animation.clone()));
+                }
+            }
+        }
     });
 
     container((
@@ -1530,7 +1614,16 @@ pub fn project_view(
                             selected_polygon_id,
                         ),
                         v_stack((
-                            play_sequence_button(editor_cloned11.clone(), selected_sequence_data),
+                            h_stack((
+                                play_sequence_button(
+                                    state_cloned10.clone(),
+                                    editor_cloned11.clone(),
+                                    selected_sequence_data,
+                                ),
+                                scrub_controls(state_cloned10.clone(), selected_sequence_data),
+                                snapping_toggles(snap_settings),
+                                keying_toggles(auto_key, record_motion, simplify_recording),
+                            )),
                             build_object_timeline(
                                 editor_cloned10.clone(),
                                 state_cloned10.clone(),
@@ -1558,6 +1651,8 @@ pub fn project_view(
                 if object_selected_real {
                     let state_cloned3 = state_cloned3.clone();
                     let state_cloned4 = state_cloned4.clone();
+                    let state_cloned12 = state_cloned4.clone();
+                    let state_cloned13 = state_cloned4.clone();
                     let editor_cloned4 = editor_cloned4.clone();
                     let state_cloned6 = state_cloned6.clone();
                     let state_cloned7 = state_cloned7.clone();
@@ -1583,9 +1678,8 @@ pub fn project_view(
                     let viewport_cloned6 = viewport_cloned6.clone();
 
                     let state = TimelineState {
-                        current_time: Duration::from_secs_f64(0.0),
-                        zoom_level: 1.0,
-                        scroll_offset: 0.0,
+                        current_time: keyframe_playhead,
+                        zoom: TimelineZoom::new(create_rw_signal(pixels_per_s.get())),
                         // selected_keyframes: Vec::new(),
                         property_expansions: im::HashMap::from_iter([
                             ("position".to_string(), true),
@@ -1598,6 +1692,8 @@ pub fn project_view(
                         hover_position: RwSignal::new(None),
                         hover_property: RwSignal::new(None),
                         selected_keyframes,
+                        selected_track_keyframe,
+                        snap_indicator: None,
                     };
 
                     let config = TimelineConfig {
@@ -1618,12 +1714,19 @@ pub fn project_view(
                         state,
                         config,
                         animation_data,
+                        color_tracks,
+                        shape_tracks,
+                        hold_keyframes,
                         selected_sequence_data,
                     );
 
                     h_stack((
                         dyn_container(
-                            move || polygon_selected.get() && selected_keyframes.get().len() == 0,
+                            move || {
+                                polygon_selected.get()
+                                    && selected_keyframes.get().len() == 0
+                                    && selected_track_keyframe.get().is_none()
+                            },
                             move |polygon_selected_real| {
                                 if polygon_selected_real {
                                     let state_cloned5 = state_cloned6.clone();
@@ -1655,7 +1758,11 @@ pub fn project_view(
                             },
                         ),
                         dyn_container(
-                            move || text_selected.get() && selected_keyframes.get().len() == 0,
+                            move || {
+                                text_selected.get()
+                                    && selected_keyframes.get().len() == 0
+                                    && selected_track_keyframe.get().is_none()
+                            },
                             move |text_selected_real| {
                                 if text_selected_real {
                                     let state_cloned8 = state_cloned8.clone();
@@ -1687,7 +1794,11 @@ pub fn project_view(
                             },
                         ),
                         dyn_container(
-                            move || image_selected.get() && selected_keyframes.get().len() == 0,
+                            move || {
+                                image_selected.get()
+                                    && selected_keyframes.get().len() == 0
+                                    && selected_track_keyframe.get().is_none()
+                            },
                             move |image_selected_real| {
                                 if image_selected_real {
                                     let state_cloned9 = state_cloned9.clone();
@@ -1719,7 +1830,11 @@ pub fn project_view(
                             },
                         ),
                         dyn_container(
-                            move || video_selected.get() && selected_keyframes.get().len() == 0,
+                            move || {
+                                video_selected.get()
+                                    && selected_keyframes.get().len() == 0
+                                    && selected_track_keyframe.get().is_none()
+                            },
                             move |video_selected_real| {
                                 if video_selected_real {
                                     let state_cloned11 = state_cloned11.clone();
@@ -1797,8 +1912,50 @@ pub fn project_view(
                                 }
                             },
                         ),
+                        dyn_container(
+                            move || selected_track_keyframe.get(),
+                            move |selected_track_keyframe_real| {
+                                let keyframe_id = match selected_track_keyframe_real {
+                                    Some(keyframe_id) => keyframe_id,
+                                    None => return empty().into_any(),
+                                };
+
+                                let is_color = color_tracks
+                                    .get_untracked()
+                                    .iter()
+                                    .any(|t| t.keyframes.iter().any(|k| k.id == keyframe_id));
+
+                                if is_color {
+                                    color_keyframe_view(
+                                        state_cloned13.clone(),
+                                        color_tracks,
+                                        selected_track_keyframe,
+                                        keyframe_id,
+                                    )
+                                    .into_any()
+                                } else {
+                                    shape_keyframe_view(
+                                        state_cloned13.clone(),
+                                        shape_tracks,
+                                        selected_track_keyframe,
+                                        keyframe_id,
+                                    )
+                                    .into_any()
+                                }
+                            },
+                        ),
                         v_stack((
-                            play_sequence_button(editor_cloned4, selected_sequence_data),
+                            h_stack((
+                                play_sequence_button(
+                                    state_cloned12.clone(),
+                                    editor_cloned4,
+                                    selected_sequence_data,
+                                ),
+                                scrub_controls(state_cloned12, selected_sequence_data),
+                                snapping_toggles(snap_settings),
+                                keying_toggles(auto_key, record_motion, simplify_recording),
+                                onion_skin_toggles(onion_skin),
+                            )),
                             keyframe_timeline,
                         ))
                         .style(|s| s.margin_top(425.0)),
@@ -11,7 +11,7 @@ use floem::{
     event::EventListener,
     reactive::create_signal,
     style::Style,
-    views::{button, h_stack, label, v_stack},
+    views::{button, h_stack, label, v_stack, Checkbox},
     View,
 };
 use stunts_engine::animations::Sequence;
@@ -21,6 +21,10 @@ use stunts_engine::timelines::SavedTimelineStateConfig;
 use tokio::sync::mpsc;
 
 use crate::editor_state::EditorState;
+use crate::helpers::clock::project_frame_rate;
+use crate::helpers::markers::{timeline_chapters, write_chapters};
+use crate::helpers::playback::prepare_timeline;
+use crate::helpers::timeline::TimelineModel;
 use crate::helpers::utilities::get_exports_dir;
 
 use std::thread;
@@ -35,6 +39,7 @@ pub enum ExportCommand {
         video_width: u32,
         video_height: u32,
         total_duration_s: f64,
+        frame_rate: u32,
         progress_tx: mpsc::UnboundedSender<ExportProgress>,
     },
     Stop,
@@ -61,6 +66,7 @@ pub fn spawn_export_thread() -> mpsc::Sender<ExportCommand> {
                         video_width,
                         video_height,
                         total_duration_s,
+                        frame_rate,
                         progress_tx,
                     } => {
                         println!("ExportCommand::StartExport received...");
@@ -70,6 +76,7 @@ pub fn spawn_export_thread() -> mpsc::Sender<ExportCommand> {
 
                         println!("Exporter running...");
 
+                        // rendered at the project frame rate, on the same frames preview shows
                         match exporter
                             .run(
                                 window_size,
@@ -78,6 +85,7 @@ pub fn spawn_export_thread() -> mpsc::Sender<ExportCommand> {
                                 video_width,
                                 video_height,
                                 total_duration_s,
+                                frame_rate,
                                 progress_tx.clone(),
                             )
                             .await
@@ -109,6 +117,10 @@ pub fn export_widget(
     let progress = create_signal_from_tokio_channel(progress_rx);
     let is_exporting = create_rw_signal(false);
     let progress_text = create_rw_signal(String::from("Ready to export"));
+    // markers go in a chapters file next to the video, it isn't muxed in
+    let write_chapters_file = create_rw_signal(true);
+    // chapters are written before the video, kept to report once the export is done
+    let chapters_error = create_rw_signal(None::<String>);
 
     // Create the export thread and keep its sender
     let export_thread_tx = create_rw_signal(spawn_export_thread());
@@ -120,7 +132,13 @@ pub fn export_widget(
                     progress_text.set(format!("Exporting: {:.1}%", percent));
                 }
                 ExportProgress::Complete(output_path) => {
-                    progress_text.set("Export complete!".to_string());
+                    match chapters_error.get_untracked() {
+                        Some(e) => progress_text.set(format!(
+                            "Export complete, but couldn't write chapters file: {}",
+                            e
+                        )),
+                        None => progress_text.set("Export complete!".to_string()),
+                    }
                     is_exporting.set(false);
 
                     // Open the output path in the file browser (on Windows)
@@ -147,6 +165,7 @@ pub fn export_widget(
                 is_exporting.set(true);
                 let progress_tx = progress_tx.clone();
                 progress_text.set("Starting export...".to_string());
+                chapters_error.set(None);
 
                 println!("Starting export...");
 
@@ -170,37 +189,35 @@ pub fn export_widget(
                     .expect("Couldn't get Saved State")
                     .clone();
 
-                let sequences = new_state.sequences.clone();
-
                 // let saved_timeline_state_config =
                 //     sequence_timeline.get().expect("Couldn't get a timeline");
 
                 if let Some(saved_timeline_state_config) = sequence_timeline.get() {
-                    // get total duration of sequences that are in both the timleine and the saved state
-                    let matching_sequences = sequences
-                        .iter()
-                        .filter(|s| {
-                            saved_timeline_state_config
-                                .timeline_sequences
-                                .iter()
-                                .any(|ts| ts.sequence_id == s.id)
-                        })
-                        .collect::<Vec<&Sequence>>();
-
-                    // let total_duration_s: f64 = saved_timeline_state_config
-                    //     .clone()
-                    //     .timeline_sequences
-                    //     .iter()
-                    //     .map(|s| s.duration_ms as f64 / 1000.0)
-                    //     .sum();
-
-                    let total_duration_s = matching_sequences
-                        .iter()
-                        .map(|s| s.duration_ms as f64 / 1000.0)
-                        .sum();
-
-                    let filename =
-                        format!("export_{}.mp4", Local::now().format("%Y-%m-%d_%H-%M-%S"));
+                    // track order, mutes and transitions are kept app-side,
+                    // the exporter can't clip so wipes are baked with a soft edge
+                    let (sequences, saved_timeline_state_config, _) =
+                        prepare_timeline(&new_state, &saved_timeline_state_config, false);
+
+                    // gaps count towards the length, overlaps don't
+                    let model = TimelineModel::new(&sequences, &saved_timeline_state_config);
+                    let total_duration_s = model.duration_s();
+                    let frame_rate = project_frame_rate(&new_state);
+
+                    let export_name =
+                        format!("export_{}", Local::now().format("%Y-%m-%d_%H-%M-%S"));
+                    let filename = format!("{}.mp4", export_name);
+
+                    // timeline markers go alongside in ffmpeg's chapters format, to mux in separately
+                    let chapters = timeline_chapters(&new_state.markers, model.end_time_ms());
+                    if write_chapters_file.get_untracked() && !chapters.is_empty() {
+                        let chapters_path =
+                            get_exports_dir().join(format!("{}.ffmetadata", export_name));
+
+                        if let Err(e) = write_chapters(&chapters_path, &chapters) {
+                            progress_text.set(format!("Couldn't write chapters file: {}", e));
+                            chapters_error.set(Some(e.to_string()));
+                        }
+                    }
 
                     // Spawn a new thread to handle the setup and sending
                     thread::spawn(move || {
@@ -225,6 +242,7 @@ pub fn export_widget(
                                     video_width: 1920,
                                     video_height: 1080,
                                     total_duration_s,
+                                    frame_rate,
                                     progress_tx: progress_tx.clone(),
                                 })
                                 .await
@@ -247,6 +265,10 @@ pub fn export_widget(
                 EventPropagation::Stop
             })
             .disabled(move || is_exporting.get()),
+        Checkbox::new_labeled_rw(write_chapters_file, || {
+            "Markers as chapters file (.ffmetadata)"
+        })
+        .disabled(move || is_exporting.get()),
         label(move || progress_text.get()),
     ))
     .style(|s| s.gap(10.0)),))
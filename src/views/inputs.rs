@@ -43,7 +43,7 @@ use floem::context::PaintState;
 use floem::reactive::{SignalGet, SignalUpdate};
 use floem::views::text;
 use floem::views::Decorators;
-use floem::views::{h_stack, svg, v_stack};
+use floem::views::{h_stack, svg, v_stack, Checkbox};
 use floem::{
     views::{button, dropdown},
     IntoView,
@@ -55,6 +55,12 @@ use floem::unit::{Auto, DurationUnitExt, Pct, UnitExt};
 use std::time::Duration;
 
 use crate::editor_state::EditorState;
+use crate::helpers::clock::{project_frame_rate, sync_engine, timecode, ClockMode, PlaybackClock};
+use crate::helpers::nested::nest_sequence;
+use crate::helpers::onion::{OnionSkinSettings, OnionStep};
+use crate::helpers::playback::prepare_sequence;
+use crate::helpers::scrub::{next_keyframe, previous_keyframe, step_frames};
+use crate::helpers::snapping::SnapSettings;
 
 pub fn styled_input(
     label_text: String,
@@ -415,32 +421,229 @@ where
 }
 
 pub fn play_sequence_button(
-    // editor_state: Arc<Mutex<EditorState>>,
+    editor_state: Arc<Mutex<EditorState>>,
     editor: std::sync::Arc<Mutex<Editor>>,
     // viewport: std::sync::Arc<Mutex<Viewport>>,
     selected_sequence_data: RwSignal<Sequence>,
 ) -> impl IntoView {
-    simple_button("Play Sequence".to_string(), move |_| {
-        let mut editor = editor.lock().unwrap();
+    let editor_state2 = Arc::clone(&editor_state);
+    let editor2 = Arc::clone(&editor);
 
-        if editor.is_playing {
-            println!("Pause Sequence...");
+    h_stack((
+        simple_button("Play Sequence".to_string(), move |_| {
+            let mut editor_state = editor_state.lock().unwrap();
+            let saved_state = editor_state
+                .record_state
+                .saved_state
+                .as_ref()
+                .expect("Couldn't get Saved State");
+
+            // apply repeats and other app-side settings before handing to the engine
+            let prepared_sequence = prepare_sequence(saved_state, &selected_sequence_data.get());
+            // the sequence as restored into the editor, to tell stand-ins from its own objects
+            let source_sequence = nest_sequence(
+                saved_state,
+                &selected_sequence_data.get(),
+                &|s: &Sequence| s.clone(),
+            );
+            let fps = project_frame_rate(saved_state);
+            let mode = ClockMode::from_every_frame(editor_state.play_every_frame.get_untracked());
+            let keyframe_playhead = editor_state.keyframe_playhead;
+
+            let mut editor = editor.lock().unwrap();
+
+            // only one of the sequence and the timeline plays at a time
+            if let Some(playback) = editor_state.timeline_playback.take() {
+                playback.restore_layers(&mut editor);
+            }
+
+            if editor.is_playing {
+                println!("Pause Sequence...");
+
+                // objects stay where they are, playing again picks up from the playhead
+                let position = editor_state
+                    .sequence_clock
+                    .take()
+                    .map(|clock| clock.time())
+                    .or_else(|| editor.start_playing_time.map(|start| start.elapsed()))
+                    .unwrap_or_default();
+
+                drop(editor_state);
+
+                editor.is_playing = false;
+                editor.start_playing_time = None;
+
+                drop(editor);
+
+                keyframe_playhead.set(position);
+            } else {
+                println!("Play Sequence...");
+
+                // from the playhead, or the start once it has reached the end
+                let mut position = keyframe_playhead.get_untracked();
+                if position.as_millis() as i32 >= prepared_sequence.duration_ms {
+                    position = Duration::ZERO;
+                }
+
+                let mut clock = PlaybackClock::new(fps, mode, position);
+                sync_engine(&mut editor, clock.tick());
+
+                editor_state.sequence_clock = Some(clock);
+                editor_state.staged_looks.stage(
+                    &mut editor,
+                    &[source_sequence],
+                    &[prepared_sequence.clone()],
+                );
+
+                drop(editor_state);
+
+                editor.current_sequence_data = Some(prepared_sequence);
+                editor.is_playing = true;
+            }
+
+            // EventPropagation::Continue
+        }),
+        simple_button("Stop".to_string(), move |_| {
+            let mut editor_state = editor_state2.lock().unwrap();
+
+            let timeline_playback = editor_state.timeline_playback.take();
+            editor_state.sequence_clock = None;
+            let keyframe_playhead = editor_state.keyframe_playhead;
+
+            let mut editor = editor2.lock().unwrap();
+
+            // timeline playback shares the editor, stop it too
+            if let Some(playback) = timeline_playback {
+                playback.restore_layers(&mut editor);
+            }
 
             editor.is_playing = false;
             editor.start_playing_time = None;
 
             // should return objects to the startup positions and state
             editor.reset_sequence_objects();
-        } else {
-            println!("Play Sequence...");
+            editor_state.staged_looks.unstage(&mut editor);
 
-            let now = std::time::Instant::now();
-            editor.start_playing_time = Some(now);
+            drop(editor);
+            drop(editor_state);
 
-            editor.current_sequence_data = Some(selected_sequence_data.get());
-            editor.is_playing = true;
-        }
+            keyframe_playhead.set(Duration::ZERO);
+        }),
+    ))
+}
+
+/// Step the playhead by frames or jump between keyframes, the paused preview follows it
+pub fn scrub_controls(
+    editor_state: Arc<Mutex<EditorState>>,
+    selected_sequence_data: RwSignal<Sequence>,
+) -> impl IntoView {
+    let (keyframe_playhead, frame_rate, play_every_frame) = {
+        let editor_state = editor_state.lock().unwrap();
 
-        // EventPropagation::Continue
-    })
+        (
+            editor_state.keyframe_playhead,
+            editor_state.frame_rate,
+            editor_state.play_every_frame,
+        )
+    };
+
+    let step = move |frames: i64| {
+        let duration =
+            Duration::from_millis(selected_sequence_data.get_untracked().duration_ms.max(0) as u64);
+
+        keyframe_playhead.set(step_frames(
+            keyframe_playhead.get_untracked(),
+            frames,
+            frame_rate.get_untracked(),
+            duration,
+        ));
+    };
+
+    h_stack((
+        simple_button("Prev Key".to_string(), move |_| {
+            let sequence = selected_sequence_data.get_untracked();
+
+            if let Some(time) = previous_keyframe(&sequence, keyframe_playhead.get_untracked()) {
+                keyframe_playhead.set(time);
+            }
+        }),
+        simple_button("< Frame".to_string(), move |_| step(-1)),
+        simple_button("Frame >".to_string(), move |_| step(1)),
+        simple_button("Next Key".to_string(), move |_| {
+            let sequence = selected_sequence_data.get_untracked();
+
+            if let Some(time) = next_keyframe(&sequence, keyframe_playhead.get_untracked()) {
+                keyframe_playhead.set(time);
+            }
+        }),
+        label(move || timecode(keyframe_playhead.get(), frame_rate.get()))
+            .style(|s| s.margin_horiz(5.0).selectable(false)),
+        Checkbox::new_labeled_rw(play_every_frame, || "Every Frame").style(|s| s.font_size(10.0)),
+    ))
+    .style(|s| s.items_center().margin_right(10.0))
+}
+
+/// holding Alt while dragging bypasses snapping, the label says so
+pub fn snapping_toggles(snap_settings: SnapSettings) -> impl IntoView {
+    h_stack((
+        Checkbox::new_labeled_rw(snap_settings.enabled, || "Snap (hold Alt to bypass)")
+            .style(|s| s.margin_right(10.0)),
+        Checkbox::new_labeled_rw(snap_settings.frames, || "Frames").style(|s| s.margin_right(10.0)),
+        Checkbox::new_labeled_rw(snap_settings.playhead, || "Playhead")
+            .style(|s| s.margin_right(10.0)),
+        Checkbox::new_labeled_rw(snap_settings.keyframes, || "Keyframes")
+            .style(|s| s.margin_right(10.0)),
+        Checkbox::new_labeled_rw(snap_settings.markers, || "Markers")
+            .style(|s| s.margin_right(10.0)),
+        Checkbox::new_labeled_rw(snap_settings.boundaries, || "Bounds"),
+    ))
+    .style(|s| s.margin_left(10.0).font_size(10.0))
+}
+
+/// hold the mouse on an object while playing to record its motion
+pub fn keying_toggles(
+    auto_key: RwSignal<bool>,
+    record_motion: RwSignal<bool>,
+    simplify_recording: RwSignal<bool>,
+) -> impl IntoView {
+    h_stack((
+        Checkbox::new_labeled_rw(auto_key, || "Auto-Key").style(|s| s.margin_right(10.0)),
+        Checkbox::new_labeled_rw(record_motion, || "Record Motion").style(|s| s.margin_right(10.0)),
+        Checkbox::new_labeled_rw(simplify_recording, || "Simplify"),
+    ))
+    .style(|s| s.margin_left(10.0).font_size(10.0))
+}
+
+/// ghosts at the neighbouring keyframes, or every few frames around the playhead
+pub fn onion_skin_toggles(onion_skin: OnionSkinSettings) -> impl IntoView {
+    let by_frames = create_rw_signal(onion_skin.step.get_untracked() != OnionStep::Keyframes);
+
+    create_effect(move |_| {
+        let step = if by_frames.get() {
+            OnionStep::Frames(5)
+        } else {
+            OnionStep::Keyframes
+        };
+
+        onion_skin.step.set(step);
+    });
+
+    h_stack((
+        Checkbox::new_labeled_rw(onion_skin.enabled, || "Onion Skin")
+            .style(|s| s.margin_right(10.0)),
+        Checkbox::new_labeled_rw(by_frames, || "Every 5 Frames").style(|s| s.margin_right(10.0)),
+        Checkbox::new_labeled_rw(onion_skin.frame_ticks, || "Frame Ticks")
+            .style(|s| s.margin_right(10.0)),
+        simple_button("-".to_string(), move |_| {
+            onion_skin.before.update(|n| *n = n.saturating_sub(1));
+            onion_skin.after.update(|n| *n = n.saturating_sub(1));
+        }),
+        label(move || format!("{} Ghosts", onion_skin.before.get()))
+            .style(|s| s.margin_left(5.0).margin_right(5.0)),
+        simple_button("+".to_string(), move |_| {
+            onion_skin.before.update(|n| *n = (*n + 1).min(10));
+            onion_skin.after.update(|n| *n = (*n + 1).min(10));
+        }),
+    ))
+    .style(|s| s.margin_left(10.0).font_size(10.0))
 }
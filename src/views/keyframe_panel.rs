@@ -7,6 +7,7 @@ use std::collections::HashMap;
 use std::sync::{Arc, Mutex, MutexGuard};
 use std::time::Duration;
 use stunts_engine::animations::AnimationData;
+use stunts_engine::animations::EasingType;
 use stunts_engine::animations::KeyframeValue;
 use stunts_engine::animations::ObjectType;
 use stunts_engine::animations::Sequence;
@@ -26,16 +27,27 @@ use floem::reactive::{create_effect, create_rw_signal, create_signal, RwSignal,
 use floem::reactive::{SignalGet, SignalUpdate};
 use floem::text::Weight;
 use floem::views::Decorators;
-use floem::views::{container, dyn_container, empty, label};
+use floem::views::{container, dyn_container, dyn_stack, empty, label};
 use floem::views::{h_stack, v_stack};
 use floem::GpuHelper;
 use floem::IntoView;
 
 use crate::editor_state::{self, EditorState};
+use crate::helpers::keyframes::{copy_keyframes, get_object_position};
+use crate::helpers::modifiers::{easing_from_label, easing_label, easing_types};
+use crate::helpers::retime::RetimeScope;
+use crate::helpers::saved_state::{
+    ColorKeyframe, ColorTrack, ModifierAxis, ModifierKind, PropertyModifier, ShapeKeyframe,
+    ShapeTrack,
+};
 use crate::helpers::utilities::save_saved_state_raw;
 
+use super::color_pallete::rgb_view_debounced;
 use super::inputs::debounce_input;
+use super::inputs::inline_dropdown;
 use super::inputs::styled_input;
+use super::inputs::DropdownOption;
+use super::properties_panel::retime_tools;
 
 pub fn update_keyframe(
     mut editor_state: MutexGuard<EditorState>,
@@ -118,6 +130,251 @@ pub fn update_keyframe(
     save_saved_state_raw(new_saved_state);
 }
 
+fn describe_modifier(modifier: &PropertyModifier) -> String {
+    let axis = match modifier.axis {
+        ModifierAxis::Both => "",
+        ModifierAxis::X => " (X)",
+        ModifierAxis::Y => " (Y)",
+    };
+
+    match &modifier.kind {
+        ModifierKind::Wiggle {
+            frequency_mhz,
+            amplitude,
+            ..
+        } => format!(
+            "Wiggle {}Hz x{}{}",
+            *frequency_mhz as f32 / 1000.0,
+            amplitude,
+            axis
+        ),
+        ModifierKind::Noise {
+            frequency_mhz,
+            amplitude,
+            ..
+        } => format!(
+            "Noise {}Hz x{}{}",
+            *frequency_mhz as f32 / 1000.0,
+            amplitude,
+            axis
+        ),
+        ModifierKind::Oscillator {
+            period_ms,
+            phase_deg,
+            amplitude,
+        } => format!(
+            "Oscillator {}ms {}deg x{}{}",
+            period_ms, phase_deg, amplitude, axis
+        ),
+    }
+}
+
+/// Add and remove procedural modifiers on the property of the selected keyframe
+pub fn modifier_tools(
+    editor_state: Arc<Mutex<EditorState>>,
+    animation_id: String,
+    property_path: String,
+) -> impl IntoView {
+    let editor_state2 = Arc::clone(&editor_state);
+    let editor_state3 = Arc::clone(&editor_state);
+    let editor_state4 = Arc::clone(&editor_state);
+    let editor_state5 = Arc::clone(&editor_state);
+    let editor_state6 = Arc::clone(&editor_state);
+    let editor_state7 = Arc::clone(&editor_state);
+
+    let modifiers = create_rw_signal(
+        editor_state
+            .lock()
+            .unwrap()
+            .get_property_modifiers(&animation_id, &property_path),
+    );
+
+    let frequency_hz = create_rw_signal(2.0);
+    let amplitude = create_rw_signal(10);
+    let period_ms = create_rw_signal(1000);
+    let phase_deg = create_rw_signal(0);
+    let selected_kind = create_rw_signal("Wiggle".to_string());
+    let kind_options = create_rw_signal(vec![
+        DropdownOption {
+            id: "Wiggle".to_string(),
+            label: "Wiggle".to_string(),
+        },
+        DropdownOption {
+            id: "Noise".to_string(),
+            label: "Noise".to_string(),
+        },
+        DropdownOption {
+            id: "Oscillator".to_string(),
+            label: "Oscillator".to_string(),
+        },
+    ]);
+    let selected_axis = create_rw_signal("Both".to_string());
+    let axis_options = create_rw_signal(vec![
+        DropdownOption {
+            id: "Both".to_string(),
+            label: "Both".to_string(),
+        },
+        DropdownOption {
+            id: "X".to_string(),
+            label: "X".to_string(),
+        },
+        DropdownOption {
+            id: "Y".to_string(),
+            label: "Y".to_string(),
+        },
+    ]);
+
+    let animation_id2 = animation_id.clone();
+    let property_path2 = property_path.clone();
+
+    v_stack((
+        label(|| "Modifiers").style(|s| s.margin_bottom(5.0)),
+        h_stack((
+            inline_dropdown(
+                "Type".to_string(),
+                selected_kind,
+                kind_options,
+                move |kind| selected_kind.set(kind),
+            ),
+            inline_dropdown(
+                "Axis".to_string(),
+                selected_axis,
+                axis_options,
+                move |axis| selected_axis.set(axis),
+            ),
+        )),
+        h_stack((
+            debounce_input(
+                "Frequency (Hz)".to_string(),
+                &frequency_hz.get_untracked().to_string(),
+                "Wiggle / Noise",
+                move |value| {
+                    if let Ok(value) = string_to_f32(&value) {
+                        frequency_hz.set(value);
+                    }
+                },
+                editor_state2,
+                "modifier_frequency".to_string(),
+                ObjectType::Polygon,
+            )
+            .style(|s| s.width(125.0).margin_right(5.0)),
+            debounce_input(
+                "Amplitude".to_string(),
+                &amplitude.get_untracked().to_string(),
+                "Amount",
+                move |value| {
+                    if let Ok(value) = string_to_f32(&value) {
+                        amplitude.set(value as i32);
+                    }
+                },
+                editor_state3,
+                "modifier_amplitude".to_string(),
+                ObjectType::Polygon,
+            )
+            .style(|s| s.width(125.0)),
+        )),
+        h_stack((
+            debounce_input(
+                "Period (ms)".to_string(),
+                &period_ms.get_untracked().to_string(),
+                "Oscillator",
+                move |value| {
+                    if let Ok(value) = string_to_f32(&value) {
+                        period_ms.set(value.max(1.0) as u32);
+                    }
+                },
+                editor_state4,
+                "modifier_period".to_string(),
+                ObjectType::Polygon,
+            )
+            .style(|s| s.width(125.0).margin_right(5.0)),
+            debounce_input(
+                "Phase (deg)".to_string(),
+                &phase_deg.get_untracked().to_string(),
+                "Oscillator",
+                move |value| {
+                    if let Ok(value) = string_to_f32(&value) {
+                        phase_deg.set(value as i32);
+                    }
+                },
+                editor_state5,
+                "modifier_phase".to_string(),
+                ObjectType::Polygon,
+            )
+            .style(|s| s.width(125.0)),
+        )),
+        simple_button("Add Modifier".to_string(), move |_| {
+            let frequency_mhz = (frequency_hz.get() * 1000.0).max(0.0) as u32;
+            let seed = rand::random::<u32>();
+
+            let kind = match selected_kind.get().as_str() {
+                "Noise" => ModifierKind::Noise {
+                    frequency_mhz,
+                    amplitude: amplitude.get(),
+                    seed,
+                },
+                "Oscillator" => ModifierKind::Oscillator {
+                    period_ms: period_ms.get(),
+                    phase_deg: phase_deg.get(),
+                    amplitude: amplitude.get(),
+                },
+                _ => ModifierKind::Wiggle {
+                    frequency_mhz,
+                    amplitude: amplitude.get(),
+                    seed,
+                },
+            };
+            let axis = match selected_axis.get().as_str() {
+                "X" => ModifierAxis::X,
+                "Y" => ModifierAxis::Y,
+                _ => ModifierAxis::Both,
+            };
+
+            let mut editor_state = editor_state6.lock().unwrap();
+
+            editor_state.add_property_modifier(PropertyModifier {
+                id: Uuid::new_v4().to_string(),
+                animation_id: animation_id.clone(),
+                property_path: property_path.clone(),
+                kind,
+                axis,
+            });
+
+            modifiers.set(editor_state.get_property_modifiers(&animation_id, &property_path));
+
+            drop(editor_state);
+        })
+        .style(|s| s.margin_bottom(5.0)),
+        dyn_stack(
+            move || modifiers.get(),
+            move |modifier| modifier.id.clone(),
+            move |modifier| {
+                let editor_state = editor_state7.clone();
+                let animation_id = animation_id2.clone();
+                let property_path = property_path2.clone();
+                let description = describe_modifier(&modifier);
+
+                h_stack((
+                    label(move || description.clone()).style(|s| s.margin_right(5.0)),
+                    simple_button("Remove".to_string(), move |_| {
+                        let mut editor_state = editor_state.lock().unwrap();
+
+                        editor_state.remove_property_modifier(&modifier.id);
+
+                        modifiers.set(
+                            editor_state.get_property_modifiers(&animation_id, &property_path),
+                        );
+
+                        drop(editor_state);
+                    }),
+                ))
+            },
+        )
+        .style(|s| s.flex_col()),
+    ))
+    .style(|s| s.margin_bottom(5.0))
+}
+
 pub fn keyframe_properties_view(
     editor_state: Arc<Mutex<EditorState>>,
     gpu_helper: Arc<Mutex<GpuHelper>>,
@@ -164,6 +421,10 @@ pub fn keyframe_properties_view(
     let editor_state_cloned20 = Arc::clone(&editor_state);
     let editor_state_cloned21 = Arc::clone(&editor_state);
     let editor_state_cloned22 = Arc::clone(&editor_state);
+    let editor_state_cloned23 = Arc::clone(&editor_state);
+    let editor_state_cloned24 = Arc::clone(&editor_state);
+    let editor_state_cloned25 = Arc::clone(&editor_state);
+    let editor_state_cloned26 = Arc::clone(&editor_state);
 
     let aside_width = 260.0;
     let quarters = (aside_width / 4.0) + (5.0 * 4.0);
@@ -172,6 +433,32 @@ pub fn keyframe_properties_view(
 
     let back_active = RwSignal::new(false);
     let curve_is_checked = RwSignal::new(false);
+    let retime_whole_property = RwSignal::new(false);
+    let hold_is_checked = RwSignal::new(
+        editor_state
+            .lock()
+            .unwrap()
+            .hold_keyframes
+            .get_untracked()
+            .contains(&selected_keyframe.id),
+    );
+
+    let hold_keyframe_id = selected_keyframe.id.clone();
+
+    create_effect(move |_| {
+        let hold = hold_is_checked.get();
+        let mut editor_state = editor_state_cloned26.lock().unwrap();
+
+        editor_state.set_keyframe_hold(&hold_keyframe_id, hold);
+    });
+
+    let modifier_target = animation_data.get_untracked().and_then(|animation| {
+        animation
+            .properties
+            .iter()
+            .find(|p| p.keyframes.iter().any(|k| k.id == selected_keyframe.id))
+            .map(|p| (animation.id.clone(), p.property_path.clone()))
+    });
 
     let selected_p_type = selected_keyframe.path_type.clone();
 
@@ -1047,6 +1334,76 @@ pub fn keyframe_properties_view(
             .into_any(),
             _ => empty().into_any(),
         },
+        simple_button("Copy Property Keyframes".to_string(), move |_| {
+            let current_keyframe = selected_keyframes.get();
+            let current_keyframe = current_keyframe
+                .get(0)
+                .expect("Couldn't get Selected Keyframe");
+            let current_animation_data = animation_data.get().expect("Couldn't get animation data");
+
+            let property_path = current_animation_data
+                .properties
+                .iter()
+                .find(|p| p.keyframes.iter().any(|k| k.id == current_keyframe.id))
+                .map(|p| p.property_path.clone())
+                .expect("Couldn't find keyframe property");
+
+            let source_position = get_object_position(
+                &selected_sequence_data.get(),
+                &current_animation_data.polygon_id,
+            )
+            .unwrap_or([0, 0]);
+
+            let mut editor_state = editor_state_cloned23.lock().unwrap();
+
+            let clipboard = match editor_state.record_state.saved_state.as_ref() {
+                Some(saved_state) => copy_keyframes(
+                    &current_animation_data,
+                    Some(&property_path),
+                    source_position,
+                    saved_state,
+                ),
+                None => return,
+            };
+            editor_state.keyframe_clipboard = clipboard;
+
+            println!("Copied {} keyframes!", property_path);
+        })
+        .style(|s| s.margin_bottom(5.0)),
+        Checkbox::new_labeled_rw(hold_is_checked, || "Hold (No Interpolation)")
+            .style(|s| s.margin_bottom(5.0)),
+        match modifier_target.clone() {
+            Some((animation_id, property_path)) => {
+                modifier_tools(editor_state_cloned25, animation_id, property_path).into_any()
+            }
+            None => empty().into_any(),
+        },
+        v_stack((
+            retime_tools(editor_state_cloned24, selected_sequence_data, move || {
+                let keyframes = selected_keyframes.get();
+                let first_keyframe = keyframes.get(0)?;
+
+                if !retime_whole_property.get() {
+                    return Some(RetimeScope::Keyframes(
+                        keyframes.iter().map(|k| k.id.clone()).collect(),
+                    ));
+                }
+
+                let animation = animation_data.get()?;
+                let property_path = animation
+                    .properties
+                    .iter()
+                    .find(|p| p.keyframes.iter().any(|k| k.id == first_keyframe.id))
+                    .map(|p| p.property_path.clone())?;
+
+                Some(RetimeScope::Property {
+                    animation_id: animation.id,
+                    property_path,
+                })
+            }),
+            Checkbox::new_labeled_rw(retime_whole_property, || "Retime Whole Property"),
+        ))
+        .style(|s| s.margin_bottom(5.0)),
         simple_button("Delete Keyframe".to_string(), move |_| {
             let mut current_keyframe = selected_keyframes.get();
             let mut current_keyframe = current_keyframe
@@ -1110,9 +1467,264 @@ pub fn keyframe_properties_view(
 
             save_saved_state_raw(new_saved_state);
 
+            editor_state.set_keyframe_hold(&current_keyframe.id, false);
+
             drop(editor_state);
         })
         .style(|s| s.color(Color::RED)),
     ))
     .style(|s| card_styles(s).width(300.0))
 }
+
+/// Easing toward the next keyframe, for keyframes kept beside the sequence
+fn easing_dropdown<F>(easing: &EasingType, on_select: F) -> impl IntoView
+where
+    F: Fn(EasingType) + Clone + 'static,
+{
+    let selected_easing = create_rw_signal(easing_label(easing).to_string());
+    let easing_options = create_rw_signal(
+        easing_types()
+            .iter()
+            .map(|e| DropdownOption {
+                id: easing_label(e).to_string(),
+                label: easing_label(e).to_string(),
+            })
+            .collect(),
+    );
+
+    inline_dropdown(
+        "Easing".to_string(),
+        selected_easing,
+        easing_options,
+        move |label| {
+            if let Some(easing) = easing_from_label(&label) {
+                selected_easing.set(label);
+                on_select(easing);
+            }
+        },
+    )
+}
+
+fn find_color_keyframe(
+    color_tracks: RwSignal<Vec<ColorTrack>>,
+    keyframe_id: &str,
+) -> Option<ColorKeyframe> {
+    color_tracks
+        .get_untracked()
+        .iter()
+        .flat_map(|t| t.keyframes.iter())
+        .find(|k| k.id == keyframe_id)
+        .cloned()
+}
+
+/// Edit a keyframe on a color track, picked from the color rows of the keyframe timeline
+pub fn color_keyframe_view(
+    editor_state: Arc<Mutex<EditorState>>,
+    color_tracks: RwSignal<Vec<ColorTrack>>,
+    selected_track_keyframe: RwSignal<Option<String>>,
+    keyframe_id: String,
+) -> impl IntoView {
+    let editor_state2 = Arc::clone(&editor_state);
+    let editor_state3 = Arc::clone(&editor_state);
+    let editor_state4 = Arc::clone(&editor_state);
+    let editor_state5 = Arc::clone(&editor_state);
+
+    let target = color_tracks
+        .get_untracked()
+        .iter()
+        .find(|t| t.keyframes.iter().any(|k| k.id == keyframe_id))
+        .map(|t| t.target);
+
+    let (target, keyframe) = match (target, find_color_keyframe(color_tracks, &keyframe_id)) {
+        (Some(target), Some(keyframe)) => (target, keyframe),
+        _ => return empty().into_any(),
+    };
+
+    let init_red = create_rw_signal(keyframe.color[0]);
+    let init_green = create_rw_signal(keyframe.color[1]);
+    let init_blue = create_rw_signal(keyframe.color[2]);
+
+    let keyframe_id2 = keyframe_id.clone();
+    let keyframe_id3 = keyframe_id.clone();
+    let keyframe_id4 = keyframe_id.clone();
+
+    let on_color_update = move |r: i32, g: i32, b: i32| {
+        if let Some(mut keyframe) = find_color_keyframe(color_tracks, &keyframe_id2) {
+            keyframe.color = [r, g, b, keyframe.color[3]];
+
+            let mut editor_state = editor_state2.lock().unwrap();
+            editor_state.update_color_keyframe(keyframe);
+        }
+    };
+
+    container(
+        (v_stack((
+            label(|| "Keyframe").style(|s| s.font_size(14.0).margin_bottom(10)),
+            simple_button("Back to Properties".to_string(), move |_| {
+                selected_track_keyframe.set(None);
+            })
+            .style(|s| s.margin_bottom(5.0)),
+            label(move || target.label()).style(|s| s.margin_bottom(5.0)),
+            debounce_input(
+                "Time (ms):".to_string(),
+                &keyframe.time_ms.to_string(),
+                "Enter time",
+                move |value| {
+                    let value = match string_to_f32(&value) {
+                        Ok(value) => value.max(0.0) as u64,
+                        Err(_) => return,
+                    };
+
+                    if let Some(mut keyframe) = find_color_keyframe(color_tracks, &keyframe_id3) {
+                        keyframe.time_ms = value;
+
+                        let mut editor_state = editor_state3.lock().unwrap();
+                        editor_state.update_color_keyframe(keyframe);
+                    }
+                },
+                editor_state,
+                "color_time".to_string(),
+                ObjectType::Polygon,
+            )
+            .style(|s| s.width(260.0).margin_bottom(5.0)),
+            rgb_view_debounced(on_color_update, init_red, init_green, init_blue),
+            easing_dropdown(&keyframe.easing, move |easing| {
+                if let Some(mut keyframe) = find_color_keyframe(color_tracks, &keyframe_id4) {
+                    keyframe.easing = easing;
+
+                    let mut editor_state = editor_state5.lock().unwrap();
+                    editor_state.update_color_keyframe(keyframe);
+                }
+            }),
+            simple_button("Delete Keyframe".to_string(), move |_| {
+                let mut editor_state = editor_state4.lock().unwrap();
+
+                editor_state.remove_color_keyframe(&keyframe_id);
+
+                drop(editor_state);
+
+                selected_track_keyframe.set(None);
+            })
+            .style(|s| s.color(Color::RED)),
+        ))
+        .style(|s| s.width(260.0))),
+    )
+    .style(|s| card_styles(s).width(300.0))
+    .into_any()
+}
+
+fn find_shape_keyframe(
+    shape_tracks: RwSignal<Vec<ShapeTrack>>,
+    keyframe_id: &str,
+) -> Option<ShapeKeyframe> {
+    shape_tracks
+        .get_untracked()
+        .iter()
+        .flat_map(|t| t.keyframes.iter())
+        .find(|k| k.id == keyframe_id)
+        .cloned()
+}
+
+/// Edit a keyframe on a width, height, border radius or stroke thickness track
+pub fn shape_keyframe_view(
+    editor_state: Arc<Mutex<EditorState>>,
+    shape_tracks: RwSignal<Vec<ShapeTrack>>,
+    selected_track_keyframe: RwSignal<Option<String>>,
+    keyframe_id: String,
+) -> impl IntoView {
+    let editor_state2 = Arc::clone(&editor_state);
+    let editor_state3 = Arc::clone(&editor_state);
+    let editor_state4 = Arc::clone(&editor_state);
+    let editor_state5 = Arc::clone(&editor_state);
+
+    let property = shape_tracks
+        .get_untracked()
+        .iter()
+        .find(|t| t.keyframes.iter().any(|k| k.id == keyframe_id))
+        .map(|t| t.property);
+
+    let (property, keyframe) = match (property, find_shape_keyframe(shape_tracks, &keyframe_id)) {
+        (Some(property), Some(keyframe)) => (property, keyframe),
+        _ => return empty().into_any(),
+    };
+
+    let keyframe_id2 = keyframe_id.clone();
+    let keyframe_id3 = keyframe_id.clone();
+    let keyframe_id4 = keyframe_id.clone();
+
+    container(
+        (v_stack((
+            label(|| "Keyframe").style(|s| s.font_size(14.0).margin_bottom(10)),
+            simple_button("Back to Properties".to_string(), move |_| {
+                selected_track_keyframe.set(None);
+            })
+            .style(|s| s.margin_bottom(5.0)),
+            label(move || property.label()).style(|s| s.margin_bottom(5.0)),
+            debounce_input(
+                "Time (ms):".to_string(),
+                &keyframe.time_ms.to_string(),
+                "Enter time",
+                move |value| {
+                    let value = match string_to_f32(&value) {
+                        Ok(value) => value.max(0.0) as u64,
+                        Err(_) => return,
+                    };
+
+                    if let Some(mut keyframe) = find_shape_keyframe(shape_tracks, &keyframe_id2) {
+                        keyframe.time_ms = value;
+
+                        let mut editor_state = editor_state2.lock().unwrap();
+                        editor_state.update_shape_keyframe(keyframe);
+                    }
+                },
+                editor_state.clone(),
+                "shape_time".to_string(),
+                ObjectType::Polygon,
+            )
+            .style(|s| s.width(260.0).margin_bottom(5.0)),
+            debounce_input(
+                format!("Value ({}):", property.unit()),
+                &keyframe.value.to_string(),
+                "Enter value",
+                move |value| {
+                    let value = match string_to_f32(&value) {
+                        Ok(value) => value.round() as i32,
+                        Err(_) => return,
+                    };
+
+                    if let Some(mut keyframe) = find_shape_keyframe(shape_tracks, &keyframe_id3) {
+                        keyframe.value = value;
+
+                        let mut editor_state = editor_state3.lock().unwrap();
+                        editor_state.update_shape_keyframe(keyframe);
+                    }
+                },
+                editor_state,
+                "shape_value".to_string(),
+                ObjectType::Polygon,
+            )
+            .style(|s| s.width(260.0).margin_bottom(5.0)),
+            easing_dropdown(&keyframe.easing, move |easing| {
+                if let Some(mut keyframe) = find_shape_keyframe(shape_tracks, &keyframe_id4) {
+                    keyframe.easing = easing;
+
+                    let mut editor_state = editor_state5.lock().unwrap();
+                    editor_state.update_shape_keyframe(keyframe);
+                }
+            }),
+            simple_button("Delete Keyframe".to_string(), move |_| {
+                let mut editor_state = editor_state4.lock().unwrap();
+
+                editor_state.remove_shape_keyframe(&keyframe_id);
+
+                drop(editor_state);
+
+                selected_track_keyframe.set(None);
+            })
+            .style(|s| s.color(Color::RED)),
+        ))
+        .style(|s| s.width(260.0))),
+    )
+    .style(|s| card_styles(s).width(300.0))
+    .into_any()
+}
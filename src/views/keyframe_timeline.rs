@@ -1,5 +1,5 @@
 use floem::event::EventListener;
-use floem::reactive::{create_rw_signal, RwSignal, SignalGet, SignalUpdate};
+use floem::reactive::{create_effect, create_rw_signal, RwSignal, SignalGet, SignalUpdate};
 use floem::taffy::Position;
 use floem::views::editor::keypress::key;
 use floem::{
@@ -12,7 +12,7 @@ use floem::{
     taffy::{Display, Layout, NodeId, TaffyTree},
     text::{Attrs, AttrsList, TextLayout},
     unit::UnitExt,
-    views::{container, label, stack, Decorators},
+    views::{container, label, stack, v_stack, Decorators},
     AppState, View, ViewId,
 };
 use floem_renderer::Renderer;
@@ -23,24 +23,40 @@ use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
 use stunts_engine::animations::{
-    AnimationData, AnimationProperty, EasingType, KeyType, KeyframeValue, Sequence, UIKeyframe,
+    AnimationData, AnimationProperty, EasingType, KeyType, KeyframeValue, ObjectType, Sequence,
+    UIKeyframe,
 };
 
 use crate::editor_state::EditorState;
+use crate::helpers::colors::{base_color, sample_color};
+use crate::helpers::keyframes::{find_keyframe_mut, sort_keyframes};
+use crate::helpers::markers::{marker_times, markers_on};
+use crate::helpers::saved_state::{ColorTarget, ColorTrack, Marker, ShapeProperty, ShapeTrack};
+use crate::helpers::scrub::{animation_time, sequence_time};
+use crate::helpers::shapes::{base_shape_value, sample_shape};
+use crate::helpers::snapping::{
+    keyframe_snap_candidates, snap_time, SnapTarget, SNAP_THRESHOLD_PX,
+};
 use crate::helpers::utilities::save_saved_state_raw;
+use crate::helpers::zoom::{ticks, TimelineZoom};
+
+use super::zoom::{time_ruler, wheel_event, zoom_controls, zoom_viewport};
 
 /// State for the timeline component
 #[derive(Debug, Clone)]
 pub struct TimelineState {
-    pub current_time: Duration,
-    pub zoom_level: f64,
-    pub scroll_offset: f64,
+    pub current_time: RwSignal<Duration>,
+    /// same zoom and scroll as the other timelines, from the config's pixels per second
+    pub zoom: TimelineZoom,
     pub dragging: Option<DragOperation>,
     pub hovered_keyframe: Option<(String, Duration)>,
     pub hover_position: RwSignal<Option<Point>>,
     pub hover_property: RwSignal<Option<String>>,
     pub property_expansions: im::HashMap<String, bool>,
     pub selected_keyframes: RwSignal<Vec<UIKeyframe>>,
+    /// id of the selected color or shape keyframe, these aren't UIKeyframes
+    pub selected_track_keyframe: RwSignal<Option<String>>,
+    pub snap_indicator: Option<Duration>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -62,6 +78,7 @@ pub struct TimelineConfig {
     pub width: f64,
     pub height: f64,
     pub header_height: f64,
+    /// pixels per second the zoom starts at
     pub property_width: f64,
     pub row_height: f64,
     // Add offset parameters
@@ -88,6 +105,12 @@ pub struct TimelineGridView {
     state: RwSignal<TimelineState>,
     config: TimelineConfig,
     animation_data: RwSignal<Option<AnimationData>>,
+    color_tracks: RwSignal<Vec<ColorTrack>>,
+    shape_tracks: RwSignal<Vec<ShapeTrack>>,
+    hold_keyframes: RwSignal<Vec<String>>,
+    /// markers of the selected sequence, in sequence time
+    markers: RwSignal<Vec<Marker>>,
+    frame_rate: RwSignal<u32>,
     style: Style,
 }
 
@@ -96,12 +119,22 @@ impl TimelineGridView {
         state: TimelineState,
         config: TimelineConfig,
         animation_data: RwSignal<Option<AnimationData>>,
+        color_tracks: RwSignal<Vec<ColorTrack>>,
+        shape_tracks: RwSignal<Vec<ShapeTrack>>,
+        hold_keyframes: RwSignal<Vec<String>>,
+        markers: RwSignal<Vec<Marker>>,
+        frame_rate: RwSignal<u32>,
     ) -> Self {
         Self {
             id: ViewId::new(),
             state: create_rw_signal(state),
             config: config.clone(),
             animation_data,
+            color_tracks,
+            shape_tracks,
+            hold_keyframes,
+            markers,
+            frame_rate,
             // style: Style::default(),
             style: Style::new()
                 .margin_left(300.0)
@@ -153,19 +186,15 @@ impl TimelineGridView {
             .animation_data
             .get()
             .expect("Couldn't get animation data")
-            .duration
-            .as_secs_f64();
-        let step = (0.5 / self.state.get().zoom_level).max(0.1);
-
-        for time in (0..(duration / step) as i32).map(|i| i as f64 * step) {
-            let x = self.config.offset_x
-                + time_to_x(
-                    self.state,
-                    self.config.clone(),
-                    Duration::from_secs_f64(time),
-                );
+            .duration;
+        let pixels_per_s = self.state.get().zoom.pixels_per_s.get_untracked();
+        let from = x_to_time(self.state, self.config.clone(), 0.0);
+        let to = x_to_time(self.state, self.config.clone(), self.config.width).min(duration);
+
+        // lines under the ruler's ticks, it labels them
+        for tick in ticks(pixels_per_s, from, to, self.frame_rate.get()) {
+            let x = self.config.offset_x + time_to_x(self.state, self.config.clone(), tick.time);
 
-            // Vertical grid line
             cx.stroke(
                 &Line::new(
                     Point::new(x, self.config.offset_y),
@@ -174,13 +203,6 @@ impl TimelineGridView {
                 &Color::GRAY,
                 1.0,
             );
-
-            // Time label
-            let mut attrs_list = AttrsList::new(Attrs::new().color(Color::BLACK).font_size(10.0));
-            let mut text_layout = TextLayout::new();
-            text_layout.set_text(&format!("{:.1}s", time), attrs_list);
-
-            cx.draw_text(&text_layout, Point::new(x, self.config.offset_y));
         }
 
         // Draw plus icon if hovering
@@ -207,6 +229,92 @@ impl TimelineGridView {
                 current_y = y;
             }
         }
+
+        // saved state tracks follow the engine properties
+        let animation = self
+            .animation_data
+            .get()
+            .expect("Couldn't get animation data");
+        let color_tracks = self.color_tracks.get();
+        let shape_tracks = self.shape_tracks.get();
+
+        for (index, row) in track_rows(&animation.object_type).into_iter().enumerate() {
+            let y = track_row_y(
+                &self.config,
+                &animation,
+                &self.state.get().property_expansions,
+                index,
+            );
+
+            if y > self.config.height {
+                break;
+            }
+
+            let keyframes = row_keyframes(row, &animation.id, &color_tracks, &shape_tracks);
+
+            self.draw_track_row(cx, row, &keyframes, y);
+        }
+    }
+
+    pub fn draw_track_row(
+        &self,
+        cx: &mut PaintCx,
+        row: TrackRow,
+        keyframes: &[RowKeyframe],
+        y: f64,
+    ) {
+        let mut text_layout = TextLayout::new();
+        let attrs_list = AttrsList::new(Attrs::new().color(Color::BLACK).font_size(12.0));
+        text_layout.set_text(&format!("  {}", row.label()), attrs_list);
+        cx.draw_text(
+            &text_layout,
+            Point::new(10.0, y + self.config.row_height / 2.0 - 6.0),
+        );
+
+        let selected_id = self.state.get().selected_track_keyframe.get();
+        let center_y = self.config.offset_y + y + self.config.row_height / 2.0;
+        let mut prev_x: Option<f64> = None;
+
+        for keyframe in keyframes {
+            let x = time_to_x(
+                self.state,
+                self.config.clone(),
+                Duration::from_millis(keyframe.time_ms),
+            );
+
+            if let Some(prev_x) = prev_x {
+                cx.stroke(
+                    &Line::new(
+                        Point::new(self.config.offset_x + prev_x, center_y),
+                        Point::new(self.config.offset_x + x, center_y),
+                    ),
+                    &Color::DARK_GRAY,
+                    1.0,
+                );
+            }
+            prev_x = Some(x);
+
+            if x < -10.0 || x > self.config.width + 10.0 {
+                continue;
+            }
+
+            self.draw_keyframe(
+                cx,
+                Point::new(x, y + self.config.row_height / 2.0),
+                selected_id.as_ref() == Some(&keyframe.id),
+                KeyType::Frame,
+                false,
+            );
+
+            // swatch of the keyed color inside the marker
+            if let Some([r, g, b, a]) = keyframe.swatch {
+                cx.fill(
+                    &kurbo::Circle::new(Point::new(self.config.offset_x + x, center_y), 3.5),
+                    Color::rgba8(r as u8, g as u8, b as u8, a as u8),
+                    1.0,
+                );
+            }
+        }
     }
 
     pub fn draw_property_keyframes(
@@ -224,6 +332,7 @@ impl TimelineGridView {
 
         // Draw property's own keyframes
         let selected_keyframes = self.state.get().selected_keyframes.get();
+        let hold_keyframes = self.hold_keyframes.get();
 
         for keyframe in &property.keyframes {
             let x = time_to_x(self.state, self.config.clone(), keyframe.time);
@@ -241,6 +350,7 @@ impl TimelineGridView {
                 Point::new(x, (current_y + self.config.row_height / 2.0)),
                 selected,
                 keyframe.key_type.clone(),
+                hold_keyframes.contains(&keyframe.id),
             );
 
             // draw connecting lines between keyframes
@@ -304,6 +414,7 @@ impl TimelineGridView {
         center: Point,
         selected: bool,
         key_type: KeyType,
+        hold: bool,
     ) {
         let size = 8.0;
         let mut end_time = Duration::from_millis(0);
@@ -368,6 +479,22 @@ impl TimelineGridView {
                 )),
                 kurbo::PathEl::ClosePath,
             ]);
+        } else if hold {
+            // square marker for keyframes that jump instead of interpolating
+            let half = size * 0.75;
+
+            let (x, y) = (
+                center.x + self.config.offset_x,
+                center.y + self.config.offset_y,
+            );
+
+            path = kurbo::BezPath::from_vec(vec![
+                kurbo::PathEl::MoveTo(Point::new(x - half, y - half)),
+                kurbo::PathEl::LineTo(Point::new(x + half, y - half)),
+                kurbo::PathEl::LineTo(Point::new(x + half, y + half)),
+                kurbo::PathEl::LineTo(Point::new(x - half, y + half)),
+                kurbo::PathEl::ClosePath,
+            ]);
         }
 
         cx.fill(&path, color, 1.0);
@@ -412,6 +539,48 @@ impl TimelineGridView {
         cx.stroke(&horizontal_line, &Color::rgb8(255, 255, 255), 2.0);
     }
 
+    /// Markers and regions of the sequence, moved into the animation's time
+    pub fn draw_markers(&self, cx: &mut PaintCx) {
+        let start_ms = self
+            .animation_data
+            .get()
+            .map(|animation| animation.start_time_ms)
+            .unwrap_or(0);
+        let top = self.config.offset_y;
+        let bottom = self.config.offset_y + self.config.height;
+        let x_at = |time_ms: i32| {
+            let time = Duration::from_millis((time_ms - start_ms).max(0) as u64);
+
+            self.config.offset_x + time_to_x(self.state, self.config.clone(), time)
+        };
+
+        for marker in self.markers.get() {
+            let [r, g, b] = marker.color;
+            let x = x_at(marker.time_ms);
+
+            if let Some(end_time_ms) = marker.end_time_ms {
+                let end_x = x_at(end_time_ms);
+
+                cx.fill(
+                    &kurbo::Rect::new(x, top, end_x, top + self.config.header_height),
+                    Color::rgba8(r, g, b, 60),
+                    1.0,
+                );
+                cx.stroke(
+                    &Line::new(Point::new(end_x, top), Point::new(end_x, bottom)),
+                    &Color::rgb8(r, g, b),
+                    1.0,
+                );
+            }
+
+            cx.stroke(
+                &Line::new(Point::new(x, top), Point::new(x, bottom)),
+                &Color::rgb8(r, g, b),
+                1.0,
+            );
+        }
+    }
+
     /// Calculate the Y position for a given property path
     pub fn get_property_y_position(&self, property_path: &str) -> f64 {
         let mut y_position = self.config.header_height;
@@ -525,15 +694,158 @@ fn hit_test_keyframe(
     None
 }
 
-fn time_to_x(state: RwSignal<TimelineState>, config: TimelineConfig, time: Duration) -> f64 {
-    let time_secs = time.as_secs_f64();
-    let base_spacing = config.property_width; // pixels per second at zoom level 1.0
-    (time_secs * base_spacing * state.get().zoom_level) - state.get().scroll_offset
+/// rows drawn for the properties, children only count when expanded
+fn property_row_count(
+    properties: &[AnimationProperty],
+    property_expansions: &im::HashMap<String, bool>,
+) -> usize {
+    properties
+        .iter()
+        .map(|p| {
+            let expanded = property_expansions
+                .get(&p.property_path)
+                .copied()
+                .unwrap_or(false);
+
+            if expanded {
+                1 + p.children.len()
+            } else {
+                1
+            }
+        })
+        .sum()
+}
+
+/// rows for tracks kept in the saved state rather than the engine's AnimationData
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TrackRow {
+    Color(ColorTarget),
+    Shape(ShapeProperty),
+}
+
+impl TrackRow {
+    fn label(&self) -> &'static str {
+        match self {
+            TrackRow::Color(target) => target.label(),
+            TrackRow::Shape(property) => property.label(),
+        }
+    }
+
+    fn path(&self) -> &'static str {
+        match self {
+            TrackRow::Color(target) => target.path(),
+            TrackRow::Shape(property) => property.path(),
+        }
+    }
+}
+
+/// color rows first, then shape rows
+fn track_rows(object_type: &ObjectType) -> Vec<TrackRow> {
+    ColorTarget::for_object(object_type)
+        .into_iter()
+        .map(TrackRow::Color)
+        .chain(
+            ShapeProperty::for_object(object_type)
+                .into_iter()
+                .map(TrackRow::Shape),
+        )
+        .collect()
+}
+
+/// keyframe drawn on a track row, color rows also show the keyed color
+#[derive(Clone, Debug)]
+pub struct RowKeyframe {
+    pub id: String,
+    pub time_ms: u64,
+    pub swatch: Option<[i32; 4]>,
+}
+
+fn row_keyframes(
+    row: TrackRow,
+    animation_id: &str,
+    color_tracks: &[ColorTrack],
+    shape_tracks: &[ShapeTrack],
+) -> Vec<RowKeyframe> {
+    match row {
+        TrackRow::Color(target) => color_tracks
+            .iter()
+            .filter(|t| t.animation_id == animation_id && t.target == target)
+            .flat_map(|t| t.keyframes.iter())
+            .map(|k| RowKeyframe {
+                id: k.id.clone(),
+                time_ms: k.time_ms,
+                swatch: Some(k.color),
+            })
+            .collect(),
+        TrackRow::Shape(property) => shape_tracks
+            .iter()
+            .filter(|t| t.animation_id == animation_id && t.property == property)
+            .flat_map(|t| t.keyframes.iter())
+            .map(|k| RowKeyframe {
+                id: k.id.clone(),
+                time_ms: k.time_ms,
+                swatch: None,
+            })
+            .collect(),
+    }
+}
+
+/// top of the track row at index, below all property rows
+fn track_row_y(
+    config: &TimelineConfig,
+    animation: &AnimationData,
+    property_expansions: &im::HashMap<String, bool>,
+    index: usize,
+) -> f64 {
+    let rows = property_row_count(&animation.properties, property_expansions) + index;
+
+    config.header_height + rows as f64 * config.row_height
+}
+
+fn hit_test_track_keyframe(
+    state: RwSignal<TimelineState>,
+    config: TimelineConfig,
+    animation_data: &AnimationData,
+    color_tracks: &[ColorTrack],
+    shape_tracks: &[ShapeTrack],
+    point: Point,
+) -> Option<RowKeyframe> {
+    let hit_radius = 8.0;
+    let property_expansions = state.get().property_expansions;
+
+    for (index, row) in track_rows(&animation_data.object_type)
+        .into_iter()
+        .enumerate()
+    {
+        let y_center = track_row_y(&config, animation_data, &property_expansions, index)
+            + config.row_height / 2.0;
+
+        for keyframe in row_keyframes(row, &animation_data.id, color_tracks, shape_tracks) {
+            let x = time_to_x(
+                state,
+                config.clone(),
+                Duration::from_millis(keyframe.time_ms),
+            );
+
+            if point.distance(Point::new(x, y_center)) <= hit_radius {
+                return Some(keyframe);
+            }
+        }
+    }
+
+    None
+}
+
+fn time_to_x(state: RwSignal<TimelineState>, _config: TimelineConfig, time: Duration) -> f64 {
+    let zoom = state.get_untracked().zoom;
+
+    time.as_secs_f64() * zoom.pixels_per_s.get_untracked() - zoom.scroll_x.get_untracked()
 }
 
-fn x_to_time(state: RwSignal<TimelineState>, config: TimelineConfig, x: f64) -> Duration {
-    let base_spacing = config.property_width;
-    let time_secs = (x + state.get().scroll_offset) / (base_spacing * state.get().zoom_level);
+fn x_to_time(state: RwSignal<TimelineState>, _config: TimelineConfig, x: f64) -> Duration {
+    let zoom = state.get_untracked().zoom;
+    let time_secs = (x + zoom.scroll_x.get_untracked()) / zoom.pixels_per_s.get_untracked();
+
     Duration::from_secs_f64(time_secs.max(0.0))
 }
 
@@ -545,6 +857,11 @@ pub enum DragOperation {
         original_time: Duration,
         start_x: f64,
     },
+    TrackKeyframe {
+        keyframe_id: String,
+        original_time: Duration,
+        start_x: f64,
+    },
     None,
 }
 
@@ -574,16 +891,20 @@ impl View for TimelineGridView {
         // Draw grid
         self.draw_time_grid(cx);
 
+        self.draw_markers(cx);
+
         // Draw keyframes
         self.draw_keyframes(cx);
 
-        // Draw playhead with offset
-        let playhead_x = self.config.offset_x
-            + time_to_x(
-                self.state,
-                self.config.clone(),
-                self.state.get().current_time,
-            );
+        // Draw playhead with offset, the playhead is in sequence time
+        let playhead = self.state.get().current_time.get();
+        let playhead = self
+            .animation_data
+            .get()
+            .map(|animation| animation_time(&animation, playhead))
+            .unwrap_or(playhead);
+        let playhead_x =
+            self.config.offset_x + time_to_x(self.state, self.config.clone(), playhead);
         cx.stroke(
             &Line::new(
                 Point::new(playhead_x, self.config.offset_y),
@@ -593,6 +914,20 @@ impl View for TimelineGridView {
             2.0,
         );
 
+        // Draw snap indicator while dragging
+        if let Some(snap_time) = self.state.get().snap_indicator {
+            let snap_x =
+                self.config.offset_x + time_to_x(self.state, self.config.clone(), snap_time);
+            cx.stroke(
+                &Line::new(
+                    Point::new(snap_x, self.config.offset_y),
+                    Point::new(snap_x, self.config.offset_y + self.config.height),
+                ),
+                &Color::rgb8(0, 200, 255),
+                1.0,
+            );
+        }
+
         // Add hover effects
         if let Some((property_path, time)) = &self.state.get().hovered_keyframe {
             let y = self.get_property_y_position(property_path);
@@ -639,6 +974,8 @@ struct TimelineHandle {
     state: RwSignal<TimelineState>,
     config: TimelineConfig,
     animation_data: RwSignal<Option<AnimationData>>,
+    color_tracks: RwSignal<Vec<ColorTrack>>,
+    shape_tracks: RwSignal<Vec<ShapeTrack>>,
     view_id: ViewId,
 }
 
@@ -648,17 +985,60 @@ pub fn create_timeline(
     state: TimelineState,
     config: TimelineConfig,
     animation_data: RwSignal<Option<AnimationData>>,
+    color_tracks: RwSignal<Vec<ColorTrack>>,
+    shape_tracks: RwSignal<Vec<ShapeTrack>>,
+    hold_keyframes: RwSignal<Vec<String>>,
     selected_sequence_data: RwSignal<Sequence>,
 ) -> impl View {
-    let test = TimelineGridView::new(state, config, animation_data);
+    let (markers, frame_rate) = {
+        let editor_state = editor_state.lock().unwrap();
+
+        (editor_state.markers, editor_state.frame_rate)
+    };
+    let sequence_markers = create_rw_signal(Vec::new());
+
+    let test = TimelineGridView::new(
+        state,
+        config,
+        animation_data,
+        color_tracks,
+        shape_tracks,
+        hold_keyframes,
+        sequence_markers,
+        frame_rate,
+    );
 
     let view_id = test.id;
 
+    // ticks change with the frame rate, everything moves with the zoom
+    let zoom = test.state.get_untracked().zoom;
+    create_effect(move |_| {
+        frame_rate.get();
+        zoom.pixels_per_s.get();
+        zoom.scroll_x.get();
+        view_id.request_paint();
+    });
+
+    create_effect(move |_| {
+        let sequence_id = selected_sequence_data.get().id;
+
+        sequence_markers.set(markers_on(&markers.get(), Some(&sequence_id)));
+        view_id.request_paint();
+    });
+
+    // hold toggles come from the keyframe panel, outside the timeline's own events
+    create_effect(move |_| {
+        hold_keyframes.get();
+        view_id.request_paint();
+    });
+
     // Create a lightweight handle for events
     let handle = TimelineHandle {
         state: test.state.clone(),
         config: test.config.clone(),
         animation_data: test.animation_data,
+        color_tracks: test.color_tracks,
+        shape_tracks: test.shape_tracks,
         view_id,
     };
 
@@ -666,11 +1046,46 @@ pub fn create_timeline(
     let handle_up = handle.clone();
     let handle_wheel = handle.clone();
 
-    container((test))
+    let editor_up = editor.clone();
+    let editor_state_move = editor_state.clone();
+    let editor_state_up = editor_state.clone();
+    let editor_state_wheel = editor_state.clone();
+    let editor_state_ruler = editor_state.clone();
+
+    let width = handle.config.width;
+    let view_width = move || width;
+    // room for the whole animation, at least the width of the view
+    let content_width = move || {
+        let duration = animation_data
+            .get()
+            .map(|animation| animation.duration)
+            .unwrap_or_default();
+
+        (duration.as_secs_f64() * zoom.pixels_per_s.get()).max(width)
+    };
+
+    let zoom_buttons = zoom_controls(zoom, view_width, move || {
+        if let Some(animation) = animation_data.get_untracked() {
+            zoom.fit(animation.duration, width);
+        }
+    })
+    .style(|s| s.margin_top(10.0).margin_left(25.0));
+
+    let ruler = zoom_viewport(
+        editor_state_ruler,
+        zoom,
+        view_width,
+        content_width,
+        container(time_ruler(zoom, view_width, frame_rate))
+            .style(move |s| s.width(content_width())),
+    )
+    .style(|s| s.margin_left(25.0));
+
+    let grid = container((test))
         .style(|s| {
             s.width(1200.0)
                 .height(300.0)
-                .margin_top(25.0)
+                .margin_top(5.0)
                 .margin_left(25.0)
                 .background(Color::LIGHT_CORAL)
         })
@@ -688,6 +1103,8 @@ pub fn create_timeline(
                 handle.state,
                 handle.config.clone(),
                 handle.animation_data,
+                handle.color_tracks,
+                handle.shape_tracks,
                 position,
                 selected_sequence_data,
             );
@@ -703,9 +1120,12 @@ pub fn create_timeline(
             );
 
             handle_mouse_move(
+                editor_state_move.clone(),
                 handle_move.state,
                 handle_move.config.clone(),
                 handle_move.animation_data,
+                handle_move.color_tracks,
+                handle_move.shape_tracks,
                 position,
             );
             handle.view_id.request_paint(); // Request repaint after state change
@@ -718,17 +1138,44 @@ pub fn create_timeline(
                 e.point().expect("Couldn't get point").x as f64,
                 e.point().expect("Couldn't get point").y as f64,
             );
-            handle_mouse_up(handle_up.state, position);
+            handle_mouse_up(
+                editor_up.clone(),
+                editor_state_up.clone(),
+                handle_up.state,
+                handle_up.animation_data,
+                handle_up.color_tracks,
+                handle_up.shape_tracks,
+                position,
+                selected_sequence_data,
+            );
             handle.view_id.request_paint(); // Request repaint after state change
             EventPropagation::Continue
         })
         .on_event(EventListener::PointerWheel, move |e| {
-            println!("PointerWheel");
-            // Add wheel handling
-            handle_scroll(handle_wheel.state, 0.1);
+            let (x, delta_x, delta_y) = match wheel_event(e) {
+                Some(wheel) => wheel,
+                None => return EventPropagation::Continue,
+            };
+            let zoom_key = editor_state_wheel
+                .lock()
+                .unwrap()
+                .current_modifiers
+                .control_key();
+
+            let propagation = handle_scroll(
+                handle_wheel.state,
+                handle_wheel.config.clone(),
+                handle_wheel.animation_data,
+                x,
+                delta_x,
+                delta_y,
+                zoom_key,
+            );
             handle.view_id.request_paint(); // Request repaint after state change
-            EventPropagation::Continue
-        })
+            propagation
+        });
+
+    v_stack((zoom_buttons, ruler, grid))
 }
 
 fn handle_mouse_down(
@@ -737,6 +1184,8 @@ fn handle_mouse_down(
     state: RwSignal<TimelineState>,
     config: TimelineConfig,
     animation_data: RwSignal<Option<AnimationData>>,
+    color_tracks: RwSignal<Vec<ColorTrack>>,
+    shape_tracks: RwSignal<Vec<ShapeTrack>>,
     pos: Point,
     selected_sequence_data: RwSignal<Sequence>,
 ) -> EventPropagation {
@@ -760,8 +1209,30 @@ fn handle_mouse_down(
         });
         let mut new_selection = Vec::new();
         new_selection.push(ui_keyframe);
+        state.get().selected_track_keyframe.set(None);
         state.get().selected_keyframes.set(new_selection);
         return EventPropagation::Stop;
+    } else if let Some(track_keyframe) = hit_test_track_keyframe(
+        state,
+        config.clone(),
+        &animation_data.get().expect("Couldn't get animation data"),
+        &color_tracks.get(),
+        &shape_tracks.get(),
+        pos,
+    ) {
+        state.update(|s| {
+            s.dragging = Some(DragOperation::TrackKeyframe {
+                keyframe_id: track_keyframe.id.clone(),
+                original_time: Duration::from_millis(track_keyframe.time_ms),
+                start_x: pos.x,
+            })
+        });
+        state.get().selected_keyframes.set(Vec::new());
+        state
+            .get()
+            .selected_track_keyframe
+            .set(Some(track_keyframe.id));
+        return EventPropagation::Stop;
     } else if let (Some(pos), Some(prop)) = (
         state.get().hover_position.get(),
         state.get().hover_property.get(),
@@ -769,6 +1240,68 @@ fn handle_mouse_down(
         // handle plus icon click
         println!("Add keyframe...");
 
+        let hold_keyframes = editor_state.lock().unwrap().hold_keyframes.get_untracked();
+
+        if let Some(target) = ColorTarget::from_path(&prop) {
+            let animation = animation_data.get().expect("Couldn't get animation data");
+            let time = x_to_time(state, config, pos.x);
+
+            // key the color already showing at that time
+            let color = color_tracks
+                .get()
+                .iter()
+                .find(|t| t.animation_id == animation.id && t.target == target)
+                .and_then(|t| sample_color(t, time, &hold_keyframes))
+                .or_else(|| {
+                    base_color(&selected_sequence_data.get(), &animation.polygon_id, target)
+                })
+                .unwrap_or([255, 255, 255, 255]);
+
+            let mut editor_state = editor_state.lock().unwrap();
+
+            let keyframe_id =
+                editor_state.key_color(animation.id, target, time.as_millis() as u64, color);
+
+            drop(editor_state);
+
+            state.get().selected_keyframes.set(Vec::new());
+            state.get().selected_track_keyframe.set(Some(keyframe_id));
+
+            return EventPropagation::Stop;
+        }
+
+        if let Some(property) = ShapeProperty::from_path(&prop) {
+            let animation = animation_data.get().expect("Couldn't get animation data");
+            let time = x_to_time(state, config, pos.x);
+
+            // key the value already showing at that time
+            let value = shape_tracks
+                .get()
+                .iter()
+                .find(|t| t.animation_id == animation.id && t.property == property)
+                .and_then(|t| sample_shape(t, time, &hold_keyframes))
+                .or_else(|| {
+                    base_shape_value(
+                        &selected_sequence_data.get(),
+                        &animation.polygon_id,
+                        property,
+                    )
+                })
+                .unwrap_or(100);
+
+            let mut editor_state = editor_state.lock().unwrap();
+
+            let keyframe_id =
+                editor_state.key_shape(animation.id, property, time.as_millis() as u64, value);
+
+            drop(editor_state);
+
+            state.get().selected_keyframes.set(Vec::new());
+            state.get().selected_track_keyframe.set(Some(keyframe_id));
+
+            return EventPropagation::Stop;
+        }
+
         let mut anim_data = animation_data.get().expect("Couldn't get animation data");
         let mut anim_prop = anim_data
             .properties
@@ -869,9 +1402,9 @@ fn handle_mouse_down(
 
     // Check if clicking on timeline (for playhead)
     if pos.y <= config.header_height {
-        let time = x_to_time(state, config, pos.x);
+        let time = playhead_at(animation_data, x_to_time(state, config, pos.x));
         println!("start move playhead {:?}", time);
-        state.update(|s| s.current_time = time);
+        state.get().current_time.set(time);
         state.update(|s| s.dragging = Some(DragOperation::Playhead(pos.x)));
         return EventPropagation::Stop;
     }
@@ -879,10 +1412,69 @@ fn handle_mouse_down(
     EventPropagation::Continue
 }
 
+/// Playhead for a time on this timeline, which shows the animation's own time
+fn playhead_at(animation_data: RwSignal<Option<AnimationData>>, time: Duration) -> Duration {
+    animation_data
+        .get_untracked()
+        .map(|animation| sequence_time(&animation, time))
+        .unwrap_or(time)
+}
+
+/// Snap a dragged keyframe time, returning the time and where to draw the snap indicator.
+/// Alt bypasses snapping.
+fn snap_dragged_time(
+    editor_state: Arc<Mutex<EditorState>>,
+    state_data: &TimelineState,
+    config: &TimelineConfig,
+    animation: &AnimationData,
+    dragged_id: Option<&str>,
+    new_time: Duration,
+) -> (Duration, Option<Duration>) {
+    let editor_state = editor_state.lock().unwrap();
+    let bypass_snap = editor_state.current_modifiers.alt_key();
+    let snap_settings = editor_state.snap_settings;
+    let frame_rate = editor_state.frame_rate.get_untracked();
+    let markers = editor_state
+        .sequence_data_signal
+        .map(|sequence| {
+            marker_times(
+                &editor_state.markers.get_untracked(),
+                Some(&sequence.get_untracked().id),
+            )
+        })
+        .unwrap_or_default();
+    drop(editor_state);
+
+    if bypass_snap {
+        return (new_time, None);
+    }
+
+    let candidates = keyframe_snap_candidates(
+        animation,
+        dragged_id,
+        state_data.current_time.get(),
+        &markers,
+    );
+    let threshold =
+        Duration::from_secs_f64(SNAP_THRESHOLD_PX / state_data.zoom.pixels_per_s.get_untracked());
+    let snapped = snap_time(new_time, &candidates, &snap_settings, frame_rate, threshold);
+
+    let snap_indicator = if snapped.target.is_some() && snapped.target != Some(SnapTarget::Frame) {
+        Some(snapped.time)
+    } else {
+        None
+    };
+
+    (snapped.time, snap_indicator)
+}
+
 fn handle_mouse_move(
+    editor_state: Arc<Mutex<EditorState>>,
     state: RwSignal<TimelineState>,
     config: TimelineConfig,
     animation_data: RwSignal<Option<AnimationData>>,
+    color_tracks: RwSignal<Vec<ColorTrack>>,
+    shape_tracks: RwSignal<Vec<ShapeTrack>>,
     pos: Point,
 ) -> EventPropagation {
     let state_data = state.get();
@@ -891,8 +1483,8 @@ fn handle_mouse_move(
         match dragging {
             DragOperation::Playhead(_) => {
                 println!("moving playhead");
-                let value = x_to_time(state, config.clone(), pos.x);
-                state.update(|s| s.current_time = value);
+                let value = playhead_at(animation_data, x_to_time(state, config.clone(), pos.x));
+                state.get().current_time.set(value);
                 return EventPropagation::Stop;
             }
             DragOperation::Keyframe {
@@ -907,10 +1499,73 @@ fn handle_mouse_move(
                     time_to_x(state, config.clone(), *original_time) + delta_x,
                 );
 
-                println!("moving keyframe {:?}", new_time);
+                let dragged_id = match state_data.selected_keyframes.get().get(0) {
+                    Some(keyframe) => keyframe.id.clone(),
+                    None => return EventPropagation::Stop,
+                };
+                let mut anim_data = animation_data.get().expect("Couldn't get animation data");
+
+                let (snapped_time, snap_indicator) = snap_dragged_time(
+                    editor_state,
+                    &state_data,
+                    &config,
+                    &anim_data,
+                    Some(&dragged_id),
+                    new_time,
+                );
 
-                // TODO: update_keyframe_time
-                // self.update_keyframe_time(property_path, *original_time, new_time);
+                println!("moving keyframe {:?}", snapped_time);
+
+                if let Some(keyframe) = find_keyframe_mut(&mut anim_data.properties, &dragged_id) {
+                    keyframe.time = snapped_time;
+                }
+
+                animation_data.set(Some(anim_data));
+                state.update(|s| s.snap_indicator = snap_indicator);
+
+                return EventPropagation::Stop;
+            }
+            DragOperation::TrackKeyframe {
+                keyframe_id,
+                original_time,
+                start_x,
+            } => {
+                let delta_x = pos.x - start_x;
+                let new_time = x_to_time(
+                    state,
+                    config.clone(),
+                    time_to_x(state, config.clone(), *original_time) + delta_x,
+                );
+
+                let anim_data = animation_data.get().expect("Couldn't get animation data");
+
+                let (snapped_time, snap_indicator) = snap_dragged_time(
+                    editor_state,
+                    &state_data,
+                    &config,
+                    &anim_data,
+                    None,
+                    new_time,
+                );
+
+                // the id is only on one of the two
+                let time_ms = snapped_time.as_millis() as u64;
+
+                color_tracks.update(|tracks| {
+                    tracks
+                        .iter_mut()
+                        .flat_map(|t| t.keyframes.iter_mut())
+                        .filter(|k| k.id == *keyframe_id)
+                        .for_each(|k| k.time_ms = time_ms);
+                });
+                shape_tracks.update(|tracks| {
+                    tracks
+                        .iter_mut()
+                        .flat_map(|t| t.keyframes.iter_mut())
+                        .filter(|k| k.id == *keyframe_id)
+                        .for_each(|k| k.time_ms = time_ms);
+                });
+                state.update(|s| s.snap_indicator = snap_indicator);
 
                 return EventPropagation::Stop;
             }
@@ -937,6 +1592,24 @@ fn handle_mouse_move(
             state.update(|s| s.hovered_keyframe = None);
         }
 
+        if let Some(data) = animation_data.get() {
+            if hit_test_track_keyframe(
+                state,
+                config.clone(),
+                &data,
+                &color_tracks.get(),
+                &shape_tracks.get(),
+                pos,
+            )
+            .is_some()
+            {
+                state.get().hover_position.set(None);
+                state.get().hover_property.set(None);
+
+                return EventPropagation::Continue;
+            }
+        }
+
         let row_height = config.row_height;
         let header_height = config.header_height;
 
@@ -961,6 +1634,22 @@ fn handle_mouse_move(
                 }
                 current_row += 1;
             }
+
+            // track rows sit below every property row
+            let track_start = property_row_count(&data.properties, &state_data.property_expansions);
+            let rows = track_rows(&data.object_type);
+
+            if let Some(row) = row_index
+                .checked_sub(track_start)
+                .and_then(|index| rows.get(index))
+            {
+                state.get().hover_property.set(Some(row.path().to_string()));
+                state.get().hover_position.set(Some(Point::new(
+                    pos.x,
+                    header_height + (row_index as f64 * row_height) + (row_height / 2.0),
+                )));
+                return EventPropagation::Continue;
+            }
         }
 
         // Clear hover state if not over a property row
@@ -971,34 +1660,140 @@ fn handle_mouse_move(
     }
 }
 
-fn handle_mouse_up(state: RwSignal<TimelineState>, _pos: Point) -> EventPropagation {
-    state.update(|s| s.dragging = None);
-    EventPropagation::Stop
-}
+fn handle_mouse_up(
+    editor: Arc<Mutex<Editor>>,
+    editor_state: Arc<Mutex<EditorState>>,
+    state: RwSignal<TimelineState>,
+    animation_data: RwSignal<Option<AnimationData>>,
+    color_tracks: RwSignal<Vec<ColorTrack>>,
+    shape_tracks: RwSignal<Vec<ShapeTrack>>,
+    _pos: Point,
+    selected_sequence_data: RwSignal<Sequence>,
+) -> EventPropagation {
+    let was_dragging_keyframe =
+        matches!(state.get().dragging, Some(DragOperation::Keyframe { .. }));
+    let dragged_track_keyframe = match state.get().dragging {
+        Some(DragOperation::TrackKeyframe { keyframe_id, .. }) => Some(keyframe_id),
+        _ => None,
+    };
 
-fn handle_scroll(state: RwSignal<TimelineState>, delta: f64) -> EventPropagation {
-    let state_data = state.get();
+    state.update(|s| {
+        s.dragging = None;
+        s.snap_indicator = None;
+    });
 
-    println!("handle_scroll");
-    if delta != 0.0 {
-        // Adjust zoom level based on scroll
-        let old_zoom = state_data.zoom_level;
-        state.update(|s| {
-            s.zoom_level = (state_data.zoom_level * (1.0 + delta * 0.001))
-                .max(0.1)
-                .min(10.0)
-        });
+    // persist the dragged color or shape keyframe time
+    if let Some(keyframe_id) = dragged_track_keyframe {
+        let color_keyframe = color_tracks
+            .get()
+            .iter()
+            .flat_map(|t| t.keyframes.iter())
+            .find(|k| k.id == keyframe_id)
+            .cloned();
+        let shape_keyframe = shape_tracks
+            .get()
+            .iter()
+            .flat_map(|t| t.keyframes.iter())
+            .find(|k| k.id == keyframe_id)
+            .cloned();
 
-        // Adjust scroll offset to keep the timeline position under the cursor
-        // May want to use the cursor position for more precise zooming
-        let zoom_ratio = state_data.zoom_level / old_zoom;
+        let mut editor_state = editor_state.lock().unwrap();
 
-        state.update(|s| {
-            s.scroll_offset *= zoom_ratio;
-        });
+        if let Some(keyframe) = color_keyframe {
+            editor_state.update_color_keyframe(keyframe);
+        } else if let Some(keyframe) = shape_keyframe {
+            editor_state.update_shape_keyframe(keyframe);
+        }
 
-        EventPropagation::Stop
-    } else {
-        EventPropagation::Continue
+        return EventPropagation::Stop;
+    }
+
+    if !was_dragging_keyframe {
+        return EventPropagation::Stop;
+    }
+
+    // persist the dragged keyframe time
+    let mut anim_data = animation_data.get().expect("Couldn't get animation data");
+
+    sort_keyframes(&mut anim_data.properties);
+
+    animation_data.set(Some(anim_data.clone()));
+
+    if let Some(selected_keyframe) = state.get().selected_keyframes.get().get(0) {
+        let moved_keyframe =
+            find_keyframe_mut(&mut anim_data.properties, &selected_keyframe.id).cloned();
+
+        if let Some(moved_keyframe) = moved_keyframe {
+            state.get().selected_keyframes.set(vec![moved_keyframe]);
+        }
     }
+
+    let mut editor_state = editor_state.lock().unwrap();
+    let mut new_state = editor_state
+        .record_state
+        .saved_state
+        .as_mut()
+        .expect("Couldn't get Saved State")
+        .clone();
+
+    new_state.sequences.iter_mut().for_each(|s| {
+        if s.id == selected_sequence_data.get().id {
+            s.polygon_motion_paths.iter_mut().for_each(|pm| {
+                if pm.id == anim_data.id {
+                    *pm = anim_data.clone();
+                }
+            });
+
+            selected_sequence_data.set(s.clone());
+        }
+    });
+
+    editor_state.record_state.saved_state = Some(new_state.clone());
+
+    save_saved_state_raw(new_state.clone());
+
+    drop(editor_state);
+
+    let mut editor = editor.lock().unwrap();
+
+    let updated_sequence = selected_sequence_data.get();
+
+    editor.current_sequence_data = Some(updated_sequence.clone());
+    editor.update_motion_paths(&updated_sequence);
+
+    drop(editor);
+
+    EventPropagation::Stop
+}
+
+/// Ctrl and the wheel zooms to the pointer, the wheel alone scrolls through the animation
+fn handle_scroll(
+    state: RwSignal<TimelineState>,
+    config: TimelineConfig,
+    animation_data: RwSignal<Option<AnimationData>>,
+    x: f64,
+    delta_x: f64,
+    delta_y: f64,
+    zoom_key: bool,
+) -> EventPropagation {
+    if delta_x == 0.0 && delta_y == 0.0 {
+        return EventPropagation::Continue;
+    }
+
+    let zoom = state.get_untracked().zoom;
+    let duration = animation_data
+        .get_untracked()
+        .map(|animation| animation.duration)
+        .unwrap_or_default();
+    let content_width = duration.as_secs_f64() * zoom.pixels_per_s.get_untracked();
+
+    zoom.wheel(
+        x - config.offset_x,
+        delta_x,
+        delta_y,
+        zoom_key,
+        content_width - config.width,
+    );
+
+    EventPropagation::Stop
 }
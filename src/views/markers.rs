@@ -0,0 +1,305 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use floem::action::debounce_action;
+use floem::common::{input_styles, simple_button};
+use floem::event::EventPropagation;
+use floem::peniko::Color;
+use floem::reactive::{create_rw_signal, RwSignal, SignalGet, SignalUpdate};
+use floem::taffy::Position;
+use floem::views::*;
+use floem::IntoView;
+use floem::View;
+
+use crate::editor_state::EditorState;
+use crate::helpers::clock::timecode;
+use crate::helpers::markers::{
+    color_from_label, color_label, markers_on, DEFAULT_REGION_MS, MARKER_COLORS,
+};
+use crate::helpers::saved_state::Marker;
+
+use super::inputs::{inline_dropdown, DropdownOption};
+
+const RULER_HEIGHT: f64 = 18.0;
+
+fn marker_color(marker: &Marker, alpha: u8) -> Color {
+    Color::rgba8(marker.color[0], marker.color[1], marker.color[2], alpha)
+}
+
+fn ms_to_x(time_ms: i32, pixels_per_s: f64) -> f64 {
+    time_ms.max(0) as f64 * pixels_per_s / 1000.0
+}
+
+/// Flags and region bands above a timeline's tracks, clicking one jumps the playhead to it
+pub fn marker_ruler<M, P>(markers: M, pixels_per_s: P, playhead: RwSignal<Duration>) -> impl View
+where
+    M: Fn() -> Vec<Marker> + 'static,
+    P: Fn() -> f64 + Copy + 'static,
+{
+    dyn_stack(
+        markers,
+        |marker| marker.clone(),
+        move |marker| {
+            let time = Duration::from_millis(marker.time_ms.max(0) as u64);
+            let title = marker.label.clone();
+            let flag = marker.clone();
+
+            container(label(move || title.clone()))
+                .on_click(move |_| {
+                    playhead.set(time);
+                    EventPropagation::Stop
+                })
+                .style(move |s| {
+                    let pixels_per_s = pixels_per_s();
+                    let s = s
+                        .absolute()
+                        .inset_left(ms_to_x(flag.time_ms, pixels_per_s))
+                        .height(RULER_HEIGHT)
+                        .padding_horiz(3.0)
+                        .font_size(10.0)
+                        .selectable(false);
+
+                    match flag.end_time_ms {
+                        Some(end_time_ms) => s
+                            .width(ms_to_x(end_time_ms - flag.time_ms, pixels_per_s))
+                            .background(marker_color(&flag, 90))
+                            .border_left(2.0)
+                            .border_color(marker_color(&flag, 255)),
+                        None => s.border_left(2.0).border_color(marker_color(&flag, 255)),
+                    }
+                })
+        },
+    )
+    .style(|s| s.position(Position::Relative).height(RULER_HEIGHT))
+}
+
+/// Thin lines down a track at every marker and both ends of every region
+pub fn marker_lines<M, P>(markers: M, pixels_per_s: P, height: f64) -> impl View
+where
+    M: Fn() -> Vec<Marker> + 'static,
+    P: Fn() -> f64 + Copy + 'static,
+{
+    dyn_stack(
+        move || {
+            markers()
+                .into_iter()
+                .flat_map(|marker| {
+                    let color = marker.color;
+
+                    std::iter::once((marker.time_ms, color))
+                        .chain(marker.end_time_ms.map(|end_time_ms| (end_time_ms, color)))
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>()
+        },
+        |line| *line,
+        move |(time_ms, color)| {
+            empty().style(move |s| {
+                s.absolute()
+                    .inset_left(ms_to_x(time_ms, pixels_per_s()))
+                    .width(1.0)
+                    .height(height)
+                    .background(Color::rgb8(color[0], color[1], color[2]))
+                    .z_index(5)
+            })
+        },
+    )
+    .style(|s| s.absolute())
+}
+
+/// Add markers and regions at the playhead, listed in order to jump to and edit
+pub fn marker_list<S>(
+    editor_state: Arc<Mutex<EditorState>>,
+    sequence_id: S,
+    playhead: RwSignal<Duration>,
+) -> impl View
+where
+    S: Fn() -> Option<String> + Copy + 'static,
+{
+    let (markers, frame_rate) = {
+        let editor_state = editor_state.lock().unwrap();
+
+        (editor_state.markers, editor_state.frame_rate)
+    };
+
+    let editor_state2 = Arc::clone(&editor_state);
+    let editor_state3 = Arc::clone(&editor_state);
+
+    let add = move |editor_state: &Arc<Mutex<EditorState>>, region: bool| {
+        let time_ms = playhead.get_untracked().as_millis() as i32;
+        let end_time_ms = if region {
+            Some(time_ms + DEFAULT_REGION_MS)
+        } else {
+            None
+        };
+
+        editor_state
+            .lock()
+            .unwrap()
+            .add_marker(sequence_id(), time_ms, end_time_ms);
+    };
+
+    v_stack((
+        h_stack((
+            label(|| "Markers").style(|s| s.margin_right(10.0).selectable(false)),
+            simple_button("Add Marker".to_string(), move |_| {
+                add(&editor_state2, false);
+            })
+            .style(|s| s.margin_right(5.0)),
+            simple_button("Add Region".to_string(), move |_| {
+                add(&editor_state3, true);
+            }),
+        ))
+        .style(|s| s.items_center().margin_bottom(5.0)),
+        dyn_stack(
+            move || markers_on(&markers.get(), sequence_id().as_deref()),
+            |marker| (marker.id.clone(), marker.time_ms, marker.end_time_ms),
+            move |marker| marker_row(editor_state.clone(), marker, playhead, frame_rate),
+        )
+        .style(|s| s.flex_col()),
+    ))
+}
+
+/// Jump button, label, color and note of a marker
+fn marker_row(
+    editor_state: Arc<Mutex<EditorState>>,
+    marker: Marker,
+    playhead: RwSignal<Duration>,
+    frame_rate: RwSignal<u32>,
+) -> impl View {
+    let marker_label = create_rw_signal(marker.label.clone());
+    let note = create_rw_signal(marker.note.clone());
+    let selected_color = create_rw_signal(color_label(marker.color));
+    let color_options = create_rw_signal(
+        MARKER_COLORS
+            .iter()
+            .map(|(label, _)| DropdownOption {
+                id: label.to_string(),
+                label: label.to_string(),
+            })
+            .collect::<Vec<_>>(),
+    );
+
+    let marker_id = marker.id.clone();
+    let start = Duration::from_millis(marker.time_ms.max(0) as u64);
+    let end = marker
+        .end_time_ms
+        .map(|end_time_ms| Duration::from_millis(end_time_ms.max(0) as u64));
+
+    // changes go onto the stored marker, so edits from other rows aren't undone
+    let edit = move |editor_state: &Arc<Mutex<EditorState>>, change: &dyn Fn(&mut Marker)| {
+        let mut editor_state = editor_state.lock().unwrap();
+
+        let mut marker = match editor_state
+            .markers
+            .get_untracked()
+            .into_iter()
+            .find(|m| m.id == marker_id)
+        {
+            Some(marker) => marker,
+            None => return,
+        };
+
+        change(&mut marker);
+
+        editor_state.update_marker(marker);
+    };
+
+    let editor_state2 = Arc::clone(&editor_state);
+    let editor_state3 = Arc::clone(&editor_state);
+    let editor_state4 = Arc::clone(&editor_state);
+    let editor_state5 = Arc::clone(&editor_state);
+    let editor_state6 = Arc::clone(&editor_state);
+    let edit2 = edit.clone();
+    let edit3 = edit.clone();
+    let edit4 = edit.clone();
+    let edit5 = edit.clone();
+    let remove_id = marker.id.clone();
+
+    debounce_action(marker_label, Duration::from_millis(300), move || {
+        edit(&editor_state2, &|m: &mut Marker| {
+            m.label = marker_label.get_untracked()
+        });
+    });
+
+    debounce_action(note, Duration::from_millis(300), move || {
+        edit2(&editor_state3, &|m: &mut Marker| {
+            m.note = note.get_untracked()
+        });
+    });
+
+    let swatch = marker_color(&marker, 255);
+
+    let end_button = if end.is_some() {
+        simple_button("End at Playhead".to_string(), move |_| {
+            let time_ms = playhead.get_untracked().as_millis() as i32;
+
+            edit5(&editor_state6, &|m: &mut Marker| {
+                m.end_time_ms = Some(time_ms.max(m.time_ms + 1))
+            });
+        })
+        .style(|s| s.margin_right(5.0))
+        .into_any()
+    } else {
+        empty().into_any()
+    };
+
+    h_stack((
+        empty().style(move |s| {
+            s.width(10.0)
+                .height(10.0)
+                .background(swatch)
+                .margin_right(5.0)
+        }),
+        button(label(move || {
+            let frame_rate = frame_rate.get();
+
+            match end {
+                Some(end) => format!(
+                    "{} - {}",
+                    timecode(start, frame_rate),
+                    timecode(end, frame_rate)
+                ),
+                None => timecode(start, frame_rate),
+            }
+        }))
+        .on_click(move |_| {
+            playhead.set(start);
+            EventPropagation::Stop
+        })
+        .style(|s| s.margin_right(5.0)),
+        text_input(marker_label).style(|s| input_styles(s).width(100.0).margin_right(5.0)),
+        inline_dropdown(
+            "Color".to_string(),
+            selected_color,
+            color_options,
+            move |option| {
+                let color = match color_from_label(&option) {
+                    Some(color) => color,
+                    None => return,
+                };
+
+                selected_color.set(option);
+                edit3(&editor_state4, &|m: &mut Marker| m.color = color);
+            },
+        ),
+        text_input(note)
+            .placeholder("Note")
+            .style(|s| input_styles(s).width(140.0).margin_horiz(5.0)),
+        simple_button("Move to Playhead".to_string(), move |_| {
+            let time_ms = playhead.get_untracked().as_millis() as i32;
+
+            // regions keep their length
+            edit4(&editor_state5, &|m: &mut Marker| {
+                m.end_time_ms = m.end_time_ms.map(|end| time_ms + end - m.time_ms);
+                m.time_ms = time_ms;
+            });
+        })
+        .style(|s| s.margin_right(5.0)),
+        end_button,
+        simple_button("Remove".to_string(), move |_| {
+            editor_state.lock().unwrap().remove_marker(&remove_id);
+        }),
+    ))
+    .style(|s| s.items_center().margin_bottom(5.0))
+}
@@ -7,6 +7,8 @@ pub mod images_panel;
 pub mod inputs;
 pub mod keyframe_panel;
 pub mod keyframe_timeline;
+pub mod markers;
+pub mod nested;
 pub mod object_timeline;
 pub mod project_browser;
 pub mod properties_panel;
@@ -16,3 +18,4 @@ pub mod sequence_timeline;
 pub mod sequences_panel;
 pub mod settings_panel;
 pub mod upload_field;
+pub mod zoom;
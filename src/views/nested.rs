@@ -0,0 +1,351 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use floem::action::debounce_action;
+use floem::common::{input_styles, simple_button};
+use floem::reactive::{create_rw_signal, RwSignal, SignalGet, SignalUpdate};
+use floem::views::*;
+use floem::IntoView;
+use floem::View;
+use stunts_engine::animations::{KeyframeValue, Sequence};
+use stunts_engine::editor::Editor;
+
+use crate::editor_state::EditorState;
+use crate::helpers::clock::timecode;
+use crate::helpers::nested::{
+    key_placement, nestable_sequences, nested_in, nested_objects, sample_placement,
+};
+use crate::helpers::saved_state::NestedSequence;
+use crate::helpers::timeline_playback::set_sequence_hidden;
+
+use super::inputs::{inline_dropdown, DropdownOption};
+
+/// Dropdown options for the sequences that can go inside one, with the sequence id of each.
+/// Names are numbered when they repeat, the dropdown only shows ids.
+fn nestable_options(editor_state: &EditorState, parent_id: &str) -> Vec<(DropdownOption, String)> {
+    let saved_state = match editor_state.record_state.saved_state.as_ref() {
+        Some(saved_state) => saved_state,
+        None => return Vec::new(),
+    };
+
+    let mut options: Vec<(DropdownOption, String)> = Vec::new();
+
+    for sequence in nestable_sequences(saved_state, parent_id) {
+        let repeats = options
+            .iter()
+            .filter(|(_, id)| {
+                saved_state
+                    .sequences
+                    .iter()
+                    .any(|s| s.id == *id && s.name == sequence.name)
+            })
+            .count();
+        let label = if repeats > 0 {
+            format!("{} ({})", sequence.name, repeats + 1)
+        } else {
+            sequence.name.clone()
+        };
+
+        options.push((
+            DropdownOption {
+                id: label.clone(),
+                label,
+            },
+            sequence.id.clone(),
+        ));
+    }
+
+    options
+}
+
+/// Nest other sequences in the open one at the playhead, listed with their timing and placement
+pub fn nested_sequence_list(
+    editor: Arc<Mutex<Editor>>,
+    editor_state: Arc<Mutex<EditorState>>,
+    selected_sequence_data: RwSignal<Sequence>,
+) -> impl View {
+    let (nested_sequences, keyframe_playhead, frame_rate, options) = {
+        let editor_state = editor_state.lock().unwrap();
+        let options = nestable_options(&editor_state, &selected_sequence_data.get_untracked().id);
+
+        (
+            editor_state.nested_sequences,
+            editor_state.keyframe_playhead,
+            editor_state.frame_rate,
+            options,
+        )
+    };
+
+    let selected_child = create_rw_signal(String::new());
+    let dropdown_options = create_rw_signal(
+        options
+            .iter()
+            .map(|(option, _)| option.clone())
+            .collect::<Vec<_>>(),
+    );
+    let child_options = create_rw_signal(options);
+
+    let editor_state2 = Arc::clone(&editor_state);
+    let editor2 = Arc::clone(&editor);
+
+    v_stack((
+        h_stack((
+            label(|| "Nested Sequences").style(|s| s.margin_right(10.0).selectable(false)),
+            inline_dropdown(
+                "Sequence".to_string(),
+                selected_child,
+                dropdown_options,
+                move |option| selected_child.set(option),
+            ),
+            simple_button("Nest at Playhead".to_string(), move |_| {
+                let child_id = match child_options
+                    .get_untracked()
+                    .into_iter()
+                    .find(|(option, _)| option.id == selected_child.get_untracked())
+                {
+                    Some((_, child_id)) => child_id,
+                    None => return,
+                };
+                let parent_id = selected_sequence_data.get_untracked().id;
+                let start_time_ms = keyframe_playhead.get_untracked().as_millis() as i32;
+
+                let mut editor_state = editor_state2.lock().unwrap();
+
+                let nested =
+                    match editor_state.add_nested_sequence(&parent_id, &child_id, start_time_ms) {
+                        Ok(nested) => nested,
+                        Err(e) => {
+                            println!("Couldn't nest sequence: {}", e);
+                            return;
+                        }
+                    };
+
+                // the parent can't go into what it now contains
+                let options = nestable_options(&editor_state, &parent_id);
+                let objects = editor_state
+                    .record_state
+                    .saved_state
+                    .as_ref()
+                    .and_then(|saved_state| nested_objects(saved_state, &nested));
+
+                drop(editor_state);
+
+                dropdown_options.set(options.iter().map(|(option, _)| option.clone()).collect());
+                child_options.set(options);
+
+                // the copies of its objects are shown with the parent's
+                if let Some(objects) = objects {
+                    let mut editor = editor2.lock().unwrap();
+                    let camera = editor.camera.expect("Couldn't get camera");
+
+                    editor.restore_sequence_objects(
+                        &objects,
+                        camera.window_size.clone(),
+                        &camera,
+                        false,
+                    );
+                }
+            })
+            .style(|s| s.margin_left(5.0)),
+        ))
+        .style(|s| s.items_center().margin_bottom(5.0)),
+        dyn_stack(
+            move || {
+                let playhead = keyframe_playhead.get();
+
+                nested_in(&nested_sequences.get(), &selected_sequence_data.get().id)
+                    .into_iter()
+                    .map(|nested| (nested, playhead))
+                    .collect::<Vec<_>>()
+            },
+            // inputs show the placement at the playhead, so rows follow it
+            |(nested, playhead)| {
+                (
+                    nested.id.clone(),
+                    nested.start_time_ms,
+                    nested.time_offset_ms,
+                    nested.speed_pct,
+                    *playhead,
+                )
+            },
+            move |(nested, playhead)| {
+                nested_sequence_row(
+                    editor.clone(),
+                    editor_state.clone(),
+                    nested,
+                    playhead,
+                    frame_rate,
+                )
+            },
+        )
+        .style(|s| s.flex_col()),
+    ))
+}
+
+/// Start, offset and speed of a nested sequence, and its placement keyed at the playhead
+fn nested_sequence_row(
+    editor: Arc<Mutex<Editor>>,
+    editor_state: Arc<Mutex<EditorState>>,
+    nested: NestedSequence,
+    playhead: Duration,
+    frame_rate: RwSignal<u32>,
+) -> impl View {
+    // placement keys count from when the nested sequence starts
+    let local = playhead.saturating_sub(Duration::from_millis(nested.start_time_ms.max(0) as u64));
+    let placement =
+        |property_path: &str| sample_placement(&nested, property_path, local).unwrap_or_default();
+    let position = placement("position");
+    let value_at = |values: &[f64], index: usize| {
+        values
+            .get(index)
+            .map(|value| value.round().to_string())
+            .unwrap_or_default()
+    };
+
+    let start = create_rw_signal(nested.start_time_ms.to_string());
+    let offset = create_rw_signal(nested.time_offset_ms.to_string());
+    let speed = create_rw_signal(nested.speed_pct.to_string());
+    let x = create_rw_signal(value_at(&position, 0));
+    let y = create_rw_signal(value_at(&position, 1));
+    let scale = create_rw_signal(value_at(&placement("scale"), 0));
+    let opacity = create_rw_signal(value_at(&placement("opacity"), 0));
+
+    let nested_id = nested.id.clone();
+
+    // changes go onto the stored nested sequence, so edits from other inputs aren't undone
+    let edit = move |editor_state: &Arc<Mutex<EditorState>>,
+                     change: &dyn Fn(&mut NestedSequence)| {
+        let mut editor_state = editor_state.lock().unwrap();
+
+        let mut nested = match editor_state
+            .nested_sequences
+            .get_untracked()
+            .into_iter()
+            .find(|n| n.id == nested_id)
+        {
+            Some(nested) => nested,
+            None => return,
+        };
+
+        change(&mut nested);
+
+        editor_state.update_nested_sequence(nested);
+    };
+
+    let timing = |signal: RwSignal<String>, apply: fn(&mut NestedSequence, i32)| {
+        let editor_state = Arc::clone(&editor_state);
+        let edit = edit.clone();
+
+        debounce_action(signal, Duration::from_millis(300), move || {
+            if let Ok(value) = signal.get_untracked().trim().parse::<i32>() {
+                edit(&editor_state, &|n: &mut NestedSequence| apply(n, value));
+            }
+        });
+    };
+
+    timing(start, |n, value| n.start_time_ms = value);
+    timing(offset, |n, value| n.time_offset_ms = value);
+    timing(speed, |n, value| n.speed_pct = value.max(0) as u32);
+
+    // typing a placement value keys it at the playhead
+    let keyed = |signals: Vec<RwSignal<String>>, property_path: &'static str| {
+        for signal in signals.clone() {
+            let editor_state = Arc::clone(&editor_state);
+            let edit = edit.clone();
+            let signals = signals.clone();
+
+            debounce_action(signal, Duration::from_millis(300), move || {
+                let values: Vec<i32> = match signals
+                    .iter()
+                    .map(|s| s.get_untracked().trim().parse::<i32>())
+                    .collect()
+                {
+                    Ok(values) => values,
+                    Err(_) => return,
+                };
+                let value = match property_path {
+                    "position" => KeyframeValue::Position([values[0], values[1]]),
+                    "scale" => KeyframeValue::Scale(values[0]),
+                    _ => KeyframeValue::Opacity(values[0]),
+                };
+                let fps = frame_rate.get_untracked();
+
+                edit(&editor_state, &|n: &mut NestedSequence| {
+                    key_placement(n, property_path, local, value.clone(), fps)
+                });
+            });
+        }
+    };
+
+    keyed(vec![x, y], "position");
+    keyed(vec![scale], "scale");
+    keyed(vec![opacity], "opacity");
+
+    let field = |name: &'static str, signal: RwSignal<String>| {
+        h_stack((
+            label(move || name).style(|s| s.margin_right(3.0).selectable(false)),
+            text_input(signal).style(|s| input_styles(s).width(55.0).margin_right(5.0)),
+        ))
+        .style(|s| s.items_center())
+    };
+
+    let remove_id = nested.id.clone();
+    let title = nested.name.clone();
+
+    v_stack((
+        h_stack((
+            label(move || {
+                format!(
+                    "{} at {}",
+                    title,
+                    timecode(
+                        Duration::from_millis(nested.start_time_ms.max(0) as u64),
+                        frame_rate.get()
+                    )
+                )
+            })
+            .style(|s| s.margin_right(10.0).selectable(false)),
+            simple_button("Remove".to_string(), move |_| {
+                let mut editor_state = editor_state.lock().unwrap();
+
+                let objects = editor_state
+                    .nested_sequences
+                    .get_untracked()
+                    .iter()
+                    .find(|n| n.id == remove_id)
+                    .and_then(|nested| {
+                        editor_state
+                            .record_state
+                            .saved_state
+                            .as_ref()
+                            .and_then(|saved_state| nested_objects(saved_state, nested))
+                    });
+
+                editor_state.remove_nested_sequence(&remove_id);
+
+                drop(editor_state);
+
+                // its copies stay in the editor until the project is reopened
+                if let Some(objects) = objects {
+                    let mut editor = editor.lock().unwrap();
+
+                    set_sequence_hidden(&mut editor, &objects, true);
+                }
+            }),
+        ))
+        .style(|s| s.items_center().margin_bottom(3.0)),
+        h_stack((
+            field("Start", start),
+            field("Offset", offset),
+            field("Speed %", speed),
+        ))
+        .style(|s| s.margin_bottom(3.0)),
+        h_stack((
+            field("X", x),
+            field("Y", y),
+            field("Scale", scale),
+            field("Opacity", opacity),
+        )),
+    ))
+    .style(|s| s.margin_bottom(8.0))
+}
@@ -26,9 +26,12 @@ use serde::Serialize;
 use std::str::FromStr;
 use std::sync::Arc;
 use std::sync::Mutex;
+use std::time::Duration;
 use stunts_engine::animations::AnimationData;
+use stunts_engine::animations::EasingType;
 use stunts_engine::animations::ObjectType;
 use stunts_engine::animations::Sequence;
+use stunts_engine::editor::string_to_f32;
 use stunts_engine::editor::wgpu_to_human;
 use stunts_engine::editor::Editor;
 use stunts_engine::editor::Point;
@@ -43,7 +46,19 @@ use stunts_engine::timelines::TrackType;
 use uuid::Uuid;
 
 use crate::editor_state::EditorState;
+use crate::helpers::markers::{marker_times, markers_on};
+use crate::helpers::retime::RetimeScope;
+use crate::helpers::snapping::{
+    animation_snap_candidates, snap_animation_start, SNAP_THRESHOLD_PX,
+};
+use crate::helpers::stagger::{stagger_animations, StaggerConfig, StaggerOrder};
 use crate::helpers::utilities::save_saved_state_raw;
+use crate::helpers::zoom::TimelineZoom;
+
+use super::inputs::{debounce_input, inline_dropdown, DropdownOption};
+use super::markers::{marker_lines, marker_list, marker_ruler};
+use super::properties_panel::retime_tools;
+use super::zoom::{time_ruler, zoom_controls, zoom_viewport};
 
 pub fn build_object_timeline(
     editor: Arc<Mutex<Editor>>,
@@ -55,6 +70,38 @@ pub fn build_object_timeline(
 ) -> impl View {
     let editor_cloned = Arc::clone(&editor);
     let editor_state2 = Arc::clone(&editor_state);
+    let snap_indicator: RwSignal<Option<i32>> = create_rw_signal(None);
+    let stagger_selection: RwSignal<Vec<String>> = create_rw_signal(Vec::new());
+    let editor_state3 = Arc::clone(&editor_state);
+    let (keyframe_playhead, markers, frame_rate) = {
+        let editor_state = editor_state.lock().unwrap();
+
+        (
+            editor_state.keyframe_playhead,
+            editor_state.markers,
+            editor_state.frame_rate,
+        )
+    };
+    let zoom = TimelineZoom::new(pixels_per_s);
+    let view_width = move || timeline_width.get();
+    // room for the whole sequence, at least the width of the view
+    let content_width = move || {
+        let duration_s = selected_sequence_data.get().duration_ms.max(0) as f64 / 1000.0;
+
+        (duration_s * pixels_per_s.get()).max(timeline_width.get())
+    };
+    let sequence_id = move || Some(selected_sequence_data.get().id);
+    let sequence_markers = move || markers_on(&markers.get(), sequence_id().as_deref());
+    // pointer held on the ruler
+    let scrubbing = create_rw_signal(false);
+
+    // the paused preview follows the playhead to the time under the pointer
+    let scrub_to = move |x: f64| {
+        let duration_ms = selected_sequence_data.get_untracked().duration_ms.max(0) as f64;
+        let time_ms = (x * 1000.0 / pixels_per_s.get_untracked()).clamp(0.0, duration_ms);
+
+        keyframe_playhead.set(Duration::from_millis(time_ms as u64));
+    };
     // let pixels_per_s = create_rw_signal(deafult_pixels_per_s);
     // let timeline_width = create_rw_signal(700);
 
@@ -78,72 +125,316 @@ pub fn build_object_timeline(
     // });
 
     v_stack((
-        // Tick marks for the timeline
-        container(dyn_stack(
-            move || {
-                let total_s = selected_sequence_data.get().duration_ms / 1000;
-                (0..=total_s).collect::<Vec<_>>() // Create a tick for each second
-            },
-            move |time| time.to_string(), // Unique identifier
-            move |time| {
-                let pixels_per_second = pixels_per_s.get();
-                container((label(move || time.to_string())))
-                    .style(move |s| {
-                        s.width(1.0)
-                            .height(20.0)
-                            .background(Color::rgb8(50, 50, 50)) // Tick mark color
-                            .position(Position::Absolute)
-                            .margin_left(time as f64 * pixels_per_second)
+        stagger_tools(
+            editor_cloned.clone(),
+            editor_state2.clone(),
+            selected_sequence_data,
+            stagger_selection,
+        ),
+        retime_tools(editor_state2.clone(), selected_sequence_data, move || {
+            // checked tracks, or every animation when none are checked
+            Some(RetimeScope::Animations(stagger_selection.get()))
+        }),
+        zoom_controls(zoom, view_width, move || {
+            let duration_ms = selected_sequence_data.get_untracked().duration_ms.max(0);
+
+            zoom.fit(
+                Duration::from_millis(duration_ms as u64),
+                timeline_width.get_untracked(),
+            );
+        }),
+        zoom_viewport(
+            editor_state3,
+            zoom,
+            view_width,
+            content_width,
+            v_stack((
+                // Tick marks for the timeline
+                container(time_ruler(zoom, view_width, frame_rate))
+                    .on_event(EventListener::PointerDown, move |evt| {
+                        if let Some(point) = evt.point() {
+                            scrubbing.set(true);
+                            scrub_to(point.x);
+                        }
+                        EventPropagation::Continue
                     })
-                    .style(|s| s.margin_top(0.0))
-            },
-        ))
-        .style(|s| s.height(20).position(Position::Relative)),
-        // tracks
-        dyn_container(
-            move || selected_sequence_data.get(),
-            move |data| {
-                let editor = editor.clone();
-                let editor_state = editor_state.clone();
-                let timeline_width = timeline_width.clone();
-
-                if data.id.len() > 0 && data.polygon_motion_paths.len() > 0 {
-                    dyn_stack(
-                        move || data.polygon_motion_paths.clone(),
-                        move |timeline_animation| timeline_animation.id.clone(),
-                        {
-                            move |animation| {
-                                container(stack((
-                                    // background
-                                    container((empty()))
-                                        .style(move |s| {
-                                            s.width(timeline_width.get())
-                                                .height(50)
-                                                .background(Color::rgb8(200, 150, 100))
-                                                .z_index(1)
-                                        })
-                                        .style(|s| s.absolute().margin_left(0.0)),
-                                    // timeline_sequences
-                                    timeline_object_track(
-                                        editor.clone(),
-                                        editor_state.clone(),
-                                        selected_sequence_data,
-                                        pixels_per_s,
-                                        animation,
-                                    ),
-                                )))
-                                .style(|s| s.position(Position::Relative).height(50))
+                    .on_event(EventListener::PointerMove, move |evt| {
+                        if let (true, Some(point)) = (scrubbing.get_untracked(), evt.point()) {
+                            scrub_to(point.x);
+                        }
+                        EventPropagation::Continue
+                    })
+                    .on_event(EventListener::PointerUp, move |_| {
+                        scrubbing.set(false);
+                        EventPropagation::Continue
+                    })
+                    .style(move |s| {
+                        s.height(20)
+                            .width(content_width())
+                            .position(Position::Relative)
+                            .cursor(CursorStyle::ColResize)
+                    }),
+                marker_ruler(
+                    sequence_markers,
+                    move || pixels_per_s.get(),
+                    keyframe_playhead,
+                )
+                .style(move |s| s.width(content_width())),
+                // tracks
+                stack((
+                    dyn_container(
+                        // bars are laid out again when zoomed
+                        move || (selected_sequence_data.get(), pixels_per_s.get()),
+                        move |(data, _)| {
+                            let editor = editor.clone();
+                            let editor_state = editor_state.clone();
+                            let timeline_width = timeline_width.clone();
+
+                            if data.id.len() > 0 && data.polygon_motion_paths.len() > 0 {
+                                dyn_stack(
+                                    move || data.polygon_motion_paths.clone(),
+                                    move |timeline_animation| timeline_animation.id.clone(),
+                                    {
+                                        move |animation| {
+                                            container(stack((
+                                                // background
+                                                container((empty()))
+                                                    .style(move |s| {
+                                                        s.width(content_width())
+                                                            .height(50)
+                                                            .background(Color::rgb8(200, 150, 100))
+                                                            .z_index(1)
+                                                    })
+                                                    .style(|s| s.absolute().margin_left(0.0)),
+                                                marker_lines(
+                                                    sequence_markers,
+                                                    move || pixels_per_s.get(),
+                                                    50.0,
+                                                ),
+                                                // timeline_sequences
+                                                timeline_object_track(
+                                                    editor.clone(),
+                                                    editor_state.clone(),
+                                                    selected_sequence_data,
+                                                    zoom,
+                                                    animation,
+                                                    snap_indicator,
+                                                    stagger_selection,
+                                                ),
+                                            )))
+                                            .style(|s| s.position(Position::Relative).height(50))
+                                        }
+                                    },
+                                )
+                                .style(|s| s.flex_col().gap(1.0))
+                                .into_any()
+                            } else {
+                                container((empty())).into_any()
                             }
                         },
-                    )
-                    .style(|s| s.flex_col().gap(1.0))
-                    .into_any()
-                } else {
-                    container((empty())).into_any()
+                    ),
+                    // playhead
+                    container((empty())).style(move |s| {
+                        s.absolute()
+                            .inset_left(keyframe_playhead.get().as_secs_f64() * pixels_per_s.get())
+                            .inset_top(0.0)
+                            .width(2.0)
+                            .height_pct(100.0)
+                            .background(Color::RED)
+                            .z_index(9)
+                    }),
+                    // snap indicator
+                    container((empty())).style(move |s| match snap_indicator.get() {
+                        Some(time_ms) => s
+                            .absolute()
+                            .inset_left(time_ms as f64 * pixels_per_s.get() / 1000.0)
+                            .inset_top(0.0)
+                            .width(1.0)
+                            .height_pct(100.0)
+                            .background(Color::rgb8(0, 200, 255))
+                            .z_index(10),
+                        None => s.display(Display::None),
+                    }),
+                ))
+                .style(|s| s.position(Position::Relative)),
+            )),
+        ),
+        container(marker_list(editor_state2, sequence_id, keyframe_playhead))
+            .style(|s| s.margin_top(10.0)),
+    ))
+}
+
+/// Offset the start times of the checked animations (or all of them) as a cascade
+pub fn stagger_tools(
+    editor: Arc<Mutex<Editor>>,
+    editor_state: Arc<Mutex<EditorState>>,
+    selected_sequence_data: RwSignal<Sequence>,
+    stagger_selection: RwSignal<Vec<String>>,
+) -> impl View {
+    let editor_state2 = Arc::clone(&editor_state);
+
+    let interval_ms = create_rw_signal(100);
+    let mirror = create_rw_signal(false);
+    let selected_order = create_rw_signal("Layer".to_string());
+    let order_options = create_rw_signal(vec![
+        DropdownOption {
+            id: "Layer".to_string(),
+            label: "Layer".to_string(),
+        },
+        DropdownOption {
+            id: "Spatial".to_string(),
+            label: "Spatial".to_string(),
+        },
+        DropdownOption {
+            id: "Random".to_string(),
+            label: "Random".to_string(),
+        },
+    ]);
+    let selected_easing = create_rw_signal("Linear".to_string());
+    let easing_options = create_rw_signal(vec![
+        DropdownOption {
+            id: "Linear".to_string(),
+            label: "Linear".to_string(),
+        },
+        DropdownOption {
+            id: "EaseIn".to_string(),
+            label: "Ease In".to_string(),
+        },
+        DropdownOption {
+            id: "EaseOut".to_string(),
+            label: "Ease Out".to_string(),
+        },
+        DropdownOption {
+            id: "EaseInOut".to_string(),
+            label: "Ease In Out".to_string(),
+        },
+    ]);
+
+    h_stack((
+        debounce_input(
+            "Stagger (ms)".to_string(),
+            &interval_ms.get_untracked().to_string(),
+            "Milliseconds",
+            move |value| {
+                if let Ok(value) = string_to_f32(&value) {
+                    interval_ms.set(value as i32);
                 }
             },
+            editor_state2,
+            "stagger_interval".to_string(),
+            ObjectType::Polygon,
+        )
+        .style(|s| s.width(100.0).margin_right(5.0)),
+        inline_dropdown(
+            "Order".to_string(),
+            selected_order,
+            order_options,
+            move |order| selected_order.set(order),
         ),
+        inline_dropdown(
+            "Easing".to_string(),
+            selected_easing,
+            easing_options,
+            move |easing| selected_easing.set(easing),
+        ),
+        Checkbox::new_labeled_rw(mirror, || "Mirror from Center").style(|s| s.margin_right(5.0)),
+        simple_button("Apply Stagger".to_string(), move |_| {
+            let config = StaggerConfig {
+                interval_ms: interval_ms.get(),
+                easing: match selected_easing.get().as_str() {
+                    "EaseIn" => EasingType::EaseIn,
+                    "EaseOut" => EasingType::EaseOut,
+                    "EaseInOut" => EasingType::EaseInOut,
+                    _ => EasingType::Linear,
+                },
+                order: match selected_order.get().as_str() {
+                    "Spatial" => StaggerOrder::Spatial,
+                    "Random" => StaggerOrder::Random,
+                    _ => StaggerOrder::Layer,
+                },
+                mirror: mirror.get(),
+            };
+
+            let sequence = selected_sequence_data.get();
+            let mut anims: Vec<AnimationData> = sequence.polygon_motion_paths.clone();
+
+            stagger_animations(&sequence, &mut anims, &stagger_selection.get(), &config);
+
+            selected_sequence_data.update(|s| {
+                s.polygon_motion_paths = anims.clone();
+            });
+
+            // update the saved_state
+            let mut editor_state = editor_state.lock().unwrap();
+            let mut new_state = editor_state
+                .record_state
+                .saved_state
+                .as_mut()
+                .expect("Couldn't get Saved State")
+                .clone();
+
+            new_state.sequences.iter_mut().for_each(|s| {
+                if s.id == sequence.id {
+                    s.polygon_motion_paths = anims.clone();
+                }
+            });
+
+            editor_state.record_state.saved_state = Some(new_state.clone());
+
+            save_saved_state_raw(new_state.clone());
+
+            drop(editor_state);
+
+            let mut editor = editor.lock().unwrap();
+
+            editor.current_sequence_data = Some(selected_sequence_data.get());
+
+            drop(editor);
+
+            println!("Stagger applied!");
+        }),
     ))
+    .style(|s| s.margin_bottom(5.0))
+}
+
+/// Snap a dragged animation bar against the other bars, markers, playhead and sequence bounds.
+/// Returns the new start time and where the snap indicator should be drawn.
+fn snap_animation_drag(
+    editor_state: Arc<Mutex<EditorState>>,
+    sequence: &Sequence,
+    animation_id: &str,
+    animation_duration: Duration,
+    raw_time_ms: i32,
+    pixels_per_ms: f32,
+) -> (i32, Option<i32>) {
+    let editor_state = editor_state.lock().unwrap();
+    let bypass_snap = editor_state.current_modifiers.alt_key();
+    let snap_settings = editor_state.snap_settings;
+    let playhead = editor_state.keyframe_playhead.get();
+    let frame_rate = editor_state.frame_rate.get_untracked();
+    let markers = marker_times(&editor_state.markers.get_untracked(), Some(&sequence.id));
+    drop(editor_state);
+
+    if bypass_snap {
+        return (raw_time_ms.max(0), None);
+    }
+
+    let candidates = animation_snap_candidates(sequence, animation_id, playhead, &markers);
+    let threshold = Duration::from_secs_f64(SNAP_THRESHOLD_PX / (pixels_per_ms as f64 * 1000.0));
+
+    let (start, indicator) = snap_animation_start(
+        Duration::from_millis(raw_time_ms.max(0) as u64),
+        animation_duration,
+        &candidates,
+        &snap_settings,
+        frame_rate,
+        threshold,
+    );
+
+    (
+        start.as_millis() as i32,
+        indicator.map(|time| time.as_millis() as i32),
+    )
 }
 
 pub fn timeline_object_track(
@@ -151,8 +442,10 @@ pub fn timeline_object_track(
     editor_state: Arc<Mutex<EditorState>>,
     // timeline_animations: RwSignal<Vec<AnimationData>>,
     selected_sequence_data: RwSignal<Sequence>,
-    pixels_per_s: RwSignal<f64>,
+    zoom: TimelineZoom,
     animation: AnimationData,
+    snap_indicator: RwSignal<Option<i32>>,
+    stagger_selection: RwSignal<Vec<String>>,
 ) -> impl View {
     // let state_2 = state.clone();
     let editor_cloned = Arc::clone(&editor);
@@ -160,7 +453,26 @@ pub fn timeline_object_track(
     let dragger_id = create_rw_signal(String::new());
 
     let animation_id = animation.id.clone();
-    let pixels_per_ms = pixels_per_s.get() as f32 / 1000.0;
+    let animation_duration = animation.duration;
+
+    let stagger_checked =
+        create_rw_signal(stagger_selection.get_untracked().contains(&animation.id));
+
+    create_effect({
+        let animation_id = animation.id.clone();
+
+        move |_| {
+            let checked = stagger_checked.get();
+
+            stagger_selection.update(|selection| {
+                selection.retain(|id| *id != animation_id);
+                if checked {
+                    selection.push(animation_id.clone());
+                }
+            });
+        }
+    });
+    let pixels_per_ms = zoom.pixels_per_s.get_untracked() as f32 / 1000.0;
     let left = animation.start_time_ms as f32 * pixels_per_ms;
     let left_signal = create_rw_signal(left);
     let width = animation.duration.as_millis() as f32 * pixels_per_ms;
@@ -220,7 +532,10 @@ pub fn timeline_object_track(
 
     container(
         container((
-            label(move || small_label.clone()).style(|s| s.padding(5).selectable(false)),
+            h_stack((
+                Checkbox::new_labeled_rw(stagger_checked, || ""),
+                label(move || small_label.clone()).style(|s| s.padding(5).selectable(false)),
+            )),
             simple_button("Edit Keyframes".to_string(), move |_| {
                 let mut editor = editor_cloned.lock().unwrap();
 
@@ -400,6 +715,35 @@ pub fn timeline_object_track(
                 EventPropagation::Continue
             }
         })
+        .on_event(EventListener::PointerMove, {
+            let editor = editor.clone();
+            let editor_state = editor_state.clone();
+
+            move |evt| {
+                let id = dragger_id.get();
+
+                if id.len() > 0 {
+                    let editor = editor.lock().unwrap();
+                    let raw_time_ms = ((editor.last_screen.x - 600.0
+                        + zoom.scroll_x.get_untracked() as f32)
+                        / pixels_per_ms) as i32;
+                    drop(editor);
+
+                    let (_, indicator) = snap_animation_drag(
+                        editor_state.clone(),
+                        &selected_sequence_data.get(),
+                        &id,
+                        animation_duration,
+                        raw_time_ms,
+                        pixels_per_ms,
+                    );
+
+                    snap_indicator.set(indicator);
+                }
+
+                EventPropagation::Continue
+            }
+        })
         .on_event(EventListener::DragEnd, {
             // let state = state.clone();
             let editor = editor.clone();
@@ -412,7 +756,8 @@ pub fn timeline_object_track(
                     // let camera = editor.camera.expect("Couldn't get camera");
 
                     let position = Point {
-                        x: editor.last_screen.x - 600.0, // 600.0 for sidebar
+                        // 600.0 for sidebar, plus however far the timeline is scrolled
+                        x: editor.last_screen.x - 600.0 + zoom.scroll_x.get_untracked() as f32,
                         y: editor.last_screen.y - 400.0, // 400.0 for size of canvas
                     };
 
@@ -425,6 +770,19 @@ pub fn timeline_object_track(
 
                     drop(editor);
 
+                    let (snapped_time_ms, _) = snap_animation_drag(
+                        editor_state.clone(),
+                        &selected_sequence_data.get(),
+                        &id,
+                        animation_duration,
+                        new_time_ms,
+                        pixels_per_ms,
+                    );
+                    new_time_ms = snapped_time_ms;
+
+                    snap_indicator.set(None);
+                    dragger_id.set(String::new());
+
                     // state.get().move_timeline_sequence(&id, new_time_ms);
 
                     let mut anims: Vec<AnimationData> =
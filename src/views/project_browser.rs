@@ -32,6 +32,7 @@ use floem::IntoView;
 use floem::{GpuHelper, View, WindowHandle};
 
 use crate::editor_state::EditorState;
+use crate::helpers::clock::project_frame_rate;
 use crate::helpers::projects::{get_projects, ProjectInfo};
 use crate::helpers::utilities::{
     clear_auth_token, create_project_state, fetch_subscription_details, load_auth_token,
@@ -506,6 +507,25 @@ pub fn project_browser(
                             let saved_state = load_project_state(uuid.clone().to_string())
                                 .expect("Couldn't get Saved State");
                             editor_state.record_state.saved_state = Some(saved_state.clone());
+                            editor_state
+                                .color_tracks
+                                .set(saved_state.color_tracks.clone());
+                            editor_state
+                                .shape_tracks
+                                .set(saved_state.shape_tracks.clone());
+                            editor_state
+                                .text_animators
+                                .set(saved_state.text_animators.clone());
+                            editor_state
+                                .hold_keyframes
+                                .set(saved_state.hold_keyframes.clone());
+                            editor_state
+                                .frame_rate
+                                .set(project_frame_rate(&saved_state));
+                            editor_state.markers.set(saved_state.markers.clone());
+                            editor_state
+                                .nested_sequences
+                                .set(saved_state.nested_sequences.clone());
 
                             // update the UI signal
                             let project_selected = editor_state
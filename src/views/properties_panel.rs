@@ -3,7 +3,7 @@ use floem::common::simple_button;
 use floem::common::small_button;
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex, MutexGuard};
-use std::time::Duration;
+use stunts_engine::animations::KeyframeValue;
 use stunts_engine::animations::ObjectType;
 use stunts_engine::animations::Sequence;
 use stunts_engine::editor::color_to_wgpu;
@@ -21,14 +21,22 @@ use wgpu::util::DeviceExt;
 use floem::peniko::{Brush, Color};
 use floem::reactive::{create_effect, create_rw_signal, create_signal, RwSignal, SignalRead};
 use floem::reactive::{SignalGet, SignalUpdate};
+use floem::taffy::Display;
 use floem::text::Weight;
+use floem::views::Checkbox;
 use floem::views::Decorators;
-use floem::views::{container, dyn_container, empty, label};
+use floem::views::{container, dyn_container, dyn_stack, empty, label};
 use floem::views::{h_stack, v_stack};
 use floem::GpuHelper;
 use floem::IntoView;
 
 use crate::editor_state::{self, EditorState};
+use crate::helpers::keyframes::{copy_keyframes, get_object_position, paste_keyframes, PasteMode};
+use crate::helpers::modifiers::sample_property;
+use crate::helpers::retime::{Pivot, RetimeScope};
+use crate::helpers::saved_state::{RepeatMode, TextAnimator};
+use crate::helpers::scrub::animation_time;
+use crate::helpers::text_animators::{sample_selector, TextPreset};
 use crate::helpers::utilities::save_saved_state_raw;
 
 use super::color_pallete::rgb_view_debounced;
@@ -46,12 +54,165 @@ pub fn keyframe_tools(
     selected_sequence_data: RwSignal<Sequence>,
     object_type: ObjectType,
 ) -> impl IntoView {
-    let editor_2 = Arc::clone(&editor);
     let editor_state2 = Arc::clone(&editor_state);
     let editor_state3 = Arc::clone(&editor_state);
     let editor_state4 = Arc::clone(&editor_state);
     let editor_state5 = Arc::clone(&editor_state);
     let editor_state6 = Arc::clone(&editor_state);
+    let editor_state7 = Arc::clone(&editor_state);
+    let editor_state8 = Arc::clone(&editor_state);
+    let editor_state9 = Arc::clone(&editor_state);
+    let editor_state10 = Arc::clone(&editor_state);
+    let editor_state11 = Arc::clone(&editor_state);
+    let editor_state12 = Arc::clone(&editor_state);
+    let editor_state13 = Arc::clone(&editor_state);
+    let editor_state14 = Arc::clone(&editor_state);
+    let editor_state15 = Arc::clone(&editor_state);
+    let editor_state16 = Arc::clone(&editor_state);
+    let editor_state17 = Arc::clone(&editor_state);
+    let editor_3 = Arc::clone(&editor);
+
+    let paste_relative = create_rw_signal(true);
+
+    // with auto-key on, rotating or scaling here keys the value at the playhead
+    let (auto_key, rotation_at_playhead, scale_at_playhead) = {
+        let editor_state = editor_state.lock().unwrap();
+        let object_id = selected_polygon_id.get_untracked().to_string();
+        let playhead = editor_state.keyframe_playhead.get_untracked();
+        let sequence = selected_sequence_data.get_untracked();
+        let hold_keyframes = editor_state
+            .record_state
+            .saved_state
+            .as_ref()
+            .map(|s| s.hold_keyframes.clone())
+            .unwrap_or_default();
+
+        let value_at_playhead = |property_path: &str, default: f64| {
+            sequence
+                .polygon_motion_paths
+                .iter()
+                .find(|pm| pm.polygon_id == object_id)
+                .and_then(|pm| {
+                    let property = pm
+                        .properties
+                        .iter()
+                        .find(|p| p.property_path == property_path)?;
+
+                    sample_property(property, animation_time(pm, playhead), &hold_keyframes)
+                })
+                .and_then(|values| values.first().copied())
+                .unwrap_or(default)
+                .round()
+                .to_string()
+        };
+
+        (
+            editor_state.auto_key,
+            value_at_playhead("rotation", 0.0),
+            value_at_playhead("scale", 100.0),
+        )
+    };
+
+    let key_at_playhead =
+        move |editor_state: &Arc<Mutex<EditorState>>, property_path: &str, value: KeyframeValue| {
+            let object_id = selected_polygon_id.get_untracked().to_string();
+            let mut editor_state = editor_state.lock().unwrap();
+
+            editor_state.auto_key(
+                selected_sequence_id.get_untracked(),
+                Some(selected_sequence_data),
+                &object_id,
+                property_path,
+                value,
+            );
+        };
+
+    // the repeat mode is stored per animation, so look up the current one when opened
+    let current_repeat = {
+        let editor_state = editor_state9.lock().unwrap();
+        let object_id = selected_polygon_id.get_untracked().to_string();
+
+        selected_sequence_data
+            .get_untracked()
+            .polygon_motion_paths
+            .iter()
+            .find(|pm| pm.polygon_id == object_id)
+            .and_then(|pm| {
+                editor_state
+                    .record_state
+                    .saved_state
+                    .as_ref()
+                    .and_then(|saved_state| {
+                        saved_state
+                            .animation_repeats
+                            .iter()
+                            .find(|r| r.animation_id == pm.id)
+                            .map(|r| r.mode.clone())
+                    })
+            })
+            .unwrap_or(RepeatMode::Once)
+    };
+
+    let loop_count = create_rw_signal(match current_repeat {
+        RepeatMode::Loop(count) => count,
+        _ => 2,
+    });
+    let selected_repeat = create_rw_signal(
+        match current_repeat {
+            RepeatMode::Once => "Once",
+            RepeatMode::Loop(_) => "Loop",
+            RepeatMode::LoopForever => "LoopForever",
+            RepeatMode::PingPong => "PingPong",
+        }
+        .to_string(),
+    );
+    let repeat_options = create_rw_signal(vec![
+        DropdownOption {
+            id: "Once".to_string(),
+            label: "Once".to_string(),
+        },
+        DropdownOption {
+            id: "Loop".to_string(),
+            label: "Loop".to_string(),
+        },
+        DropdownOption {
+            id: "LoopForever".to_string(),
+            label: "Loop Forever".to_string(),
+        },
+        DropdownOption {
+            id: "PingPong".to_string(),
+            label: "Ping-Pong".to_string(),
+        },
+    ]);
+
+    let apply_repeat = move |editor_state: &Arc<Mutex<EditorState>>| {
+        let mode = match selected_repeat.get_untracked().as_str() {
+            "Loop" => RepeatMode::Loop(loop_count.get_untracked()),
+            "LoopForever" => RepeatMode::LoopForever,
+            "PingPong" => RepeatMode::PingPong,
+            _ => RepeatMode::Once,
+        };
+
+        let object_id = selected_polygon_id.get_untracked().to_string();
+        let animation_id = match selected_sequence_data
+            .get_untracked()
+            .polygon_motion_paths
+            .iter()
+            .find(|pm| pm.polygon_id == object_id)
+        {
+            Some(pm) => pm.id.clone(),
+            None => {
+                println!("No animation to repeat");
+                return;
+            }
+        };
+
+        let mut editor_state = editor_state.lock().unwrap();
+
+        editor_state.set_animation_repeat(animation_id, mode);
+
+        drop(editor_state);
+    };
 
     let sequence_duration_input = create_rw_signal(String::new());
     let target_duration_signal = create_rw_signal(String::new());
@@ -72,42 +233,29 @@ pub fn keyframe_tools(
     v_stack((
         label(|| "Update Keyframes").style(|s| s.margin_bottom(5.0)),
         v_stack((simple_button("Reverse Keyframes".to_string(), move |_| {
-            let mut editor_state = editor_state6.lock().unwrap();
-
-            let mut new_sequence = selected_sequence_data.get();
-
-            for (i, animation) in new_sequence.polygon_motion_paths.iter_mut().enumerate() {
-                if animation.polygon_id == selected_polygon_id.get().to_string() {
-                    let target_animation = editor_state.reverse_keyframes(animation.clone());
-                    *animation = target_animation.clone();
-                }
+            let sequence = selected_sequence_data.get();
+            let object_id = selected_polygon_id.get().to_string();
+            let animation_ids: Vec<String> = sequence
+                .polygon_motion_paths
+                .iter()
+                .filter(|pm| pm.polygon_id == object_id)
+                .map(|pm| pm.id.clone())
+                .collect();
+
+            // an empty list would mean every animation
+            if animation_ids.is_empty() {
+                return;
             }
 
-            selected_sequence_data.set(new_sequence.clone());
-
-            let mut saved_state = editor_state
-                .record_state
-                .saved_state
-                .as_mut()
-                .expect("Couldn't get Saved State");
-
-            saved_state.sequences.iter_mut().for_each(|s| {
-                if s.id == selected_sequence_id.get() {
-                    *s = new_sequence.clone()
-                }
-            });
-
-            save_saved_state_raw(saved_state.clone());
+            let mut editor_state = editor_state6.lock().unwrap();
 
-            editor_state.record_state.saved_state = Some(saved_state.clone());
+            editor_state.reverse_scope(
+                sequence.id.clone(),
+                Some(selected_sequence_data),
+                RetimeScope::Animations(animation_ids),
+            );
 
             drop(editor_state);
-
-            let mut editor = editor_2.lock().unwrap();
-
-            editor.update_motion_paths(&new_sequence);
-
-            drop(editor);
         }),)),
         v_stack((
             debounce_input(
@@ -123,71 +271,38 @@ pub fn keyframe_tools(
             ),
             h_stack((
                 simple_button("Shrink / Stretch".to_string(), move |_| {
-                    // TODO: integrate with undo/redo
-                    if target_duration_signal.get().len() < 1 {
-                        return;
-                    }
-
-                    let mut editor_state = editor_state2.lock().unwrap();
-                    let saved_state = editor_state
-                        .record_state
-                        .saved_state
-                        .as_mut()
-                        .expect("Couldn't get saved state");
-                    let selected_sequence = saved_state
-                        .sequences
-                        .iter()
-                        .find(|s| s.id == selected_sequence_id.get())
-                        .expect("Couldn't get selected sequence");
-                    let current_animation_path = selected_sequence
+                    let target_duration = match string_to_f32(&target_duration_signal.get()) {
+                        Ok(target_duration) if target_duration > 0.0 => target_duration,
+                        _ => return,
+                    };
+
+                    let sequence = selected_sequence_data.get();
+                    let object_id = selected_polygon_id.get().to_string();
+                    let animation = match sequence
                         .polygon_motion_paths
                         .iter()
-                        .find(|p| p.polygon_id == selected_polygon_id.get().to_string())
-                        .expect("Couldn't find matching polygon");
-
-                    let target_duration = string_to_f32(&target_duration_signal.get())
-                        .expect("Couldn't get duration");
+                        .find(|pm| pm.polygon_id == object_id)
+                    {
+                        Some(animation) => animation,
+                        None => return,
+                    };
 
-                    let motion_path = current_animation_path.clone();
+                    if animation.duration.is_zero() {
+                        return;
+                    }
 
-                    drop(editor_state);
+                    let factor = target_duration / animation.duration.as_secs_f32();
 
                     let mut editor_state = editor_state2.lock().unwrap();
 
-                    let target_keyframes = editor_state
-                        .scale_animation(motion_path, Duration::from_secs_f32(target_duration));
-
-                    let saved_state = editor_state
-                        .record_state
-                        .saved_state
-                        .as_mut()
-                        .expect("Couldn't get saved state");
-
-                    let mut new_sequence = selected_sequence_data.get();
-                    let polygon_path = new_sequence
-                        .polygon_motion_paths
-                        .iter_mut()
-                        .find(|pm| pm.polygon_id == selected_polygon_id.get().to_string())
-                        .expect("Couldn't find amtching polygon path");
-                    *polygon_path = target_keyframes.clone();
-
-                    selected_sequence_data.set(new_sequence);
-
-                    saved_state.sequences.iter_mut().for_each(|s| {
-                        if s.id == selected_sequence_id.get() {
-                            // s.duration_ms = target_duration as i32 * 1000; // no
-                            let polygon_path = s
-                                .polygon_motion_paths
-                                .iter_mut()
-                                .find(|pm| pm.polygon_id == selected_polygon_id.get().to_string())
-                                .expect("Couldn't find amtching polygon path");
-                            *polygon_path = target_keyframes.clone();
-                        }
-                    });
-
-                    save_saved_state_raw(saved_state.clone());
-
-                    editor_state.record_state.saved_state = Some(saved_state.clone());
+                    editor_state.scale_scope(
+                        sequence.id.clone(),
+                        Some(selected_sequence_data),
+                        RetimeScope::Animations(vec![animation.id.clone()]),
+                        factor,
+                        Pivot::Start,
+                        0,
+                    );
 
                     drop(editor_state);
                 }),
@@ -229,7 +344,646 @@ pub fn keyframe_tools(
             )),
         ))
         .style(|s| s.margin_bottom(5.0)),
+        v_stack((
+            h_stack((
+                simple_button("Copy Keyframes".to_string(), move |_| {
+                    let mut editor_state = editor_state7.lock().unwrap();
+
+                    let sequence = selected_sequence_data.get();
+                    let object_id = selected_polygon_id.get().to_string();
+
+                    let animation = match sequence
+                        .polygon_motion_paths
+                        .iter()
+                        .find(|pm| pm.polygon_id == object_id)
+                    {
+                        Some(animation) => animation,
+                        None => return,
+                    };
+                    let source_position =
+                        get_object_position(&sequence, &object_id).unwrap_or([0, 0]);
+
+                    let clipboard = match editor_state.record_state.saved_state.as_ref() {
+                        Some(saved_state) => {
+                            copy_keyframes(animation, None, source_position, saved_state)
+                        }
+                        None => return,
+                    };
+                    editor_state.keyframe_clipboard = clipboard;
+
+                    println!("Keyframes copied!");
+                })
+                .style(|s| s.margin_right(5.0)),
+                simple_button("Paste at Playhead".to_string(), move |_| {
+                    let mut editor_state = editor_state8.lock().unwrap();
+
+                    let clipboard = match editor_state.keyframe_clipboard.clone() {
+                        Some(clipboard) => clipboard,
+                        None => {
+                            println!("Nothing to paste");
+                            return;
+                        }
+                    };
+
+                    let mode = if paste_relative.get() {
+                        PasteMode::Relative
+                    } else {
+                        PasteMode::Absolute
+                    };
+
+                    let mut new_sequence = selected_sequence_data.get();
+                    let object_id = selected_polygon_id.get().to_string();
+                    let target_position =
+                        get_object_position(&new_sequence, &object_id).unwrap_or([0, 0]);
+
+                    let animation = match new_sequence
+                        .polygon_motion_paths
+                        .iter_mut()
+                        .find(|pm| pm.polygon_id == object_id)
+                    {
+                        Some(animation) => animation,
+                        None => return,
+                    };
+
+                    let playhead = animation_time(animation, editor_state.keyframe_playhead.get());
+
+                    // the holds, color and shape keys land beside the sequence
+                    let pasted = editor_state.edit_project(|saved_state| {
+                        paste_keyframes(
+                            &clipboard,
+                            animation,
+                            playhead,
+                            mode,
+                            target_position,
+                            saved_state,
+                        )
+                    });
+
+                    let saved_state = match editor_state.record_state.saved_state.as_mut() {
+                        Some(saved_state) => saved_state,
+                        None => return,
+                    };
+
+                    selected_sequence_data.set(new_sequence.clone());
+
+                    saved_state.sequences.iter_mut().for_each(|s| {
+                        if s.id == selected_sequence_id.get() {
+                            *s = new_sequence.clone()
+                        }
+                    });
+
+                    save_saved_state_raw(saved_state.clone());
+
+                    drop(editor_state);
+
+                    let mut editor = editor_3.lock().unwrap();
+
+                    editor.current_sequence_data = Some(new_sequence.clone());
+                    editor.update_motion_paths(&new_sequence);
+
+                    drop(editor);
+
+                    println!("Pasted {} keyframes!", pasted);
+                }),
+            ))
+            .style(|s| s.margin_bottom(5.0)),
+            Checkbox::new_labeled_rw(paste_relative, || "Paste Relative to Object"),
+        ))
+        .style(|s| s.margin_bottom(5.0)),
+        v_stack((
+            inline_dropdown(
+                "Repeat".to_string(),
+                selected_repeat,
+                repeat_options,
+                move |repeat| {
+                    selected_repeat.set(repeat);
+                    apply_repeat(&editor_state10);
+                },
+            ),
+            debounce_input(
+                "Loop Count".to_string(),
+                &loop_count.get_untracked().to_string(),
+                "Times to play",
+                move |value| {
+                    if let Ok(value) = string_to_f32(&value) {
+                        loop_count.set((value as u32).max(1));
+
+                        if selected_repeat.get_untracked() == "Loop" {
+                            apply_repeat(&editor_state11);
+                        }
+                    }
+                },
+                editor_state12,
+                "loop_count".to_string(),
+                object_type,
+            ),
+        ))
+        .style(|s| s.margin_bottom(5.0)),
+        v_stack((
+            debounce_input(
+                "Rotation at Playhead (deg)".to_string(),
+                &rotation_at_playhead,
+                "Degrees",
+                move |value| {
+                    if let Ok(value) = string_to_f32(&value) {
+                        key_at_playhead(
+                            &editor_state14,
+                            "rotation",
+                            KeyframeValue::Rotation(value as i32),
+                        );
+                    }
+                },
+                editor_state15,
+                "auto_key_rotation".to_string(),
+                object_type,
+            ),
+            debounce_input(
+                "Scale at Playhead (100 default)".to_string(),
+                &scale_at_playhead,
+                "Scale",
+                move |value| {
+                    if let Ok(value) = string_to_f32(&value) {
+                        key_at_playhead(
+                            &editor_state16,
+                            "scale",
+                            KeyframeValue::Scale(value as i32),
+                        );
+                    }
+                },
+                editor_state17,
+                "auto_key_scale".to_string(),
+                object_type,
+            ),
+        ))
+        .style(move |s| {
+            s.margin_bottom(5.0)
+                .apply_if(!auto_key.get(), |s| s.display(Display::None))
+        }),
+        retime_tools(editor_state13, selected_sequence_data, move || {
+            let object_id = selected_polygon_id.get().to_string();
+            let animation_ids: Vec<String> = selected_sequence_data
+                .get()
+                .polygon_motion_paths
+                .iter()
+                .filter(|pm| pm.polygon_id == object_id)
+                .map(|pm| pm.id.clone())
+                .collect();
+
+            if animation_ids.is_empty() {
+                None
+            } else {
+                Some(RetimeScope::Animations(animation_ids))
+            }
+        }),
+    ))
+}
+
+/// Reverse, scale and offset for whatever keyframes get_scope picks, each as one undo step
+pub fn retime_tools<F>(
+    editor_state: Arc<Mutex<EditorState>>,
+    selected_sequence_data: RwSignal<Sequence>,
+    get_scope: F,
+) -> impl IntoView
+where
+    F: Fn() -> Option<RetimeScope> + Clone + 'static,
+{
+    let editor_state2 = Arc::clone(&editor_state);
+    let editor_state3 = Arc::clone(&editor_state);
+    let editor_state4 = Arc::clone(&editor_state);
+    let editor_state5 = Arc::clone(&editor_state);
+    let editor_state6 = Arc::clone(&editor_state);
+    let get_scope2 = get_scope.clone();
+    let get_scope3 = get_scope.clone();
+
+    let scale_percent = create_rw_signal(100.0);
+    let offset_ms = create_rw_signal(0);
+    let selected_pivot = create_rw_signal("Start".to_string());
+    let pivot_options = create_rw_signal(vec![
+        DropdownOption {
+            id: "Start".to_string(),
+            label: "Start".to_string(),
+        },
+        DropdownOption {
+            id: "Playhead".to_string(),
+            label: "Playhead".to_string(),
+        },
+        DropdownOption {
+            id: "End".to_string(),
+            label: "End".to_string(),
+        },
+    ]);
+
+    v_stack((
+        label(|| "Retime").style(|s| s.margin_bottom(5.0)),
+        h_stack((
+            debounce_input(
+                "Scale %".to_string(),
+                &scale_percent.get_untracked().to_string(),
+                "Percent",
+                move |value| {
+                    if let Ok(value) = string_to_f32(&value) {
+                        scale_percent.set(value);
+                    }
+                },
+                editor_state5,
+                "retime_scale".to_string(),
+                ObjectType::Polygon,
+            )
+            .style(|s| s.width(100.0).margin_right(5.0)),
+            debounce_input(
+                "Offset (ms)".to_string(),
+                &offset_ms.get_untracked().to_string(),
+                "Milliseconds",
+                move |value| {
+                    if let Ok(value) = string_to_f32(&value) {
+                        offset_ms.set(value as i64);
+                    }
+                },
+                editor_state6,
+                "retime_offset".to_string(),
+                ObjectType::Polygon,
+            )
+            .style(|s| s.width(100.0)),
+        )),
+        inline_dropdown(
+            "Pivot".to_string(),
+            selected_pivot,
+            pivot_options,
+            move |pivot| selected_pivot.set(pivot),
+        ),
+        h_stack((
+            simple_button("Reverse".to_string(), move |_| {
+                let scope = match get_scope() {
+                    Some(scope) => scope,
+                    None => return,
+                };
+
+                let mut editor_state = editor_state2.lock().unwrap();
+
+                editor_state.reverse_scope(
+                    selected_sequence_data.get().id,
+                    Some(selected_sequence_data),
+                    scope,
+                );
+
+                drop(editor_state);
+            })
+            .style(|s| s.margin_right(5.0)),
+            simple_button("Scale".to_string(), move |_| {
+                let scope = match get_scope2() {
+                    Some(scope) => scope,
+                    None => return,
+                };
+
+                let sequence = selected_sequence_data.get();
+                let pivot = match selected_pivot.get().as_str() {
+                    "Playhead" => Pivot::Playhead,
+                    "End" => Pivot::End,
+                    _ => Pivot::Start,
+                };
+
+                let mut editor_state = editor_state3.lock().unwrap();
+
+                let playhead_ms = editor_state.keyframe_playhead.get().as_millis() as i64;
+
+                editor_state.scale_scope(
+                    sequence.id.clone(),
+                    Some(selected_sequence_data),
+                    scope,
+                    scale_percent.get() / 100.0,
+                    pivot,
+                    playhead_ms,
+                );
+
+                drop(editor_state);
+            })
+            .style(|s| s.margin_right(5.0)),
+            simple_button("Offset".to_string(), move |_| {
+                let scope = match get_scope3() {
+                    Some(scope) => scope,
+                    None => return,
+                };
+
+                let mut editor_state = editor_state4.lock().unwrap();
+
+                editor_state.offset_scope(
+                    selected_sequence_data.get().id,
+                    Some(selected_sequence_data),
+                    scope,
+                    offset_ms.get(),
+                );
+
+                drop(editor_state);
+            }),
+        )),
     ))
+    .style(|s| s.margin_bottom(5.0))
+}
+
+/// Change one field of a saved text animator
+fn edit_text_animator<F>(editor_state: &Arc<Mutex<EditorState>>, animator_id: &str, edit: F)
+where
+    F: FnOnce(&mut TextAnimator),
+{
+    let mut editor_state = editor_state.lock().unwrap();
+
+    let mut animator = match editor_state
+        .text_animators
+        .get_untracked()
+        .into_iter()
+        .find(|a| a.id == animator_id)
+    {
+        Some(animator) => animator,
+        None => return,
+    };
+
+    edit(&mut animator);
+
+    editor_state.save_text_animator(animator);
+
+    drop(editor_state);
+}
+
+fn text_animator_input<F>(
+    label_text: &str,
+    initial_value: i32,
+    editor_state: Arc<Mutex<EditorState>>,
+    animator_id: String,
+    name: &str,
+    apply: F,
+) -> impl IntoView
+where
+    F: Fn(&mut TextAnimator, i32) + Clone + 'static,
+{
+    let editor_state2 = Arc::clone(&editor_state);
+    let name = format!("{}_{}", name, animator_id);
+
+    debounce_input(
+        label_text.to_string(),
+        &initial_value.to_string(),
+        "Enter value",
+        move |value| {
+            let value = match string_to_f32(&value) {
+                Ok(value) => value.round() as i32,
+                Err(_) => return,
+            };
+            let apply = apply.clone();
+
+            edit_text_animator(&editor_state2, &animator_id, move |animator| {
+                apply(animator, value)
+            });
+        },
+        editor_state,
+        name,
+        ObjectType::TextItem,
+    )
+}
+
+fn text_animator_row(
+    editor_state: Arc<Mutex<EditorState>>,
+    animators: RwSignal<Vec<TextAnimator>>,
+    animation: AnimationData,
+    animator: TextAnimator,
+) -> impl IntoView {
+    let editor_state2 = Arc::clone(&editor_state);
+    let editor_state3 = Arc::clone(&editor_state);
+
+    let animator_id = animator.id.clone();
+    let animator_id2 = animator.id.clone();
+
+    let keyframe_playhead = editor_state.lock().unwrap().keyframe_playhead;
+
+    // the range keyed at the playhead, starting from the current one
+    let (range_start, range_end) = sample_selector(
+        &animator.selector,
+        animation_time(&animation, keyframe_playhead.get_untracked()),
+    );
+    let range_start = create_rw_signal(range_start.round() as i32);
+    let range_end = create_rw_signal(range_end.round() as i32);
+
+    let name = animator.name.clone();
+
+    v_stack((
+        label(move || format!("{} (by line)", name)).style(|s| s.margin_bottom(5.0)),
+        h_stack((
+            text_animator_input(
+                "Offset X:",
+                animator.offset[0],
+                Arc::clone(&editor_state),
+                animator.id.clone(),
+                "text_animator_offset_x",
+                |animator, value| animator.offset[0] = value,
+            )
+            .style(|s| s.width(120.0).margin_right(5.0)),
+            text_animator_input(
+                "Offset Y:",
+                animator.offset[1],
+                Arc::clone(&editor_state),
+                animator.id.clone(),
+                "text_animator_offset_y",
+                |animator, value| animator.offset[1] = value,
+            )
+            .style(|s| s.width(120.0)),
+        ))
+        .style(|s| s.margin_bottom(5.0)),
+        h_stack((
+            text_animator_input(
+                "Opacity (%):",
+                animator.opacity,
+                Arc::clone(&editor_state),
+                animator.id.clone(),
+                "text_animator_opacity",
+                |animator, value| animator.opacity = value.clamp(0, 100),
+            )
+            .style(|s| s.width(120.0).margin_right(5.0)),
+            text_animator_input(
+                "Scale (%):",
+                animator.scale,
+                Arc::clone(&editor_state),
+                animator.id.clone(),
+                "text_animator_scale",
+                |animator, value| animator.scale = value.max(0),
+            )
+            .style(|s| s.width(120.0)),
+        ))
+        .style(|s| s.margin_bottom(5.0)),
+        h_stack((
+            text_animator_input(
+                "Rotation (deg):",
+                animator.rotation,
+                Arc::clone(&editor_state),
+                animator.id.clone(),
+                "text_animator_rotation",
+                |animator, value| animator.rotation = value,
+            )
+            .style(|s| s.width(120.0).margin_right(5.0)),
+            text_animator_input(
+                "Softness (units):",
+                animator.softness as i32,
+                Arc::clone(&editor_state),
+                animator.id.clone(),
+                "text_animator_softness",
+                |animator, value| animator.softness = value.max(0) as u32,
+            )
+            .style(|s| s.width(120.0)),
+        ))
+        .style(|s| s.margin_bottom(5.0)),
+        h_stack((
+            debounce_input(
+                "Range Start (%):".to_string(),
+                &range_start.get_untracked().to_string(),
+                "Start",
+                move |value| {
+                    if let Ok(value) = string_to_f32(&value) {
+                        range_start.set(value.round() as i32);
+                    }
+                },
+                Arc::clone(&editor_state),
+                format!("text_animator_range_start_{}", animator.id),
+                ObjectType::TextItem,
+            )
+            .style(|s| s.width(120.0).margin_right(5.0)),
+            debounce_input(
+                "Range End (%):".to_string(),
+                &range_end.get_untracked().to_string(),
+                "End",
+                move |value| {
+                    if let Ok(value) = string_to_f32(&value) {
+                        range_end.set(value.round() as i32);
+                    }
+                },
+                Arc::clone(&editor_state),
+                format!("text_animator_range_end_{}", animator.id),
+                ObjectType::TextItem,
+            )
+            .style(|s| s.width(120.0)),
+        ))
+        .style(|s| s.margin_bottom(5.0)),
+        h_stack((
+            simple_button("Key Range at Playhead".to_string(), move |_| {
+                let time_ms = animation_time(&animation, keyframe_playhead.get_untracked())
+                    .as_millis() as u64;
+                let mut editor_state = editor_state2.lock().unwrap();
+
+                editor_state.key_text_selector(
+                    &animator_id,
+                    time_ms,
+                    range_start.get_untracked(),
+                    range_end.get_untracked(),
+                );
+
+                drop(editor_state);
+            })
+            .style(|s| s.margin_right(5.0)),
+            simple_button("Delete".to_string(), move |_| {
+                let mut editor_state = editor_state3.lock().unwrap();
+
+                editor_state.remove_text_animator(&animator_id2);
+
+                drop(editor_state);
+
+                // the list holds debounced inputs, so refresh it after releasing the state
+                animators.update(|animators| animators.retain(|a| a.id != animator_id2));
+            })
+            .style(|s| s.color(Color::RED)),
+        )),
+    ))
+    .style(|s| s.margin_bottom(10.0).padding_bottom(5.0).border_bottom(1.0))
+}
+
+/// Per-character, word or line animators of the selected text
+pub fn text_animator_tools(
+    editor_state: Arc<Mutex<EditorState>>,
+    selected_text_id: RwSignal<Uuid>,
+    selected_sequence_data: RwSignal<Sequence>,
+) -> impl IntoView {
+    let editor_state2 = Arc::clone(&editor_state);
+    let editor_state3 = Arc::clone(&editor_state);
+
+    // the animators belong to the text's animation, look it up when opened
+    let object_id = selected_text_id.get_untracked().to_string();
+    let animation = selected_sequence_data
+        .get_untracked()
+        .polygon_motion_paths
+        .iter()
+        .find(|pm| pm.polygon_id == object_id)
+        .cloned();
+
+    let animation = match animation {
+        Some(animation) => animation,
+        None => return empty().into_any(),
+    };
+    let animation_id = animation.id.clone();
+    let animation_id2 = animation_id.clone();
+    let duration = animation.duration;
+
+    let animators = create_rw_signal(
+        editor_state
+            .lock()
+            .unwrap()
+            .text_animators
+            .get_untracked()
+            .into_iter()
+            .filter(|a| a.animation_id == animation_id)
+            .collect::<Vec<TextAnimator>>(),
+    );
+
+    let selected_preset = create_rw_signal(TextPreset::Reveal.label().to_string());
+    let preset_options = create_rw_signal(
+        TextPreset::all()
+            .iter()
+            .map(|preset| DropdownOption {
+                id: preset.label().to_string(),
+                label: preset.label().to_string(),
+            })
+            .collect::<Vec<DropdownOption>>(),
+    );
+
+    v_stack((
+        label(|| "Text Animators").style(|s| s.margin_bottom(5.0)),
+        h_stack((
+            inline_dropdown(
+                "Preset".to_string(),
+                selected_preset,
+                preset_options,
+                move |_| {},
+            ),
+            simple_button("Add Preset".to_string(), move |_| {
+                let preset = match TextPreset::all()
+                    .into_iter()
+                    .find(|p| p.label() == selected_preset.get_untracked())
+                {
+                    Some(preset) => preset,
+                    None => return,
+                };
+                let animator = preset.build(animation_id2.clone(), duration);
+
+                let mut editor_state = editor_state2.lock().unwrap();
+
+                editor_state.save_text_animator(animator.clone());
+
+                drop(editor_state);
+
+                animators.update(|animators| animators.push(animator));
+            }),
+        ))
+        .style(|s| s.margin_bottom(5.0)),
+        dyn_stack(
+            move || animators.get(),
+            move |animator| animator.id.clone(),
+            move |animator| {
+                text_animator_row(
+                    editor_state3.clone(),
+                    animators,
+                    animation.clone(),
+                    animator,
+                )
+            },
+        )
+        .style(|s| s.flex_col()),
+    ))
+    .style(|s| s.margin_top(10.0))
+    .into_any()
 }
 
 pub fn properties_view(
@@ -557,6 +1311,7 @@ pub fn text_properties_view(
     let editor_state15 = Arc::clone(&editor_state);
     let editor_state16 = Arc::clone(&editor_state);
     let editor_state17 = Arc::clone(&editor_state);
+    let editor_state18 = Arc::clone(&editor_state);
 
     let aside_width = 260.0;
     let quarters = (aside_width / 4.0) + (5.0 * 4.0);
@@ -927,6 +1682,11 @@ pub fn text_properties_view(
                             selected_sequence_data,
                             ObjectType::TextItem,
                         ),
+                        text_animator_tools(
+                            editor_state18,
+                            selected_text_id,
+                            selected_sequence_data,
+                        ),
                     ))
                 } else {
                     v_stack((empty(),))
@@ -45,6 +45,7 @@ use uuid::Uuid;
 
 use crate::editor_state::{self, EditorState};
 use crate::helpers::saved_state;
+use crate::helpers::simplify::{simplify_animation, smooth_animation};
 use crate::helpers::utilities::{
     get_captures_dir, get_ground_truth_dir, get_images_dir, get_videos_dir, save_saved_state_raw,
 };
@@ -54,6 +55,7 @@ use stunts_engine::animations::{
 };
 
 use super::inputs::{debounce_input, inline_dropdown, DropdownOption};
+use super::nested::nested_sequence_list;
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum LayerKind {
@@ -355,6 +357,96 @@ pub fn import_video_to_scene(
     drop(editor);
 }
 
+/// Simplify or smooth every motion path in the sequence, each as one undo step
+pub fn path_cleanup_tools(
+    editor_state: Arc<Mutex<EditorState>>,
+    selected_sequence_data: RwSignal<Sequence>,
+) -> impl IntoView {
+    let editor_state2 = Arc::clone(&editor_state);
+    let editor_state3 = Arc::clone(&editor_state);
+    let editor_state4 = Arc::clone(&editor_state);
+
+    let tolerance = create_rw_signal(2.0);
+    let smooth_radius = create_rw_signal(2);
+
+    v_stack((
+        h_stack((
+            debounce_input(
+                "Tolerance".to_string(),
+                &tolerance.get_untracked().to_string(),
+                "Pixels",
+                move |value| {
+                    if let Ok(value) = string_to_f32(&value) {
+                        tolerance.set(value.max(0.0));
+                    }
+                },
+                editor_state2,
+                "simplify_tolerance".to_string(),
+                ObjectType::Polygon,
+            )
+            .style(|s| s.width(125.0).margin_right(5.0)),
+            debounce_input(
+                "Smoothing".to_string(),
+                &smooth_radius.get_untracked().to_string(),
+                "Keyframes each side",
+                move |value| {
+                    if let Ok(value) = string_to_u32(&value) {
+                        smooth_radius.set(value as usize);
+                    }
+                },
+                editor_state3,
+                "smooth_radius".to_string(),
+                ObjectType::Polygon,
+            )
+            .style(|s| s.width(125.0)),
+        )),
+        h_stack((
+            simple_button("Simplify Paths".to_string(), move |_| {
+                let mut editor_state = editor_state.lock().unwrap();
+                let hold_keyframes = editor_state.hold_keyframes.get_untracked();
+
+                editor_state.edit_sequence(
+                    selected_sequence_data.get().id,
+                    Some(selected_sequence_data),
+                    |sequence| {
+                        sequence
+                            .polygon_motion_paths
+                            .iter_mut()
+                            .for_each(|animation| {
+                                simplify_animation(
+                                    animation,
+                                    tolerance.get_untracked(),
+                                    &hold_keyframes,
+                                )
+                            });
+                    },
+                );
+
+                drop(editor_state);
+            })
+            .style(|s| s.margin_right(5.0)),
+            simple_button("Smooth Paths".to_string(), move |_| {
+                let mut editor_state = editor_state4.lock().unwrap();
+
+                editor_state.edit_sequence(
+                    selected_sequence_data.get().id,
+                    Some(selected_sequence_data),
+                    |sequence| {
+                        sequence
+                            .polygon_motion_paths
+                            .iter_mut()
+                            .for_each(|animation| {
+                                smooth_animation(animation, smooth_radius.get_untracked())
+                            });
+                    },
+                );
+
+                drop(editor_state);
+            }),
+        )),
+    ))
+}
+
 pub fn sequence_panel(
     editor_state: Arc<Mutex<EditorState>>,
     gpu_helper: Arc<Mutex<GpuHelper>>,
@@ -387,6 +479,7 @@ pub fn sequence_panel(
     let state_cloned_19 = Arc::clone(&editor_state);
     let state_cloned_20 = Arc::clone(&editor_state);
     let state_cloned_21 = Arc::clone(&editor_state);
+    let state_cloned_22 = Arc::clone(&editor_state);
     let editor_cloned = Arc::clone(&editor);
     let editor_cloned_2 = Arc::clone(&editor);
     let editor_cloned_3 = Arc::clone(&editor);
@@ -402,6 +495,7 @@ pub fn sequence_panel(
     let editor_cloned_13 = Arc::clone(&editor);
     let editor_cloned_14 = Arc::clone(&editor);
     let editor_cloned_15 = Arc::clone(&editor);
+    let editor_cloned_16 = Arc::clone(&editor);
     let gpu_cloned = Arc::clone(&gpu_helper);
     let viewport_cloned = Arc::clone(&viewport);
     let gpu_cloned_2 = Arc::clone(&gpu_helper);
@@ -1102,7 +1196,7 @@ pub fn sequence_panel(
                 .expect("Couldn't get saved state");
             let mut sequence = selected_sequence_data.get();
 
-            match kind {
+            let removed_animation = match kind {
                 LayerKind::Polygon => {
                     let object_index = sequence
                         .active_polygons
@@ -1117,7 +1211,7 @@ pub fn sequence_panel(
                         .expect("Couldn't find object match");
 
                     sequence.active_polygons.remove(object_index);
-                    sequence.polygon_motion_paths.remove(path_index);
+                    sequence.polygon_motion_paths.remove(path_index)
                 }
                 LayerKind::Text => {
                     let object_index = sequence
@@ -1133,7 +1227,7 @@ pub fn sequence_panel(
                         .expect("Couldn't find object match");
 
                     sequence.active_text_items.remove(object_index);
-                    sequence.polygon_motion_paths.remove(path_index);
+                    sequence.polygon_motion_paths.remove(path_index)
                 }
                 LayerKind::Image => {
                     let object_index = sequence
@@ -1149,7 +1243,7 @@ pub fn sequence_panel(
                         .expect("Couldn't find object match");
 
                     sequence.active_image_items.remove(object_index);
-                    sequence.polygon_motion_paths.remove(path_index);
+                    sequence.polygon_motion_paths.remove(path_index)
                 }
                 LayerKind::Video => {
                     let object_index = sequence
@@ -1165,9 +1259,9 @@ pub fn sequence_panel(
                         .expect("Couldn't find object match");
 
                     sequence.active_video_items.remove(object_index);
-                    sequence.polygon_motion_paths.remove(path_index);
+                    sequence.polygon_motion_paths.remove(path_index)
                 }
-            }
+            };
 
             saved_state.sequences.iter_mut().for_each(|s| {
                 if s.id == selected_sequence_id.get() {
@@ -1175,6 +1269,9 @@ pub fn sequence_panel(
                 }
             });
 
+            // its repeats, modifiers, color and shape tracks go with it
+            editor_state.remove_animation_tables(&removed_animation);
+
             drop(editor_state);
 
             // rerender motion paths
@@ -1317,43 +1414,53 @@ pub fn sequence_panel(
                         Checkbox::new_labeled_rw(fade_is_checked, || "Auto-Fade"),
                     ))
                     .style(|s| s.margin_bottom(5.0)),
-                    v_stack((simple_button("Generate Animation".to_string(), move |_| {
-                        // hook into CommonMotion2D run_motion_inference
-                        let mut editor = editor_cloned_4.lock().unwrap();
+                    v_stack((
+                        simple_button("Generate Animation".to_string(), move |_| {
+                            // hook into CommonMotion2D run_motion_inference
+                            let mut editor = editor_cloned_4.lock().unwrap();
 
-                        let predicted_keyframes = editor.run_motion_inference();
+                            let predicted_keyframes = editor.run_motion_inference();
 
-                        let mut new_sequence = selected_sequence_data.get();
-                        new_sequence.polygon_motion_paths = predicted_keyframes.clone();
+                            let mut new_sequence = selected_sequence_data.get();
+                            new_sequence.polygon_motion_paths = predicted_keyframes.clone();
 
-                        selected_sequence_data.set(new_sequence);
+                            selected_sequence_data.set(new_sequence);
 
-                        editor.update_motion_paths(&selected_sequence_data.get());
-                        println!("Motion Paths updated!");
+                            editor.update_motion_paths(&selected_sequence_data.get());
+                            println!("Motion Paths updated!");
 
-                        drop(editor);
+                            drop(editor);
 
-                        let mut editor_state = state_cloned_4.lock().unwrap();
+                            let mut editor_state = state_cloned_4.lock().unwrap();
 
-                        let mut saved_state = editor_state
-                            .record_state
-                            .saved_state
-                            .as_mut()
-                            .expect("Couldn't get Saved State");
+                            let mut saved_state = editor_state
+                                .record_state
+                                .saved_state
+                                .as_mut()
+                                .expect("Couldn't get Saved State");
 
-                        saved_state.sequences.iter_mut().for_each(|s| {
-                            if s.id == selected_sequence_id.get() {
-                                s.polygon_motion_paths = predicted_keyframes.clone();
-                            }
-                        });
+                            saved_state.sequences.iter_mut().for_each(|s| {
+                                if s.id == selected_sequence_id.get() {
+                                    s.polygon_motion_paths = predicted_keyframes.clone();
+                                }
+                            });
 
-                        save_saved_state_raw(saved_state.clone());
+                            save_saved_state_raw(saved_state.clone());
 
-                        editor_state.record_state.saved_state = Some(saved_state.clone());
+                            editor_state.record_state.saved_state = Some(saved_state.clone());
 
-                        drop(editor_state);
-                    })
-                    .style(|s| s.background(Color::rgb8(255, 25, 25)).color(Color::WHITE)),))
+                            drop(editor_state);
+                        })
+                        .style(|s| s.background(Color::rgb8(255, 25, 25)).color(Color::WHITE)),
+                        // generated paths are often dense and jittery
+                        path_cleanup_tools(state_cloned_22.clone(), selected_sequence_data),
+                    ))
+                    .style(|s| s.margin_bottom(5.0)),
+                    nested_sequence_list(
+                        editor_cloned_16,
+                        state_cloned_22.clone(),
+                        selected_sequence_data,
+                    )
                     .style(|s| s.margin_bottom(5.0)),
                     h_stack((
                         small_button(
@@ -1,7 +1,10 @@
 use cgmath::Vector2;
+use floem::action::debounce_action;
+use floem::common::{input_styles, simple_button};
 use floem::event::EventListener;
 use floem::event::EventPropagation;
 use floem::peniko::Color;
+use floem::reactive::create_effect;
 use floem::reactive::create_rw_signal;
 use floem::reactive::RwSignal;
 use floem::reactive::SignalGet;
@@ -18,6 +21,8 @@ use serde::Deserialize;
 use serde::Serialize;
 use std::sync::Arc;
 use std::sync::Mutex;
+use std::time::Duration;
+use stunts_engine::animations::EasingType;
 use stunts_engine::animations::Sequence;
 use stunts_engine::editor::Editor;
 use stunts_engine::editor::Point;
@@ -25,8 +30,31 @@ use stunts_engine::timelines::SavedTimelineStateConfig;
 use stunts_engine::timelines::TimelineSequence;
 use stunts_engine::timelines::TrackType;
 
+use uuid::Uuid;
+
 use crate::editor_state::EditorState;
-use crate::helpers::utilities::save_saved_state_raw;
+use crate::helpers::clips::clip_range;
+use crate::helpers::clock::{timecode, FRAME_RATES};
+use crate::helpers::markers::{marker_times, markers_on};
+use crate::helpers::modifiers::{easing_from_label, easing_label, easing_types};
+use crate::helpers::saved_state::{
+    ClipTrim, SequenceTransition, TimelineTrack, TrackAssignment, TransitionKind,
+};
+use crate::helpers::snapping::{clip_snap_candidates, snap_animation_start, SNAP_THRESHOLD_PX};
+use crate::helpers::timeline::TimelineModel;
+use crate::helpers::tracks::track_of;
+use crate::helpers::transitions::{
+    adjacent_pairs, dip_color_from_label, dip_color_label, DEFAULT_TRANSITION_MS, DIP_COLORS,
+};
+use crate::helpers::zoom::TimelineZoom;
+
+use super::inputs::{inline_dropdown, DropdownOption};
+use super::markers::{marker_lines, marker_list, marker_ruler};
+use super::zoom::{time_ruler, zoom_controls, zoom_viewport};
+
+pub const TRACK_HEIGHT: f64 = 50.0;
+const TRACK_WIDTH: f64 = 700.0;
+const TRACK_HEADER_WIDTH: f64 = 200.0;
 
 // #[derive(Clone)]
 // pub struct TimelineState {
@@ -94,36 +122,6 @@ use crate::helpers::utilities::save_saved_state_raw;
 //     }
 // }
 
-use std::cmp::max;
-
-fn calculate_end_time(
-    sequences: &Vec<Sequence>,
-    timeline_sequences: &Vec<TimelineSequence>,
-) -> Result<i32, &'static str> {
-    // if durations.len() != start_times.len() {
-    //     return Err("Durations and start times arrays must have the same length.");
-    // }
-
-    if timeline_sequences.is_empty() {
-        return Ok(0);
-    }
-
-    let mut end_time = 0;
-
-    for i in 0..timeline_sequences.len() {
-        if let Some(timeline_sequences) = timeline_sequences.get(i) {
-            if let Some(sequences) = sequences.get(i) {
-                end_time = max(
-                    end_time,
-                    timeline_sequences.start_time_ms + sequences.duration_ms,
-                );
-            }
-        }
-    }
-
-    Ok(end_time)
-}
-
 pub fn build_timeline(
     editor: Arc<Mutex<Editor>>,
     editor_state: Arc<Mutex<EditorState>>,
@@ -131,65 +129,596 @@ pub fn build_timeline(
     timeline_sequences: RwSignal<Vec<TimelineSequence>>,
     dragging_timeline_sequence: RwSignal<Option<(String, i32)>>,
     export_play_timeline_config: RwSignal<Option<SavedTimelineStateConfig>>,
-    pixels_per_s: i32,
+    zoom: TimelineZoom,
     sequence_quick_access: RwSignal<HashMap<String, String>>,
     sequence_durations: RwSignal<HashMap<String, i32>>,
+    tracks: RwSignal<Vec<TimelineTrack>>,
+    track_assignments: RwSignal<Vec<TrackAssignment>>,
+    clip_trims: RwSignal<Vec<ClipTrim>>,
+    transitions: RwSignal<Vec<SequenceTransition>>,
 ) -> impl View {
-    // TODO: many tracks
+    let editor_state2 = Arc::clone(&editor_state);
+    let editor_state3 = Arc::clone(&editor_state);
+    let editor_state4 = Arc::clone(&editor_state);
+    let editor_state5 = Arc::clone(&editor_state);
+    let editor_state6 = Arc::clone(&editor_state);
+    let editor_state7 = Arc::clone(&editor_state);
+    let editor_state8 = Arc::clone(&editor_state);
+    let editor_state9 = Arc::clone(&editor_state);
+    let pixels_per_s = zoom.pixels_per_s;
+
+    let (timeline_playhead, frame_rate, play_every_frame, markers) = {
+        let editor_state = editor_state.lock().unwrap();
+
+        (
+            editor_state.timeline_playhead,
+            editor_state.frame_rate,
+            editor_state.play_every_frame,
+            editor_state.markers,
+        )
+    };
+    let timeline_markers = move || markers_on(&markers.get(), None);
+    let selected_frame_rate = create_rw_signal(String::new());
+    let frame_rate_options = create_rw_signal(
+        FRAME_RATES
+            .iter()
+            .map(|fps| DropdownOption {
+                id: format!("{} fps", fps),
+                label: format!("{} fps", fps),
+            })
+            .collect::<Vec<_>>(),
+    );
+
+    create_effect(move |_| {
+        selected_frame_rate.set(format!("{} fps", frame_rate.get()));
+    });
+    let selected_clip: RwSignal<Option<String>> = create_rw_signal(None);
+    // dragged clips push the ones after them along
+    let ripple_mode = create_rw_signal(false);
+
+    // track under the pointer while a sequence is dragged
+    let drop_track: RwSignal<Option<String>> = create_rw_signal(None);
+
+    let add_track = move |editor_state: &Arc<Mutex<EditorState>>, kind: TrackType| {
+        editor_state.lock().unwrap().add_track(kind);
+    };
+
+    // split or ripple delete the selected clip
+    let edit_selected_clip =
+        move |editor_state: &Arc<Mutex<EditorState>>,
+              edit: &dyn Fn(&mut EditorState, &str) -> Result<(), String>| {
+            let clip_id = match selected_clip.get_untracked() {
+                Some(clip_id) => clip_id,
+                None => return,
+            };
+
+            let mut editor_state = editor_state.lock().unwrap();
+
+            if let Err(e) = edit(&mut editor_state, &clip_id) {
+                println!("Couldn't edit clip: {}", e);
+            }
+        };
+
+    let timeline_model = move || {
+        let durations = sequence_durations.get();
+
+        TimelineModel::from_durations(
+            |id| durations.get(id).copied(),
+            &SavedTimelineStateConfig {
+                timeline_sequences: timeline_sequences.get(),
+            },
+        )
+        .with_trims(&clip_trims.get())
+    };
+
+    // the tracks show TRACK_WIDTH at a time, scrolling through the timeline
+    // with a track's width to spare for dragging clips past the end
+    let view_width = || TRACK_WIDTH;
+    let content_width = move || timeline_model().duration_s() * pixels_per_s.get() + TRACK_WIDTH;
+
+    // length, gaps and overlaps across every track
+    let summary = move || {
+        let model = timeline_model();
+
+        format!(
+            "Length {:.1}s, {} gaps, {} overlaps",
+            model.duration_s(),
+            model.gaps().len(),
+            model.overlaps().len()
+        )
+    };
+
+    // back to back sequences on a track, where transitions can go
+    let transition_pairs = move || {
+        let durations = sequence_durations.get();
+        let config = SavedTimelineStateConfig {
+            timeline_sequences: timeline_sequences.get(),
+        };
+        let model = TimelineModel::from_durations(|id| durations.get(id).copied(), &config)
+            .with_trims(&clip_trims.get());
+
+        adjacent_pairs(&tracks.get(), &track_assignments.get(), &model, &config)
+    };
+
     v_stack((
-        // Audio track
-        container(stack((
-            // Background
-            container((empty()))
-                .style(|s| {
-                    s.width(700.0)
-                        .height(50)
-                        .background(Color::rgb8(100, 150, 200))
-                        .z_index(1)
-                })
-                .style(|s| s.absolute().margin_left(0.0)),
-            // TimelineSequences
-            timeline_sequence_track(
-                editor.clone(),
-                editor_state.clone(),
-                // state,
-                timeline_sequences,
-                dragging_timeline_sequence,
-                export_play_timeline_config,
-                TrackType::Audio,
-                pixels_per_s,
-                sequence_quick_access,
-                sequence_durations,
+        h_stack((
+            label(summary).style(|s| s.margin_right(10.0).selectable(false)),
+            simple_button("Add Video Track".to_string(), move |_| {
+                add_track(&editor_state2, TrackType::Video);
+            })
+            .style(|s| s.margin_right(5.0)),
+            simple_button("Add Audio Track".to_string(), move |_| {
+                add_track(&editor_state3, TrackType::Audio);
+            })
+            .style(|s| s.margin_right(10.0)),
+            inline_dropdown(
+                "Frame Rate".to_string(),
+                selected_frame_rate,
+                frame_rate_options,
+                move |option| {
+                    let fps = match option.trim_end_matches(" fps").parse::<u32>() {
+                        Ok(fps) => fps,
+                        Err(_) => return,
+                    };
+
+                    editor_state7.lock().unwrap().set_frame_rate(fps);
+                },
             ),
-        )))
-        .style(|s| s.position(Position::Relative).height(50)),
-        // Video track
-        container(stack((
-            // background
-            container((empty()))
-                .style(|s| {
-                    s.width(700.0)
-                        .height(50)
-                        .background(Color::rgb8(200, 150, 100))
-                        .z_index(1)
-                })
-                .style(|s| s.absolute().margin_left(0.0)),
-            // timeline_sequences
-            timeline_sequence_track(
-                editor.clone(),
-                editor_state.clone(),
-                // state,
-                timeline_sequences,
-                dragging_timeline_sequence,
-                export_play_timeline_config,
-                TrackType::Video,
-                pixels_per_s,
-                sequence_quick_access,
-                sequence_durations,
+            Checkbox::new_labeled_rw(play_every_frame, || "Play Every Frame")
+                .style(|s| s.margin_left(10.0)),
+        ))
+        .style(|s| s.items_center().margin_bottom(5.0)),
+        h_stack((
+            label(move || {
+                format!(
+                    "Playhead {}",
+                    timecode(timeline_playhead.get(), frame_rate.get())
+                )
+            })
+            .style(|s| s.margin_right(10.0).selectable(false)),
+            simple_button("Split at Playhead".to_string(), move |_| {
+                let time_ms = timeline_playhead.get_untracked().as_millis() as i32;
+
+                edit_selected_clip(
+                    &editor_state5,
+                    &|editor_state: &mut EditorState, clip_id: &str| {
+                        editor_state.split_clip(clip_id, time_ms).map(|_| ())
+                    },
+                );
+            })
+            .style(|s| s.margin_right(5.0)),
+            simple_button("Ripple Delete".to_string(), move |_| {
+                edit_selected_clip(
+                    &editor_state6,
+                    &|editor_state: &mut EditorState, clip_id: &str| {
+                        editor_state.ripple_delete_clip(clip_id)
+                    },
+                );
+                selected_clip.set(None);
+            })
+            .style(|s| s.margin_right(5.0)),
+            Checkbox::new_labeled_rw(ripple_mode, || "Ripple Moves")
+                .style(|s| s.margin_right(10.0)),
+            zoom_controls(zoom, view_width, move || {
+                let duration = Duration::from_secs_f64(timeline_model().duration_s().max(0.0));
+
+                zoom.fit(duration, TRACK_WIDTH);
+            }),
+        ))
+        .style(|s| s.items_center().margin_bottom(5.0)),
+        h_stack((
+            empty().style(|s| s.width(TRACK_HEADER_WIDTH)),
+            zoom_viewport(
+                editor_state9,
+                zoom,
+                view_width,
+                content_width,
+                v_stack((
+                    time_ruler(zoom, view_width, frame_rate),
+                    marker_ruler(
+                        timeline_markers,
+                        move || pixels_per_s.get(),
+                        timeline_playhead,
+                    )
+                    .style(move |s| s.width(content_width())),
+                )),
             ),
-        )))
-        .style(|s| s.position(Position::Relative).height(50)),
+        )),
+        dyn_stack(
+            move || tracks.get(),
+            move |track| track.id.clone(),
+            move |track| {
+                let track_id = track.id.clone();
+
+                h_stack((
+                    track_header(editor_state.clone(), track.clone(), tracks),
+                    zoom_viewport(
+                        editor_state.clone(),
+                        zoom,
+                        view_width,
+                        content_width,
+                        stack((
+                            // background
+                            container((empty()))
+                                .style(move |s| {
+                                    s.width(content_width())
+                                        .height(TRACK_HEIGHT)
+                                        .background(if track.kind == TrackType::Audio {
+                                            Color::rgb8(100, 150, 200)
+                                        } else {
+                                            Color::rgb8(200, 150, 100)
+                                        })
+                                        .z_index(1)
+                                })
+                                .style(|s| s.absolute().margin_left(0.0))
+                                .on_event(EventListener::PointerDown, move |evt| {
+                                    // clicking the track moves the playhead there
+                                    if let Some(point) = evt.point() {
+                                        let time_ms = (point.x * 1000.0
+                                            / pixels_per_s.get_untracked())
+                                        .max(0.0);
+
+                                        timeline_playhead
+                                            .set(Duration::from_millis(time_ms as u64));
+                                    }
+
+                                    EventPropagation::Continue
+                                }),
+                            // playhead
+                            empty().style(move |s| {
+                                let x = timeline_playhead.get().as_secs_f64() * pixels_per_s.get();
+
+                                s.absolute()
+                                    .inset_left(x)
+                                    .width(2.0)
+                                    .height(TRACK_HEIGHT)
+                                    .background(Color::RED)
+                                    .z_index(6)
+                            }),
+                            marker_lines(
+                                timeline_markers,
+                                move || pixels_per_s.get(),
+                                TRACK_HEIGHT,
+                            ),
+                            // timeline_sequences
+                            timeline_sequence_track(
+                                editor.clone(),
+                                editor_state.clone(),
+                                // state,
+                                timeline_sequences,
+                                dragging_timeline_sequence,
+                                export_play_timeline_config,
+                                track.clone(),
+                                tracks,
+                                track_assignments,
+                                drop_track,
+                                clip_trims,
+                                selected_clip,
+                                ripple_mode,
+                                zoom,
+                                sequence_quick_access,
+                                sequence_durations,
+                            ),
+                        )),
+                    )
+                    .on_event(EventListener::DragEnter, move |_| {
+                        drop_track.set(Some(track_id.clone()));
+                        EventPropagation::Continue
+                    })
+                    .style(move |s| {
+                        // muted tracks are left out of playback, show them faded
+                        let muted = tracks.get().iter().any(|t| t.id == track.id && t.muted);
+
+                        s.position(Position::Relative)
+                            .height(TRACK_HEIGHT)
+                            .apply_if(muted, |s| s.opacity(0.4))
+                    }),
+                ))
+            },
+        )
+        .style(|s| s.flex_col()),
+        label(|| "Transitions").style(|s| s.margin_top(10.0).margin_bottom(5.0)),
+        dyn_stack(
+            transition_pairs,
+            |(from, to)| format!("{}-{}", from.id, to.id),
+            move |(from, to)| {
+                transition_row(
+                    editor_state4.clone(),
+                    transitions,
+                    sequence_quick_access,
+                    from,
+                    to,
+                )
+            },
+        )
+        .style(|s| s.flex_col()),
+        container(marker_list(editor_state8, || None, timeline_playhead))
+            .style(|s| s.margin_top(10.0)),
+    ))
+}
+
+/// Kind, duration, easing and dip color of the transition between two sequences on a track
+fn transition_row(
+    editor_state: Arc<Mutex<EditorState>>,
+    transitions: RwSignal<Vec<SequenceTransition>>,
+    sequence_quick_access: RwSignal<HashMap<String, String>>,
+    from: TimelineSequence,
+    to: TimelineSequence,
+) -> impl View {
+    let existing = transitions
+        .get_untracked()
+        .into_iter()
+        .find(|t| t.from_timeline_sequence_id == from.id && t.to_timeline_sequence_id == to.id);
+
+    let selected_kind = create_rw_signal(
+        existing
+            .as_ref()
+            .map(|t| t.kind.label().to_string())
+            .unwrap_or("None".to_string()),
+    );
+    let selected_easing = create_rw_signal(
+        easing_label(
+            &existing
+                .as_ref()
+                .map(|t| t.easing.clone())
+                .unwrap_or(EasingType::EaseInOut),
+        )
+        .to_string(),
+    );
+    let duration = create_rw_signal(
+        existing
+            .as_ref()
+            .map(|t| t.duration_ms)
+            .unwrap_or(DEFAULT_TRANSITION_MS)
+            .to_string(),
+    );
+    let selected_dip_color = create_rw_signal(dip_color_label(
+        existing.as_ref().map(|t| t.dip_color).unwrap_or_default(),
+    ));
+
+    let kind_options = create_rw_signal(
+        std::iter::once("None")
+            .chain(TransitionKind::all().iter().map(|k| k.label()))
+            .map(|label| DropdownOption {
+                id: label.to_string(),
+                label: label.to_string(),
+            })
+            .collect(),
+    );
+    let easing_options = create_rw_signal(
+        easing_types()
+            .iter()
+            .map(|e| DropdownOption {
+                id: easing_label(e).to_string(),
+                label: easing_label(e).to_string(),
+            })
+            .collect(),
+    );
+    let dip_color_options = create_rw_signal(
+        DIP_COLORS
+            .iter()
+            .map(|(label, _)| DropdownOption {
+                id: label.to_string(),
+                label: label.to_string(),
+            })
+            .collect(),
+    );
+
+    let names = sequence_quick_access.get_untracked();
+    let title = format!(
+        "{} to {}",
+        names.get(&from.sequence_id).cloned().unwrap_or_default(),
+        names.get(&to.sequence_id).cloned().unwrap_or_default()
+    );
+
+    let apply = move |editor_state: &Arc<Mutex<EditorState>>| {
+        let mut editor_state = editor_state.lock().unwrap();
+
+        match TransitionKind::from_label(&selected_kind.get_untracked()) {
+            Some(kind) => {
+                let id = transitions
+                    .get_untracked()
+                    .iter()
+                    .find(|t| {
+                        t.from_timeline_sequence_id == from.id && t.to_timeline_sequence_id == to.id
+                    })
+                    .map(|t| t.id.clone())
+                    .unwrap_or(Uuid::new_v4().to_string());
+
+                editor_state.save_transition(SequenceTransition {
+                    id,
+                    from_timeline_sequence_id: from.id.clone(),
+                    to_timeline_sequence_id: to.id.clone(),
+                    kind,
+                    duration_ms: duration
+                        .get_untracked()
+                        .trim()
+                        .parse()
+                        .unwrap_or(DEFAULT_TRANSITION_MS),
+                    easing: easing_from_label(&selected_easing.get_untracked())
+                        .unwrap_or(EasingType::EaseInOut),
+                    dip_color: dip_color_from_label(&selected_dip_color.get_untracked())
+                        .unwrap_or_default(),
+                });
+            }
+            None => editor_state.remove_transition(&from.id, &to.id),
+        }
+    };
+
+    let editor_state2 = Arc::clone(&editor_state);
+    let editor_state3 = Arc::clone(&editor_state);
+    let editor_state4 = Arc::clone(&editor_state);
+    let apply2 = apply.clone();
+    let apply3 = apply.clone();
+    let apply4 = apply.clone();
+
+    debounce_action(duration, Duration::from_millis(300), {
+        move || {
+            if selected_kind.get_untracked() != "None" {
+                apply(&editor_state);
+            }
+        }
+    });
+
+    h_stack((
+        label(move || title.clone()).style(|s| s.width(160.0).selectable(false)),
+        inline_dropdown(
+            "Kind".to_string(),
+            selected_kind,
+            kind_options,
+            move |kind| {
+                selected_kind.set(kind);
+                apply2(&editor_state2);
+            },
+        ),
+        text_input(duration).style(|s| input_styles(s).width(60.0).margin_horiz(5.0)),
+        label(|| "ms").style(|s| s.margin_right(5.0)),
+        inline_dropdown(
+            "Easing".to_string(),
+            selected_easing,
+            easing_options,
+            move |easing| {
+                selected_easing.set(easing);
+
+                if selected_kind.get_untracked() != "None" {
+                    apply3(&editor_state3);
+                }
+            },
+        ),
+        container(inline_dropdown(
+            "Dip Color".to_string(),
+            selected_dip_color,
+            dip_color_options,
+            move |color| {
+                selected_dip_color.set(color);
+
+                if selected_kind.get_untracked() != "None" {
+                    apply4(&editor_state4);
+                }
+            },
+        ))
+        .style(move |s| {
+            s.margin_left(5.0).apply_if(
+                TransitionKind::from_label(&selected_kind.get()) != Some(TransitionKind::Dip),
+                |s| s.display(Display::None),
+            )
+        }),
     ))
+    .style(|s| s.items_center().margin_bottom(5.0))
+}
+
+/// Name, order, lock and mute controls at the start of a track
+fn track_header(
+    editor_state: Arc<Mutex<EditorState>>,
+    track: TimelineTrack,
+    tracks: RwSignal<Vec<TimelineTrack>>,
+) -> impl View {
+    let editor_state2 = Arc::clone(&editor_state);
+    let editor_state3 = Arc::clone(&editor_state);
+    let editor_state4 = Arc::clone(&editor_state);
+
+    let track_id = track.id.clone();
+    let track_id2 = track.id.clone();
+    let track_id3 = track.id.clone();
+
+    let name = create_rw_signal(track.name.clone());
+    let locked = create_rw_signal(track.locked);
+    let muted = create_rw_signal(track.muted);
+    // a track with sequences on it takes a second click to remove
+    let confirm_remove = create_rw_signal(false);
+
+    let update_track = move |editor_state: &Arc<Mutex<EditorState>>,
+                             track_id: &str,
+                             edit: &dyn Fn(&mut TimelineTrack)| {
+        let mut track = match tracks
+            .get_untracked()
+            .into_iter()
+            .find(|t| t.id == track_id)
+        {
+            Some(track) => track,
+            None => return,
+        };
+        let before = track.clone();
+
+        edit(&mut track);
+
+        // the effects below also run when the header is built
+        if track == before {
+            return;
+        }
+
+        editor_state.lock().unwrap().update_track(track);
+    };
+
+    debounce_action(name, Duration::from_millis(300), {
+        let track_id = track.id.clone();
+        let editor_state = editor_state.clone();
+
+        move || {
+            let name = name.get_untracked();
+
+            if !name.trim().is_empty() {
+                update_track(&editor_state, &track_id, &|track: &mut TimelineTrack| {
+                    track.name = name.clone()
+                });
+            }
+        }
+    });
+
+    create_effect({
+        let track_id = track.id.clone();
+        let editor_state = editor_state.clone();
+
+        move |_| {
+            let (locked, muted) = (locked.get(), muted.get());
+
+            update_track(&editor_state, &track_id, &|track: &mut TimelineTrack| {
+                track.locked = locked;
+                track.muted = muted;
+            });
+        }
+    });
+
+    v_stack((
+        text_input(name).style(|s| input_styles(s).width(140.0).margin_bottom(2.0)),
+        h_stack((
+            simple_button("Up".to_string(), move |_| {
+                editor_state2.lock().unwrap().move_track(&track_id, -1);
+            }),
+            simple_button("Down".to_string(), move |_| {
+                editor_state3.lock().unwrap().move_track(&track_id2, 1);
+            }),
+            Checkbox::new_labeled_rw(locked, || "Lock").style(|s| s.margin_right(5.0)),
+            Checkbox::new_labeled_rw(muted, || "Mute").style(|s| s.margin_right(5.0)),
+            simple_button("Remove".to_string(), move |_| {
+                let mut editor_state = editor_state4.lock().unwrap();
+
+                if !confirm_remove.get_untracked()
+                    && !editor_state.track_sequence_ids(&track_id3).is_empty()
+                {
+                    confirm_remove.set(true);
+                    return;
+                }
+
+                confirm_remove.set(false);
+
+                if let Err(e) = editor_state.remove_track(&track_id3, true) {
+                    println!("Couldn't remove track: {}", e);
+                }
+            }),
+        ))
+        .style(|s| s.items_center()),
+        label(|| "Remove again to delete its sequences".to_string()).style(move |s| {
+            s.font_size(10.0)
+                .color(Color::rgb8(200, 60, 60))
+                .apply_if(!confirm_remove.get(), |s| s.display(Display::None))
+        }),
+    ))
+    .style(|s| {
+        s.width(TRACK_HEADER_WIDTH)
+            .height(TRACK_HEIGHT)
+            .padding_right(5.0)
+    })
 }
 
 pub fn timeline_sequence_track(
@@ -199,41 +728,69 @@ pub fn timeline_sequence_track(
     dragging_timeline_sequence: RwSignal<Option<(String, i32)>>,
     export_play_timeline_config: RwSignal<Option<SavedTimelineStateConfig>>,
     // state: RwSignal<TimelineState>,
-    track_type: TrackType,
-    pixels_per_s: i32,
+    track: TimelineTrack,
+    tracks: RwSignal<Vec<TimelineTrack>>,
+    track_assignments: RwSignal<Vec<TrackAssignment>>,
+    drop_track: RwSignal<Option<String>>,
+    clip_trims: RwSignal<Vec<ClipTrim>>,
+    selected_clip: RwSignal<Option<String>>,
+    ripple_mode: RwSignal<bool>,
+    zoom: TimelineZoom,
     sequence_quick_access: RwSignal<HashMap<String, String>>,
     sequence_durations: RwSignal<HashMap<String, i32>>,
 ) -> impl View {
     // let state_2 = state.clone();
 
     let dragger_id = create_rw_signal(String::new());
+    let track_id = track.id.clone();
 
     dyn_stack(
-        move || timeline_sequences.get(),
-        move |timeline_sequence| timeline_sequence.id.clone(),
+        move || {
+            let tracks = tracks.get();
+            let track_assignments = track_assignments.get();
+            let durations = sequence_durations.get();
+            let trims = clip_trims.get();
+            let pixels_per_s = zoom.pixels_per_s.get();
+
+            timeline_sequences
+                .get()
+                .into_iter()
+                .filter(|ts| {
+                    track_of(&tracks, &track_assignments, ts).map(|t| &t.id) == Some(&track_id)
+                })
+                .filter_map(|ts| {
+                    let sequence_duration_ms = *durations.get(&ts.sequence_id)?;
+                    let (_, duration_ms) = clip_range(&trims, &ts.id, sequence_duration_ms);
+
+                    Some((ts, duration_ms, pixels_per_s))
+                })
+                .collect::<Vec<(TimelineSequence, i32, f64)>>()
+        },
+        // rebuilt when moved, trimmed or zoomed, after ripple edits too
+        move |(timeline_sequence, duration_ms, pixels_per_s)| {
+            (
+                timeline_sequence.id.clone(),
+                timeline_sequence.start_time_ms,
+                *duration_ms,
+                pixels_per_s.to_bits(),
+            )
+        },
         {
             // let state = state.clone();
-            let track_type = track_type.clone();
+            let track = track.clone();
 
-            move |seq: TimelineSequence| {
+            move |(seq, duration_ms, pixels_per_s): (TimelineSequence, i32, f64)| {
                 let seq_id = seq.id.clone();
-                let seq_seq_id = seq.sequence_id.clone();
-                let track_type = track_type.clone();
+                let track_type = track.kind.clone();
+                let track_id = track.id.clone();
                 let pixels_per_ms = pixels_per_s as f32 / 1000.0;
                 let left = seq.start_time_ms as f32 * pixels_per_ms;
                 let left_signal = create_rw_signal(left);
                 // println!("seq {:?} {:?}", seq_id, left);
                 // let width = seq.duration_ms as f32 * pixels_per_ms;
-                let sequence_durations = sequence_durations.get();
-                let matching_duration = sequence_durations
-                    .get(&seq_seq_id)
-                    .expect("Couldn't find matching duration")
-                    .clone();
-                let width = matching_duration as f32 * pixels_per_ms;
-
-                if (seq.track_type != track_type) {
-                    return container((empty())).into_view();
-                }
+                let width = duration_ms as f32 * pixels_per_ms;
+                let seq_id2 = seq.id.clone();
+                let seq_id3 = seq.id.clone();
 
                 let sequence_quick_access = sequence_quick_access.get();
                 let quick_access_info = sequence_quick_access
@@ -245,9 +802,30 @@ pub fn timeline_sequence_track(
                 // drop(sequence_quick_access);
 
                 container(
-                    label(move || quick_access_info.clone())
-                        .style(|s| s.padding(5).selectable(false)),
+                    h_stack((
+                        trim_handle(
+                            editor.clone(),
+                            editor_state.clone(),
+                            seq.id.clone(),
+                            true,
+                            zoom,
+                        ),
+                        label(move || quick_access_info.clone())
+                            .style(|s| s.padding(5).selectable(false).flex_grow(1.0)),
+                        trim_handle(
+                            editor.clone(),
+                            editor_state.clone(),
+                            seq.id.clone(),
+                            false,
+                            zoom,
+                        ),
+                    ))
+                    .style(|s| s.width_full().height_full()),
                 )
+                .on_event(EventListener::PointerDown, move |_| {
+                    selected_clip.set(Some(seq_id2.clone()));
+                    EventPropagation::Continue
+                })
                 // .style(move |s| {
                 //     s.absolute()
                 //         .margin_left(left)
@@ -264,7 +842,20 @@ pub fn timeline_sequence_track(
                 .on_event(EventListener::DragStart, {
                     // let state = state.clone();
 
+                    let track_id = track_id.clone();
+
                     move |evt| {
+                        // sequences on locked tracks stay where they are
+                        let locked = tracks
+                            .get_untracked()
+                            .iter()
+                            .any(|t| t.id == track_id && t.locked);
+
+                        if locked {
+                            return EventPropagation::Continue;
+                        }
+
+                        drop_track.set(None);
                         dragging_timeline_sequence
                             .set(Some((seq_id.clone(), (left / pixels_per_ms) as i32)));
 
@@ -275,6 +866,7 @@ pub fn timeline_sequence_track(
                     // let state = state.clone();
                     let editor = editor.clone();
                     let editor_state = editor_state.clone();
+                    let track_id = track_id.clone();
 
                     move |evt| {
                         if let Some((id, _)) = dragging_timeline_sequence.get().take() {
@@ -291,7 +883,9 @@ pub fn timeline_sequence_track(
                             // let camera = editor.camera.expect("Couldn't get camera");
 
                             let position = Point {
-                                x: editor.last_screen.x - 600.0, // 600.0 for sidebar
+                                // 600.0 for sidebar, then the track headers and the scroll
+                                x: editor.last_screen.x - 600.0 - TRACK_HEADER_WIDTH as f32
+                                    + zoom.scroll_x.get_untracked() as f32,
                                 y: editor.last_screen.y - 400.0, // 400.0 for size of canvas
                             };
 
@@ -304,47 +898,38 @@ pub fn timeline_sequence_track(
 
                             drop(editor);
 
-                            // state.get().move_timeline_sequence(&id, new_time_ms);
+                            let new_time_ms = snap_clip_time(
+                                &editor_state,
+                                new_time_ms,
+                                duration_ms,
+                                pixels_per_ms,
+                            );
 
-                            let mut seqs: Vec<TimelineSequence> = timeline_sequences.get();
+                            if ripple_mode.get_untracked() {
+                                let mut editor_state = editor_state.lock().unwrap();
 
-                            seqs.iter_mut().for_each(|ts| {
-                                if ts.id == id {
-                                    ts.start_time_ms = new_time_ms;
+                                if let Err(e) = editor_state.ripple_move_clip(&id, new_time_ms) {
+                                    println!("Couldn't move clip: {}", e);
                                 }
-                            });
 
-                            timeline_sequences.set(seqs);
+                                return EventPropagation::Continue;
+                            }
 
-                            // timeline_sequences.update(|seqs| {
-                            //     seqs.iter_mut().for_each(|ts| {
-                            //         if ts.id == id {
-                            //             ts.start_time_ms = new_time_ms;
-                            //         }
-                            //     })
-                            // });
-                            export_play_timeline_config.set(Some(SavedTimelineStateConfig {
-                                timeline_sequences: timeline_sequences.get(),
-                            }));
+                            // state.get().move_timeline_sequence(&id, new_time_ms);
 
                             left_signal.set(new_time_ms as f32 * pixels_per_ms);
 
-                            // update the saved_state
-                            let mut editor_state = editor_state.lock().unwrap();
-                            let mut new_state = editor_state
-                                .record_state
-                                .saved_state
-                                .as_mut()
-                                .expect("Couldn't get Saved State")
-                                .clone();
+                            // dropped over another track
+                            let target_track_id =
+                                drop_track.get_untracked().filter(|t| *t != track_id);
 
-                            // need to update start_times for sequence items
-                            // timeline_sequences (sortable_items) gets set in move_timeline_sequence
-                            new_state.timeline_state.timeline_sequences = timeline_sequences.get();
-
-                            editor_state.record_state.saved_state = Some(new_state.clone());
+                            let mut editor_state = editor_state.lock().unwrap();
 
-                            save_saved_state_raw(new_state.clone());
+                            if let Err(e) =
+                                editor_state.move_clip(&id, new_time_ms, target_track_id.as_deref())
+                            {
+                                println!("Couldn't move sequence: {}", e);
+                            }
                         }
                         EventPropagation::Continue
                     }
@@ -363,6 +948,9 @@ pub fn timeline_sequence_track(
                         } else {
                             Color::rgb8(100, 200, 100)
                         })
+                        .apply_if(selected_clip.get().as_ref() == Some(&seq_id3), |s| {
+                            s.border(2.0).border_color(Color::WHITE)
+                        })
                         .cursor(CursorStyle::Pointer)
                         .z_index(5)
                 })
@@ -432,3 +1020,86 @@ pub fn timeline_sequence_track(
     .style(|s: floem::style::Style| s.display(Display::Block).padding(5))
     .style(|s| s.absolute().margin_left(0.0).height(50))
 }
+
+/// Snap a clip edit against the timeline markers, region edges and playhead. A moved clip
+/// snaps by whichever end lands closer, a trimmed edge has no length. Alt bypasses snapping.
+fn snap_clip_time(
+    editor_state: &Arc<Mutex<EditorState>>,
+    raw_time_ms: i32,
+    length_ms: i32,
+    pixels_per_ms: f32,
+) -> i32 {
+    let editor_state = editor_state.lock().unwrap();
+    let bypass_snap = editor_state.current_modifiers.alt_key();
+    let snap_settings = editor_state.snap_settings;
+    let playhead = editor_state.timeline_playhead.get_untracked();
+    let frame_rate = editor_state.frame_rate.get_untracked();
+    let markers = marker_times(&editor_state.markers.get_untracked(), None);
+    drop(editor_state);
+
+    if bypass_snap {
+        return raw_time_ms.max(0);
+    }
+
+    let candidates = clip_snap_candidates(playhead, &markers);
+    let threshold = Duration::from_secs_f64(SNAP_THRESHOLD_PX / (pixels_per_ms as f64 * 1000.0));
+
+    let (start, _) = snap_animation_start(
+        Duration::from_millis(raw_time_ms.max(0) as u64),
+        Duration::from_millis(length_ms.max(0) as u64),
+        &candidates,
+        &snap_settings,
+        frame_rate,
+        threshold,
+    );
+
+    start.as_millis() as i32
+}
+
+/// Grip at one end of a clip, dragging it moves the clip's in or out point
+fn trim_handle(
+    editor: Arc<Mutex<Editor>>,
+    editor_state: Arc<Mutex<EditorState>>,
+    timeline_sequence_id: String,
+    is_in_point: bool,
+    zoom: TimelineZoom,
+) -> impl View {
+    empty()
+        .on_event(EventListener::DragStart, |_| {
+            // keep the clip itself from moving
+            EventPropagation::Stop
+        })
+        .on_event(EventListener::DragEnd, move |_| {
+            let editor = editor.lock().unwrap();
+            // same offsets as dropping a whole clip
+            let x = editor.last_screen.x - 600.0 - TRACK_HEADER_WIDTH as f32
+                + zoom.scroll_x.get_untracked() as f32;
+            let pixels_per_ms = zoom.pixels_per_s.get_untracked() as f32 / 1000.0;
+            let time_ms = (x.max(0.0) / pixels_per_ms) as i32;
+
+            drop(editor);
+
+            let time_ms = snap_clip_time(&editor_state, time_ms, 0, pixels_per_ms);
+
+            let mut editor_state = editor_state.lock().unwrap();
+
+            let trimmed = if is_in_point {
+                editor_state.trim_clip_in(&timeline_sequence_id, time_ms)
+            } else {
+                editor_state.trim_clip_out(&timeline_sequence_id, time_ms)
+            };
+
+            if let Err(e) = trimmed {
+                println!("Couldn't trim clip: {}", e);
+            }
+
+            EventPropagation::Stop
+        })
+        .style(|s| {
+            s.width(6.0)
+                .height_full()
+                .background(Color::rgba8(0, 0, 0, 60))
+                .cursor(CursorStyle::ColResize)
+        })
+        .draggable()
+}
@@ -21,8 +21,16 @@ use stunts_engine::text_due::{TextRenderer, TextRendererConfig};
 use stunts_engine::timelines::{SavedTimelineStateConfig, TimelineSequence, TrackType};
 use uuid::Uuid;
 
-use crate::editor_state::EditorState;
+use crate::editor_state::{EditorState, TimelineSignals};
+use crate::helpers::clock::{project_frame_rate, ClockMode};
+use crate::helpers::nested::{nest_sequence, nest_sequences, nested_videos};
+use crate::helpers::playback::prepare_timeline;
+use crate::helpers::saved_state::{ClipTrim, SequenceTransition, TimelineTrack, TrackAssignment};
+use crate::helpers::timeline::TimelineModel;
+use crate::helpers::timeline_playback::{set_sequence_hidden, TimelinePlayback};
+use crate::helpers::tracks::project_tracks;
 use crate::helpers::utilities::{parse_animation_data, save_saved_state_raw};
+use crate::helpers::zoom::TimelineZoom;
 use stunts_engine::animations::{
     AnimationData, AnimationProperty, BackgroundFill, EasingType, KeyframeValue, Sequence,
     UIKeyframe,
@@ -55,7 +63,6 @@ pub fn sequences_view(
     let editor_cloned3 = Arc::clone(&editor);
     let gpu_cloned = Arc::clone(&gpu_helper);
     let viewport_cloned = Arc::clone(&viewport);
-    let viewport_cloned2 = Arc::clone(&viewport);
     let state_cloned = Arc::clone(&editor_state);
     let state_cloned2 = Arc::clone(&editor_state);
     let state_cloned3 = Arc::clone(&editor_state);
@@ -76,6 +83,10 @@ pub fn sequences_view(
     let dragging_timeline_sequence: RwSignal<Option<(String, i32)>> = create_rw_signal(None);
     let export_play_timeline_config: RwSignal<Option<SavedTimelineStateConfig>> =
         create_rw_signal(None);
+    let tracks: RwSignal<Vec<TimelineTrack>> = create_rw_signal(Vec::new());
+    let track_assignments: RwSignal<Vec<TrackAssignment>> = create_rw_signal(Vec::new());
+    let clip_trims: RwSignal<Vec<ClipTrim>> = create_rw_signal(Vec::new());
+    let transitions: RwSignal<Vec<SequenceTransition>> = create_rw_signal(Vec::new());
 
     create_effect(move |_| {
         let mut editor_state = editor_state.lock().unwrap();
@@ -116,14 +127,16 @@ pub fn sequences_view(
         sequences.set(im_sequences);
         sequence_quick_access.set(qa_sequences);
         sequence_durations.set(sequence_durs);
+        tracks.set(project_tracks(saved_state));
+        track_assignments.set(saved_state.track_assignments.clone());
+        clip_trims.set(saved_state.clip_trims.clone());
+        transitions.set(saved_state.transitions.clone());
 
         // initialize TimelineState based on stored config if exists or saved sequences if not
         if saved_state.timeline_state.timeline_sequences.len() > 0 {
             timeline_sequences.set(saved_state.timeline_state.timeline_sequences.clone());
 
-            export_play_timeline_config.set(Some(SavedTimelineStateConfig {
-                timeline_sequences: timeline_sequences.get(),
-            }));
+            export_play_timeline_config.set(Some(saved_state.timeline_state.clone()));
 
             // sequence_timeline_signal.set(new_timeline_state);
         } else {
@@ -147,6 +160,16 @@ pub fn sequences_view(
 
             // sequence_timeline_signal.set(new_timeline_state);
         }
+
+        // project edits and their undo refresh the timeline through these
+        editor_state.timeline_signals = Some(TimelineSignals {
+            tracks,
+            track_assignments,
+            timeline_sequences,
+            clip_trims,
+            transitions,
+            export_play_timeline_config,
+        });
     });
 
     h_stack((
@@ -291,6 +314,10 @@ pub fn sequences_view(
                                     .find(|s| s.id == item.clone())
                                     .expect("Couldn't find matching sequence")
                                     .clone();
+                                let nested_objects =
+                                    nest_sequence(saved_state, &saved_sequence, &|s: &Sequence| {
+                                        s.clone()
+                                    });
 
                                 let mut background_fill = Some(BackgroundFill::Color([
                                     wgpu_to_human(0.8) as i32,
@@ -308,6 +335,9 @@ pub fn sequences_view(
                                     Uuid::from_str(&saved_sequence.id)
                                         .expect("Couldn't convert string to uuid");
 
+                                // every object is hidden below and the sequence's shown again
+                                editor_state.staged_looks.release();
+
                                 drop(editor_state);
 
                                 println!("Opening Sequence...");
@@ -382,6 +412,8 @@ pub fn sequences_view(
                                         .expect("Couldn't find image");
                                     video.hidden = false;
                                 });
+                                // copies of the objects of nested sequences
+                                set_sequence_hidden(&mut editor, &nested_objects, false);
 
                                 match background_fill.expect("Couldn't get default background fill")
                                 {
@@ -468,22 +500,15 @@ pub fn sequences_view(
                                     let mut existing_timeline = timeline_sequences.get();
                                     let sequence_durations = sequence_durations.get();
 
-                                    // Find the sequence that ends at the latest point in time
-                                    let start_time = if existing_timeline.is_empty() {
-                                        0
-                                    } else {
-                                        existing_timeline
-                                            .iter()
-                                            .map(|seq| {
-                                                let duration_ms = sequence_durations
-                                                    .get(&seq.sequence_id)
-                                                    .expect("Couldn't get duration");
-
-                                                seq.start_time_ms + duration_ms
-                                            })
-                                            .max()
-                                            .unwrap_or(0)
-                                    };
+                                    // after the sequence that ends at the latest point in time
+                                    let start_time = TimelineModel::from_durations(
+                                        |id| sequence_durations.get(id).copied(),
+                                        &SavedTimelineStateConfig {
+                                            timeline_sequences: existing_timeline.clone(),
+                                        },
+                                    )
+                                    .with_trims(&clip_trims.get())
+                                    .end_time_ms();
 
                                     existing_timeline.push(TimelineSequence {
                                         id: Uuid::new_v4().to_string(),
@@ -538,151 +563,77 @@ pub fn sequences_view(
         v_stack((
             simple_button("Play Video".to_string(), move |_| {
                 let mut editor_state = state_cloned5.lock().unwrap();
-
-                let saved_state = editor_state
-                    .record_state
-                    .saved_state
-                    .as_ref()
-                    .expect("Couldn't get saved state");
-                let cloned_sequences = saved_state.sequences.clone();
-
-                drop(editor_state);
-
                 let mut editor = editor_cloned2.lock().unwrap();
-                let viewport = viewport_cloned2.lock().unwrap();
-                let camera = editor.camera.expect("Couldn't get camera");
+                let timeline_playhead = editor_state.timeline_playhead;
 
-                if editor.video_is_playing {
+                if let Some(playback) = editor_state.timeline_playback.take() {
                     println!("Pause Video...");
 
-                    editor.video_is_playing = false;
-                    editor.video_start_playing_time = None;
-                    editor.video_current_sequence_timeline = None;
-                    editor.video_current_sequences_data = None;
+                    // tracks go back to their own layers, playing again lays them out again
+                    playback.restore_layers(&mut editor);
+
+                    // objects stay as they are, playing again picks up from the playhead
                     editor.is_playing = false;
                     editor.start_playing_time = None;
 
-                    // TODO: reset_sequence_objects?
-                    editor.video_items.iter_mut().for_each(|v| {
-                        v.reset_playback().expect("Couldn't reset video playback");
-                    });
-                } else {
-                    println!("Play Video...");
-
-                    // editor.polygons = Vec::new();
-                    // editor.text_items = Vec::new();
-                    // editor.image_items = Vec::new();
-
-                    // cloned_sequences.iter().enumerate().for_each(|(i, s)| {
-                    //     editor.restore_sequence_objects(
-                    //         &s,
-                    //         WindowSize {
-                    //             width: viewport.width as u32,
-                    //             height: viewport.height as u32,
-                    //         },
-                    //         &camera,
-                    //         if i == 0 { false } else { true },
-                    //     );
-                    // });
-
-                    // set hidden to false for first sequence
-                    let mut first_sequence: Option<Sequence> = None;
-                    cloned_sequences.iter().enumerate().for_each(|(i, s)| {
-                        if i == 0 {
-                            first_sequence = Some(s.clone());
-                            s.active_polygons.iter().for_each(|ap| {
-                                let polygon = editor
-                                    .polygons
-                                    .iter_mut()
-                                    .find(|p| p.id.to_string() == ap.id)
-                                    .expect("Couldn't find polygon");
-                                polygon.hidden = false;
-                            });
-                            s.active_image_items.iter().for_each(|si| {
-                                let image = editor
-                                    .image_items
-                                    .iter_mut()
-                                    .find(|i| i.id.to_string() == si.id)
-                                    .expect("Couldn't find image");
-                                image.hidden = false;
-                            });
-                            s.active_text_items.iter().for_each(|tr| {
-                                let text = editor
-                                    .text_items
-                                    .iter_mut()
-                                    .find(|t| t.id.to_string() == tr.id)
-                                    .expect("Couldn't find image");
-                                text.hidden = false;
-                            });
-                            s.active_video_items.iter().for_each(|tr| {
-                                let video = editor
-                                    .video_items
-                                    .iter_mut()
-                                    .find(|t| t.id.to_string() == tr.id)
-                                    .expect("Couldn't find image");
-                                video.hidden = false;
-                            });
-                        } else {
-                            s.active_polygons.iter().for_each(|ap| {
-                                let polygon = editor
-                                    .polygons
-                                    .iter_mut()
-                                    .find(|p| p.id.to_string() == ap.id)
-                                    .expect("Couldn't find polygon");
-                                polygon.hidden = true;
-                            });
-                            s.active_image_items.iter().for_each(|si| {
-                                let image = editor
-                                    .image_items
-                                    .iter_mut()
-                                    .find(|i| i.id.to_string() == si.id)
-                                    .expect("Couldn't find image");
-                                image.hidden = true;
-                            });
-                            s.active_text_items.iter().for_each(|tr| {
-                                let text = editor
-                                    .text_items
-                                    .iter_mut()
-                                    .find(|t| t.id.to_string() == tr.id)
-                                    .expect("Couldn't find image");
-                                text.hidden = true;
-                            });
-                            s.active_video_items.iter().for_each(|tr| {
-                                let video = editor
-                                    .video_items
-                                    .iter_mut()
-                                    .find(|t| t.id.to_string() == tr.id)
-                                    .expect("Couldn't find image");
-                                video.hidden = true;
-                            });
-                        }
-                    });
+                    drop(editor);
+                    drop(editor_state);
 
-                    println!("All sequence objects restored...");
+                    timeline_playhead.set(playback.position());
+
+                    return;
+                }
 
-                    editor.current_sequence_data =
-                        Some(first_sequence.expect("Couldn't get first sequence"));
+                let config = match export_play_timeline_config.get() {
+                    Some(config) => config,
+                    None => {
+                        println!("Add sequences to the timeline first");
+                        return;
+                    }
+                };
+
+                let saved_state = editor_state
+                    .record_state
+                    .saved_state
+                    .as_ref()
+                    .expect("Couldn't get saved state");
+                let (sequences, timeline, wipes) = prepare_timeline(saved_state, &config, true);
+                let sources = nest_sequences(saved_state);
+                // trimmed clips start their videos part way in
+                let model = TimelineModel::new(&sequences, &timeline)
+                    .with_source_offsets(&saved_state.clip_trims, &config);
+                let fps = project_frame_rate(saved_state);
+                let mode =
+                    ClockMode::from_every_frame(editor_state.play_every_frame.get_untracked());
+                let nested = saved_state
+                    .sequences
+                    .iter()
+                    .flat_map(|s| nested_videos(saved_state, &s.id))
+                    .collect();
 
-                    let now = std::time::Instant::now();
-                    editor.video_start_playing_time = Some(now.clone());
+                editor_state
+                    .staged_looks
+                    .stage(&mut editor, &sources, &sequences);
 
-                    editor.video_current_sequence_timeline = Some(
-                        export_play_timeline_config
-                            .get()
-                            .expect("Couldn't get a timeline"),
-                    );
-                    editor.video_current_sequences_data = Some(cloned_sequences);
+                println!("Play Video...");
 
-                    editor.video_is_playing = true;
+                let mut playback = TimelinePlayback::new(
+                    sequences,
+                    model,
+                    timeline_playhead.get_untracked(),
+                    fps,
+                    mode,
+                )
+                .with_nested_videos(nested)
+                .with_wipes(wipes);
+                let position = playback.step(&mut editor);
 
-                    // also set motion path playing
-                    editor.start_playing_time = Some(now);
-                    editor.is_playing = true;
+                editor_state.timeline_playback = Some(playback);
 
-                    println!("Video playing!");
-                }
+                drop(editor);
+                drop(editor_state);
 
-                // EventPropagation::Continue
+                timeline_playhead.set(position);
             }),
             build_timeline(
                 editor_cloned3,
@@ -690,9 +641,13 @@ pub fn sequences_view(
                 timeline_sequences,
                 dragging_timeline_sequence,
                 export_play_timeline_config,
-                10,
+                TimelineZoom::new(create_rw_signal(10.0)),
                 sequence_quick_access,
                 sequence_durations,
+                tracks,
+                track_assignments,
+                clip_trims,
+                transitions,
             ),
         ))
         .style(|s| s.margin_top(425.0).margin_left(25.0)),
@@ -0,0 +1,109 @@
+use std::sync::{Arc, Mutex};
+
+use floem::common::simple_button;
+use floem::event::{Event, EventListener, EventPropagation};
+use floem::peniko::Color;
+use floem::reactive::{RwSignal, SignalGet};
+use floem::taffy::Position;
+use floem::views::*;
+use floem::IntoView;
+use floem::View;
+
+use crate::editor_state::EditorState;
+use crate::helpers::zoom::{ticks, TimelineZoom, ZOOM_STEP};
+
+const RULER_HEIGHT: f64 = 20.0;
+
+/// Pointer x and wheel deltas of a wheel event
+pub fn wheel_event(evt: &Event) -> Option<(f64, f64, f64)> {
+    match evt {
+        Event::PointerWheel(wheel) => Some((wheel.pos.x, wheel.delta.x, wheel.delta.y)),
+        _ => None,
+    }
+}
+
+/// Shows part of a timeline a width wide, scrolled and zoomed with the wheel.
+/// Hold ctrl to zoom to the pointer.
+pub fn zoom_viewport<V, W, C>(
+    editor_state: Arc<Mutex<EditorState>>,
+    zoom: TimelineZoom,
+    width: W,
+    content_width: C,
+    child: V,
+) -> impl View
+where
+    V: IntoView + 'static,
+    W: Fn() -> f64 + Copy + 'static,
+    C: Fn() -> f64 + Copy + 'static,
+{
+    clip(container(child).style(move |s| {
+        s.position(Position::Relative)
+            .inset_left(-zoom.scroll_x.get())
+            .width(content_width())
+    }))
+    .on_event(EventListener::PointerWheel, move |evt| {
+        if let Some((x, delta_x, delta_y)) = wheel_event(evt) {
+            let zoom_key = editor_state.lock().unwrap().current_modifiers.control_key();
+
+            zoom.wheel(x, delta_x, delta_y, zoom_key, content_width() - width());
+        }
+
+        EventPropagation::Stop
+    })
+    .style(move |s| s.width(width()))
+}
+
+/// Zoom in and out about the middle of the view, or fit everything in it
+pub fn zoom_controls<W, F>(zoom: TimelineZoom, width: W, fit: F) -> impl View
+where
+    W: Fn() -> f64 + Copy + 'static,
+    F: Fn() + 'static,
+{
+    h_stack((
+        simple_button("-".to_string(), move |_| {
+            zoom.zoom_at(width() / 2.0, 1.0 / ZOOM_STEP);
+        })
+        .style(|s| s.margin_right(5.0)),
+        simple_button("+".to_string(), move |_| {
+            zoom.zoom_at(width() / 2.0, ZOOM_STEP);
+        })
+        .style(|s| s.margin_right(5.0)),
+        simple_button("Fit".to_string(), move |_| fit()),
+    ))
+    .style(|s| s.items_center().margin_bottom(5.0))
+}
+
+/// Time labels along the top of a zoomable timeline, seconds or frames depending on the zoom
+pub fn time_ruler<W>(zoom: TimelineZoom, width: W, frame_rate: RwSignal<u32>) -> impl View
+where
+    W: Fn() -> f64 + Copy + 'static,
+{
+    dyn_stack(
+        move || {
+            let (from, to) = zoom.visible_range(width());
+            let pixels_per_s = zoom.pixels_per_s.get();
+
+            ticks(pixels_per_s, from, to, frame_rate.get())
+                .into_iter()
+                .map(|tick| (tick, pixels_per_s))
+                .collect::<Vec<_>>()
+        },
+        |(tick, pixels_per_s)| (tick.time, pixels_per_s.to_bits()),
+        |(tick, pixels_per_s)| {
+            let x = tick.time.as_secs_f64() * pixels_per_s;
+            let text = tick.label.clone();
+
+            container(label(move || text.clone())).style(move |s| {
+                s.absolute()
+                    .inset_left(x)
+                    .height(RULER_HEIGHT)
+                    .padding_left(2.0)
+                    .border_left(1.0)
+                    .border_color(Color::rgb8(50, 50, 50))
+                    .font_size(10.0)
+                    .selectable(false)
+            })
+        },
+    )
+    .style(|s| s.position(Position::Relative).height(RULER_HEIGHT))
+}